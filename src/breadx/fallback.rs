@@ -5,21 +5,29 @@
 
 use super::image;
 use crate::{
-    fill::FillRule,
-    surface::{Surface, SurfaceFeatures},
-    util::clamp,
+    fill::{FillMode, FillRule},
+    path_from_arc, path_from_arc_closed, path_to_lines, path_to_points,
+    stroke::{LineCap, LineJoin},
+    surface::{
+        copy_pixels, FallbackPolicy, Surface, SurfaceFeatures, DEFAULT_TOLERANCE, MIN_TOLERANCE,
+    },
+    transform::Transform,
+    util::{clamp, intersect_clip},
     Color, Image, ImageFormat,
 };
 use breadx::{
     auto::xproto::{
-        Arc as XArc, Colormap, CoordMode, GetGeometryReply, Pixmap, Point as XPoint, PolyShape,
-        Rectangle as XRect, Segment, Window,
+        Arc as XArc, ArcMode, CapStyle, ClipOrdering, Colormap, CoordMode, CopyGcRequest,
+        FillRule as XFillRule, Gc, GetGeometryReply, JoinStyle, LineStyle, Pixmap, Point as XPoint,
+        PolyPointRequest, PolyShape, Rectangle as XRect, Segment, SetClipRectanglesRequest,
+        SetDashesRequest, Window,
     },
     display::{prelude::*, Display, DisplayBase, GcParameters},
     Drawable, Gcontext,
 };
 use lyon_geom::{Angle, Arc, LineSegment, Point, Rect, Size, Vector};
 use std::{
+    borrow::Cow,
     cmp::Ordering,
     collections::hash_map::{Entry, HashMap},
     mem::{self, MaybeUninit},
@@ -28,14 +36,26 @@ use std::{
 };
 
 #[cfg(feature = "async")]
-use breadx::display::AsyncConnection;
+use breadx::auto::xproto::AllocColorRequest;
 
 const FEATURES: SurfaceFeatures = SurfaceFeatures {
     transparency: false,
     gradients: false,
     floats: false,
+    clipping: true,
+    transforms: true,
+    paths: true,
+    text: false,
+    images: true,
+    patterns: false,
+    size: false,
+    antialiasing: false,
+    dirty_tracking: false,
 };
 
+// mask containing every component of a GC
+const ALL_GC_COMPONENTS: u32 = (1 << 23) - 1;
+
 /// Fallback BreadX surface. This uses XProto commands to render, even if they are slower than XRender or OpenGL
 /// rendering.
 #[derive(Debug)]
@@ -55,6 +75,107 @@ pub struct FallbackBreadxSurface<'dpy, Dpy: DisplayBase + ?Sized> {
     manager: ColorManager,
 
     line_width: Option<usize>,
+    line_cap: Option<LineCap>,
+    line_join: Option<LineJoin>,
+
+    // X11 fills arcs as pie slices by default, but fill_arc() fills them as chords
+    arc_mode: Option<ArcMode>,
+
+    // the rule passed to set_fill()
+    fill: Option<FillRule>,
+
+    // gradient fill, which is rasterized in software since the X core protocol can't draw gradients
+    gradient: Option<FillRule>,
+
+    // stack of clipping rectangles, each intersected with the one below it
+    clips: Vec<Rect<f32>>,
+
+    // X11 has no concept of transforms, so we apply this to points before we send them
+    transform: Transform,
+
+    // stack of states saved by save()
+    saved: Vec<SavedState>,
+
+    // tolerance used to flatten curves into lines
+    tolerance: f32,
+
+    // whether gradients are rasterized or rejected
+    fallback: FallbackPolicy,
+
+    // what happens to coordinates that don't fit into the core protocol's 16-bit integers
+    coordinates: CoordinatePolicy,
+
+    // back buffer that drawing is redirected to between begin_frame() and end_frame()
+    frame: Option<Frame>,
+}
+
+/// What the fallback surface does with coordinates that don't fit into the 16-bit integers used by the X11 core
+/// protocol.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CoordinatePolicy {
+    /// Clamp coordinates to the nearest value that fits.
+    Clamp,
+    /// Fail with an error.
+    Error,
+}
+
+impl Default for CoordinatePolicy {
+    #[inline]
+    fn default() -> Self {
+        Self::Clamp
+    }
+}
+
+impl CoordinatePolicy {
+    /// Convert a coordinate to a signed 16-bit integer.
+    #[inline]
+    pub fn coordinate(self, value: f32) -> crate::Result<i16> {
+        self.check(value, i16::MIN.into(), i16::MAX.into())?;
+        // float to integer casts saturate
+        Ok(value as i16)
+    }
+
+    /// Convert a width or height to an unsigned 16-bit integer.
+    #[inline]
+    pub fn length(self, value: f32) -> crate::Result<u16> {
+        self.check(value, u16::MIN.into(), u16::MAX.into())?;
+        Ok(value as u16)
+    }
+
+    #[inline]
+    fn check(self, value: f32, min: f32, max: f32) -> crate::Result {
+        match self {
+            CoordinatePolicy::Error if !(min..=max).contains(&value) => {
+                Err(crate::Error::StaticMsg("coordinate out of X11 range"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// An offscreen pixmap standing in for the target while a frame is being drawn.
+#[derive(Debug)]
+struct Frame {
+    front: Drawable,
+    back: Pixmap,
+    gc: Gcontext,
+    width: u16,
+    height: u16,
+}
+
+/// Drawing state saved by `save()`. The GC holds a copy of every component of our GC at the time.
+#[derive(Debug)]
+struct SavedState {
+    gc: Gcontext,
+    manager: ColorManager,
+    line_width: Option<usize>,
+    line_cap: Option<LineCap>,
+    line_join: Option<LineJoin>,
+    arc_mode: Option<ArcMode>,
+    fill: Option<FillRule>,
+    gradient: Option<FillRule>,
+    clips: Vec<Rect<f32>>,
+    transform: Transform,
 }
 
 /// Maps our colors to breadx pixel colors.
@@ -107,18 +228,24 @@ impl ColorMapper {
                 let r: u16 = clamp(color.red());
                 let g: u16 = clamp(color.green());
                 let b: u16 = clamp(color.blue());
-                let clr = cmap
-                    .alloc_color_immediate_async(dpy, r, g, b)
-                    .await?
-                    .pixel();
-                Ok(*v.insert(clr))
+                // breadx's alloc_color_immediate_async can't be awaited, so exchange the request directly
+                let reply = dpy
+                    .exchange_request_async(AllocColorRequest {
+                        cmap,
+                        red: r,
+                        green: g,
+                        blue: b,
+                        ..Default::default()
+                    })
+                    .await?;
+                Ok(*v.insert(reply.pixel))
             }
         }
     }
 }
 
 /// Figure out which color to set to the drawing color of the GC.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Copy, Clone)]
 struct ColorManager {
     stroke: ManagedColor,
     fill: ManagedColor,
@@ -161,6 +288,7 @@ impl ColorManager {
         match self.stroke {
             ManagedColor::Submitted(_) => None,
             ManagedColor::Unsubmitted(stroke) => {
+                self.stroke = ManagedColor::Submitted(stroke);
                 if let ManagedColor::Submitted(fill) = self.fill {
                     self.fill = ManagedColor::Unsubmitted(fill);
                 }
@@ -175,6 +303,7 @@ impl ColorManager {
         match self.fill {
             ManagedColor::Submitted(_) => None,
             ManagedColor::Unsubmitted(fill) => {
+                self.fill = ManagedColor::Submitted(fill);
                 if let ManagedColor::Submitted(stroke) = self.stroke {
                     self.stroke = ManagedColor::Unsubmitted(stroke);
                 }
@@ -223,9 +352,34 @@ impl<'dpy, Dpy: DisplayBase + ?Sized> FallbackBreadxSurface<'dpy, Dpy> {
             mapper: Some(ColorMapper::new(map)),
             manager: Default::default(),
             line_width: None,
+            line_cap: None,
+            line_join: None,
+            arc_mode: Some(ArcMode::Chord),
+            fill: None,
+            gradient: None,
+            clips: vec![],
+            transform: Transform::identity(),
+            saved: vec![],
+            tolerance: DEFAULT_TOLERANCE,
+            fallback: FallbackPolicy::default(),
+            coordinates: CoordinatePolicy::default(),
+            frame: None,
         }
     }
 
+    /// Set what happens to coordinates that don't fit into the 16-bit integers used by the X11 core protocol.
+    /// By default, they are clamped.
+    #[inline]
+    pub fn set_coordinate_policy(&mut self, policy: CoordinatePolicy) {
+        self.coordinates = policy;
+    }
+
+    /// Get the drawable that is shown to the user, even if we are currently drawing to a back buffer.
+    #[inline]
+    fn front(&self) -> Drawable {
+        self.frame.as_ref().map_or(self.target, |frame| frame.front)
+    }
+
     #[inline]
     fn submit_draw_params(&mut self, draw_type: DrawType) -> Option<GcParameters> {
         let mut changed = false;
@@ -240,9 +394,33 @@ impl<'dpy, Dpy: DisplayBase + ?Sized> FallbackBreadxSurface<'dpy, Dpy> {
         }
 
         if let Some(line_width) = self.line_width.take() {
+            changed = true;
             params.line_width = Some(line_width as _);
         }
 
+        if let Some(line_cap) = self.line_cap.take() {
+            changed = true;
+            params.cap_style = Some(match line_cap {
+                LineCap::Butt => CapStyle::Butt,
+                LineCap::Round => CapStyle::Round,
+                LineCap::Square => CapStyle::Projecting,
+            });
+        }
+
+        if let Some(line_join) = self.line_join.take() {
+            changed = true;
+            params.join_style = Some(match line_join {
+                LineJoin::Miter => JoinStyle::Miter,
+                LineJoin::Round => JoinStyle::Round,
+                LineJoin::Bevel => JoinStyle::Bevel,
+            });
+        }
+
+        if let Some(arc_mode) = self.arc_mode.take() {
+            changed = true;
+            params.arc_mode = Some(arc_mode);
+        }
+
         if changed {
             Some(params)
         } else {
@@ -261,12 +439,125 @@ impl<'dpy, Dpy: DisplayBase + ?Sized> Drop for FallbackBreadxSurface<'dpy, Dpy>
 impl<'dpy, Dpy: Display + ?Sized> FallbackBreadxSurface<'dpy, Dpy> {
     #[inline]
     fn submit_draw(&mut self, draw_type: DrawType) -> crate::Result {
+        // only rectangles can be filled with gradients
+        if let (Fill, Some(_)) = (draw_type, &self.gradient) {
+            return Err(crate::Error::NotSupported(crate::NSOpType::Gradients));
+        }
+
         if let Some(params) = self.submit_draw_params(draw_type) {
             self.gc.change(self.display, params)?;
         }
 
         Ok(())
     }
+
+    #[inline]
+    fn apply_clip(&mut self) -> crate::Result {
+        match self.clips.last() {
+            Some(clip) => {
+                let policy = self.coordinates;
+                let rectangles = if clip.is_empty() {
+                    vec![]
+                } else {
+                    vec![XRect {
+                        x: policy.coordinate(clip.origin.x)?,
+                        y: policy.coordinate(clip.origin.y)?,
+                        width: policy.length(clip.size.width.ceil())?,
+                        height: policy.length(clip.size.height.ceil())?,
+                    }]
+                };
+                self.display.send_request(SetClipRectanglesRequest {
+                    ordering: ClipOrdering::Unsorted,
+                    gc: self.gc,
+                    rectangles: Cow::Owned(rectangles),
+                    ..Default::default()
+                })?;
+            }
+            None => {
+                self.gc.change(
+                    self.display,
+                    GcParameters {
+                        clip_mask: Some(Pixmap::default()),
+                        ..Default::default()
+                    },
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Begin drawing a frame. Until `end_frame` is called, drawing operations are directed to an offscreen
+    /// back buffer, which starts out as a copy of the target's current contents. This prevents partially
+    /// drawn frames from flickering onto the screen.
+    #[inline]
+    pub fn begin_frame(&mut self) -> crate::Result {
+        if self.frame.is_some() {
+            return Err(crate::Error::StaticMsg("A frame is already being drawn"));
+        }
+
+        let geom_key = self.display.get_drawable_geometry(self.target)?;
+        let GetGeometryReply {
+            width,
+            height,
+            depth,
+            ..
+        } = self.display.resolve_request(geom_key)?;
+
+        let back = self
+            .display
+            .create_pixmap(self.target, width, height, depth)?;
+        let gc = self.display.create_gc(
+            self.target,
+            GcParameters {
+                graphics_exposures: Some(0),
+                ..Default::default()
+            },
+        )?;
+        self.display
+            .copy_area(self.target, back, gc, 0, 0, width, height, 0, 0)?;
+
+        self.frame = Some(Frame {
+            front: self.target,
+            back,
+            gc,
+            width,
+            height,
+        });
+        self.target = back.into();
+        Ok(())
+    }
+
+    /// Finish drawing a frame, copying the contents of the back buffer onto the target.
+    #[inline]
+    pub fn end_frame(&mut self) -> crate::Result {
+        let Frame {
+            front,
+            back,
+            gc,
+            width,
+            height,
+        } = self
+            .frame
+            .take()
+            .ok_or(crate::Error::StaticMsg("No frame is being drawn"))?;
+        self.target = front;
+
+        self.display
+            .copy_area(back, front, gc, 0, 0, width, height, 0, 0)?;
+        gc.free(self.display)?;
+        back.free(self.display)?;
+        Ok(())
+    }
+
+    /// Get the size of the pixmap backing an image.
+    #[inline]
+    fn image_size(&mut self, image: Image) -> crate::Result<Size<f32>> {
+        let pixmap = Pixmap::const_from_xid(image.into_raw().get() as u32);
+        let geom_key = self.display.get_drawable_geometry(pixmap)?;
+        let GetGeometryReply { width, height, .. } = self.display.resolve_request(geom_key)?;
+        Ok(Size::new(width as _, height as _))
+    }
 }
 
 #[cfg(feature = "async")]
@@ -307,10 +598,22 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for FallbackBreadxSurface<'dpy, Dpy> {
                 .unwrap()
                 .map_color(self.display, self.cmap, color)?;
             self.manager.set_fill(clr);
-            Ok(())
+            self.gradient = None;
+        } else if let FillRule::Pattern(_) = rule {
+            return Err(crate::Error::NotSupported(crate::NSOpType::Pattern));
+        } else if self.fallback == FallbackPolicy::Reject {
+            return Err(crate::Error::NotSupported(crate::NSOpType::Gradients));
         } else {
-            Err(crate::Error::NotSupported(crate::NSOpType::Gradients))
+            self.gradient = Some(rule.clone());
         }
+
+        self.fill = Some(rule);
+        Ok(())
+    }
+
+    #[inline]
+    fn fill_rule(&self) -> Option<&FillRule> {
+        self.fill.as_ref()
     }
 
     #[inline]
@@ -319,12 +622,207 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for FallbackBreadxSurface<'dpy, Dpy> {
         Ok(())
     }
 
+    #[inline]
+    fn tolerance(&self) -> f32 {
+        self.tolerance
+    }
+
+    #[inline]
+    fn set_tolerance(&mut self, tolerance: f32) -> crate::Result {
+        self.tolerance = tolerance.max(MIN_TOLERANCE);
+        Ok(())
+    }
+
+    #[inline]
+    fn set_fallback_policy(&mut self, policy: FallbackPolicy) -> crate::Result {
+        self.fallback = policy;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_line_cap(&mut self, cap: LineCap) -> crate::Result {
+        self.line_cap = Some(cap);
+        Ok(())
+    }
+
+    #[inline]
+    fn set_line_join(&mut self, join: LineJoin) -> crate::Result {
+        self.line_join = Some(join);
+        Ok(())
+    }
+
+    /// The core protocol always bevels joins sharper than about 11 degrees, so this has no effect.
+    #[inline]
+    fn set_miter_limit(&mut self, _limit: f32) -> crate::Result {
+        Ok(())
+    }
+
+    #[inline]
+    fn set_fill_mode(&mut self, mode: FillMode) -> crate::Result {
+        let fill_rule = match mode {
+            FillMode::EvenOdd => XFillRule::EvenOdd,
+            FillMode::NonZero => XFillRule::Winding,
+        };
+        self.gc.change(
+            self.display,
+            GcParameters {
+                fill_rule: Some(fill_rule),
+                ..Default::default()
+            },
+        )?;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_dash(&mut self, pattern: &[f32], offset: f32) -> crate::Result {
+        if pattern.is_empty() {
+            self.gc.change(
+                self.display,
+                GcParameters {
+                    line_style: Some(LineStyle::Solid),
+                    ..Default::default()
+                },
+            )?;
+            return Ok(());
+        }
+
+        // X11 dashes are measured in whole pixels and cannot be zero
+        let dashes: Vec<u8> = pattern
+            .iter()
+            .map(|length| length.abs().round().max(1.0).min(255.0) as u8)
+            .collect();
+        let total: f32 = dashes.iter().map(|&dash| dash as f32).sum();
+        self.display.send_request(SetDashesRequest {
+            gc: self.gc,
+            dash_offset: offset.rem_euclid(total) as _,
+            dashes: Cow::Owned(dashes),
+            ..Default::default()
+        })?;
+        self.gc.change(
+            self.display,
+            GcParameters {
+                line_style: Some(LineStyle::OnOffDash),
+                ..Default::default()
+            },
+        )?;
+        Ok(())
+    }
+
+    #[inline]
+    fn push_clip(&mut self, rect: Rect<f32>) -> crate::Result {
+        let rect = self.transform.transform_rect(&rect);
+        let clip = intersect_clip(self.clips.last(), rect);
+        self.clips.push(clip);
+        self.apply_clip()
+    }
+
+    #[inline]
+    fn pop_clip(&mut self) -> crate::Result {
+        self.clips.pop().ok_or(crate::Error::NoClip)?;
+        self.apply_clip()
+    }
+
+    #[inline]
+    fn set_transform(&mut self, transform: Transform) -> crate::Result {
+        self.transform = transform;
+        Ok(())
+    }
+
+    #[inline]
+    fn transform(&mut self, transform: Transform) -> crate::Result {
+        self.transform = transform.then(&self.transform);
+        Ok(())
+    }
+
+    #[inline]
+    fn save(&mut self) -> crate::Result {
+        let gc = self
+            .display
+            .create_gc(self.target, GcParameters::default())?;
+        self.display.send_request(CopyGcRequest {
+            src_gc: self.gc,
+            dst_gc: gc,
+            value_mask: Gc {
+                inner: ALL_GC_COMPONENTS,
+            },
+            ..Default::default()
+        })?;
+
+        self.saved.push(SavedState {
+            gc,
+            manager: self.manager,
+            line_width: self.line_width,
+            line_cap: self.line_cap,
+            line_join: self.line_join,
+            arc_mode: self.arc_mode,
+            fill: self.fill.clone(),
+            gradient: self.gradient.clone(),
+            clips: self.clips.clone(),
+            transform: self.transform,
+        });
+        Ok(())
+    }
+
+    #[inline]
+    fn restore(&mut self) -> crate::Result {
+        let SavedState {
+            gc,
+            manager,
+            line_width,
+            line_cap,
+            line_join,
+            arc_mode,
+            fill,
+            gradient,
+            clips,
+            transform,
+        } = self
+            .saved
+            .pop()
+            .ok_or(crate::Error::StaticMsg("No saved state to restore"))?;
+
+        self.display.send_request(CopyGcRequest {
+            src_gc: gc,
+            dst_gc: self.gc,
+            value_mask: Gc {
+                inner: ALL_GC_COMPONENTS,
+            },
+            ..Default::default()
+        })?;
+        gc.free(self.display)?;
+
+        self.manager = manager;
+        self.line_width = line_width;
+        self.line_cap = line_cap;
+        self.line_join = line_join;
+        self.arc_mode = arc_mode;
+        self.fill = fill;
+        self.gradient = gradient;
+        self.clips = clips;
+        self.transform = transform;
+        Ok(())
+    }
+
     #[inline]
     fn flush(&mut self) -> crate::Result {
         self.display.synchronize()?;
         Ok(())
     }
 
+    /// `size` can't send requests to the X server through a shared reference, so the geometry of the target
+    /// is queried here instead.
+    #[inline]
+    fn clear(&mut self, color: Color) -> crate::Result {
+        let geom_key = Window::const_from_xid(self.target.xid).geometry(self.display)?;
+        let GetGeometryReply { width, height, .. } = self.display.resolve_request(geom_key)?;
+        self.save()?;
+        self.set_transform(Transform::identity())?;
+        self.set_fill(FillRule::SolidColor(color))?;
+        let res = self.fill_rectangle(0.0, 0.0, width as _, height as _);
+        self.restore()?;
+        res
+    }
+
     #[inline]
     fn create_image(
         &mut self,
@@ -333,7 +831,8 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for FallbackBreadxSurface<'dpy, Dpy> {
         height: u32,
         image_format: ImageFormat,
     ) -> crate::Result<Image> {
-        let target = self.target;
+        // the image's visual comes from the window, not the back buffer
+        let target = self.front();
         let pixmap = image::image_to_pixmap(
             &mut self.display,
             target,
@@ -355,17 +854,45 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for FallbackBreadxSurface<'dpy, Dpy> {
         Ok(())
     }
 
+    #[inline]
+    fn draw_points(&mut self, points: &[Point<f32>]) -> crate::Result {
+        self.submit_draw(Stroke)?;
+        let transform = self.transform;
+        let policy = self.coordinates;
+        let points: Vec<XPoint> = points
+            .iter()
+            .map(|&point| transform.transform_point(point))
+            .map(|Point { x, y, .. }| {
+                Ok(XPoint {
+                    x: policy.coordinate(x)?,
+                    y: policy.coordinate(y)?,
+                })
+            })
+            .collect::<crate::Result<_>>()?;
+        self.display.send_request(PolyPointRequest {
+            coordinate_mode: CoordMode::Origin,
+            drawable: self.target,
+            gc: self.gc,
+            points: Cow::Owned(points),
+            ..Default::default()
+        })?;
+        Ok(())
+    }
+
     #[inline]
     fn draw_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> crate::Result {
         self.submit_draw(Stroke)?;
+        let Point { x: x1, y: y1, .. } = self.transform.transform_point(Point::new(x1, y1));
+        let Point { x: x2, y: y2, .. } = self.transform.transform_point(Point::new(x2, y2));
+        let policy = self.coordinates;
         self.gc.draw_line(
             self.display,
             self.target,
             Segment {
-                x1: x1 as _,
-                y1: y1 as _,
-                x2: x2 as _,
-                y2: y2 as _,
+                x1: policy.coordinate(x1)?,
+                y1: policy.coordinate(y1)?,
+                x2: policy.coordinate(x2)?,
+                y2: policy.coordinate(y2)?,
             },
         )?;
         Ok(())
@@ -374,33 +901,45 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for FallbackBreadxSurface<'dpy, Dpy> {
     #[inline]
     fn draw_lines(&mut self, lines: &[LineSegment<f32>]) -> crate::Result {
         self.submit_draw(Stroke)?;
+        let transform = self.transform;
+        let policy = self.coordinates;
         let lines: Vec<Segment> = lines
             .iter()
-            .copied()
+            .map(|line| line.transformed(&transform.into_inner()))
             .map(
                 |LineSegment {
                      from: Point { x: x1, y: y1, .. },
                      to: Point { x: x2, y: y2, .. },
-                 }| Segment {
-                    x1: x1 as _,
-                    y1: y1 as _,
-                    x2: x2 as _,
-                    y2: y2 as _,
+                 }| {
+                    Ok(Segment {
+                        x1: policy.coordinate(x1)?,
+                        y1: policy.coordinate(y1)?,
+                        x2: policy.coordinate(x2)?,
+                        y2: policy.coordinate(y2)?,
+                    })
                 },
             )
-            .collect();
+            .collect::<crate::Result<_>>()?;
         self.gc.draw_lines(self.display, self.target, &lines)?;
         Ok(())
     }
 
     #[inline]
     fn draw_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> crate::Result {
+        if !self.transform.is_identity() {
+            return self.draw_lines(&rect_lines(Rect::new(
+                Point::new(x, y),
+                Size::new(width, height),
+            )));
+        }
+
         self.submit_draw(Stroke)?;
+        let policy = self.coordinates;
         let rect = XRect {
-            x: x as _,
-            y: y as _,
-            width: width as _,
-            height: height as _,
+            x: policy.coordinate(x)?,
+            y: policy.coordinate(y)?,
+            width: policy.length(width)?,
+            height: policy.length(height)?,
         };
         self.gc.draw_rectangle(self.display, self.target, rect)?;
         Ok(())
@@ -408,7 +947,13 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for FallbackBreadxSurface<'dpy, Dpy> {
 
     #[inline]
     fn draw_rectangles(&mut self, rects: &[Rect<f32>]) -> crate::Result {
+        if !self.transform.is_identity() {
+            let lines: Vec<LineSegment<f32>> = rects.iter().copied().flat_map(rect_lines).collect();
+            return self.draw_lines(&lines);
+        }
+
         self.submit_draw(Stroke)?;
+        let policy = self.coordinates;
         let rects: Vec<XRect> = rects
             .iter()
             .copied()
@@ -416,14 +961,16 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for FallbackBreadxSurface<'dpy, Dpy> {
                 |Rect {
                      origin: Point { x, y, .. },
                      size: Size { width, height, .. },
-                 }| XRect {
-                    x: x as _,
-                    y: y as _,
-                    width: width as _,
-                    height: height as _,
+                 }| {
+                    Ok(XRect {
+                        x: policy.coordinate(x)?,
+                        y: policy.coordinate(y)?,
+                        width: policy.length(width)?,
+                        height: policy.length(height)?,
+                    })
                 },
             )
-            .collect();
+            .collect::<crate::Result<_>>()?;
         self.gc.draw_rectangles(self.display, self.target, rects)?;
         Ok(())
     }
@@ -438,15 +985,26 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for FallbackBreadxSurface<'dpy, Dpy> {
         start_angle: Angle<f32>,
         sweep_angle: Angle<f32>,
     ) -> crate::Result {
+        if !self.transform.is_identity() {
+            return self.draw_arcs(&[Arc {
+                center: Point::new(xcenter, ycenter),
+                radii: Vector::new(xradius, yradius),
+                start_angle,
+                sweep_angle,
+                x_rotation: Angle::zero(),
+            }]);
+        }
+
         self.submit_draw(Stroke)?;
         let arc = convert_arc(
-            xcenter as _,
-            ycenter as _,
-            xradius as _,
-            yradius as _,
+            self.coordinates,
+            xcenter,
+            ycenter,
+            xradius,
+            yradius,
             start_angle,
             sweep_angle,
-        );
+        )?;
         println!("arc is {:?}", &arc);
         self.gc.draw_arc(self.display, self.target, arc)?;
         Ok(())
@@ -454,7 +1012,20 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for FallbackBreadxSurface<'dpy, Dpy> {
 
     #[inline]
     fn draw_arcs(&mut self, arcs: &[Arc<f32>]) -> crate::Result {
+        // transformed arcs may no longer be elliptical, so draw them as lines instead
+        if !self.transform.is_identity() {
+            let tolerance = self.tolerance();
+            let lines: Vec<LineSegment<f32>> = arcs
+                .iter()
+                .copied()
+                .filter_map(|arc| path_from_arc(arc, tolerance))
+                .flat_map(|path| path_to_lines(path.iter(), tolerance).collect::<Vec<_>>())
+                .collect();
+            return self.draw_lines(&lines);
+        }
+
         self.submit_draw(Stroke)?;
+        let policy = self.coordinates;
         let arcs: Vec<XArc> = arcs
             .iter()
             .copied()
@@ -477,16 +1048,17 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for FallbackBreadxSurface<'dpy, Dpy> {
                      ..
                  }| {
                     convert_arc(
-                        xcenter as _,
-                        ycenter as _,
-                        xradius as _,
-                        yradius as _,
+                        policy,
+                        xcenter,
+                        ycenter,
+                        xradius,
+                        yradius,
                         start_angle,
                         sweep_angle,
                     )
                 },
             )
-            .collect();
+            .collect::<crate::Result<_>>()?;
         self.gc.draw_arcs(self.display, self.target, arcs)?;
         Ok(())
     }
@@ -494,14 +1066,18 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for FallbackBreadxSurface<'dpy, Dpy> {
     #[inline]
     fn fill_polygon(&mut self, points: &[Point<f32>]) -> crate::Result {
         self.submit_draw(Fill)?;
+        let transform = self.transform;
+        let policy = self.coordinates;
         let points: Vec<XPoint> = points
             .iter()
-            .copied()
-            .map(|Point { x, y, .. }| XPoint {
-                x: x as _,
-                y: y as _,
+            .map(|&point| transform.transform_point(point))
+            .map(|Point { x, y, .. }| {
+                Ok(XPoint {
+                    x: policy.coordinate(x)?,
+                    y: policy.coordinate(y)?,
+                })
             })
-            .collect();
+            .collect::<crate::Result<_>>()?;
         self.gc.fill_polygon(
             self.display,
             self.target,
@@ -514,12 +1090,28 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for FallbackBreadxSurface<'dpy, Dpy> {
 
     #[inline]
     fn fill_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> crate::Result {
+        if let Some(ref gradient) = self.gradient {
+            let rect = Rect::new(Point::new(x, y), Size::new(width, height)).round_out();
+            let pixels = gradient
+                .rasterize(rect)
+                .ok_or(crate::Error::NotSupported(crate::NSOpType::Pattern))?;
+            return copy_pixels(self, &pixels, rect);
+        }
+
+        if !self.transform.is_identity() {
+            return self.fill_polygon(&rect_points(Rect::new(
+                Point::new(x, y),
+                Size::new(width, height),
+            )));
+        }
+
         self.submit_draw(Fill)?;
+        let policy = self.coordinates;
         let rect = XRect {
-            x: x as _,
-            y: y as _,
-            width: width as _,
-            height: height as _,
+            x: policy.coordinate(x)?,
+            y: policy.coordinate(y)?,
+            width: policy.length(width)?,
+            height: policy.length(height)?,
         };
         self.gc.fill_rectangle(self.display, self.target, rect)?;
         Ok(())
@@ -527,7 +1119,26 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for FallbackBreadxSurface<'dpy, Dpy> {
 
     #[inline]
     fn fill_rectangles(&mut self, rects: &[Rect<f32>]) -> crate::Result {
+        if self.gradient.is_some() {
+            return rects.iter().try_for_each(|rect| {
+                self.fill_rectangle(
+                    rect.origin.x,
+                    rect.origin.y,
+                    rect.size.width,
+                    rect.size.height,
+                )
+            });
+        }
+
+        if !self.transform.is_identity() {
+            return rects
+                .iter()
+                .copied()
+                .try_for_each(|rect| self.fill_polygon(&rect_points(rect)));
+        }
+
         self.submit_draw(Fill)?;
+        let policy = self.coordinates;
         let rects: Vec<XRect> = rects
             .iter()
             .copied()
@@ -535,14 +1146,16 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for FallbackBreadxSurface<'dpy, Dpy> {
                 |Rect {
                      origin: Point { x, y, .. },
                      size: Size { width, height, .. },
-                 }| XRect {
-                    x: x as _,
-                    y: y as _,
-                    width: width as _,
-                    height: height as _,
+                 }| {
+                    Ok(XRect {
+                        x: policy.coordinate(x)?,
+                        y: policy.coordinate(y)?,
+                        width: policy.length(width)?,
+                        height: policy.length(height)?,
+                    })
                 },
             )
-            .collect();
+            .collect::<crate::Result<_>>()?;
         self.gc.fill_rectangles(self.display, self.target, rects)?;
         Ok(())
     }
@@ -557,15 +1170,26 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for FallbackBreadxSurface<'dpy, Dpy> {
         start_angle: Angle<f32>,
         sweep_angle: Angle<f32>,
     ) -> crate::Result {
+        if !self.transform.is_identity() {
+            return self.fill_arcs(&[Arc {
+                center: Point::new(xcenter, ycenter),
+                radii: Vector::new(xradius, yradius),
+                start_angle,
+                sweep_angle,
+                x_rotation: Angle::zero(),
+            }]);
+        }
+
         self.submit_draw(Fill)?;
         let arc = convert_arc(
-            xcenter as _,
-            ycenter as _,
-            xradius as _,
-            yradius as _,
+            self.coordinates,
+            xcenter,
+            ycenter,
+            xradius,
+            yradius,
             start_angle,
             sweep_angle,
-        );
+        )?;
         println!("arc is {:?}", &arc);
         self.gc.fill_arc(self.display, self.target, arc)?;
         Ok(())
@@ -573,7 +1197,21 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for FallbackBreadxSurface<'dpy, Dpy> {
 
     #[inline]
     fn fill_arcs(&mut self, arcs: &[Arc<f32>]) -> crate::Result {
+        // transformed arcs may no longer be elliptical, so fill them as polygons instead
+        if !self.transform.is_identity() {
+            let tolerance = self.tolerance();
+            return arcs
+                .iter()
+                .copied()
+                .filter_map(|arc| path_from_arc_closed(arc, tolerance))
+                .try_for_each(|path| {
+                    let points: Vec<Point<f32>> = path_to_points(path.iter(), tolerance).collect();
+                    self.fill_polygon(&points)
+                });
+        }
+
         self.submit_draw(Fill)?;
+        let policy = self.coordinates;
         let arcs: Vec<XArc> = arcs
             .iter()
             .copied()
@@ -596,16 +1234,17 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for FallbackBreadxSurface<'dpy, Dpy> {
                      ..
                  }| {
                     convert_arc(
-                        xcenter as _,
-                        ycenter as _,
-                        xradius as _,
-                        yradius as _,
+                        policy,
+                        xcenter,
+                        ycenter,
+                        xradius,
+                        yradius,
                         start_angle,
                         sweep_angle,
                     )
                 },
             )
-            .collect();
+            .collect::<crate::Result<_>>()?;
         self.gc.fill_arcs(self.display, self.target, arcs)?;
         Ok(())
     }
@@ -622,43 +1261,104 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for FallbackBreadxSurface<'dpy, Dpy> {
         height: u32,
     ) -> crate::Result {
         let pixmap = Pixmap::const_from_xid(src.into_raw().get() as u32);
+        let Point {
+            x: dst_x, y: dst_y, ..
+        } = self
+            .transform
+            .transform_point(Point::new(dst_x as f32, dst_y as f32));
+        let policy = self.coordinates;
         self.display.copy_area(
             pixmap,
             self.target,
             self.gc,
-            src_x as _,
-            src_y as _,
-            width as _,
-            height as _,
-            dst_x as _,
-            dst_y as _,
+            policy.coordinate(src_x as f32)?,
+            policy.coordinate(src_y as f32)?,
+            policy.length(width as f32)?,
+            policy.length(height as f32)?,
+            policy.coordinate(dst_x)?,
+            policy.coordinate(dst_y)?,
         )?;
         Ok(())
     }
+
+    #[inline]
+    fn draw_image(&mut self, image: &Image, dst: Rect<f32>) -> crate::Result {
+        let size = self.image_size(*image)?;
+        self.draw_image_region(image, Rect::new(Point::origin(), size), dst)
+    }
+
+    /// The core protocol cannot scale images, so this only works if the source and destination are the same
+    /// size.
+    #[inline]
+    fn draw_image_region(
+        &mut self,
+        image: &Image,
+        src: Rect<f32>,
+        dst: Rect<f32>,
+    ) -> crate::Result {
+        let size = self.image_size(*image)?;
+        crate::image::check_image_region(src, size)?;
+
+        let (src, dst) = (src.round(), dst.round());
+        if src.size != dst.size {
+            return Err(crate::Error::NotSupported(crate::NSOpType::Image));
+        }
+
+        self.copy_image(
+            *image,
+            src.origin.x as i32,
+            src.origin.y as i32,
+            dst.origin.x as i32,
+            dst.origin.y as i32,
+            src.size.width as u32,
+            src.size.height as u32,
+        )
+    }
+}
+
+#[inline]
+fn rect_points(rect: Rect<f32>) -> [Point<f32>; 4] {
+    [
+        rect.min(),
+        Point::new(rect.max_x(), rect.min_y()),
+        rect.max(),
+        Point::new(rect.min_x(), rect.max_y()),
+    ]
+}
+
+#[inline]
+fn rect_lines(rect: Rect<f32>) -> [LineSegment<f32>; 4] {
+    let [p1, p2, p3, p4] = rect_points(rect);
+    [
+        LineSegment { from: p1, to: p2 },
+        LineSegment { from: p2, to: p3 },
+        LineSegment { from: p3, to: p4 },
+        LineSegment { from: p4, to: p1 },
+    ]
 }
 
 #[inline]
 fn convert_arc(
-    xcenter: i32,
-    ycenter: i32,
-    xradius: i32,
-    yradius: i32,
+    policy: CoordinatePolicy,
+    xcenter: f32,
+    ycenter: f32,
+    xradius: f32,
+    yradius: f32,
     start: Angle<f32>,
     end: Angle<f32>,
-) -> XArc {
-    let x = xcenter - xradius;
-    let y = ycenter - yradius;
-    let width = xradius * 2;
-    let height = yradius * 2;
-
-    XArc {
-        x: x as i16,
-        y: y as i16,
-        width: width as u16,
-        height: height as u16,
+) -> crate::Result<XArc> {
+    // round towards zero, like the casts used for other shapes
+    let (xcenter, ycenter) = (xcenter.trunc(), ycenter.trunc());
+    let (xradius, yradius) = (xradius.trunc(), yradius.trunc());
+
+    Ok(XArc {
+        x: policy.coordinate(xcenter - xradius)?,
+        y: policy.coordinate(ycenter - yradius)?,
+        width: policy.length(xradius * 2.0)?,
+        height: policy.length(yradius * 2.0)?,
         angle1: convert_angle(start),
         angle2: convert_angle(end),
-    }
+    })
 }
 
 #[inline]
@@ -669,3 +1369,185 @@ fn convert_angle(angle: Angle<f32>) -> i16 {
         };
     (angle.to_degrees() * 64.0) as i16
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        color::consts::{BLUE, RED},
+        gradient::{ColorStop, Gradient},
+        Intensity,
+    };
+    use breadx::{auto::xproto::ImageFormat as XImageFormat, DisplayConnection};
+
+    /// Read back the pixel at `x, y`, with each channel scaled to between zero and one.
+    fn read_pixel(
+        conn: &mut DisplayConnection,
+        pixmap: Pixmap,
+        x: isize,
+        y: isize,
+    ) -> (f32, f32, f32) {
+        let image = conn
+            .get_image_immediate(pixmap, x, y, 1, 1, !0, XImageFormat::ZPixmap)
+            .unwrap();
+        let screen = conn.default_screen();
+        let visual = screen
+            .allowed_depths
+            .iter()
+            .flat_map(|depth| depth.visuals.iter())
+            .find(|visual| visual.visual_id == screen.root_visual)
+            .unwrap();
+        let pixel = image.pixel(0, 0);
+        let channel = |mask: u32| (pixel & mask) as f32 / mask as f32;
+        (
+            channel(visual.red_mask),
+            channel(visual.green_mask),
+            channel(visual.blue_mask),
+        )
+    }
+
+    #[test]
+    fn same_color_is_only_submitted_once() {
+        let mut manager = ColorManager::default();
+        manager.set_stroke(7);
+        manager.set_fill(9);
+
+        assert_eq!(manager.submit_stroke(), Some(7));
+        assert_eq!(manager.submit_stroke(), None);
+
+        // switching between stroking and filling swaps the GC's foreground back and forth
+        assert_eq!(manager.submit_fill(), Some(9));
+        assert_eq!(manager.submit_fill(), None);
+        assert_eq!(manager.submit_stroke(), Some(7));
+    }
+
+    #[test]
+    fn huge_coordinates_follow_the_policy() {
+        assert_eq!(CoordinatePolicy::default(), CoordinatePolicy::Clamp);
+        let clamp = CoordinatePolicy::Clamp;
+        assert_eq!(clamp.coordinate(100_000.0).unwrap(), i16::MAX);
+        assert_eq!(clamp.coordinate(-100_000.0).unwrap(), i16::MIN);
+        assert_eq!(clamp.length(100_000.0).unwrap(), u16::MAX);
+
+        let error = CoordinatePolicy::Error;
+        assert!(matches!(
+            error.coordinate(100_000.0),
+            Err(crate::Error::StaticMsg("coordinate out of X11 range"))
+        ));
+        assert!(matches!(
+            error.length(100_000.0),
+            Err(crate::Error::StaticMsg(_))
+        ));
+
+        // coordinates that fit are passed through either way
+        assert_eq!(error.coordinate(-12.0).unwrap(), -12);
+        assert_eq!(clamp.coordinate(-12.0).unwrap(), -12);
+    }
+
+    #[test]
+    #[ignore = "needs an X server"]
+    fn clear_then_read_pixel() {
+        let mut conn = DisplayConnection::create(None, None).unwrap();
+        let root = conn.default_root();
+        let depth = conn.default_screen().root_depth;
+        let pixmap = conn.create_pixmap(root, 8, 8, depth).unwrap();
+        let gc = conn.create_gc(pixmap, GcParameters::default()).unwrap();
+
+        let mut surface = FallbackBreadxSurface::new(&mut conn, pixmap, gc);
+        surface.clear(RED).unwrap();
+        surface.flush().unwrap();
+        mem::drop(surface);
+
+        let (r, g, b) = read_pixel(&mut conn, pixmap, 3, 3);
+        assert_eq!((r, g, b), (1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    #[ignore = "needs an X server"]
+    fn gradient_rectangle_endpoints() {
+        let mut conn = DisplayConnection::create(None, None).unwrap();
+        let root = conn.default_root();
+        let depth = conn.default_screen().root_depth;
+        let pixmap = conn.create_pixmap(root, 16, 4, depth).unwrap();
+        let gc = conn.create_gc(pixmap, GcParameters::default()).unwrap();
+
+        let gradient = Gradient::try_new(vec![
+            ColorStop {
+                color: RED,
+                position: Intensity::new(0.0).unwrap(),
+            },
+            ColorStop {
+                color: BLUE,
+                position: Intensity::new(1.0).unwrap(),
+            },
+        ])
+        .unwrap();
+        let mut surface = FallbackBreadxSurface::new(&mut conn, pixmap, gc);
+        surface
+            .set_fill(FillRule::LinearGradient(gradient, Angle::zero()))
+            .unwrap();
+        surface.fill_rectangle(0.0, 0.0, 16.0, 4.0).unwrap();
+        surface.flush().unwrap();
+        mem::drop(surface);
+
+        let (r, _, b) = read_pixel(&mut conn, pixmap, 0, 1);
+        assert!(r > 0.9 && b < 0.1);
+        let (r, _, b) = read_pixel(&mut conn, pixmap, 15, 1);
+        assert!(r < 0.1 && b > 0.9);
+    }
+
+    #[test]
+    #[ignore = "needs an X server"]
+    fn frame_appears_after_end_frame() {
+        let mut conn = DisplayConnection::create(None, None).unwrap();
+        let root = conn.default_root();
+        let depth = conn.default_screen().root_depth;
+        let pixmap = conn.create_pixmap(root, 8, 8, depth).unwrap();
+        let gc = conn.create_gc(pixmap, GcParameters::default()).unwrap();
+
+        let mut surface = FallbackBreadxSurface::new(&mut conn, pixmap, gc);
+        surface.clear(BLUE).unwrap();
+        surface.begin_frame().unwrap();
+        surface.clear(RED).unwrap();
+        surface.flush().unwrap();
+        assert_eq!(read_pixel(surface.display, pixmap, 3, 3), (0.0, 0.0, 1.0));
+
+        surface.end_frame().unwrap();
+        surface.flush().unwrap();
+        assert_eq!(read_pixel(surface.display, pixmap, 3, 3), (1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    #[ignore = "needs an X server"]
+    fn gradient_fill_follows_fallback_policy() {
+        let mut conn = DisplayConnection::create(None, None).unwrap();
+        let root = conn.default_root();
+        let depth = conn.default_screen().root_depth;
+        let pixmap = conn.create_pixmap(root, 4, 4, depth).unwrap();
+        let gc = conn.create_gc(pixmap, GcParameters::default()).unwrap();
+
+        let gradient = Gradient::try_new(vec![
+            ColorStop {
+                color: RED,
+                position: Intensity::new(0.0).unwrap(),
+            },
+            ColorStop {
+                color: BLUE,
+                position: Intensity::new(1.0).unwrap(),
+            },
+        ])
+        .unwrap();
+        let rule = FillRule::LinearGradient(gradient, Angle::zero());
+
+        let mut surface = FallbackBreadxSurface::new(&mut conn, pixmap, gc);
+        Surface::set_fallback_policy(&mut surface, FallbackPolicy::Reject).unwrap();
+        assert!(matches!(
+            Surface::set_fill(&mut surface, rule.clone()),
+            Err(crate::Error::NotSupported(crate::NSOpType::Gradients))
+        ));
+
+        Surface::set_fallback_policy(&mut surface, FallbackPolicy::Emulate).unwrap();
+        Surface::set_fill(&mut surface, rule).unwrap();
+        Surface::fill_rectangle(&mut surface, 0.0, 0.0, 4.0, 4.0).unwrap();
+    }
+}
@@ -31,10 +31,12 @@ pub(crate) fn breadx_image<Dpy: Display + ?Sized>(
         .visual_id_to_visual(visualid)
         .ok_or(crate::Error::ImageNotAvailable)?;
 
+    let (image_bytes, format) = crate::image::bgra_to_rgba(image_bytes, format);
+
     // allocate sufficient heap space for the image
     let quantum = match format {
         ImageFormat::Grayscale => 1usize,
-        ImageFormat::Rgb | ImageFormat::Rgba => 4,
+        ImageFormat::Rgb | ImageFormat::Rgba | ImageFormat::Bgra => 4,
     };
     let heap_space: Box<[u8]> =
         unsafe { Box::new_zeroed_slice(quantum * (width * height) as usize).assume_init() };
@@ -55,7 +57,7 @@ pub(crate) fn breadx_image<Dpy: Display + ?Sized>(
     .ok_or(crate::Error::ImageNotAvailable)?;
 
     // fill the image with pixels
-    crate::image::iterate_pixels(image_bytes, width, height, format).fold(
+    crate::image::iterate_pixels(&image_bytes, width, height, format).fold(
         (0, 0),
         |(x, y), pixel| {
             let pixel = pixel
@@ -91,7 +93,22 @@ pub(crate) fn image_to_pixmap<Dpy: Display + ?Sized>(
     let (image, _, _) = breadx_image(display, target, image_bytes, width, height, image_format)?;
 
     // create a pixmap and draw the image onto it
-    let pixmap = display.create_pixmap_from_image(target, &image)?;
+    pixmap_from_image(display, target, &image)
+}
+
+/// Create a pixmap containing the given image, using shared memory to upload it if possible.
+#[inline]
+pub(crate) fn pixmap_from_image<Dpy: Display + ?Sized>(
+    display: &mut Dpy,
+    target: Drawable,
+    image: &breadx::Image<Box<[u8]>>,
+) -> crate::Result<Pixmap> {
+    #[cfg(feature = "shm")]
+    {
+        if let Some(pixmap) = super::shm::image_to_pixmap(display, target, image)? {
+            return Ok(pixmap);
+        }
+    }
 
-    Ok(pixmap)
+    Ok(display.create_pixmap_from_image(target, image)?)
 }
@@ -9,3 +9,5 @@ pub use fallback::*;
 pub use xrender::*;
 
 pub(crate) mod image;
+#[cfg(feature = "shm")]
+mod shm;
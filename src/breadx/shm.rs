@@ -0,0 +1,179 @@
+// MIT/Apache2 License
+
+//! MIT-SHM support. Large images are uploaded to the X server through a shared memory segment, rather than
+//! being copied through the connection by `PutImage`.
+
+use breadx::{
+    auto::{
+        shm::{AttachRequest, DetachRequest, PutImageRequest, QueryVersionRequest, Seg},
+        xproto::ImageFormat as XImageFormat,
+    },
+    prelude::*,
+    BreadError, Display, Drawable, Image, Pixmap,
+};
+use std::{ptr, slice};
+
+/// Images with less data than this are cheap enough to send through the connection.
+const SHM_THRESHOLD: usize = 64 * 1024;
+
+/// A shared memory segment attached to both us and the X server.
+struct Segment {
+    seg: Seg,
+    addr: *mut u8,
+}
+
+impl Segment {
+    /// Create a new segment and attach it to the server. Returns `None` if the server is unable to attach
+    /// to it, which usually means the server is not running on this machine.
+    #[inline]
+    fn new<Dpy: Display + ?Sized>(display: &mut Dpy, size: usize) -> crate::Result<Option<Self>> {
+        let shmid = unsafe { libc::shmget(libc::IPC_PRIVATE, size, libc::IPC_CREAT | 0o600) };
+        if shmid == -1 {
+            return Ok(None);
+        }
+
+        let addr = unsafe { libc::shmat(shmid, ptr::null(), 0) };
+        if addr as isize == -1 {
+            unsafe { libc::shmctl(shmid, libc::IPC_RMID, ptr::null_mut()) };
+            return Ok(None);
+        }
+
+        let seg = Seg::const_from_xid(
+            display
+                .generate_xid()
+                .ok_or(crate::Error::StaticMsg("Ran out of XIDs"))?,
+        );
+
+        // the server reports whether it could attach asynchronously, so we need to check for errors here
+        let old_checked = display.checked();
+        display.set_checked(true);
+        let res = display.exchange_request(AttachRequest {
+            shmseg: seg,
+            shmid: shmid as _,
+            read_only: true,
+            ..Default::default()
+        });
+        display.set_checked(old_checked);
+
+        // the segment is destroyed once both we and the server have detached from it
+        unsafe { libc::shmctl(shmid, libc::IPC_RMID, ptr::null_mut()) };
+
+        match res {
+            Ok(()) => Ok(Some(Self {
+                seg,
+                addr: addr as *mut u8,
+            })),
+            Err(BreadError::XProtocol { .. }) => {
+                unsafe { libc::shmdt(addr) };
+                Ok(None)
+            }
+            Err(e) => {
+                unsafe { libc::shmdt(addr) };
+                Err(e.into())
+            }
+        }
+    }
+
+    #[inline]
+    fn detach<Dpy: Display + ?Sized>(self, display: &mut Dpy) -> crate::Result {
+        let res = display.send_request(DetachRequest {
+            shmseg: self.seg,
+            ..Default::default()
+        });
+        unsafe { libc::shmdt(self.addr as *const _) };
+        res?;
+        Ok(())
+    }
+}
+
+/// Tell whether or not the server supports the MIT-SHM extension.
+#[inline]
+fn shm_present<Dpy: Display + ?Sized>(display: &mut Dpy) -> crate::Result<bool> {
+    match display.exchange_request(QueryVersionRequest::default()) {
+        Ok(_) => Ok(true),
+        Err(BreadError::ExtensionNotPresent(_)) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Upload an image to a new pixmap through shared memory. Returns `None` if the image is too small to
+/// benefit from shared memory, or if shared memory is not available.
+#[inline]
+pub(crate) fn image_to_pixmap<Dpy: Display + ?Sized>(
+    display: &mut Dpy,
+    target: Drawable,
+    image: &Image<Box<[u8]>>,
+) -> crate::Result<Option<Pixmap>> {
+    let len = image.data.len();
+    if len < SHM_THRESHOLD || !shm_present(display)? {
+        return Ok(None);
+    }
+
+    let segment = match Segment::new(display, len)? {
+        Some(segment) => segment,
+        None => return Ok(None),
+    };
+    unsafe { slice::from_raw_parts_mut(segment.addr, len) }.copy_from_slice(&image.data);
+
+    let pixmap = display.create_pixmap(target, image.width as _, image.height as _, image.depth)?;
+    let gc = display.create_gc(pixmap, Default::default())?;
+    display.send_request(PutImageRequest {
+        drawable: pixmap.into(),
+        gc,
+        total_width: image.width as _,
+        total_height: image.height as _,
+        src_width: image.width as _,
+        src_height: image.height as _,
+        depth: image.depth,
+        format: XImageFormat::ZPixmap as _,
+        shmseg: segment.seg,
+        ..Default::default()
+    })?;
+    gc.free(display)?;
+
+    // requests are processed in order, so the server is done reading the image before it detaches
+    segment.detach(display)?;
+
+    Ok(Some(pixmap))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ImageFormat;
+    use breadx::DisplayConnection;
+
+    #[test]
+    #[ignore = "needs an X server"]
+    fn large_image_blits_through_shm() {
+        let mut conn = DisplayConnection::create(None, None).unwrap();
+        let root = conn.default_root();
+        let bytes: Vec<u8> = (0..256 * 256)
+            .flat_map(|i| vec![(i % 256) as u8, (i / 256) as u8, 0x80, 0xFF])
+            .collect();
+        let (image, _, _) = crate::breadx::image::breadx_image(
+            &mut conn,
+            root.into(),
+            &bytes,
+            256,
+            256,
+            ImageFormat::Rgba,
+        )
+        .unwrap();
+        assert!(image.data.len() >= SHM_THRESHOLD);
+
+        let pixmap = image_to_pixmap(&mut conn, root.into(), &image).unwrap();
+        if !shm_present(&mut conn).unwrap() {
+            assert!(pixmap.is_none());
+            return;
+        }
+
+        let pixmap = pixmap.expect("the image should be uploaded through shared memory");
+        let readback = conn
+            .get_image_immediate(pixmap, 0, 0, 256, 256, !0, XImageFormat::ZPixmap)
+            .unwrap();
+        for (x, y) in [(0, 0), (17, 3), (255, 255)] {
+            assert_eq!(readback.pixel(x, y), image.pixel(x, y));
+        }
+    }
+}
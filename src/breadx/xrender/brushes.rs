@@ -26,6 +26,9 @@ use lyon_geom::Angle;
 use std::collections::hash_map::{Entry, HashMap};
 use tinyvec::TinyVec;
 
+#[cfg(feature = "async")]
+use breadx::display::AsyncDisplay;
+
 /// A container for "brushes" (e.g. things we use to composite against the mask) that cleans itself up if it
 /// allocates too much memory.
 #[derive(Debug)]
@@ -62,6 +65,18 @@ impl Brushes {
             .try_for_each(|(_, Collected { inner, .. })| inner.free(display))
     }
 
+    #[cfg(feature = "async")]
+    #[inline]
+    pub(crate) async fn free_async<D: AsyncDisplay + ?Sized>(
+        self,
+        display: &mut D,
+    ) -> crate::Result {
+        for (_, Collected { inner, .. }) in self.brushes {
+            inner.free_async(display).await?;
+        }
+        Ok(())
+    }
+
     #[inline]
     pub(crate) fn fill<D: Display + ?Sized>(
         &mut self,
@@ -207,7 +222,8 @@ impl Brushes {
 // helper function to get stops and color from a gradient
 #[inline]
 fn gradient_to_stops_and_color(grad: &Gradient) -> (TinyVec<[Fixed; 6]>, TinyVec<[XrColor; 3]>) {
-    grad.iter()
+    grad.srgb_stops()
+        .iter()
         .map(|r| {
             (
                 double_to_fixed(r.position.into_inner().into()),
@@ -34,9 +34,7 @@ pub(crate) fn image_to_pixmap_picture<Dpy: Display + ?Sized>(
     )?;
 
     // create the pixmap
-    let pixmap = display
-        .inner_mut()
-        .create_pixmap_from_image(target, &image)?;
+    let pixmap = crate::breadx::image::pixmap_from_image(display.inner_mut(), target, &image)?;
 
     // if this is not an alpha-channel, we're finished after we turn this into a pixmap-picture
     if format.has_alpha_component() {
@@ -1,19 +1,23 @@
 // MIT/Apache2 License
 
 use crate::{
-    fill::FillRule,
+    fill::{FillMode, FillRule, ImagePattern, TileMode},
     gradient::Gradient,
-    surface::{Surface, SurfaceFeatures},
-    util::DebugContainer,
+    path_to_lines,
+    stroke::{dash_lines, LineCap, LineJoin},
+    surface::{Surface, SurfaceFeatures, DEFAULT_MITER_LIMIT, DEFAULT_TOLERANCE, MIN_TOLERANCE},
+    transform::Transform,
+    util::{intersect_clip, DebugContainer},
     Color, Image, ImageFormat,
 };
 use breadx::{
     auto::{
         render::{
-            Color as XrColor, Fixed, Linefix, PictOp, Pictformat, Picture, Pointfix, Repeat,
-            Trapezoid, Triangle,
+            Color as XrColor, Fixed, Linefix, PictOp, Pictformat, Picture, Pointfix, PolyEdge,
+            Repeat, SetPictureClipRectanglesRequest, SetPictureTransformRequest,
+            Transform as XrTransform, Trapezoid, Triangle,
         },
-        xproto::{Rectangle as XRectangle, Window},
+        xproto::{GetGeometryReply, Rectangle as XRectangle, Window},
     },
     display::{prelude::*, Display, DisplayBase},
     render::{
@@ -25,8 +29,9 @@ use breadx::{
 use lyon_geom::{Angle, LineSegment, Point, Rect, Size};
 use lyon_path::{Path, PathBuffer, PathBufferSlice, PathEvent, PathSlice};
 use lyon_tessellation::{
-    BuffersBuilder, FillTessellator, FillVertex, FillVertexConstructor, LineCap, LineJoin,
-    StrokeOptions, StrokeTessellator, StrokeVertex, StrokeVertexConstructor, VertexBuffers,
+    BuffersBuilder, FillOptions, FillRule as LyonFillRule, FillTessellator, FillVertex,
+    FillVertexConstructor, LineCap as LyonLineCap, LineJoin as LyonLineJoin, StrokeOptions,
+    StrokeTessellator, StrokeVertex, StrokeVertexConstructor, VertexBuffers,
 };
 use ordered_float::NotNan;
 use std::{
@@ -53,6 +58,15 @@ const FEATURES: SurfaceFeatures = SurfaceFeatures {
     transparency: true,
     gradients: true,
     floats: true,
+    clipping: true,
+    transforms: true,
+    paths: true,
+    text: false,
+    images: true,
+    patterns: true,
+    size: true,
+    antialiasing: true,
+    dirty_tracking: false,
 };
 const XCLR_TRANS: XrColor = XrColor {
     red: 0,
@@ -99,6 +113,8 @@ pub struct RenderBreadxSurface<'dpy, Dpy: ?Sized> {
 
     // we draw shapes onto this picture to use as a mask
     mask: PixmapPicture,
+    // whether the mask currently rasterizes shapes with smooth edges
+    mask_antialiased: bool,
 
     // a 1x1 image containing solid black, used for drawing shapes on the mask
     solid: PixmapPicture,
@@ -110,6 +126,27 @@ pub struct RenderBreadxSurface<'dpy, Dpy: ?Sized> {
     stroke_color: XrColor,
     fill: FillRule,
     line_width: i32,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    miter_limit: f32,
+    dash: Vec<f32>,
+    dash_offset: f32,
+    fill_mode: FillMode,
+    antialias: bool,
+    // opacity multiplied into everything that is drawn
+    alpha: f32,
+
+    // tolerance used when tesselating and flattening curves
+    tolerance: f32,
+
+    // stack of clipping rectangles, each intersected with the one below it
+    clips: Vec<Rect<f32>>,
+
+    // XRender can't transform triangles, so we apply this to vertices before we send them
+    transform: Transform,
+
+    // stack of states saved by save()
+    saved: Vec<SavedState>,
 
     // map associating images to pixmaps containing those images
     images: Option<HashMap<Image, PixmapPicture>>,
@@ -120,6 +157,24 @@ pub struct RenderBreadxSurface<'dpy, Dpy: ?Sized> {
     dropper: DebugContainer<fn(&mut RenderBreadxSurface<'dpy, Dpy>)>,
 }
 
+/// Drawing state saved by `save()`.
+#[derive(Debug)]
+struct SavedState {
+    stroke_color: XrColor,
+    fill: FillRule,
+    line_width: i32,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    miter_limit: f32,
+    dash: Vec<f32>,
+    dash_offset: f32,
+    fill_mode: FillMode,
+    antialias: bool,
+    alpha: f32,
+    clips: Vec<Rect<f32>>,
+    transform: Transform,
+}
+
 /// Tesselation helper struct.
 #[derive(Debug)]
 struct Tesselation {
@@ -131,11 +186,11 @@ struct Tesselation {
     stroke_tesselator: DebugContainer<StrokeTessellator>,
 }
 
-struct PointfixCvt;
+struct PointfixCvt(Transform);
 
 impl FillVertexConstructor<Pointfix> for PointfixCvt {
     fn new_vertex(&mut self, vert: FillVertex<'_>) -> Pointfix {
-        let p = vert.position();
+        let p = self.0.transform_point(vert.position());
         Pointfix {
             x: double_to_fixed(p.x.into()),
             y: double_to_fixed(p.y.into()),
@@ -145,7 +200,7 @@ impl FillVertexConstructor<Pointfix> for PointfixCvt {
 
 impl StrokeVertexConstructor<Pointfix> for PointfixCvt {
     fn new_vertex(&mut self, vert: StrokeVertex<'_, '_>) -> Pointfix {
-        let p = vert.position();
+        let p = self.0.transform_point(vert.position());
         Pointfix {
             x: double_to_fixed(p.x.into()),
             y: double_to_fixed(p.y.into()),
@@ -173,6 +228,7 @@ pub(crate) enum FillRuleKey {
 #[derive(Debug)]
 pub struct RenderResidual {
     mask: PixmapPicture,
+    mask_antialiased: bool,
     solid: PixmapPicture,
     brushes: Option<Brushes>,
     a8_format: Pictformat,
@@ -209,6 +265,9 @@ impl RenderResidual {
         self.mask.free_async(display).await?;
         self.solid.free_async(display).await?;
         self.brushes.take().unwrap().free_async(display).await?;
+        for (_, pic) in self.images.take().unwrap() {
+            pic.free_async(display).await?;
+        }
         let _ = self.tesselation.take();
         mem::forget(self);
         Ok(())
@@ -306,16 +365,22 @@ impl PixmapPicture {
     ) -> crate::Result<PixmapPicture> {
         let pixmap = display
             .inner_mut()
-            .create_pixmap_async(parent, width, height, dpeth.into())
+            .create_pixmap_async(parent, width, height, depth.into())
             .await?;
         let format = display
             .find_standard_format(format)
             .expect("Format not available");
+        let params = if repeat {
+            PictureParameters {
+                repeat: Some(Repeat::Normal),
+                ..Default::default()
+            }
+        } else {
+            Default::default()
+        };
         let pp = PixmapPicture {
             pixmap,
-            picture: display
-                .create_picture_async(pixmap, format, Default::default())
-                .await?,
+            picture: display.create_picture_async(pixmap, format, params).await?,
         };
 
         pp.picture
@@ -485,6 +550,7 @@ impl<'dpy, Dpy: ?Sized> RenderBreadxSurface<'dpy, Dpy> {
     pub fn into_residual(mut self) -> RenderResidual {
         let res = RenderResidual {
             mask: self.mask,
+            mask_antialiased: self.mask_antialiased,
             solid: self.solid,
             brushes: Some(self.brushes.take().expect("NPP")),
             width: self.width,
@@ -519,6 +585,7 @@ impl<'dpy, Dpy: Display + ?Sized> RenderBreadxSurface<'dpy, Dpy> {
             residual.mask.free(display.inner_mut())?;
             residual.mask =
                 PixmapPicture::new_a8(display, width, height, XCLR_TRANS, parent.into(), false)?;
+            residual.mask_antialiased = true;
         }
 
         let this = Self {
@@ -532,10 +599,23 @@ impl<'dpy, Dpy: Display + ?Sized> RenderBreadxSurface<'dpy, Dpy> {
             window_format: residual.window_format,
             target: picture,
             mask: residual.mask,
+            mask_antialiased: residual.mask_antialiased,
             solid: residual.solid,
             stroke_color: XCLR_BLACK,
             fill: FillRule::SolidColor(Color::BLACK),
             line_width: 1,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            miter_limit: DEFAULT_MITER_LIMIT,
+            dash: vec![],
+            dash_offset: 0.0,
+            fill_mode: FillMode::EvenOdd,
+            antialias: true,
+            alpha: 1.0,
+            tolerance: DEFAULT_TOLERANCE,
+            clips: vec![],
+            transform: Transform::identity(),
+            saved: vec![],
             brushes: residual.brushes.take(),
             tesselation: residual.tesselation.take(),
             images: residual.images.take(),
@@ -580,6 +660,7 @@ impl<'dpy, Dpy: Display + ?Sized> RenderBreadxSurface<'dpy, Dpy> {
             height,
             RenderResidual {
                 mask,
+                mask_antialiased: true,
                 solid,
                 brushes: Some(Brushes::new()),
                 width,
@@ -639,6 +720,10 @@ impl<'dpy, Dpy: Display + ?Sized> RenderBreadxSurface<'dpy, Dpy> {
     #[inline]
     fn fill_picture(&mut self, width: i32, height: i32) -> crate::Result<Picture> {
         let key = match &self.fill {
+            FillRule::Pattern(pattern) => {
+                let pattern = *pattern;
+                return self.pattern_picture(pattern);
+            }
             FillRule::SolidColor(clr) => FillRuleKey::Color(cvt_color(*clr)),
             FillRule::LinearGradient(grad, angle) => FillRuleKey::LinearGradient(
                 grad.to_owned(),
@@ -663,6 +748,169 @@ impl<'dpy, Dpy: Display + ?Sized> RenderBreadxSurface<'dpy, Dpy> {
         )
     }
 
+    /// Set up an image's picture so that it can be used as the source for a pattern fill.
+    #[inline]
+    fn pattern_picture(&mut self, pattern: ImagePattern) -> crate::Result<Picture> {
+        let picture = self
+            .images
+            .as_ref()
+            .expect("NPP")
+            .get(&pattern.image)
+            .ok_or(crate::Error::ImageNotAvailable)?
+            .picture;
+
+        let repeat = match pattern.tiling {
+            TileMode::Tile => Repeat::Normal,
+            TileMode::Clamp => Repeat::Pad,
+            TileMode::Mirror => Repeat::Reflect,
+        };
+        picture.change(
+            self.display.inner_mut(),
+            PictureParameters {
+                repeat: Some(repeat),
+                ..Default::default()
+            },
+        )?;
+
+        // XRender transforms map destination coordinates to source coordinates, so use the inverse
+        let transform = pattern
+            .transform
+            .unwrap_or_default()
+            .inverse()
+            .unwrap_or_default();
+        self.set_picture_transform(picture, transform)?;
+
+        Ok(picture)
+    }
+
+    /// Set the transform that maps destination coordinates to source coordinates for a picture.
+    #[inline]
+    fn set_picture_transform(&mut self, picture: Picture, transform: Transform) -> crate::Result {
+        let transform = transform.into_inner();
+        self.display
+            .inner_mut()
+            .exchange_request(SetPictureTransformRequest {
+                picture,
+                transform: XrTransform {
+                    matrix11: double_to_fixed(transform.m11 as f64),
+                    matrix12: double_to_fixed(transform.m21 as f64),
+                    matrix13: double_to_fixed(transform.m31 as f64),
+                    matrix21: double_to_fixed(transform.m12 as f64),
+                    matrix22: double_to_fixed(transform.m22 as f64),
+                    matrix23: double_to_fixed(transform.m32 as f64),
+                    matrix33: 1 << 16,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })?;
+        Ok(())
+    }
+
+    /// Get the pixmap-picture backing an image, as well as the image's size.
+    #[inline]
+    fn image_and_size(&mut self, image: &Image) -> crate::Result<(PixmapPicture, Size<f32>)> {
+        let pp = *self
+            .images
+            .as_ref()
+            .expect("NPP")
+            .get(image)
+            .ok_or(crate::Error::ImageNotAvailable)?;
+
+        let geom_key = self.display.inner_mut().get_drawable_geometry(pp.pixmap)?;
+        let GetGeometryReply { width, height, .. } =
+            self.display.inner_mut().resolve_request(geom_key)?;
+        Ok((pp, Size::new(width as _, height as _)))
+    }
+
+    /// Composite the `src` area of an image onto the `dst` area of the target, scaling it to fit.
+    #[inline]
+    fn composite_image_region(
+        &mut self,
+        pp: PixmapPicture,
+        src: Rect<f32>,
+        dst: Rect<f32>,
+    ) -> crate::Result {
+        if dst.is_empty() {
+            return Ok(());
+        }
+
+        // map the destination area onto the source area
+        self.set_picture_transform(
+            pp.picture,
+            Transform::scale(
+                src.size.width / dst.size.width,
+                src.size.height / dst.size.height,
+            )
+            .then(&Transform::translation(src.origin.x, src.origin.y)),
+        )?;
+
+        let origin = self.transform.transform_point(dst.origin);
+        let mask = self.alpha_mask()?;
+        let res = pp.picture.composite(
+            self.display.inner_mut(),
+            PictOp::Over,
+            mask,
+            self.target,
+            0,
+            0,
+            origin.x as _,
+            origin.y as _,
+            origin.x as _,
+            origin.y as _,
+            dst.size.width as _,
+            dst.size.height as _,
+        );
+
+        self.set_picture_transform(pp.picture, Transform::identity())?;
+        res?;
+        Ok(())
+    }
+
+    /// Get the mask that images are composited with, which is empty unless the global alpha is translucent. The
+    /// mask covers the whole target, so it should be offset by the destination coordinates.
+    #[inline]
+    fn alpha_mask(&mut self) -> crate::Result<Picture> {
+        if self.alpha >= 1.0 {
+            return Ok(Picture::const_from_xid(0));
+        }
+
+        self.mask.picture.fill_rectangles(
+            self.display.inner_mut(),
+            PictOp::Src,
+            alpha_color(self.alpha),
+            [XRectangle {
+                x: 0,
+                y: 0,
+                width: self.width,
+                height: self.height,
+            }]
+            .as_ref(),
+        )?;
+        Ok(self.mask.picture)
+    }
+
+    /// Undo the changes made by `pattern_picture`, so that the image can be copied normally.
+    #[inline]
+    fn reset_pattern_picture(&mut self) -> crate::Result {
+        let image = match self.fill {
+            FillRule::Pattern(ImagePattern { image, .. }) => image,
+            _ => return Ok(()),
+        };
+        let picture = match self.images.as_ref().expect("NPP").get(&image) {
+            Some(pp) => pp.picture,
+            None => return Ok(()),
+        };
+
+        picture.change(
+            self.display.inner_mut(),
+            PictureParameters {
+                repeat: Some(Repeat::None),
+                ..Default::default()
+            },
+        )?;
+        self.set_picture_transform(picture, Transform::identity())
+    }
+
     #[inline]
     fn fill_triangles(
         &mut self,
@@ -675,6 +923,22 @@ impl<'dpy, Dpy: Display + ?Sized> RenderBreadxSurface<'dpy, Dpy> {
             return Ok(());
         }
 
+        // the mask's edge mode decides whether the triangles drawn onto it are antialiased
+        if self.mask_antialiased != self.antialias {
+            self.mask.picture.change(
+                self.display.inner_mut(),
+                PictureParameters {
+                    poly_edge: Some(if self.antialias {
+                        PolyEdge::Smooth
+                    } else {
+                        PolyEdge::Sharp
+                    }),
+                    ..Default::default()
+                },
+            )?;
+            self.mask_antialiased = self.antialias;
+        }
+
         // clear the mask
         self.mask.picture.fill_rectangles(
             self.display.inner_mut(),
@@ -700,6 +964,22 @@ impl<'dpy, Dpy: Display + ?Sized> RenderBreadxSurface<'dpy, Dpy> {
             triangles,
         )?;
 
+        // scale the coverage of the mask by the global alpha
+        if self.alpha < 1.0 {
+            self.mask.picture.fill_rectangles(
+                self.display.inner_mut(),
+                PictOp::In,
+                alpha_color(self.alpha),
+                [XRectangle {
+                    x: 0,
+                    y: 0,
+                    width: self.width,
+                    height: self.height,
+                }]
+                .as_ref(),
+            )?;
+        }
+
         // use the mask to copy the trapezoids and the desired color onto the destination picture
         source.composite(
             self.display.inner_mut(),
@@ -720,11 +1000,73 @@ impl<'dpy, Dpy: Display + ?Sized> RenderBreadxSurface<'dpy, Dpy> {
         Ok(())
     }
 
+    #[inline]
+    fn apply_clip(&mut self) -> crate::Result {
+        match self.clips.last() {
+            Some(clip) => {
+                let rectangles = if clip.is_empty() {
+                    vec![]
+                } else {
+                    vec![XRectangle {
+                        x: clip.origin.x as _,
+                        y: clip.origin.y as _,
+                        width: clip.size.width.ceil() as _,
+                        height: clip.size.height.ceil() as _,
+                    }]
+                };
+                self.display
+                    .inner_mut()
+                    .exchange_request(SetPictureClipRectanglesRequest {
+                        picture: self.target,
+                        rectangles: rectangles.into(),
+                        ..Default::default()
+                    })?;
+            }
+            None => {
+                self.target.change(
+                    self.display.inner_mut(),
+                    PictureParameters {
+                        clipmask: Some(Pixmap::default()),
+                        ..Default::default()
+                    },
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
     #[inline]
     fn draw_lines_internal<I: IntoIterator<Item = LineSegment<f32>>>(
         &mut self,
         lines: I,
     ) -> crate::Result {
+        // break the lines up into dashes, if we have a dash pattern
+        let lines: Vec<LineSegment<f32>> = if self.dash.is_empty() {
+            lines.into_iter().collect()
+        } else {
+            dash_lines(lines, &self.dash, self.dash_offset)
+        };
+
+        // the fast path below only produces untransformed butt caps, so use the tesselator for anything else
+        if self.line_cap != LineCap::Butt || !self.transform.is_identity() {
+            let triangles = self.tesselate_stroke_path(lines.into_iter().flat_map(
+                |LineSegment { from, to }| {
+                    IntoIterator::into_iter([
+                        PathEvent::Begin { at: from },
+                        PathEvent::Line { from, to },
+                        PathEvent::End {
+                            last: to,
+                            first: from,
+                            close: false,
+                        },
+                    ])
+                },
+            ))?;
+            let src = self.stroke_picture()?;
+            return self.fill_triangles(triangles, src, 0, 0);
+        }
+
         let src = self.stroke_picture()?;
         let line_width = self.line_width;
         let triangles: Vec<Triangle> = lines
@@ -739,9 +1081,22 @@ impl<'dpy, Dpy: Display + ?Sized> RenderBreadxSurface<'dpy, Dpy> {
         &mut self,
         rects: I,
     ) -> crate::Result {
-        // fast path: if all we have are solid colors, just use fill_rectangles()
-        if let FillRule::SolidColor(clr) = self.fill {
-            let clr = cvt_color(clr);
+        // transformed rectangles may no longer be axis-aligned, so fill them as paths instead
+        if !self.transform.is_identity() {
+            return rects.into_iter().try_for_each(|rect| {
+                let mut builder = Path::builder();
+                builder.begin(rect.min());
+                builder.line_to(Point::new(rect.max_x(), rect.min_y()));
+                builder.line_to(rect.max());
+                builder.line_to(Point::new(rect.min_x(), rect.max_y()));
+                builder.close();
+                self.fill_path(builder.build().iter())
+            });
+        }
+
+        // fast path: if all we have are opaque solid colors, just use fill_rectangles()
+        if let (FillRule::SolidColor(clr), true) = (&self.fill, self.alpha >= 1.0) {
+            let clr = cvt_color(*clr);
             let rects: Vec<XRectangle> = rects
                 .into_iter()
                 .map(
@@ -762,9 +1117,7 @@ impl<'dpy, Dpy: Display + ?Sized> RenderBreadxSurface<'dpy, Dpy> {
         }
 
         // slow path: convert every rectangle to two triangles and then composite it
-        let rects: Vec<Rect<f32>> = rects
-            .into_iter()
-            .collect();
+        let rects: Vec<Rect<f32>> = rects.into_iter().collect();
         if rects.is_empty() {
             return Ok(());
         }
@@ -857,7 +1210,8 @@ impl<'dpy, Dpy: Display + ?Sized> RenderBreadxSurface<'dpy, Dpy> {
             .collect();
 
         let fill = self.fill_picture(max_x - min_x, max_y - min_y)?;
-        self.fill_triangles(triangles, fill, -min_x as i16, -min_y as i16)
+        self.fill_triangles(triangles, fill, -min_x as i16, -min_y as i16)?;
+        self.reset_pattern_picture()
     }
 
     #[inline]
@@ -866,18 +1220,29 @@ impl<'dpy, Dpy: Display + ?Sized> RenderBreadxSurface<'dpy, Dpy> {
         path: impl Iterator<Item = PathEvent>,
     ) -> crate::Result<Vec<Triangle>> {
         // use lyon_tesselate to tesselate the stroke
-        let mut stroke_options = StrokeOptions::default();
-        stroke_options.start_cap = LineCap::Butt;
-        stroke_options.end_cap = LineCap::Butt;
-        stroke_options.line_join = LineJoin::Miter;
+        let mut stroke_options = StrokeOptions::default().with_tolerance(self.tolerance);
+        let line_cap = match self.line_cap {
+            LineCap::Butt => LyonLineCap::Butt,
+            LineCap::Round => LyonLineCap::Round,
+            LineCap::Square => LyonLineCap::Square,
+        };
+        stroke_options.start_cap = line_cap;
+        stroke_options.end_cap = line_cap;
+        stroke_options.line_join = match self.line_join {
+            LineJoin::Miter => LyonLineJoin::Miter,
+            LineJoin::Round => LyonLineJoin::Round,
+            LineJoin::Bevel => LyonLineJoin::Bevel,
+        };
         stroke_options.line_width = self.line_width as f32;
+        stroke_options.miter_limit = self.miter_limit;
 
+        let transform = self.transform;
         let mut tesselate = self.tesselation.as_mut().expect("NPP");
 
         tesselate.buffers.vertices.clear();
         tesselate.buffers.indices.clear();
 
-        let mut buffer = BuffersBuilder::new(&mut tesselate.buffers, PointfixCvt);
+        let mut buffer = BuffersBuilder::new(&mut tesselate.buffers, PointfixCvt(transform));
         tesselate
             .stroke_tesselator
             .tessellate(path, &stroke_options, &mut buffer)
@@ -903,6 +1268,12 @@ impl<'dpy, Dpy: Display + ?Sized> RenderBreadxSurface<'dpy, Dpy> {
 
     #[inline]
     fn stroke_path(&mut self, path: impl Iterator<Item = PathEvent>) -> crate::Result {
+        // dashed paths are flattened and drawn as a series of lines
+        if !self.dash.is_empty() {
+            let lines: Vec<LineSegment<f32>> = path_to_lines(path, self.tolerance()).collect();
+            return self.draw_lines_internal(lines);
+        }
+
         let triangles = self.tesselate_stroke_path(path)?;
         let fill = self.stroke_picture()?;
         self.fill_triangles(triangles, fill, 0, 0)
@@ -913,14 +1284,21 @@ impl<'dpy, Dpy: Display + ?Sized> RenderBreadxSurface<'dpy, Dpy> {
         &mut self,
         path: impl Iterator<Item = PathEvent>,
     ) -> crate::Result<FillPathInfo> {
+        let transform = self.transform;
+        let options = FillOptions::default()
+            .with_tolerance(self.tolerance)
+            .with_fill_rule(match self.fill_mode {
+                FillMode::EvenOdd => LyonFillRule::EvenOdd,
+                FillMode::NonZero => LyonFillRule::NonZero,
+            });
         let mut tesselate = self.tesselation.as_mut().expect("NPP");
         tesselate.buffers.vertices.clear();
         tesselate.buffers.indices.clear();
 
-        let mut buffer = BuffersBuilder::new(&mut tesselate.buffers, PointfixCvt);
+        let mut buffer = BuffersBuilder::new(&mut tesselate.buffers, PointfixCvt(transform));
         tesselate
             .fill_tesselator
-            .tessellate(path, &Default::default(), &mut buffer)
+            .tessellate(path, &options, &mut buffer)
             .map_err(|e| {
                 log::error!("Tesselation error occurred: {:?}", e);
                 crate::Error::FailedToTesselate
@@ -982,7 +1360,8 @@ impl<'dpy, Dpy: Display + ?Sized> RenderBreadxSurface<'dpy, Dpy> {
             fill,
             -fixed_to_double(min_x) as i16,
             -fixed_to_double(min_y) as i16,
-        )
+        )?;
+        self.reset_pattern_picture()
     }
 }
 
@@ -999,58 +1378,100 @@ struct FillPathInfo {
 impl<'dpy, Dpy: AsyncDisplay + ?Sized> RenderBreadxSurface<'dpy, Dpy> {
     /// Create a new RenderBreadxSurface from residiual leftover, async redox.
     #[inline]
-    pub async fn from_residual_async<Target: Into<Drawable>>(
+    pub async fn from_residual_async(
         display: &'dpy mut RenderDisplay<Dpy>,
         picture: Picture,
-        parent: Target,
+        parent: Window,
         width: u16,
         height: u16,
         mut residual: RenderResidual,
     ) -> crate::Result<RenderBreadxSurface<'dpy, Dpy>> {
         let old_checked = display.inner_mut().checked();
-        display.inner_mut().set_checked(true);
-        let parent: Drawable = parent.into();
+        display.inner_mut().set_checked(false);
 
         // if the width and height doesn't match up, create a new mask
         if width != residual.width || height != residual.height {
             residual.mask.free_async(display.inner_mut()).await?;
-            residual.mask =
-                PixmapPicture::new_a8_async(display, width, height, XCLR_TRANS, parent, false)
-                    .await?;
+            residual.mask = PixmapPicture::new_a8_async(
+                display,
+                width,
+                height,
+                XCLR_TRANS,
+                parent.into(),
+                false,
+            )
+            .await?;
+            residual.mask_antialiased = true;
         }
 
-        Ok(Self {
+        let this = Self {
             width,
             height,
+            depth: residual.depth,
             display,
             old_checked,
             parent,
+            a8_format: residual.a8_format,
+            window_format: residual.window_format,
             target: picture,
             mask: residual.mask,
+            mask_antialiased: residual.mask_antialiased,
             solid: residual.solid,
-            stroke: Color::BLACK,
-            stroke_applied: true,
+            stroke_color: XCLR_BLACK,
             fill: FillRule::SolidColor(Color::BLACK),
-            next_gc: residual.next_gc,
+            line_width: 1,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            miter_limit: DEFAULT_MITER_LIMIT,
+            dash: vec![],
+            dash_offset: 0.0,
+            fill_mode: FillMode::EvenOdd,
+            antialias: true,
+            alpha: 1.0,
+            tolerance: DEFAULT_TOLERANCE,
+            clips: vec![],
+            transform: Transform::identity(),
+            saved: vec![],
             brushes: residual.brushes.take(),
+            tesselation: residual.tesselation.take(),
+            images: residual.images.take(),
             dropper: DebugContainer::new(Dropper::<'dpy, Dpy>::async_dropper),
-        })
+        };
+
+        mem::forget(residual);
+
+        Ok(this)
     }
 
+    /// Create a new RenderBreadxSurface, async redox.
     #[inline]
-    pub async fn new_async<Target: Into<Drawable>>(
+    pub async fn new_async(
         display: &'dpy mut RenderDisplay<Dpy>,
         picture: Picture,
-        parent: Target,
+        parent: Window,
         width: u16,
         height: u16,
+        depth: u8,
     ) -> crate::Result<RenderBreadxSurface<'dpy, Dpy>> {
-        let parent: Drawable = parent.into();
         let mask =
-            PixmapPicture::new_a8_async(display, width, height, XCLR_TRANS, parent, false).await?;
-        let solid =
-            PixmapPicture::new_argb32_async(display, width, height, XCLR_WHITE, parent, true)
+            PixmapPicture::new_a8_async(display, width, height, XCLR_TRANS, parent.into(), false)
                 .await?;
+        let solid =
+            PixmapPicture::new_a8_async(display, 1, 1, XCLR_WHITE, parent.into(), true).await?;
+
+        let window_attrs = parent.window_attributes_immediate_async(display).await?;
+        let window_visual = window_attrs.visual;
+        let window_visual = display
+            .visual_id_to_visual(window_visual)
+            .expect("Window visual does not exist");
+        let window_format = display
+            .find_visual_format(window_visual)
+            .expect("Window format does not exist");
+
+        let a8_format = display
+            .find_standard_format(StandardFormat::A8)
+            .expect("No A8 format");
+
         Self::from_residual_async(
             display,
             picture,
@@ -1059,11 +1480,20 @@ impl<'dpy, Dpy: AsyncDisplay + ?Sized> RenderBreadxSurface<'dpy, Dpy> {
             height,
             RenderResidual {
                 mask,
+                mask_antialiased: true,
                 solid,
-                next_gc: 32,
-                brushes: Some(HashMap::new()),
+                brushes: Some(Brushes::new()),
                 width,
                 height,
+                depth,
+                window_format,
+                a8_format,
+                tesselation: Some(Tesselation {
+                    buffers: VertexBuffers::new(),
+                    fill_tesselator: FillTessellator::new().into(),
+                    stroke_tesselator: StrokeTessellator::new().into(),
+                }),
+                images: Some(HashMap::new()),
             },
         )
         .await
@@ -1072,8 +1502,14 @@ impl<'dpy, Dpy: AsyncDisplay + ?Sized> RenderBreadxSurface<'dpy, Dpy> {
     #[inline]
     async fn free_internal_async(&mut self) -> crate::Result {
         self.mask.free_async(self.display.inner_mut()).await?;
-        for v in self.brushes.take().unwrap().values() {
-            v.free_async(self.display.inner_mut()).await?;
+        self.solid.free_async(self.display.inner_mut()).await?;
+        self.brushes
+            .take()
+            .unwrap()
+            .free_async(self.display.inner_mut())
+            .await?;
+        for (_, image) in self.images.take().unwrap() {
+            image.free_async(self.display.inner_mut()).await?;
         }
         self.display.inner_mut().set_checked(self.old_checked);
         Ok(())
@@ -1106,18 +1542,166 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for RenderBreadxSurface<'dpy, Dpy> {
         Ok(())
     }
 
+    #[inline]
+    fn fill_rule(&self) -> Option<&FillRule> {
+        Some(&self.fill)
+    }
+
     #[inline]
     fn set_line_width(&mut self, width: usize) -> crate::Result {
         self.line_width = width as _;
         Ok(())
     }
 
+    #[inline]
+    fn tolerance(&self) -> f32 {
+        self.tolerance
+    }
+
+    #[inline]
+    fn set_tolerance(&mut self, tolerance: f32) -> crate::Result {
+        self.tolerance = tolerance.max(MIN_TOLERANCE);
+        Ok(())
+    }
+
+    #[inline]
+    fn set_line_cap(&mut self, cap: LineCap) -> crate::Result {
+        self.line_cap = cap;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_line_join(&mut self, join: LineJoin) -> crate::Result {
+        self.line_join = join;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_miter_limit(&mut self, limit: f32) -> crate::Result {
+        self.miter_limit = limit.max(1.0);
+        Ok(())
+    }
+
+    #[inline]
+    fn set_fill_mode(&mut self, mode: FillMode) -> crate::Result {
+        self.fill_mode = mode;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_dash(&mut self, pattern: &[f32], offset: f32) -> crate::Result {
+        self.dash.clear();
+        self.dash.extend_from_slice(pattern);
+        self.dash_offset = offset;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_antialias(&mut self, on: bool) -> crate::Result {
+        self.antialias = on;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_global_alpha(&mut self, alpha: f32) -> crate::Result {
+        self.alpha = alpha.clamp(0.0, 1.0);
+        Ok(())
+    }
+
+    #[inline]
+    fn push_clip(&mut self, rect: Rect<f32>) -> crate::Result {
+        let rect = self.transform.transform_rect(&rect);
+        let clip = intersect_clip(self.clips.last(), rect);
+        self.clips.push(clip);
+        self.apply_clip()
+    }
+
+    #[inline]
+    fn pop_clip(&mut self) -> crate::Result {
+        self.clips.pop().ok_or(crate::Error::NoClip)?;
+        self.apply_clip()
+    }
+
+    #[inline]
+    fn set_transform(&mut self, transform: Transform) -> crate::Result {
+        self.transform = transform;
+        Ok(())
+    }
+
+    #[inline]
+    fn transform(&mut self, transform: Transform) -> crate::Result {
+        self.transform = transform.then(&self.transform);
+        Ok(())
+    }
+
+    #[inline]
+    fn save(&mut self) -> crate::Result {
+        self.saved.push(SavedState {
+            stroke_color: self.stroke_color,
+            fill: self.fill.clone(),
+            line_width: self.line_width,
+            line_cap: self.line_cap,
+            line_join: self.line_join,
+            miter_limit: self.miter_limit,
+            dash: self.dash.clone(),
+            dash_offset: self.dash_offset,
+            fill_mode: self.fill_mode,
+            antialias: self.antialias,
+            alpha: self.alpha,
+            clips: self.clips.clone(),
+            transform: self.transform,
+        });
+        Ok(())
+    }
+
+    #[inline]
+    fn restore(&mut self) -> crate::Result {
+        let SavedState {
+            stroke_color,
+            fill,
+            line_width,
+            line_cap,
+            line_join,
+            miter_limit,
+            dash,
+            dash_offset,
+            fill_mode,
+            antialias,
+            alpha,
+            clips,
+            transform,
+        } = self
+            .saved
+            .pop()
+            .ok_or(crate::Error::StaticMsg("No saved state to restore"))?;
+
+        self.stroke_color = stroke_color;
+        self.fill = fill;
+        self.line_width = line_width;
+        self.line_cap = line_cap;
+        self.line_join = line_join;
+        self.miter_limit = miter_limit;
+        self.dash = dash;
+        self.dash_offset = dash_offset;
+        self.fill_mode = fill_mode;
+        self.antialias = antialias;
+        self.alpha = alpha;
+        self.clips = clips;
+        self.transform = transform;
+        self.apply_clip()
+    }
+
     #[inline]
     fn flush(&mut self) -> crate::Result {
         self.display.inner_mut().synchronize()?;
         Ok(())
     }
 
+    #[inline]
+    fn size(&self) -> crate::Result<Size<f32>> {
+        Ok(Size::new(self.width as _, self.height as _))
+    }
+
     #[inline]
     fn create_image(
         &mut self,
@@ -1258,16 +1842,22 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for RenderBreadxSurface<'dpy, Dpy> {
         width: u32,
         height: u32,
     ) -> crate::Result {
+        let Point {
+            x: dst_x, y: dst_y, ..
+        } = self
+            .transform
+            .transform_point(Point::new(dst_x as f32, dst_y as f32));
         if let Some(src) = self.images.as_mut().expect("NPP").get(&src).copied() {
+            let mask = self.alpha_mask()?;
             src.picture.composite(
                 self.display.inner_mut(),
                 PictOp::Over,
-                Picture::const_from_xid(0),
+                mask,
                 self.target,
                 src_x as _,
                 src_y as _,
-                0,
-                0,
+                dst_x as _,
+                dst_y as _,
                 dst_x as _,
                 dst_y as _,
                 width as _,
@@ -1277,6 +1867,24 @@ impl<'dpy, Dpy: Display + ?Sized> Surface for RenderBreadxSurface<'dpy, Dpy> {
 
         Ok(())
     }
+
+    #[inline]
+    fn draw_image(&mut self, image: &Image, dst: Rect<f32>) -> crate::Result {
+        let (pp, size) = self.image_and_size(image)?;
+        self.composite_image_region(pp, Rect::new(Point::origin(), size), dst)
+    }
+
+    #[inline]
+    fn draw_image_region(
+        &mut self,
+        image: &Image,
+        src: Rect<f32>,
+        dst: Rect<f32>,
+    ) -> crate::Result {
+        let (pp, size) = self.image_and_size(image)?;
+        crate::image::check_image_region(src, size)?;
+        self.composite_image_region(pp, src, dst)
+    }
 }
 
 #[inline]
@@ -1348,6 +1956,15 @@ pub(crate) fn cvt_color(color: Color) -> XrColor {
     }
 }
 
+/// A color whose alpha is the given opacity, for scaling the alpha of a mask.
+#[inline]
+fn alpha_color(alpha: f32) -> XrColor {
+    XrColor {
+        alpha: (alpha.clamp(0.0, 1.0) * 65535.0).round() as u16,
+        ..XCLR_TRANS
+    }
+}
+
 #[inline]
 fn cvt_rect(rect: Rect<f32>) -> XRectangle {
     XRectangle {
@@ -1357,3 +1974,51 @@ fn cvt_rect(rect: Rect<f32>) -> XRectangle {
         height: rect.size.height as _,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use breadx::DisplayConnection;
+    use lyon_geom::point;
+
+    #[test]
+    #[ignore = "needs an X server"]
+    fn antialias_toggle_reaches_residual() {
+        let mut conn = DisplayConnection::create(None, None).unwrap();
+        let window = conn
+            .create_simple_window(conn.default_root(), 0, 0, 16, 16, 0, 0, 0)
+            .unwrap();
+        let depth = window.geometry_immediate(&mut conn).unwrap().depth;
+        let mut conn = RenderDisplay::new(conn, 0, 10).map_err(|(_, e)| e).unwrap();
+        let visual = window
+            .window_attributes_immediate(&mut conn)
+            .unwrap()
+            .visual;
+        let visual = conn.visual_id_to_visual(visual).unwrap();
+        let format = conn.find_visual_format(visual).unwrap();
+        let picture = conn
+            .create_picture(window, format, Default::default())
+            .unwrap();
+
+        let mut surface =
+            RenderBreadxSurface::new(&mut conn, picture, window, 16, 16, depth).unwrap();
+        assert!(surface.antialias);
+        surface.set_antialias(false).unwrap();
+        surface
+            .fill_polygon(&[point(0.0, 0.0), point(16.0, 0.0), point(0.0, 16.0)])
+            .unwrap();
+        let residual = surface.into_residual();
+        assert!(!residual.mask_antialiased);
+
+        let mut surface =
+            RenderBreadxSurface::from_residual(&mut conn, picture, window, 16, 16, residual)
+                .unwrap();
+        surface.set_antialias(true).unwrap();
+        surface
+            .fill_polygon(&[point(0.0, 0.0), point(16.0, 0.0), point(0.0, 16.0)])
+            .unwrap();
+        let residual = surface.into_residual();
+        assert!(residual.mask_antialiased);
+        residual.free(&mut conn).unwrap();
+    }
+}
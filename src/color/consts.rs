@@ -0,0 +1,26 @@
+// MIT/Apache2 License
+
+//! Commonly used colors, available as constants.
+
+use super::Color;
+
+/// Opaque black.
+pub const BLACK: Color = Color::BLACK;
+/// Opaque white.
+pub const WHITE: Color = Color::WHITE;
+/// Opaque red.
+pub const RED: Color = unsafe { Color::new_unchecked(1.0, 0.0, 0.0, 1.0) };
+/// Opaque green.
+pub const GREEN: Color = unsafe { Color::new_unchecked(0.0, 1.0, 0.0, 1.0) };
+/// Opaque blue.
+pub const BLUE: Color = unsafe { Color::new_unchecked(0.0, 0.0, 1.0, 1.0) };
+/// Opaque yellow.
+pub const YELLOW: Color = unsafe { Color::new_unchecked(1.0, 1.0, 0.0, 1.0) };
+/// Opaque cyan.
+pub const CYAN: Color = unsafe { Color::new_unchecked(0.0, 1.0, 1.0, 1.0) };
+/// Opaque magenta.
+pub const MAGENTA: Color = unsafe { Color::new_unchecked(1.0, 0.0, 1.0, 1.0) };
+/// Opaque, medium gray.
+pub const GRAY: Color = unsafe { Color::new_unchecked(0.5, 0.5, 0.5, 1.0) };
+/// Fully transparent black.
+pub const TRANSPARENT: Color = unsafe { Color::new_unchecked(0.0, 0.0, 0.0, 0.0) };
@@ -0,0 +1,657 @@
+// MIT/Apache2 License
+
+use super::Intensity;
+
+pub mod consts;
+mod names;
+
+/// A four-element color.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Color {
+    r: Intensity,
+    g: Intensity,
+    b: Intensity,
+    a: Intensity,
+}
+
+impl Color {
+    pub const WHITE: Color = unsafe { Color::new_unchecked(1.0, 1.0, 1.0, 1.0) };
+    pub const BLACK: Color = unsafe { Color::new_unchecked(0.0, 0.0, 0.0, 1.0) };
+
+    /// Create a new color.
+    ///
+    /// # Safety
+    ///
+    /// Behavior is undefined if any of the elements are NaN.
+    #[inline]
+    pub const unsafe fn new_unchecked(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self {
+            r: Intensity::new_unchecked(r),
+            g: Intensity::new_unchecked(g),
+            b: Intensity::new_unchecked(b),
+            a: Intensity::new_unchecked(a),
+        }
+    }
+
+    /// Creates a new color. Each element is expected to be between `0.0` and `1.0`; this function returns `None`
+    /// if any of the elements are NaN or out of that range.
+    ///
+    /// This can be used in constant expressions, e.g. to define a palette at compile time.
+    #[inline]
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Option<Self> {
+        // `?` isn't usable in a const fn
+        match (
+            Intensity::new(r),
+            Intensity::new(g),
+            Intensity::new(b),
+            Intensity::new(a),
+        ) {
+            (Some(r), Some(g), Some(b), Some(a)) => Some(Self { r, g, b, a }),
+            _ => None,
+        }
+    }
+
+    /// Creates a new, opaque color. Each element is expected to be between `0.0` and `1.0`; this function
+    /// returns `None` if any of the elements are NaN or out of that range.
+    #[inline]
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Option<Self> {
+        Self::new(r, g, b, 1.0)
+    }
+
+    /// Create a new color from 8-bit red, green, blue and alpha elements.
+    #[inline]
+    pub fn from_rgba_u8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        let channel = |c: u8| f32::from(c) / 255.0;
+        unsafe { Self::new_unchecked(channel(r), channel(g), channel(b), channel(a)) }
+    }
+
+    /// Create a new, opaque color from 8-bit red, green and blue elements.
+    #[inline]
+    pub fn from_rgb_u8(r: u8, g: u8, b: u8) -> Self {
+        Self::from_rgba_u8(r, g, b, u8::MAX)
+    }
+
+    /// Get a color from its CSS name, ignoring case. This returns `None` if there is no color with that name.
+    #[inline]
+    pub fn from_name(name: &str) -> Option<Self> {
+        names::from_name(name)
+    }
+
+    /// Creates a new color, clamping the elements between zero and one. This function returns `None` if any of
+    /// the elements are NaN.
+    #[inline]
+    fn new_clamped(r: f32, g: f32, b: f32, a: f32) -> Option<Self> {
+        let clamp = |c: f32| c.clamp(0.0, 1.0);
+        Self::new(clamp(r), clamp(g), clamp(b), clamp(a))
+    }
+
+    /// Get a copy of this color with its alpha element replaced. The new alpha is clamped between zero and one,
+    /// and this color is returned unchanged if it is NaN.
+    #[inline]
+    pub fn with_alpha(&self, a: f32) -> Color {
+        match Intensity::new(a.clamp(0.0, 1.0)) {
+            Some(a) => Color { a, ..*self },
+            None => *self,
+        }
+    }
+
+    /// Get a copy of this color with its alpha element multiplied by `factor`. The new alpha is clamped between
+    /// zero and one.
+    #[inline]
+    pub fn scale_alpha(&self, factor: f32) -> Color {
+        self.with_alpha(self.alpha() * factor)
+    }
+
+    /// Gets the red element.
+    #[inline]
+    pub fn red(self) -> f32 {
+        self.r.into_inner()
+    }
+
+    /// Gets the green element.
+    #[inline]
+    pub fn green(self) -> f32 {
+        self.g.into_inner()
+    }
+
+    /// Gets the blue element.
+    #[inline]
+    pub fn blue(self) -> f32 {
+        self.b.into_inner()
+    }
+
+    /// Gets the alpha element.
+    #[inline]
+    pub fn alpha(self) -> f32 {
+        self.a.into_inner()
+    }
+
+    /// Gets the red element as an 8-bit integer, rounded to the nearest value.
+    #[inline]
+    pub fn r_u8(self) -> u8 {
+        round_u8(self.red())
+    }
+
+    /// Gets the green element as an 8-bit integer, rounded to the nearest value.
+    #[inline]
+    pub fn g_u8(self) -> u8 {
+        round_u8(self.green())
+    }
+
+    /// Gets the blue element as an 8-bit integer, rounded to the nearest value.
+    #[inline]
+    pub fn b_u8(self) -> u8 {
+        round_u8(self.blue())
+    }
+
+    /// Gets the alpha element as an 8-bit integer, rounded to the nearest value.
+    #[inline]
+    pub fn a_u8(self) -> u8 {
+        round_u8(self.alpha())
+    }
+
+    /// Multiply the red, green and blue elements by the alpha element. Note that this is different from
+    /// `clamp_u8`, which only converts the elements to integers.
+    #[inline]
+    pub fn premultiply(&self) -> Color {
+        let a = self.alpha();
+        Self::new(self.red() * a, self.green() * a, self.blue() * a, a).unwrap_or(*self)
+    }
+
+    /// Undo `premultiply`, dividing the red, green and blue elements by the alpha element. Fully transparent
+    /// colors become transparent black.
+    #[inline]
+    pub fn unpremultiply(&self) -> Color {
+        let a = self.alpha();
+        if a <= 0.0 {
+            return unsafe { Color::new_unchecked(0.0, 0.0, 0.0, 0.0) };
+        }
+
+        let channel = |c: f32| (c / a).min(1.0);
+        Self::new(
+            channel(self.red()),
+            channel(self.green()),
+            channel(self.blue()),
+            a,
+        )
+        .unwrap_or(*self)
+    }
+
+    /// Clamp to u8's.
+    #[inline]
+    pub fn clamp_u8(self) -> (u8, u8, u8, u8) {
+        let r = self.r.clamp_u8();
+        let g = self.g.clamp_u8();
+        let b = self.b.clamp_u8();
+        let a = self.a.clamp_u8();
+        (r, g, b, a)
+    }
+
+    /// Clamp to u16's.
+    #[inline]
+    pub fn clamp_u16(self) -> (u16, u16, u16, u16) {
+        let r: u16 = self.r.clamp_u16();
+        let g: u16 = self.g.clamp_u16();
+        let b: u16 = self.b.clamp_u16();
+        let a: u16 = self.a.clamp_u16();
+        (r, g, b, a)
+    }
+
+    /// Create a color from hue, saturation, lightness and alpha. The hue is in degrees and wraps around at 360,
+    /// while the other components are clamped between zero and one. This function returns `None` if any of the
+    /// elements are NaN.
+    #[inline]
+    pub fn from_hsl(h: f32, s: f32, l: f32, a: f32) -> Option<Self> {
+        let s = s.clamp(0.0, 1.0);
+        let l = l.clamp(0.0, 1.0);
+        let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        let (r, g, b) = hue_to_rgb(h, chroma);
+        let m = l - chroma / 2.0;
+        Self::new_clamped(r + m, g + m, b + m, a)
+    }
+
+    /// Create a color from hue, saturation, value and alpha. The hue is in degrees and wraps around at 360,
+    /// while the other components are clamped between zero and one. This function returns `None` if any of the
+    /// elements are NaN.
+    #[inline]
+    pub fn from_hsv(h: f32, s: f32, v: f32, a: f32) -> Option<Self> {
+        let s = s.clamp(0.0, 1.0);
+        let v = v.clamp(0.0, 1.0);
+        let chroma = v * s;
+        let (r, g, b) = hue_to_rgb(h, chroma);
+        let m = v - chroma;
+        Self::new_clamped(r + m, g + m, b + m, a)
+    }
+
+    /// Get the hue, saturation, lightness and alpha of this color. The hue is in degrees.
+    #[inline]
+    pub fn to_hsl(&self) -> (f32, f32, f32, f32) {
+        let (h, max, min) = self.hue();
+        let l = (max + min) / 2.0;
+        let s = if l <= 0.0 || l >= 1.0 {
+            0.0
+        } else {
+            (max - min) / (1.0 - (2.0 * l - 1.0).abs())
+        };
+        (h, s, l, self.alpha())
+    }
+
+    /// Get the hue, saturation, value and alpha of this color. The hue is in degrees.
+    #[inline]
+    pub fn to_hsv(&self) -> (f32, f32, f32, f32) {
+        let (h, max, min) = self.hue();
+        let s = if max <= 0.0 { 0.0 } else { (max - min) / max };
+        (h, s, max, self.alpha())
+    }
+
+    /// Get the hue of this color, as well as its largest and smallest RGB components.
+    #[inline]
+    fn hue(&self) -> (f32, f32, f32) {
+        let (r, g, b) = (self.red(), self.green(), self.blue());
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let chroma = max - min;
+
+        let h = if chroma <= 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / chroma)
+        } else if max == g {
+            60.0 * ((b - r) / chroma + 2.0)
+        } else {
+            60.0 * ((r - g) / chroma + 4.0)
+        };
+
+        (h.rem_euclid(360.0), max, min)
+    }
+
+    /// Interpolate between this color and another one, where `t` is clamped between zero and one. The
+    /// interpolation is done in linear light, which keeps midpoints from looking darker than they should.
+    #[inline]
+    pub fn lerp(&self, other: &Color, t: f32) -> Color {
+        // avoid rounding errors from the conversions at the ends
+        let t = t.clamp(0.0, 1.0);
+        if t <= 0.0 {
+            return *self;
+        } else if t >= 1.0 {
+            return *other;
+        }
+
+        let (from, to) = (self.to_linear(), other.to_linear());
+        let channel = |i: usize| from[i] + (to[i] - from[i]) * t;
+        Self::from_linear([channel(0), channel(1), channel(2), channel(3)])
+    }
+
+    /// Interpolate between this color and another one by blending their sRGB components directly, where `t` is
+    /// clamped between zero and one.
+    #[inline]
+    pub fn mix_srgb(&self, other: &Color, t: f32) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let channel = |a: f32, b: f32| a + (b - a) * t;
+        Self::new_clamped(
+            channel(self.red(), other.red()),
+            channel(self.green(), other.green()),
+            channel(self.blue(), other.blue()),
+            channel(self.alpha(), other.alpha()),
+        )
+        .unwrap_or(*self)
+    }
+
+    /// Interpolate between this color and another one by blending their hue, saturation and lightness, where
+    /// `t` is clamped between zero and one. The hue goes around the shorter way, and gray colors take on the hue
+    /// of the other color so that they don't add a hue of their own.
+    #[inline]
+    pub fn mix_hsl(&self, other: &Color, t: f32) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        if t <= 0.0 {
+            return *self;
+        } else if t >= 1.0 {
+            return *other;
+        }
+
+        let (mut h1, s1, l1, a1) = self.to_hsl();
+        let (mut h2, s2, l2, a2) = other.to_hsl();
+        if s1 <= 0.0 {
+            h1 = h2;
+        } else if s2 <= 0.0 {
+            h2 = h1;
+        }
+
+        // take the shorter way around the color wheel
+        let mut dh = h2 - h1;
+        if dh > 180.0 {
+            dh -= 360.0;
+        } else if dh < -180.0 {
+            dh += 360.0;
+        }
+
+        let channel = |a: f32, b: f32| a + (b - a) * t;
+        Self::from_hsl(
+            h1 + dh * t,
+            channel(s1, s2),
+            channel(l1, l2),
+            channel(a1, a2),
+        )
+        .unwrap_or(*self)
+    }
+
+    /// Composite this color over a background color, using the Porter-Duff source-over operator. The
+    /// colors are blended in premultiplied linear light.
+    #[inline]
+    pub fn over(&self, background: &Color) -> Color {
+        // avoid rounding errors from the conversions at the extremes
+        let sa = self.alpha();
+        if sa >= 1.0 {
+            return *self;
+        } else if sa <= 0.0 {
+            return *background;
+        }
+
+        let ba = background.alpha();
+        let a = sa + ba * (1.0 - sa);
+
+        let (src, dst) = (self.to_linear(), background.to_linear());
+        let channel = |i: usize| (src[i] * sa + dst[i] * ba * (1.0 - sa)) / a;
+        Self::from_linear([channel(0), channel(1), channel(2), a])
+    }
+
+    /// Get the elements of this color in linear light, using the sRGB transfer function. The alpha element is
+    /// left unchanged.
+    #[inline]
+    pub fn to_linear(&self) -> [f32; 4] {
+        [
+            srgb_to_linear(self.red()),
+            srgb_to_linear(self.green()),
+            srgb_to_linear(self.blue()),
+            self.alpha(),
+        ]
+    }
+
+    /// Create a color from elements in linear light, converting them to sRGB. The elements are clamped between
+    /// zero and one, and NaN elements are treated as zero.
+    #[inline]
+    pub fn from_linear(linear: [f32; 4]) -> Color {
+        let [r, g, b, a] = linear.map(|c| if c.is_nan() { 0.0 } else { c.clamp(0.0, 1.0) });
+        Self::new_clamped(linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b), a)
+            .unwrap_or_default()
+    }
+
+    /// Parse a color from a hex string, in the `rgb`, `rgba`, `rrggbb` or `rrggbbaa` forms. The string may
+    /// start with a `#`.
+    #[inline]
+    pub fn from_hex(s: &str) -> crate::Result<Color> {
+        const INVALID_HEX: crate::Error = crate::Error::StaticMsg("Invalid hex color");
+
+        let s = s.strip_prefix('#').unwrap_or(s);
+        let digits = s
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or(INVALID_HEX)?;
+
+        // expand the short forms so that every channel is two digits long
+        let channels: Vec<u8> = match digits.len() {
+            3 | 4 => digits.iter().map(|d| d * 17).collect(),
+            6 | 8 => digits.chunks(2).map(|d| d[0] * 16 + d[1]).collect(),
+            _ => return Err(INVALID_HEX),
+        };
+
+        let channel = |i: usize| f32::from(channels.get(i).copied().unwrap_or(u8::MAX)) / 255.0;
+        Ok(unsafe { Color::new_unchecked(channel(0), channel(1), channel(2), channel(3)) })
+    }
+
+    /// Convert this color to a hex string, in the `#rrggbbaa` form.
+    #[inline]
+    pub fn to_hex(&self) -> String {
+        let (r, g, b, a) = self.clamp_u8();
+        format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+    }
+}
+
+/// Convert an element between zero and one to the nearest 8-bit integer.
+#[inline]
+fn round_u8(c: f32) -> u8 {
+    (c * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// Get the RGB components of a color with the given hue and chroma, before lightness is added.
+#[inline]
+fn hue_to_rgb(h: f32, chroma: f32) -> (f32, f32, f32) {
+    let h = h.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (h.rem_euclid(2.0) - 1.0).abs());
+    match h as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    }
+}
+
+/// Convert an sRGB component to linear light.
+#[inline]
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Convert a linear light component to sRGB.
+#[inline]
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use consts::{BLUE, CYAN, RED};
+
+    /// `Option::unwrap` can't be used in constants, so this stands in for it.
+    const fn valid(color: Option<Color>) -> Color {
+        match color {
+            Some(color) => color,
+            None => panic!("invalid color"),
+        }
+    }
+
+    /// A palette built entirely at compile time.
+    const PALETTE: [Color; 3] = [
+        valid(Color::rgb(1.0, 0.0, 0.0)),
+        valid(Color::new(0.0, 1.0, 1.0, 1.0)),
+        valid(Color::new(0.0, 0.0, 1.0, 0.5)),
+    ];
+    const OUT_OF_RANGE: Option<Color> = Color::rgb(1.5, 0.0, 0.0);
+
+    #[test]
+    fn const_palette() {
+        assert_eq!(PALETTE[0], RED);
+        assert_eq!(PALETTE[1], CYAN);
+        assert_eq!(PALETTE[2], BLUE.with_alpha(0.5));
+        assert_eq!(OUT_OF_RANGE, None);
+    }
+
+    #[test]
+    fn hex_round_trips() {
+        [
+            ("#f00", "#ff0000ff"),
+            ("0f08", "#00ff0088"),
+            ("#12aB34", "#12ab34ff"),
+            ("#12ab3456", "#12ab3456"),
+        ]
+        .iter()
+        .for_each(|&(input, expected)| {
+            let color = Color::from_hex(input).unwrap();
+            assert_eq!(color.to_hex(), expected);
+            assert_eq!(Color::from_hex(&color.to_hex()).unwrap(), color);
+        });
+    }
+
+    #[test]
+    fn short_hex_expands_to_full_red() {
+        assert_eq!(Color::from_hex("#f00").unwrap(), RED);
+        assert_eq!(
+            Color::from_hex("#f00").unwrap().clamp_u8(),
+            (255, 0, 0, 255)
+        );
+    }
+
+    #[test]
+    fn invalid_hex_is_rejected() {
+        ["", "#", "#ff", "#fffff", "#ggg", "#ff00ff00ff"]
+            .iter()
+            .for_each(|s| {
+                assert!(matches!(
+                    Color::from_hex(s),
+                    Err(crate::Error::StaticMsg(_))
+                ))
+            });
+    }
+
+    fn assert_color_close(a: Color, b: Color) {
+        let close = |x: f32, y: f32| (x - y).abs() < 1e-3;
+        assert!(
+            close(a.red(), b.red())
+                && close(a.green(), b.green())
+                && close(a.blue(), b.blue())
+                && close(a.alpha(), b.alpha()),
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn hsl_and_hsv_corners() {
+        assert_color_close(Color::from_hsl(0.0, 1.0, 0.5, 1.0).unwrap(), RED);
+        assert_color_close(Color::from_hsl(180.0, 1.0, 0.5, 1.0).unwrap(), CYAN);
+        assert_color_close(Color::from_hsv(0.0, 1.0, 1.0, 1.0).unwrap(), RED);
+        assert_color_close(Color::from_hsv(180.0, 1.0, 1.0, 1.0).unwrap(), CYAN);
+
+        // hue wraps around, and saturation and lightness are clamped
+        assert_color_close(Color::from_hsl(360.0, 1.0, 0.5, 1.0).unwrap(), RED);
+        assert_color_close(Color::from_hsl(-180.0, 2.0, 0.5, 1.0).unwrap(), CYAN);
+        assert_color_close(Color::from_hsl(0.0, 0.0, 2.0, 1.0).unwrap(), Color::WHITE);
+        assert!(Color::from_hsl(f32::NAN, 1.0, 0.5, 1.0).is_none());
+    }
+
+    #[test]
+    fn hsl_and_hsv_round_trip() {
+        let color = Color::new(0.2, 0.6, 0.9, 0.5).unwrap();
+
+        let (h, s, l, a) = color.to_hsl();
+        assert_color_close(Color::from_hsl(h, s, l, a).unwrap(), color);
+        let (h, s, v, a) = color.to_hsv();
+        assert_color_close(Color::from_hsv(h, s, v, a).unwrap(), color);
+
+        let (h, s, l, _) = CYAN.to_hsl();
+        assert!((h - 180.0).abs() < 1e-3 && (s - 1.0).abs() < 1e-3 && (l - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn linear_and_srgb_midpoints_differ() {
+        let linear = Color::BLACK.lerp(&Color::WHITE, 0.5);
+        let srgb = Color::BLACK.mix_srgb(&Color::WHITE, 0.5);
+
+        assert!((srgb.red() - 0.5).abs() < 1e-6);
+        // half of the light, encoded in sRGB, is brighter than the sRGB halfway point
+        assert!((linear.red() - 0.7354).abs() < 1e-3);
+        assert!(linear.red() > srgb.red());
+        assert_eq!(linear.red(), linear.blue());
+    }
+
+    #[test]
+    fn lerp_clamps_t() {
+        assert_eq!(Color::BLACK.lerp(&RED, -1.0), Color::BLACK);
+        assert_eq!(Color::BLACK.lerp(&RED, 2.0), RED);
+        assert_eq!(Color::BLACK.mix_srgb(&RED, 2.0), RED);
+    }
+
+    #[test]
+    fn premultiply_half_transparent_red() {
+        let color = RED.with_alpha(0.5);
+        let premultiplied = color.premultiply();
+        assert_eq!(premultiplied.red(), 0.5);
+        assert_eq!(premultiplied.green(), 0.0);
+        assert_eq!(premultiplied.alpha(), 0.5);
+        assert_color_close(premultiplied.unpremultiply(), color);
+        assert_eq!(
+            Color::WHITE.with_alpha(0.0).unpremultiply(),
+            consts::TRANSPARENT
+        );
+    }
+
+    #[test]
+    fn with_alpha_then_scale_alpha() {
+        let color = Color::new(0.25, 0.5, 0.75, 1.0).unwrap();
+        let faded = color.with_alpha(0.5).scale_alpha(0.5);
+        assert_eq!(faded.alpha(), 0.25);
+        assert_eq!(
+            (faded.red(), faded.green(), faded.blue()),
+            (0.25, 0.5, 0.75)
+        );
+
+        // alpha is clamped, and NaN leaves the color alone
+        assert_eq!(color.with_alpha(2.0).alpha(), 1.0);
+        assert_eq!(color.scale_alpha(-1.0).alpha(), 0.0);
+        assert_eq!(color.with_alpha(f32::NAN), color);
+    }
+
+    #[test]
+    fn over_at_the_extremes() {
+        let background = CYAN.with_alpha(0.25);
+        assert_eq!(RED.over(&background), RED);
+        assert_eq!(RED.with_alpha(0.0).over(&background), background);
+    }
+
+    #[test]
+    fn half_transparent_red_over_blue() {
+        let blended = RED.with_alpha(0.5).over(&BLUE);
+        // half of each color in linear light, converted back to sRGB
+        let half = linear_to_srgb(0.5);
+        assert_color_close(blended, Color::new(half, 0.0, half, 1.0).unwrap());
+        assert!((half - 0.7354).abs() < 1e-3);
+    }
+
+    #[test]
+    fn u8_channels_round_trip() {
+        let color = Color::from_rgba_u8(0, 255, 0, 255);
+        assert_eq!(
+            (color.r_u8(), color.g_u8(), color.b_u8(), color.a_u8()),
+            (0, 255, 0, 255)
+        );
+        assert_eq!(color, Color::from_rgb_u8(0, 255, 0));
+
+        let color = Color::from_rgba_u8(255, 0, 255, 0);
+        assert_eq!(
+            (color.r_u8(), color.g_u8(), color.b_u8(), color.a_u8()),
+            (255, 0, 255, 0)
+        );
+        for c in 0..=u8::MAX {
+            assert_eq!(Color::from_rgba_u8(c, c, c, c).g_u8(), c);
+        }
+    }
+
+    #[test]
+    fn srgb_linear_known_pairs() {
+        let half = Color::new(0.5, 0.0, 1.0, 0.25).unwrap();
+        let [r, g, b, a] = half.to_linear();
+        assert!((r - 0.214_041).abs() < 1e-4, "{}", r);
+        assert_eq!((g, b, a), (0.0, 1.0, 0.25));
+
+        // the linear segment near black
+        let dark = Color::new(0.04045, 0.0, 0.0, 1.0).unwrap();
+        assert!((dark.to_linear()[0] - 0.04045 / 12.92).abs() < 1e-6);
+
+        let back = Color::from_linear([0.214_041, 0.0, 1.0, 0.25]);
+        assert!((back.red() - 0.5).abs() < 1e-4);
+        assert_eq!(back.alpha(), 0.25);
+    }
+}
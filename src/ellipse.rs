@@ -1,9 +1,108 @@
 // MIT/Apache2 License
 
-use lyon_geom::{Point, Vector};
+use lyon_geom::{Angle, Point, Rect, Size, Transform, Vector};
+use lyon_path::Path;
+
+/// The distance of the control points from the ends of a cubic bezier curve approximating a
+/// quarter circle, relative to the radius.
+const KAPPA: f32 = 0.552_284_8;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Ellipse {
     pub center: Point<f32>,
     pub radii: Vector<f32>,
 }
+
+impl Ellipse {
+    /// Get the smallest axis-aligned rectangle containing this ellipse.
+    #[must_use]
+    #[inline]
+    pub fn bounding_box(&self) -> Rect<f32> {
+        let radii = self.radii.abs();
+        Rect::new(self.center - radii, Size::new(radii.x * 2.0, radii.y * 2.0))
+    }
+
+    /// Tell whether or not the given point lies inside of or on the edge of this ellipse.
+    #[must_use]
+    #[inline]
+    pub fn contains(&self, p: Point<f32>) -> bool {
+        let radii = self.radii.abs();
+        if radii.x <= 0.0 || radii.y <= 0.0 {
+            return false;
+        }
+
+        let dx = (p.x - self.center.x) / radii.x;
+        let dy = (p.y - self.center.y) / radii.y;
+        dx * dx + dy * dy <= 1.0
+    }
+
+    /// Create a path approximating this ellipse out of four cubic bezier curves.
+    #[inline]
+    pub fn to_path(&self) -> Path {
+        self.to_rotated_path(Angle::zero())
+    }
+
+    /// Create a path approximating this ellipse, rotated around its center by the given angle.
+    #[inline]
+    pub fn to_rotated_path(&self, rotation: Angle<f32>) -> Path {
+        let Vector { x: rx, y: ry, .. } = self.radii;
+        let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+        let transform = Transform::rotation(rotation).then_translate(self.center.to_vector());
+        let pt = |x: f32, y: f32| transform.transform_point(Point::new(x, y));
+
+        let mut builder = Path::builder();
+        builder.begin(pt(rx, 0.0));
+        builder.cubic_bezier_to(pt(rx, ky), pt(kx, ry), pt(0.0, ry));
+        builder.cubic_bezier_to(pt(-kx, ry), pt(-rx, ky), pt(-rx, 0.0));
+        builder.cubic_bezier_to(pt(-rx, -ky), pt(-kx, -ry), pt(0.0, -ry));
+        builder.cubic_bezier_to(pt(kx, -ry), pt(rx, -ky), pt(rx, 0.0));
+        builder.close();
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::PathExt;
+    use lyon_geom::{point, vector};
+
+    fn ellipse() -> Ellipse {
+        Ellipse {
+            center: point(10.0, 20.0),
+            radii: vector(4.0, 2.0),
+        }
+    }
+
+    #[test]
+    fn bounding_box_spans_the_radii() {
+        assert_eq!(
+            ellipse().bounding_box(),
+            Rect::new(point(6.0, 18.0), Size::new(8.0, 4.0))
+        );
+    }
+
+    #[test]
+    fn contains_center_boundary_not_outside() {
+        let e = ellipse();
+        assert!(e.contains(point(10.0, 20.0)));
+        assert!(e.contains(point(14.0, 20.0)));
+        assert!(e.contains(point(10.0, 18.0)));
+        assert!(!e.contains(point(14.0, 22.0)));
+        assert!(!e.contains(point(14.1, 20.0)));
+    }
+
+    #[test]
+    fn path_bounds_match_bounding_box() {
+        let e = ellipse();
+        let bounds = e.to_path().bounds();
+        let expected = e.bounding_box();
+        assert!((bounds.min() - expected.min()).length() < 1e-3);
+        assert!((bounds.max() - expected.max()).length() < 1e-3);
+
+        // a quarter turn swaps the extents
+        let rotated = e.to_rotated_path(Angle::frac_pi_2()).bounds();
+        assert!((rotated.min() - point(8.0, 16.0)).length() < 1e-3);
+        assert!((rotated.max() - point(12.0, 24.0)).length() < 1e-3);
+    }
+}
@@ -35,6 +35,14 @@ pub enum Error {
     NoValidDraw(NonZeroUsize),
     /// Cannot create an image.
     ImageNotAvailable,
+    /// Attempted to pop a clip when no clips were pushed.
+    NoClip,
+    /// Image data could not be decoded.
+    ImageDecode(String),
+    /// A region of an image lies outside of the image's bounds.
+    RegionOutOfBounds,
+    /// Several operations failed.
+    Multiple(Vec<Error>),
     /// A BreadX error occurred.
     #[cfg(all(unix, feature = "breadx"))]
     BreadX(BreadError),
@@ -44,22 +52,73 @@ pub enum Error {
 }
 
 /// An operation that is not supported.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum NSOpType {
     Gradients,
+    Size,
+    Pattern,
+    Image,
+    Text,
+    Antialiasing,
+    Clipping,
+    Transform,
+    Path,
+    Transparency,
+    DirtyTracking,
+}
+
+impl fmt::Display for NSOpType {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Gradients => "gradients",
+            Self::Size => "reporting its size",
+            Self::Pattern => "image patterns",
+            Self::Image => "this image operation",
+            Self::Text => "text",
+            Self::Antialiasing => "antialiasing",
+            Self::Clipping => "clipping",
+            Self::Transform => "transforms",
+            Self::Path => "paths",
+            Self::Transparency => "transparency",
+            Self::DirtyTracking => "tracking the area drawn to",
+        })
+    }
 }
 
 impl std::error::Error for Error {}
 
+#[cfg(all(windows, feature = "yaww"))]
+impl Error {
+    /// Run every operation, even if an earlier one fails, and collect the errors they produce.
+    #[inline]
+    pub(crate) fn collect_errors<E: Into<Error>>(
+        results: impl IntoIterator<Item = std::result::Result<(), E>>,
+    ) -> Vec<Error> {
+        results
+            .into_iter()
+            .filter_map(|res| res.err().map(Into::into))
+            .collect()
+    }
+
+    /// Combine the errors produced by several operations, succeeding if there were none.
+    #[inline]
+    pub(crate) fn from_errors(errors: Vec<Error>) -> Result {
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Self::Multiple(errors))
+        }
+    }
+}
+
 impl fmt::Display for Error {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::StaticMsg(s) => f.write_str(s),
             Self::Msg(s) => f.write_str(s),
-            Self::NotSupported(nsop) => {
-                write!(f, "Surface does not support feature \"{:?}\"", nsop)
-            }
+            Self::NotSupported(nsop) => write!(f, "Surface does not support {}", nsop),
             Self::NoInitializer => f.write_str("Could not find initializer for current platform"),
             Self::NoScreen(i) => write!(f, "Screen #{} does not exist", i),
             Self::NotOurWindow(w) => write!(f, "Window of ID {:#010x} does not exist", w),
@@ -69,6 +128,16 @@ impl fmt::Display for Error {
             Self::NoValidDraw(w) => write!(f, "Window of ID {:#010x} cannot be drawn upon", w),
             Self::FailedToTesselate => f.write_str("Unable to tesselate shape"),
             Self::ImageNotAvailable => f.write_str("Unable to create image"),
+            Self::NoClip => f.write_str("No clipping region to pop"),
+            Self::ImageDecode(s) => write!(f, "Unable to decode image: {}", s),
+            Self::RegionOutOfBounds => f.write_str("Region lies outside of the image"),
+            Self::Multiple(errors) => {
+                write!(f, "{} errors occurred", errors.len())?;
+                errors
+                    .iter()
+                    .enumerate()
+                    .try_for_each(|(i, e)| write!(f, "{} {}", if i == 0 { ":" } else { ";" }, e))
+            }
             #[cfg(all(unix, feature = "breadx"))]
             Self::BreadX(bx) => fmt::Display::fmt(bx, f),
             #[cfg(all(windows, feature = "yaww"))]
@@ -95,3 +164,27 @@ impl From<YawwError> for Error {
 
 /// Convenience result type.
 pub type Result<T = ()> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn not_supported_names_the_operation() {
+        let message = |op| Error::NotSupported(op).to_string();
+        assert_eq!(
+            message(NSOpType::Gradients),
+            "Surface does not support gradients"
+        );
+        assert_eq!(message(NSOpType::Text), "Surface does not support text");
+        assert_eq!(
+            message(NSOpType::Clipping),
+            "Surface does not support clipping"
+        );
+        assert_eq!(
+            message(NSOpType::Transform),
+            "Surface does not support transforms"
+        );
+        assert_eq!(message(NSOpType::Path), "Surface does not support paths");
+    }
+}
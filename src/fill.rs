@@ -1,13 +1,180 @@
 // MIT/Apache2 License
 
-use crate::{gradient::Gradient, Color};
-use lyon_geom::Angle;
+use crate::{
+    gradient::{ConicGradient, Gradient, LinearGradient, RadialGradient, SpreadMode},
+    transform::Transform,
+    Color, Image,
+};
+use lyon_geom::{Angle, Rect, Vector};
 
-/// Defines how a particular space is filled.
+/// Defines how a particular space is filled. When the `serde` feature is enabled, every rule except `Pattern` can
+/// be serialized, since images only make sense on the surface that created them.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FillRule {
     SolidColor(Color),
     LinearGradient(Gradient<'static>, Angle<f32>),
     RadialGradient(Gradient<'static>),
     ConicalGradient(Gradient<'static>),
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Pattern(ImagePattern),
+}
+
+/// How to decide which parts of a self-intersecting shape are inside of it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FillMode {
+    /// A point is inside the shape if a ray from it crosses the outline an odd number of times.
+    EvenOdd,
+    /// A point is inside the shape if the outline winds around it at least once.
+    NonZero,
+}
+
+impl Default for FillMode {
+    #[inline]
+    fn default() -> Self {
+        Self::EvenOdd
+    }
+}
+
+/// An image that is repeated to fill a space.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ImagePattern {
+    /// The image to fill with. This must have been created by the surface that the pattern is used on.
+    pub image: Image,
+    /// How the image is repeated past its edges.
+    pub tiling: TileMode,
+    /// A transform applied to the image before it is repeated.
+    pub transform: Option<Transform>,
+}
+
+impl ImagePattern {
+    /// Create a new `ImagePattern` without a transform.
+    #[inline]
+    pub fn new(image: Image, tiling: TileMode) -> ImagePattern {
+        ImagePattern {
+            image,
+            tiling,
+            transform: None,
+        }
+    }
+}
+
+/// How an image is drawn past its edges.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TileMode {
+    /// The image is repeated.
+    Tile,
+    /// The pixels at the edges of the image are extended forever.
+    Clamp,
+    /// The image is repeated, but every other repetition is mirrored.
+    Mirror,
+}
+
+impl Default for TileMode {
+    #[inline]
+    fn default() -> Self {
+        Self::Tile
+    }
+}
+
+impl FillRule {
+    /// Render this fill rule over `rect` into a buffer of RGBA pixels, for use with surfaces that can't draw
+    /// gradients themselves. Linear gradients run across the rectangle at their angle, while radial gradients
+    /// spread out from its center. Conical gradients sweep around the center, starting from the right. Image
+    /// patterns live on the server, so this returns `None` for them.
+    #[inline]
+    pub fn rasterize(&self, rect: Rect<f32>) -> Option<Vec<u8>> {
+        self.rasterize_region(rect, rect)
+    }
+
+    /// Render the part of this fill rule that covers `region` into a buffer of RGBA pixels, where the gradient
+    /// is laid out over `rect` in the same way as `rasterize`. This avoids shading the parts of a large shape
+    /// that would be clipped away anyways.
+    #[inline]
+    pub fn rasterize_region(&self, rect: Rect<f32>, region: Rect<f32>) -> Option<Vec<u8>> {
+        Some(match self {
+            FillRule::SolidColor(color) => {
+                let (r, g, b, a) = color.clamp_u8();
+                let count =
+                    (region.size.width.max(0.0) as usize) * (region.size.height.max(0.0) as usize);
+                [r, g, b, a].repeat(count)
+            }
+            FillRule::LinearGradient(gradient, angle) => {
+                let direction = Vector::new(angle.radians.cos(), angle.radians.sin());
+                let half = (direction.x.abs() * rect.size.width
+                    + direction.y.abs() * rect.size.height)
+                    / 2.0;
+                let center = rect.center();
+                LinearGradient::new(
+                    center - direction * half,
+                    center + direction * half,
+                    gradient.clone(),
+                    SpreadMode::Pad,
+                )
+                .rasterize(region)
+            }
+            FillRule::RadialGradient(gradient) => RadialGradient::new(
+                rect.center(),
+                rect.size.width.max(rect.size.height) / 2.0,
+                gradient.clone(),
+            )
+            .rasterize(region),
+            FillRule::ConicalGradient(gradient) => {
+                ConicGradient::new(rect.center(), Angle::zero(), gradient.clone()).rasterize(region)
+            }
+            FillRule::Pattern(_) => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        color::consts::{BLUE, RED},
+        gradient::ColorStop,
+        Intensity,
+    };
+    use lyon_geom::{point, size};
+
+    fn red_to_blue() -> Gradient<'static> {
+        Gradient::try_new(vec![
+            ColorStop {
+                color: RED,
+                position: Intensity::new(0.0).unwrap(),
+            },
+            ColorStop {
+                color: BLUE,
+                position: Intensity::new(1.0).unwrap(),
+            },
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn linear_gradient_endpoints() {
+        let rule = FillRule::LinearGradient(red_to_blue(), Angle::zero());
+        let pixels = rule
+            .rasterize(Rect::new(point(0.0, 0.0), size(16.0, 2.0)))
+            .unwrap();
+        assert_eq!(pixels.len(), 16 * 2 * 4);
+
+        let left = &pixels[..4];
+        let right = &pixels[15 * 4..16 * 4];
+        assert!(left[0] > 240 && left[2] < 16, "{:?}", left);
+        assert!(right[0] < 16 && right[2] > 240, "{:?}", right);
+        // every row is the same for a horizontal gradient
+        assert_eq!(&pixels[..16 * 4], &pixels[16 * 4..]);
+    }
+
+    #[test]
+    fn region_is_part_of_the_full_rasterization() {
+        let rule = FillRule::LinearGradient(red_to_blue(), Angle::zero());
+        let rect = Rect::new(point(0.0, 0.0), size(16.0, 1.0));
+        let full = rule.rasterize(rect).unwrap();
+        let part = rule
+            .rasterize_region(rect, Rect::new(point(4.0, 0.0), size(8.0, 1.0)))
+            .unwrap();
+        assert_eq!(part, &full[4 * 4..12 * 4]);
+    }
 }
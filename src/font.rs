@@ -0,0 +1,160 @@
+// MIT/Apache2 License
+
+use std::{
+    borrow::Cow,
+    hash::{Hash, Hasher},
+};
+
+/// The index of a glyph within a font, as produced by a text shaping engine.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct GlyphId(pub u16);
+
+/// A font that text can be drawn with. Fonts can be hashed, so that surfaces can use them as cache keys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Font {
+    /// The name of the font's family, e.g. "Arial".
+    pub family: Cow<'static, str>,
+    /// The height of the font, in pixels.
+    pub size: f32,
+    /// Whether or not the font is bold.
+    pub bold: bool,
+    /// Whether or not the font is italic.
+    pub italic: bool,
+}
+
+impl Font {
+    /// Create a new, regular `Font` from its family and size.
+    #[inline]
+    pub fn new<Family: Into<Cow<'static, str>>>(family: Family, size: f32) -> Font {
+        Font {
+            family: family.into(),
+            size,
+            bold: false,
+            italic: false,
+        }
+    }
+}
+
+impl Font {
+    /// The family used when none is given, which lets the system pick its default font.
+    pub const DEFAULT_FAMILY: &'static str = "sans-serif";
+    /// The size used when none is given, in pixels.
+    pub const DEFAULT_SIZE: f32 = 12.0;
+
+    /// Create a builder for a `Font`, starting from the default family and size.
+    #[inline]
+    pub fn builder() -> FontBuilder {
+        FontBuilder::default()
+    }
+}
+
+impl Default for Font {
+    #[inline]
+    fn default() -> Font {
+        Font::builder().build()
+    }
+}
+
+impl Eq for Font {}
+
+impl Hash for Font {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // zero and negative zero compare equal, so they need to hash the same
+        let size = if self.size == 0.0 { 0.0 } else { self.size };
+
+        self.family.hash(state);
+        size.to_bits().hash(state);
+        self.bold.hash(state);
+        self.italic.hash(state);
+    }
+}
+
+/// A builder for a `Font`. Anything that isn't set is filled in with the defaults: the system's font, at 12
+/// pixels, with a regular weight.
+#[derive(Debug, Clone)]
+pub struct FontBuilder {
+    font: Font,
+}
+
+impl Default for FontBuilder {
+    #[inline]
+    fn default() -> FontBuilder {
+        FontBuilder {
+            font: Font::new(Font::DEFAULT_FAMILY, Font::DEFAULT_SIZE),
+        }
+    }
+}
+
+impl FontBuilder {
+    /// Set the name of the font's family.
+    #[inline]
+    pub fn family<Family: Into<Cow<'static, str>>>(mut self, family: Family) -> FontBuilder {
+        self.font.family = family.into();
+        self
+    }
+
+    /// Set the height of the font, in pixels.
+    #[inline]
+    pub fn size(mut self, size: f32) -> FontBuilder {
+        self.font.size = size;
+        self
+    }
+
+    /// Set whether or not the font is bold.
+    #[inline]
+    pub fn bold(mut self, bold: bool) -> FontBuilder {
+        self.font.bold = bold;
+        self
+    }
+
+    /// Set whether or not the font is italic.
+    #[inline]
+    pub fn italic(mut self, italic: bool) -> FontBuilder {
+        self.font.italic = italic;
+        self
+    }
+
+    /// Create the `Font`.
+    #[inline]
+    pub fn build(self) -> Font {
+        self.font
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+
+    fn hash(font: &Font) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        font.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn equal_builders_hash_equally() {
+        // the same font, built from a borrowed and an owned family name and with the setters in another order
+        let a = Font::builder()
+            .family("Arial")
+            .size(16.0)
+            .bold(true)
+            .build();
+        let b = Font::builder()
+            .bold(true)
+            .size(16.0)
+            .family(String::from("Arial"))
+            .build();
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+
+        // zero and negative zero sizes compare equal
+        let zero = Font::builder().size(0.0).build();
+        let negative_zero = Font::builder().size(-0.0).build();
+        assert_eq!(hash(&zero), hash(&negative_zero));
+
+        assert_eq!(Font::default(), Font::new(Font::DEFAULT_FAMILY, 12.0));
+    }
+}
@@ -1,21 +1,25 @@
 // MIT/Apache2 License
 
 use crate::{Color, Intensity};
+use lyon_geom::{Angle, Point, Rect};
 use std::{
     borrow::Cow,
     cmp::Ordering,
-    iter::FromIterator,
+    iter::{self, FromIterator},
     slice::{Iter as SliceIter, IterMut as SliceIterMut},
 };
 use tinyvec::{TinyVec, TinyVecIterator};
 
 const EXPECTED_CSTOPS: usize = 3;
+/// The number of stops used to approximate each span between two stops by `srgb_stops`.
+const SRGB_SAMPLES_PER_SPAN: usize = 16;
 
 /// A gradient of colors.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Gradient<'a> {
     // invariant: contains at least 1 element
     colors: Cow<'a, [ColorStop]>,
+    interpolation: ColorInterpolation,
 }
 
 impl<'a> Gradient<'a> {
@@ -27,7 +31,10 @@ impl<'a> Gradient<'a> {
     /// by their `position` field.
     #[inline]
     pub unsafe fn new_unchecked(colors: Cow<'a, [ColorStop]>) -> Gradient<'a> {
-        Gradient { colors }
+        Gradient {
+            colors,
+            interpolation: ColorInterpolation::default(),
+        }
     }
 
     /// Creates a new gradient from an item that can be converted into a `Cow<'_, [ColorStop]>`. If the item is
@@ -38,8 +45,48 @@ impl<'a> Gradient<'a> {
         if colors.is_empty() || !is_sorted(&colors) {
             None
         } else {
-            Some(Gradient { colors })
+            Some(Gradient {
+                colors,
+                interpolation: ColorInterpolation::default(),
+            })
+        }
+    }
+
+    /// Creates a new gradient from an item that can be converted into a `Cow<'_, [ColorStop]>`, sorting the
+    /// stops and removing duplicate positions. This returns an error if there are fewer than two stops.
+    #[inline]
+    pub fn try_new<Colors: Into<Cow<'a, [ColorStop]>>>(
+        colors: Colors,
+    ) -> crate::Result<Gradient<'a>> {
+        let colors = colors.into();
+        if colors.len() < 2 {
+            return Err(crate::Error::StaticMsg(
+                "Gradients need at least two color stops",
+            ));
         }
+
+        let mut gradient = Gradient {
+            colors,
+            interpolation: ColorInterpolation::default(),
+        };
+        gradient.normalize();
+        Ok(gradient)
+    }
+
+    /// Sort the color stops by their position. If several stops share a position, only the last one is kept.
+    #[inline]
+    pub fn normalize(&mut self) {
+        if is_sorted(&self.colors) && !has_duplicates(&self.colors) {
+            return;
+        }
+
+        let colors = self.colors.to_mut();
+        colors.sort_by_key(|stop| stop.position);
+
+        // dedup keeps the first of a run, so flip the list around to keep the last one instead
+        colors.reverse();
+        colors.dedup_by_key(|stop| stop.position);
+        colors.reverse();
     }
 
     /// Creates an iterator over these values.
@@ -73,34 +120,360 @@ impl<'a> Gradient<'a> {
         match self.colors {
             Cow::Borrowed(colors) => Gradient {
                 colors: Cow::Owned(colors.to_vec()),
+                interpolation: self.interpolation,
             },
             Cow::Owned(colors) => Gradient {
                 colors: Cow::Owned(colors),
+                interpolation: self.interpolation,
             },
         }
     }
 
+    /// Get the color space that colors in between stops are blended in.
+    #[inline]
+    pub fn interpolation(&self) -> ColorInterpolation {
+        self.interpolation
+    }
+
+    /// Set the color space that colors in between stops are blended in.
+    #[inline]
+    pub fn set_interpolation(&mut self, interpolation: ColorInterpolation) {
+        self.interpolation = interpolation;
+    }
+
+    /// Use a different color space to blend colors in between stops.
+    #[inline]
+    pub fn with_interpolation(mut self, interpolation: ColorInterpolation) -> Gradient<'a> {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Get color stops that look like this gradient when they are blended in sRGB, for backends that can only
+    /// blend in sRGB. Gradients that blend in other color spaces are approximated using extra stops.
+    #[inline]
+    pub fn srgb_stops(&self) -> Cow<'_, [ColorStop]> {
+        if self.interpolation == ColorInterpolation::Srgb || self.colors.len() < 2 {
+            return Cow::Borrowed(&self.colors);
+        }
+
+        let last = self.colors[self.colors.len() - 1].position.into_inner();
+        let stops = self
+            .colors
+            .windows(2)
+            .map(|pair| (pair[0].position.into_inner(), pair[1].position.into_inner()))
+            .filter(|(start, end)| end > start)
+            .flat_map(|(start, end)| {
+                (0..SRGB_SAMPLES_PER_SPAN)
+                    .map(move |i| start + (end - start) * i as f32 / SRGB_SAMPLES_PER_SPAN as f32)
+            })
+            .chain(iter::once(last))
+            .filter_map(|t| {
+                Some(ColorStop {
+                    color: self.sample(t),
+                    position: Intensity::new(t)?,
+                })
+            })
+            .collect();
+        Cow::Owned(stops)
+    }
+
+    /// Get the color of the gradient at position `t`. Positions before the first stop or after the last stop
+    /// take the color of that stop, and colors in between stops are blended in the gradient's color space.
+    #[inline]
+    pub fn sample(&self, t: f32) -> Color {
+        let first = self.colors[0];
+        let last = self.colors[self.colors.len() - 1];
+
+        if !(t > first.position.into_inner()) {
+            return first.color;
+        } else if t >= last.position.into_inner() {
+            return last.color;
+        }
+
+        // find the first stop after t; the stop before it is guaranteed to exist
+        let index = self
+            .colors
+            .partition_point(|stop| stop.position.into_inner() <= t);
+        let before = self.colors[index - 1];
+        let after = self.colors[index];
+
+        let start = before.position.into_inner();
+        let span = after.position.into_inner() - start;
+        if span <= 0.0 {
+            after.color
+        } else {
+            self.interpolation
+                .mix(&before.color, &after.color, (t - start) / span)
+        }
+    }
+
     /// Clone this `Gradient<'_>` into a `Gradient<'static>`.
     #[inline]
     pub fn to_owned(&self) -> Gradient<'static> {
         match self.colors {
             Cow::Borrowed(ref colors) => Gradient {
                 colors: Cow::Owned(colors.to_vec()),
+                interpolation: self.interpolation,
             },
             Cow::Owned(ref colors) => Gradient {
                 colors: Cow::Owned(colors.clone()),
+                interpolation: self.interpolation,
             },
         }
     }
 }
 
+/// A gradient that spreads out in a circle from a center point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RadialGradient {
+    /// The center of the circle.
+    pub center: Point<f32>,
+    /// The radius of the circle. The last color stop is reached at this distance from the center.
+    pub radius: f32,
+    /// The color stops of the gradient.
+    pub stops: Gradient<'static>,
+}
+
+impl RadialGradient {
+    /// Create a new `RadialGradient`.
+    #[inline]
+    pub fn new(center: Point<f32>, radius: f32, stops: Gradient<'static>) -> RadialGradient {
+        RadialGradient {
+            center,
+            radius,
+            stops,
+        }
+    }
+
+    /// Get the color of the gradient at a point.
+    #[inline]
+    pub fn sample_at(&self, point: Point<f32>) -> Color {
+        let distance = (point - self.center).length();
+        if self.radius <= 0.0 {
+            self.stops.sample(if distance > 0.0 { 1.0 } else { 0.0 })
+        } else {
+            self.stops.sample(distance / self.radius)
+        }
+    }
+
+    /// Render the part of the gradient covered by `rect` into a buffer of RGBA pixels, for use with surfaces
+    /// that can't draw gradients themselves. Each pixel is sampled at its center.
+    #[inline]
+    pub fn rasterize(&self, rect: Rect<f32>) -> Vec<u8> {
+        rasterize(rect, |point| self.sample_at(point))
+    }
+}
+
+/// A gradient that sweeps around a center point, like the hand of a clock.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConicGradient {
+    /// The point the gradient sweeps around.
+    pub center: Point<f32>,
+    /// The angle where the gradient starts, and where it ends after a full turn.
+    pub start_angle: Angle<f32>,
+    /// The color stops of the gradient.
+    pub stops: Gradient<'static>,
+}
+
+impl ConicGradient {
+    /// Create a new `ConicGradient`.
+    #[inline]
+    pub fn new(
+        center: Point<f32>,
+        start_angle: Angle<f32>,
+        stops: Gradient<'static>,
+    ) -> ConicGradient {
+        ConicGradient {
+            center,
+            start_angle,
+            stops,
+        }
+    }
+
+    /// Get the color of the gradient at an angle. Angles wrap around every full turn, so the start angle maps
+    /// to the first stop and the angle just before it maps to the last stop.
+    #[inline]
+    pub fn sample(&self, angle: Angle<f32>) -> Color {
+        let turn = 2.0 * std::f32::consts::PI;
+        let t = (angle.radians - self.start_angle.radians).rem_euclid(turn) / turn;
+        self.stops.sample(t)
+    }
+
+    /// Get the color of the gradient at a point.
+    #[inline]
+    pub fn sample_at(&self, point: Point<f32>) -> Color {
+        self.sample((point - self.center).angle_from_x_axis())
+    }
+
+    /// Render the part of the gradient covered by `rect` into a buffer of RGBA pixels, for use with surfaces
+    /// that can't draw gradients themselves. Each pixel is sampled at its center.
+    #[inline]
+    pub fn rasterize(&self, rect: Rect<f32>) -> Vec<u8> {
+        rasterize(rect, |point| self.sample_at(point))
+    }
+}
+
+/// A gradient that changes color along the line between two points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinearGradient {
+    /// The point where the gradient is at position zero.
+    pub start: Point<f32>,
+    /// The point where the gradient is at position one.
+    pub end: Point<f32>,
+    /// The color stops of the gradient.
+    pub stops: Gradient<'static>,
+    /// How the gradient continues past its start and end points.
+    pub spread: SpreadMode,
+}
+
+impl LinearGradient {
+    /// Create a new `LinearGradient`.
+    #[inline]
+    pub fn new(
+        start: Point<f32>,
+        end: Point<f32>,
+        stops: Gradient<'static>,
+        spread: SpreadMode,
+    ) -> LinearGradient {
+        LinearGradient {
+            start,
+            end,
+            stops,
+            spread,
+        }
+    }
+
+    /// Get the position along the gradient that a point falls on, before the spread mode is applied. Points
+    /// are projected onto the line between the start and end points.
+    #[inline]
+    pub fn parameter(&self, point: Point<f32>) -> f32 {
+        let direction = self.end - self.start;
+        let length = direction.square_length();
+        if length <= 0.0 {
+            0.0
+        } else {
+            (point - self.start).dot(direction) / length
+        }
+    }
+
+    /// Get the color of the gradient at a point.
+    #[inline]
+    pub fn sample_along(&self, point: Point<f32>) -> Color {
+        self.stops.sample(self.spread.apply(self.parameter(point)))
+    }
+
+    /// Render the part of the gradient covered by `rect` into a buffer of RGBA pixels, for use with surfaces
+    /// that can't draw gradients themselves. Each pixel is sampled at its center.
+    #[inline]
+    pub fn rasterize(&self, rect: Rect<f32>) -> Vec<u8> {
+        rasterize(rect, |point| self.sample_along(point))
+    }
+}
+
+/// How a gradient is drawn outside of the range between zero and one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SpreadMode {
+    /// The colors at the ends of the gradient are extended forever.
+    Pad,
+    /// The gradient starts over from the beginning.
+    Repeat,
+    /// The gradient is repeated, but every other repetition is mirrored.
+    Reflect,
+}
+
+impl Default for SpreadMode {
+    #[inline]
+    fn default() -> Self {
+        Self::Pad
+    }
+}
+
+impl SpreadMode {
+    /// Map a position on a gradient into the range between zero and one.
+    #[inline]
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            SpreadMode::Pad => t.clamp(0.0, 1.0),
+            SpreadMode::Repeat => t.rem_euclid(1.0),
+            SpreadMode::Reflect => {
+                let t = t.rem_euclid(2.0);
+                if t > 1.0 {
+                    2.0 - t
+                } else {
+                    t
+                }
+            }
+        }
+    }
+}
+
+/// The color space that a gradient blends colors in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorInterpolation {
+    /// Blend the sRGB components directly.
+    Srgb,
+    /// Blend in linear light, which keeps the middle of the gradient from looking too dark.
+    LinearRgb,
+    /// Blend the hue, saturation and lightness, going the short way around the color wheel. This keeps the
+    /// middle of the gradient from passing through gray.
+    Hsl,
+}
+
+impl Default for ColorInterpolation {
+    #[inline]
+    fn default() -> Self {
+        Self::Srgb
+    }
+}
+
+impl ColorInterpolation {
+    /// Blend two colors in this color space, where `t` is clamped between zero and one.
+    #[inline]
+    pub fn mix(self, from: &Color, to: &Color, t: f32) -> Color {
+        match self {
+            ColorInterpolation::Srgb => from.mix_srgb(to, t),
+            ColorInterpolation::LinearRgb => from.lerp(to, t),
+            ColorInterpolation::Hsl => from.mix_hsl(to, t),
+        }
+    }
+}
+
+/// Render a function of position into a buffer of RGBA pixels covering `rect`.
+#[inline]
+pub(crate) fn rasterize(rect: Rect<f32>, mut sample: impl FnMut(Point<f32>) -> Color) -> Vec<u8> {
+    let width = rect.size.width.max(0.0) as usize;
+    let height = rect.size.height.max(0.0) as usize;
+    let mut pixels = Vec::with_capacity(width * height * 4);
+
+    (0..height).for_each(|y| {
+        (0..width).for_each(|x| {
+            let point = rect.origin + lyon_geom::vector(x as f32 + 0.5, y as f32 + 0.5);
+            let (r, g, b, a) = sample(point).clamp_u8();
+            pixels.extend_from_slice(&[r, g, b, a]);
+        });
+    });
+
+    pixels
+}
+
 /// A color stop in a color gradient.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ColorStop {
     pub color: Color,
     pub position: Intensity,
 }
 
+/// Tell if any neighboring stops share a position.
+#[inline]
+fn has_duplicates(stops: &[ColorStop]) -> bool {
+    stops
+        .windows(2)
+        .any(|pair| pair[0].position == pair[1].position)
+}
+
 /// Tell if this is sorted.
 #[inline]
 fn is_sorted(stops: &[ColorStop]) -> bool {
@@ -121,3 +494,163 @@ fn is_sorted(stops: &[ColorStop]) -> bool {
         true
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::consts::{BLUE, GREEN, RED};
+    use lyon_geom::point;
+
+    fn stop(color: Color, position: f32) -> ColorStop {
+        ColorStop {
+            color,
+            position: Intensity::new(position).unwrap(),
+        }
+    }
+
+    fn rgb(color: Color) -> (u8, u8, u8) {
+        (color.r_u8(), color.g_u8(), color.b_u8())
+    }
+
+    fn red_green_blue() -> Gradient<'static> {
+        Gradient::try_new(vec![stop(RED, 0.0), stop(GREEN, 0.5), stop(BLUE, 1.0)]).unwrap()
+    }
+
+    #[test]
+    fn sampling_at_stops_gives_stop_colors() {
+        let gradient = red_green_blue();
+        assert_eq!(gradient.sample(0.0), RED);
+        assert_eq!(gradient.sample(0.5), GREEN);
+        assert_eq!(gradient.sample(1.0), BLUE);
+        // positions outside of the stops take the color of the nearest one
+        assert_eq!(gradient.sample(-1.0), RED);
+        assert_eq!(gradient.sample(2.0), BLUE);
+        let middle = gradient.sample(0.25);
+        assert_eq!((middle.r_u8(), middle.g_u8(), middle.b_u8()), (128, 128, 0));
+    }
+
+    #[test]
+    fn interpolation_changes_the_midpoint() {
+        let red_green = Gradient::try_new(vec![stop(RED, 0.0), stop(GREEN, 1.0)]).unwrap();
+        let linear = red_green
+            .clone()
+            .with_interpolation(ColorInterpolation::LinearRgb);
+        let hsl = red_green.with_interpolation(ColorInterpolation::Hsl);
+
+        // HSL goes around the color wheel through yellow, while linear light dims both channels evenly
+        assert_eq!(rgb(hsl.sample(0.5)), (255, 255, 0));
+        assert_eq!(rgb(linear.sample(0.5)), (188, 188, 0));
+
+        // the stops handed to sRGB-only backends keep the difference
+        let midpoint = |gradient: &Gradient<'_>| {
+            gradient
+                .srgb_stops()
+                .iter()
+                .find(|stop| stop.position.into_inner() == 0.5)
+                .map(|stop| rgb(stop.color))
+        };
+        assert_eq!(midpoint(&hsl), Some((255, 255, 0)));
+        assert_eq!(midpoint(&linear), Some((188, 188, 0)));
+    }
+
+    #[test]
+    fn radial_gradient_spreads_from_center() {
+        let radial = RadialGradient::new(point(5.0, 5.0), 4.0, red_green_blue());
+        assert_eq!(radial.sample_at(point(5.0, 5.0)), RED);
+        assert_eq!(radial.sample_at(point(7.0, 5.0)), GREEN);
+        assert_eq!(radial.sample_at(point(5.0, 9.0)), BLUE);
+        assert_eq!(radial.sample_at(point(50.0, 50.0)), BLUE);
+
+        let pixels = radial.rasterize(Rect::new(point(0.0, 0.0), lyon_geom::size(3.0, 2.0)));
+        assert_eq!(pixels.len(), 3 * 2 * 4);
+    }
+
+    #[test]
+    fn spread_modes() {
+        let apply = |mode: SpreadMode| (mode.apply(-0.25), mode.apply(1.5));
+        assert_eq!(apply(SpreadMode::Pad), (0.0, 1.0));
+        assert_eq!(apply(SpreadMode::Repeat), (0.75, 0.5));
+        assert_eq!(apply(SpreadMode::Reflect), (0.25, 0.5));
+    }
+
+    #[test]
+    fn linear_gradient_projects_points() {
+        let stops = Gradient::try_new(vec![stop(RED, 0.0), stop(BLUE, 1.0)]).unwrap();
+        let linear =
+            LinearGradient::new(point(0.0, 0.0), point(4.0, 0.0), stops, SpreadMode::Repeat);
+        assert_eq!(linear.parameter(point(1.0, 3.0)), 0.25);
+        assert_eq!(linear.parameter(point(-1.0, 0.0)), -0.25);
+        // repeating brings a point past the end back to the start
+        assert_eq!(linear.sample_along(point(4.0, 0.0)), RED);
+        assert_eq!(linear.sample_along(point(3.999, 0.0)).b_u8(), 255);
+
+        let padded = LinearGradient {
+            spread: SpreadMode::Pad,
+            ..linear
+        };
+        assert_eq!(padded.sample_along(point(6.0, 0.0)), BLUE);
+        assert_eq!(padded.sample_along(point(-6.0, 0.0)), RED);
+    }
+
+    #[test]
+    fn unsorted_stops_are_normalized() {
+        let gradient = Gradient::try_new(vec![
+            stop(BLUE, 1.0),
+            stop(RED, 0.0),
+            stop(RED, 0.5),
+            stop(GREEN, 0.5),
+        ])
+        .unwrap();
+        let positions: Vec<f32> = gradient.iter().map(|s| s.position.into_inner()).collect();
+        assert_eq!(positions, [0.0, 0.5, 1.0]);
+        // the last of the duplicate stops wins
+        assert_eq!(gradient.as_slice()[1].color, GREEN);
+        assert_eq!(gradient.sample(0.5), GREEN);
+    }
+
+    #[test]
+    fn single_stop_is_an_error() {
+        assert!(matches!(
+            Gradient::try_new(vec![stop(RED, 0.0)]),
+            Err(crate::Error::StaticMsg(_))
+        ));
+    }
+
+    #[test]
+    fn conic_gradient_quarter_turns() {
+        use std::f32::consts::{FRAC_PI_2, PI};
+
+        let stops = Gradient::try_new(vec![
+            stop(RED, 0.0),
+            stop(GREEN, 0.25),
+            stop(BLUE, 0.5),
+            stop(RED, 1.0),
+        ])
+        .unwrap();
+        let conic = ConicGradient::new(point(0.0, 0.0), Angle::zero(), stops);
+        assert_eq!(rgb(conic.sample(Angle::radians(0.0))), rgb(RED));
+        assert_eq!(rgb(conic.sample(Angle::radians(FRAC_PI_2))), rgb(GREEN));
+        assert_eq!(rgb(conic.sample(Angle::radians(PI))), rgb(BLUE));
+        assert_eq!(
+            rgb(conic.sample(Angle::radians(2.0 * PI + FRAC_PI_2))),
+            rgb(GREEN)
+        );
+        assert_eq!(
+            rgb(conic.sample(Angle::radians(-3.0 * FRAC_PI_2))),
+            rgb(GREEN)
+        );
+        // the last stop meets the first one at the start angle
+        assert_eq!(
+            rgb(conic.sample(Angle::radians(2.0 * PI - 0.001))),
+            rgb(RED)
+        );
+        assert_eq!(rgb(conic.sample_at(point(0.0, 5.0))), rgb(GREEN));
+
+        let rotated = ConicGradient {
+            start_angle: Angle::radians(FRAC_PI_2),
+            ..conic
+        };
+        assert_eq!(rgb(rotated.sample(Angle::radians(FRAC_PI_2))), rgb(RED));
+        assert_eq!(rgb(rotated.sample(Angle::radians(PI))), rgb(GREEN));
+    }
+}
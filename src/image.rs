@@ -1,6 +1,8 @@
 // MIT/Apache2 License
 
-use std::num::NonZeroUsize;
+use crate::intensity::LUMA_WEIGHTS;
+use lyon_geom::{Point, Rect, Size};
+use std::{borrow::Cow, num::NonZeroUsize};
 
 /// An image on the server side.
 ///
@@ -48,23 +50,309 @@ pub enum ImageFormat {
     /// Every group of four bytes represents the intensity of the red, blue, green and alpha components. Four
     /// bytes per pixel.
     Rgba,
+    /// Every group of four bytes represents the intensity of the blue, green, red and alpha components. Four
+    /// bytes per pixel.
+    Bgra,
 }
 
 impl ImageFormat {
+    #[inline]
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            ImageFormat::Grayscale => 1,
+            ImageFormat::Rgb => 3,
+            ImageFormat::Rgba | ImageFormat::Bgra => 4,
+        }
+    }
+
     #[inline]
     pub fn has_alpha_component(self) -> bool {
-        matches!(self, ImageFormat::Rgba)
+        matches!(self, ImageFormat::Rgba | ImageFormat::Bgra)
     }
 
     #[inline]
     pub fn alpha_component(self, pixel: &[u8]) -> u8 {
         match self {
-            ImageFormat::Rgba => pixel[3],
+            ImageFormat::Rgba | ImageFormat::Bgra => pixel[3],
             _ => panic!("Invalid format"),
         }
     }
 }
 
+/// An image on the client side, stored as a buffer of pixels.
+///
+/// This can be turned into a server-side `Image` using `Surface::create_image`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImageBuffer {
+    width: u32,
+    height: u32,
+    format: ImageFormat,
+    data: Vec<u8>,
+}
+
+impl ImageBuffer {
+    /// Wrap an existing buffer of pixels. Returns an error if the length of the buffer does not match the
+    /// image's dimensions.
+    #[inline]
+    pub fn from_raw(
+        width: u32,
+        height: u32,
+        format: ImageFormat,
+        data: Vec<u8>,
+    ) -> crate::Result<ImageBuffer> {
+        check_len(&data, width, height, format)?;
+
+        Ok(ImageBuffer {
+            width,
+            height,
+            format,
+            data,
+        })
+    }
+
+    /// Decode a PNG image into an RGBA buffer.
+    #[cfg(feature = "png")]
+    #[inline]
+    pub fn from_png_bytes(bytes: &[u8]) -> crate::Result<ImageBuffer> {
+        let decode_err = |e: png::DecodingError| crate::Error::ImageDecode(e.to_string());
+
+        // expand palettes and low bit depths so that every channel is a byte
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(decode_err)?;
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).map_err(decode_err)?;
+        data.truncate(info.buffer_size());
+
+        let data = match info.color_type {
+            png::ColorType::Rgba => data,
+            png::ColorType::Rgb => rgb_to_rgba(&data),
+            png::ColorType::Grayscale => data.iter().flat_map(|&l| [l, l, l, 255]).collect(),
+            png::ColorType::GrayscaleAlpha => data
+                .chunks_exact(2)
+                .flat_map(|la| [la[0], la[0], la[0], la[1]])
+                .collect(),
+            png::ColorType::Indexed => {
+                return Err(crate::Error::ImageDecode(
+                    "Palette was not expanded".to_string(),
+                ))
+            }
+        };
+
+        Ok(ImageBuffer {
+            width: info.width,
+            height: info.height,
+            format: ImageFormat::Rgba,
+            data,
+        })
+    }
+
+    /// Decode a JPEG image into an RGBA buffer.
+    #[cfg(feature = "jpeg")]
+    #[inline]
+    pub fn from_jpeg_bytes(bytes: &[u8]) -> crate::Result<ImageBuffer> {
+        let mut decoder = jpeg_decoder::Decoder::new(bytes);
+        let data = decoder
+            .decode()
+            .map_err(|e| crate::Error::ImageDecode(e.to_string()))?;
+        let info = decoder
+            .info()
+            .ok_or_else(|| crate::Error::ImageDecode("Missing image info".to_string()))?;
+
+        let data = match info.pixel_format {
+            jpeg_decoder::PixelFormat::RGB24 => rgb_to_rgba(&data),
+            jpeg_decoder::PixelFormat::L8 => data.iter().flat_map(|&l| [l, l, l, 255]).collect(),
+            // big endian, so the high byte comes first
+            jpeg_decoder::PixelFormat::L16 => data
+                .chunks_exact(2)
+                .flat_map(|l| [l[0], l[0], l[0], 255])
+                .collect(),
+            jpeg_decoder::PixelFormat::CMYK32 => data
+                .chunks_exact(4)
+                .flat_map(|cmyk| {
+                    let k = 255 - cmyk[3] as u32;
+                    let channel = |c: u8| ((255 - c as u32) * k / 255) as u8;
+                    [channel(cmyk[0]), channel(cmyk[1]), channel(cmyk[2]), 255]
+                })
+                .collect(),
+        };
+
+        Ok(ImageBuffer {
+            width: info.width.into(),
+            height: info.height.into(),
+            format: ImageFormat::Rgba,
+            data,
+        })
+    }
+
+    /// The width of the image, in pixels.
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the image, in pixels.
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The format of the pixels in this image.
+    #[inline]
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+
+    /// The bytes making up the pixels of this image.
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Get the bytes making up the pixels of this image.
+    #[inline]
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// The bytes making up the pixels of this image, along with their format.
+    #[inline]
+    pub fn as_raw(&self) -> (&[u8], ImageFormat) {
+        (&self.data, self.format)
+    }
+
+    /// Convert this image to grayscale, using the perceived brightness of each pixel. If this image has an
+    /// alpha channel, it is kept and the result is in the `Rgba` format.
+    #[inline]
+    pub fn to_grayscale(&self) -> ImageBuffer {
+        let luma = |pixel: [u8; 4]| {
+            let (r, g, b) = (pixel[0] as f32, pixel[1] as f32, pixel[2] as f32);
+            (r * LUMA_WEIGHTS[0] + g * LUMA_WEIGHTS[1] + b * LUMA_WEIGHTS[2])
+                .round()
+                .min(255.0) as u8
+        };
+
+        let rgba = to_rgba(&self.data, self.width, self.height, self.format);
+        let pixels = rgba.chunks_exact(4).map(|p| [p[0], p[1], p[2], p[3]]);
+        let (format, data) = if self.format.has_alpha_component() {
+            (
+                ImageFormat::Rgba,
+                pixels
+                    .flat_map(|p| {
+                        let l = luma(p);
+                        [l, l, l, p[3]]
+                    })
+                    .collect(),
+            )
+        } else {
+            (ImageFormat::Grayscale, pixels.map(luma).collect())
+        };
+
+        ImageBuffer {
+            width: self.width,
+            height: self.height,
+            format,
+            data,
+        }
+    }
+
+    /// Scale the alpha of every pixel in this image by the perceived brightness of the corresponding pixel in
+    /// `mask`. The result is in the `Rgba` format. Returns an error if the two images are not the same size.
+    #[inline]
+    pub fn apply_mask(&self, mask: &ImageBuffer) -> crate::Result<ImageBuffer> {
+        if (self.width, self.height) != (mask.width, mask.height) {
+            return Err(crate::Error::StaticMsg(
+                "Mask is not the same size as the image",
+            ));
+        }
+
+        let mask = mask.to_grayscale();
+        let bpp = mask.format.bytes_per_pixel();
+        let mut data = to_rgba(&self.data, self.width, self.height, self.format);
+        data.chunks_exact_mut(4)
+            .zip(mask.data.chunks_exact(bpp))
+            .for_each(|(pixel, mask)| {
+                pixel[3] = ((pixel[3] as u32 * mask[0] as u32 + 127) / 255) as u8;
+            });
+
+        Ok(ImageBuffer {
+            width: self.width,
+            height: self.height,
+            format: ImageFormat::Rgba,
+            data,
+        })
+    }
+
+    /// Copy a region of this image into a new image.
+    #[inline]
+    pub fn crop(&self, rect: Rect<u32>) -> crate::Result<ImageBuffer> {
+        if rect.is_empty() {
+            return Err(crate::Error::StaticMsg("Cannot crop an empty region"));
+        }
+        let in_bounds =
+            |start: u32, len: u32, max: u32| start.checked_add(len).map_or(false, |end| end <= max);
+        if !in_bounds(rect.origin.x, rect.size.width, self.width)
+            || !in_bounds(rect.origin.y, rect.size.height, self.height)
+        {
+            return Err(crate::Error::RegionOutOfBounds);
+        }
+
+        let bpp = self.format.bytes_per_pixel();
+        let stride = self.width as usize * bpp;
+        let row_len = rect.size.width as usize * bpp;
+        let data = (rect.min_y()..rect.max_y())
+            .flat_map(|y| {
+                let start = y as usize * stride + rect.min_x() as usize * bpp;
+                &self.data[start..start + row_len]
+            })
+            .copied()
+            .collect();
+
+        Ok(ImageBuffer {
+            width: rect.size.width,
+            height: rect.size.height,
+            format: self.format,
+            data,
+        })
+    }
+}
+
+/// Add an opaque alpha channel to RGB pixels.
+#[cfg(any(feature = "png", feature = "jpeg"))]
+#[inline]
+fn rgb_to_rgba(data: &[u8]) -> Vec<u8> {
+    data.chunks_exact(3)
+        .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+        .collect()
+}
+
+/// Make sure that a region lies entirely within an image of the given size.
+#[inline]
+pub(crate) fn check_image_region(region: Rect<f32>, size: Size<f32>) -> crate::Result {
+    if Rect::new(Point::origin(), size).contains_rect(&region) {
+        Ok(())
+    } else {
+        Err(crate::Error::RegionOutOfBounds)
+    }
+}
+
+/// Make sure that a buffer holds exactly the pixels of an image with the given dimensions and format.
+#[inline]
+pub(crate) fn check_len(
+    bytes: &[u8],
+    width: u32,
+    height: u32,
+    format: ImageFormat,
+) -> crate::Result {
+    if bytes.len() == width as usize * height as usize * format.bytes_per_pixel() {
+        Ok(())
+    } else {
+        Err(crate::Error::StaticMsg(
+            "Buffer length does not match the image's dimensions",
+        ))
+    }
+}
+
 /// Create an iterator over a set of pixels from a set of bytes.
 #[inline]
 pub(crate) fn iterate_pixels(
@@ -73,11 +361,158 @@ pub(crate) fn iterate_pixels(
     height: u32,
     format: ImageFormat,
 ) -> impl Iterator<Item = &[u8]> {
-    let chunks = match format {
-        ImageFormat::Grayscale => bytes.chunks(1),
-        ImageFormat::Rgb => bytes.chunks(3),
-        ImageFormat::Rgba => bytes.chunks(4),
-    };
+    bytes
+        .chunks(format.bytes_per_pixel())
+        .take((width * height) as usize)
+}
+
+/// Convert a set of pixels in any format to RGBA pixels.
+#[inline]
+pub(crate) fn to_rgba(bytes: &[u8], width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+    iterate_pixels(bytes, width, height, format)
+        .flat_map(|pixel| match format {
+            ImageFormat::Grayscale => [pixel[0], pixel[0], pixel[0], 255],
+            ImageFormat::Rgb => [pixel[0], pixel[1], pixel[2], 255],
+            ImageFormat::Rgba => [pixel[0], pixel[1], pixel[2], pixel[3]],
+            ImageFormat::Bgra => [pixel[2], pixel[1], pixel[0], pixel[3]],
+        })
+        .collect()
+}
+
+/// Swap the red and blue components of BGRA pixels, so that backends only need to deal with RGB orderings.
+#[inline]
+pub(crate) fn bgra_to_rgba(bytes: &[u8], format: ImageFormat) -> (Cow<'_, [u8]>, ImageFormat) {
+    match format {
+        ImageFormat::Bgra => {
+            let mut bytes = bytes.to_vec();
+            crate::util::swizzle_bgra_to_rgba(&mut bytes);
+            (Cow::Owned(bytes), ImageFormat::Rgba)
+        }
+        format => (Cow::Borrowed(bytes), format),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2x2 RGBA image with red and green on top, and blue and transparent white on the bottom.
+    #[cfg(feature = "png")]
+    const TINY_PNG: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x08, 0x06, 0x00, 0x00, 0x00, 0x72,
+        0xb6, 0x0d, 0x24, 0x00, 0x00, 0x00, 0x13, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0xf8,
+        0xcf, 0xc0, 0xf0, 0x1f, 0x0c, 0x81, 0x34, 0x08, 0x30, 0x00, 0x00, 0x48, 0xc9, 0x08, 0xf8,
+        0xc5, 0x34, 0xfd, 0x05, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60,
+        0x82,
+    ];
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn decode_tiny_png() {
+        let image = ImageBuffer::from_png_bytes(TINY_PNG).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 2));
+        assert_eq!(image.format(), ImageFormat::Rgba);
+        assert_eq!(
+            image.data(),
+            &[255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 0][..]
+        );
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn malformed_png_is_a_decode_error() {
+        assert!(matches!(
+            ImageBuffer::from_png_bytes(&TINY_PNG[..20]),
+            Err(crate::Error::ImageDecode(_))
+        ));
+    }
+
+    #[cfg(feature = "jpeg")]
+    #[test]
+    fn malformed_jpeg_is_a_decode_error() {
+        assert!(matches!(
+            ImageBuffer::from_jpeg_bytes(b"not a jpeg"),
+            Err(crate::Error::ImageDecode(_))
+        ));
+    }
+
+    #[test]
+    fn from_raw_rejects_length_mismatch() {
+        assert!(matches!(
+            ImageBuffer::from_raw(2, 2, ImageFormat::Rgba, vec![0; 15]),
+            Err(crate::Error::StaticMsg(_))
+        ));
+        assert!(ImageBuffer::from_raw(2, 2, ImageFormat::Rgb, vec![0; 12]).is_ok());
+    }
 
-    chunks.take((width * height) as usize)
+    #[test]
+    fn bgra_round_trip() {
+        let bgra = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        let image = ImageBuffer::from_raw(2, 1, ImageFormat::Bgra, bgra.clone()).unwrap();
+        assert_eq!(image.as_raw(), (&bgra[..], ImageFormat::Bgra));
+
+        let (rgba, format) = bgra_to_rgba(image.data(), image.format());
+        assert_eq!(format, ImageFormat::Rgba);
+        assert_eq!(&*rgba, &[3, 2, 1, 4, 7, 6, 5, 8][..]);
+    }
+
+    #[test]
+    fn crop_full_image_is_a_copy() {
+        let data: Vec<u8> = (0..2 * 3 * 3).collect();
+        let image = ImageBuffer::from_raw(2, 3, ImageFormat::Rgb, data).unwrap();
+        let cropped = image
+            .crop(Rect::new(Point::origin(), Size::new(2, 3)))
+            .unwrap();
+        assert_eq!(cropped, image);
+
+        let corner = image
+            .crop(Rect::new(Point::new(1, 2), Size::new(1, 1)))
+            .unwrap();
+        assert_eq!(corner.data(), &[15, 16, 17][..]);
+    }
+
+    #[test]
+    fn bad_crops_are_errors() {
+        let image = ImageBuffer::from_raw(2, 2, ImageFormat::Grayscale, vec![0; 4]).unwrap();
+        assert!(matches!(
+            image.crop(Rect::new(Point::origin(), Size::new(0, 2))),
+            Err(crate::Error::StaticMsg(_))
+        ));
+        assert!(matches!(
+            image.crop(Rect::new(Point::new(1, 1), Size::new(2, 1))),
+            Err(crate::Error::RegionOutOfBounds)
+        ));
+        assert!(image
+            .crop(Rect::new(Point::new(u32::MAX, 0), Size::new(1, 1)))
+            .is_err());
+    }
+
+    #[test]
+    fn grayscale_red_is_its_luminance() {
+        let red = ImageBuffer::from_raw(1, 1, ImageFormat::Rgb, vec![255, 0, 0]).unwrap();
+        let gray = red.to_grayscale();
+        assert_eq!(gray.format(), ImageFormat::Grayscale);
+        assert_eq!(gray.data(), &[54][..]);
+    }
+
+    #[test]
+    fn grayscale_keeps_alpha() {
+        let pixels = vec![255, 0, 0, 100, 0, 0, 255, 7];
+        let image = ImageBuffer::from_raw(2, 1, ImageFormat::Rgba, pixels).unwrap();
+        let gray = image.to_grayscale();
+        assert_eq!(gray.format(), ImageFormat::Rgba);
+        assert_eq!(gray.data(), &[54, 54, 54, 100, 18, 18, 18, 7][..]);
+    }
+
+    #[test]
+    fn half_intensity_mask_halves_alpha() {
+        let image = ImageBuffer::from_raw(1, 1, ImageFormat::Rgba, vec![10, 20, 30, 200]).unwrap();
+        let mask = ImageBuffer::from_raw(1, 1, ImageFormat::Grayscale, vec![128]).unwrap();
+        let masked = image.apply_mask(&mask).unwrap();
+        assert_eq!(masked.data(), &[10, 20, 30, 100][..]);
+
+        let wide = ImageBuffer::from_raw(2, 1, ImageFormat::Grayscale, vec![128; 2]).unwrap();
+        assert!(image.apply_mask(&wide).is_err());
+    }
 }
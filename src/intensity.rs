@@ -1,9 +1,13 @@
 // MIT/Apache2 License
 
+use crate::Color;
 use core::ops;
 use num_traits::{AsPrimitive, Bounded};
 use ordered_float::NotNan;
 
+/// How much the red, green and blue components of a color contribute to its perceived brightness.
+pub const LUMA_WEIGHTS: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
 /// A popular concept in is a range that goes from zero to one, defining intensity of a color or the
 /// stop of a color in a `gradient. This type is essentially a wrapper around an `f32`, but with two invariants:
 ///
@@ -34,7 +38,7 @@ impl Intensity {
     /// returns `None`.
     #[must_use]
     #[inline]
-    pub fn new(inner: f32) -> Option<Intensity> {
+    pub const fn new(inner: f32) -> Option<Intensity> {
         if inner.is_nan() || inner < 0.0 || inner > 1.0 {
             None
         } else {
@@ -44,6 +48,17 @@ impl Intensity {
         }
     }
 
+    /// Get the perceived brightness of a color, weighting its components by the Rec. 709 luma coefficients.
+    /// The alpha component is ignored.
+    #[must_use]
+    #[inline]
+    pub fn from_color(color: Color) -> Intensity {
+        let luma = color.red() * LUMA_WEIGHTS[0]
+            + color.green() * LUMA_WEIGHTS[1]
+            + color.blue() * LUMA_WEIGHTS[2];
+        Intensity::new(luma.clamp(0.0, 1.0)).expect("Luma should be in range")
+    }
+
     /// Get the inner value of the `Intensity`.
     #[must_use]
     #[inline]
@@ -83,3 +98,15 @@ impl From<Intensity> for f32 {
         i.into_inner()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::consts::{RED, WHITE};
+
+    #[test]
+    fn intensity_of_colors() {
+        assert!((Intensity::from_color(RED).into_inner() - LUMA_WEIGHTS[0]).abs() < 1e-6);
+        assert_eq!(Intensity::from_color(WHITE).into_inner(), 1.0);
+    }
+}
@@ -7,28 +7,48 @@ mod error;
 
 pub mod color;
 pub mod fill;
+pub mod font;
 pub mod gradient;
 pub mod image;
 pub mod intensity;
+pub mod stroke;
 pub mod surface;
+pub mod transform;
 
 mod ellipse;
-mod path;
+pub mod path;
 
 #[cfg(all(unix, feature = "breadx"))]
 pub mod breadx;
 #[cfg(all(windows, feature = "yaww"))]
 pub mod yaww;
+#[cfg(feature = "raqote")]
+pub mod raqote;
+#[cfg(feature = "softbuffer")]
+pub mod softbuffer;
+#[cfg(feature = "tiny-skia")]
+pub mod tiny_skia;
+#[cfg(feature = "wgpu")]
+pub mod wgpu;
 
 pub(crate) mod util;
 
+#[cfg(feature = "serde")]
+pub mod serialize;
+
 pub use color::*;
 pub use ellipse::*;
 pub use error::*;
 pub use fill::*;
+pub use font::*;
 pub use gradient::*;
 pub use image::*;
 pub use intensity::*;
+pub use stroke::*;
 pub use surface::*;
+pub use transform::*;
 
 pub(crate) use path::*;
+
+pub use lyon_geom;
+pub use lyon_path;
@@ -0,0 +1,699 @@
+// MIT/Apache2 License
+
+//! Path construction and helpers.
+
+use crate::{fill::FillMode, surface::DEFAULT_TOLERANCE, util::flatten_arc, Transform};
+use lyon_geom::{
+    Angle, Arc, ArcFlags, CubicBezierSegment, LineSegment, Point, QuadraticBezierSegment, Rect,
+    SvgArc, Vector,
+};
+use lyon_path::{iterator::PathIterator, path::Builder, Path, PathEvent, PathSlice};
+use std::f32::consts::{FRAC_PI_2, PI};
+
+mod repair;
+pub use repair::*;
+
+/// Simple combinator to turn a path into a lines.
+#[inline]
+pub(crate) fn path_to_lines(
+    i: impl IntoIterator<Item = PathEvent>,
+    tolerance: f32,
+) -> impl Iterator<Item = LineSegment<f32>> {
+    i.into_iter()
+        .flattened(tolerance)
+        .filter_map(|pe| match pe {
+            PathEvent::Begin { .. } => None,
+            PathEvent::Line { from, to } => Some(LineSegment { from, to }),
+            PathEvent::End { last, first, close } => {
+                if close {
+                    Some(LineSegment {
+                        from: last,
+                        to: first,
+                    })
+                } else {
+                    None
+                }
+            }
+            _ => unreachable!(),
+        })
+}
+
+/// Simple combinator to turn a path into the points of a polygon.
+#[inline]
+pub(crate) fn path_to_points(
+    i: impl IntoIterator<Item = PathEvent>,
+    tolerance: f32,
+) -> impl Iterator<Item = Point<f32>> {
+    i.into_iter().flattened(tolerance).map(|pe| match pe {
+        PathEvent::Begin { at } => at,
+        PathEvent::Line { to, .. } => to,
+        PathEvent::End { last, .. } => last,
+        _ => unreachable!(),
+    })
+}
+
+/// Flatten a path into one polygon for each of its subpaths, so that they can be filled together without being
+/// bridged into one shape.
+#[inline]
+pub(crate) fn path_to_polygons(
+    i: impl IntoIterator<Item = PathEvent>,
+    tolerance: f32,
+) -> Vec<Vec<Point<f32>>> {
+    let mut polys: Vec<Vec<Point<f32>>> = vec![];
+    i.into_iter().flattened(tolerance).for_each(|pe| match pe {
+        PathEvent::Begin { at } => polys.push(vec![at]),
+        PathEvent::Line { to, .. } => {
+            if let Some(poly) = polys.last_mut() {
+                poly.push(to);
+            }
+        }
+        PathEvent::End { .. } => {}
+        _ => unreachable!(),
+    });
+    polys
+}
+
+#[inline]
+pub(crate) fn path_from_curve(curve: CubicBezierSegment<f32>) -> Path {
+    let mut builder = Path::builder();
+    builder.begin(curve.from);
+    builder.cubic_bezier_to(curve.ctrl1, curve.ctrl2, curve.to);
+    builder.end(false);
+    builder.build()
+}
+
+#[inline]
+pub(crate) fn path_from_arc(arc: Arc<f32>, tolerance: f32) -> Option<Path> {
+    let mut builder = Path::builder();
+    let mut iter = flatten_arc(&arc, tolerance).into_iter();
+    builder.begin(iter.next()?);
+
+    let mut builder = build_arc(builder, iter);
+
+    builder.end(false);
+    Some(builder.build())
+}
+
+/// Build the chord of an arc: the arc itself, closed by a line from its end back to its start.
+#[inline]
+pub(crate) fn path_from_arc_closed(arc: Arc<f32>, tolerance: f32) -> Option<Path> {
+    let mut builder = Path::builder();
+    builder.begin(arc.from());
+    let mut builder = build_arc(builder, flatten_arc(&arc, tolerance).into_iter().skip(1));
+    builder.close();
+    Some(builder.build())
+}
+
+/// Clamp the corner radii of a rounded rectangle so that the corners never overlap.
+#[inline]
+pub(crate) fn clamp_round_rect_radii(rect: Rect<f32>, rx: f32, ry: f32) -> (f32, f32) {
+    (
+        rx.max(0.0).min(rect.size.width.abs() / 2.0),
+        ry.max(0.0).min(rect.size.height.abs() / 2.0),
+    )
+}
+
+#[inline]
+pub(crate) fn path_from_round_rect(rect: Rect<f32>, rx: f32, ry: f32, tolerance: f32) -> Path {
+    let (rx, ry) = clamp_round_rect_radii(rect, rx, ry);
+    let mut builder = Path::builder();
+
+    // without any radius, this is just a rectangle
+    if approx::abs_diff_eq!(rx, 0.0) || approx::abs_diff_eq!(ry, 0.0) {
+        builder.begin(rect.min());
+        builder.line_to(Point::new(rect.max_x(), rect.min_y()));
+        builder.line_to(rect.max());
+        builder.line_to(Point::new(rect.min_x(), rect.max_y()));
+        builder.close();
+        return builder.build();
+    }
+
+    let corner = |x: f32, y: f32, start_angle: f32| Arc {
+        center: Point::new(x, y),
+        radii: Vector::new(rx, ry),
+        start_angle: Angle::radians(start_angle),
+        sweep_angle: Angle::radians(FRAC_PI_2),
+        x_rotation: Angle::zero(),
+    };
+    let corners = [
+        corner(rect.max_x() - rx, rect.min_y() + ry, -FRAC_PI_2),
+        corner(rect.max_x() - rx, rect.max_y() - ry, 0.0),
+        corner(rect.min_x() + rx, rect.max_y() - ry, FRAC_PI_2),
+        corner(rect.min_x() + rx, rect.min_y() + ry, PI),
+    ];
+
+    builder.begin(Point::new(rect.min_x() + rx, rect.min_y()));
+    let mut builder = IntoIterator::into_iter(corners).fold(builder, |mut builder, arc| {
+        builder.line_to(arc.from());
+        build_arc(builder, flatten_arc(&arc, tolerance).into_iter().skip(1))
+    });
+    builder.close();
+    builder.build()
+}
+
+#[inline]
+fn build_arc(builder: Builder, pt_iter: impl Iterator<Item = Point<f32>>) -> Builder {
+    pt_iter.fold(builder, |mut builder, point| {
+        builder.line_to(point);
+        builder
+    })
+}
+
+/// Stroke a path by offsetting it by half of `width` on both sides, producing an outline that can be filled
+/// to draw the stroke. Corners are mitered, falling back to bevels when they get too sharp, and the ends of
+/// open subpaths are butt-capped. This is used by backends that can't natively stroke thick curves.
+///
+/// Offsetting a curve magnifies the error introduced by flattening it, so `tolerance` is tightened for wide
+/// strokes to keep them from looking faceted.
+#[inline]
+pub fn stroke(path: PathSlice<'_>, width: f32, tolerance: f32) -> Path {
+    let half = width.abs() / 2.0;
+    let mut builder = Path::builder();
+    let mut points: Vec<Point<f32>> = vec![];
+
+    path.iter()
+        .flattened(tolerance / half.max(1.0))
+        .for_each(|pe| match pe {
+            PathEvent::Begin { at } => {
+                points.clear();
+                points.push(at);
+            }
+            PathEvent::Line { to, .. } => {
+                if points.last() != Some(&to) {
+                    points.push(to);
+                }
+            }
+            PathEvent::End { close, .. } => stroke_subpath(&mut builder, &mut points, close, half),
+            _ => unreachable!(),
+        });
+
+    builder.build()
+}
+
+/// The longest a miter can be, relative to half of the line width, before it is beveled instead.
+const STROKE_MITER_LIMIT: f32 = 4.0;
+
+/// Add the outline of one flattened subpath to a builder.
+#[inline]
+fn stroke_subpath(builder: &mut Builder, points: &mut Vec<Point<f32>>, close: bool, half: f32) {
+    if close && points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    if points.len() < 2 || half <= 0.0 {
+        return;
+    }
+
+    // offsetting the reversed subpath to its left gives the right side, in reverse order
+    let left = offset_points(points, close, half);
+    points.reverse();
+    let right = offset_points(points, close, half);
+
+    if close {
+        // the two rings run in opposite directions, so the inside of the subpath is left unfilled
+        outline_ring(builder, left.into_iter());
+        outline_ring(builder, right.into_iter());
+    } else {
+        outline_ring(builder, left.into_iter().chain(right));
+    }
+}
+
+/// Offset the points of a flattened subpath to their left by `half`.
+#[inline]
+fn offset_points(points: &[Point<f32>], close: bool, half: f32) -> Vec<Point<f32>> {
+    let len = points.len();
+    let segments = if close { len } else { len - 1 };
+    let normal = |i: usize| {
+        let v = (points[(i + 1) % len] - points[i]).normalize();
+        Vector::new(-v.y, v.x)
+    };
+
+    let mut offset = Vec::with_capacity(len);
+    (0..len).for_each(|i| {
+        let pt = points[i];
+        let incoming = match i {
+            0 if close => Some(normal(segments - 1)),
+            0 => None,
+            i => Some(normal(i - 1)),
+        };
+        let outgoing = if i < segments { Some(normal(i)) } else { None };
+
+        match (incoming, outgoing) {
+            (Some(n1), Some(n2)) => {
+                // the miter's length relative to half the width is sqrt(2 / (1 + cos(theta)))
+                let k = 1.0 + n1.dot(n2);
+                if k * STROKE_MITER_LIMIT * STROKE_MITER_LIMIT > 2.0 {
+                    offset.push(pt + (n1 + n2) * (half / k));
+                } else {
+                    offset.push(pt + n1 * half);
+                    offset.push(pt + n2 * half);
+                }
+            }
+            (Some(n), None) | (None, Some(n)) => offset.push(pt + n * half),
+            (None, None) => {}
+        }
+    });
+    offset
+}
+
+#[inline]
+fn outline_ring(builder: &mut Builder, mut points: impl Iterator<Item = Point<f32>>) {
+    if let Some(first) = points.next() {
+        builder.begin(first);
+        points.for_each(|pt| {
+            builder.line_to(pt);
+        });
+        builder.close();
+    }
+}
+
+/// A builder for `Path`s, tracking the current point so that arcs can be drawn relative to it.
+#[derive(Clone)]
+pub struct PathBuilder {
+    builder: Builder,
+    current: Point<f32>,
+    start: Point<f32>,
+    in_subpath: bool,
+}
+
+impl Default for PathBuilder {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PathBuilder {
+    /// Create a new, empty path builder.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            builder: Path::builder(),
+            current: Point::zero(),
+            start: Point::zero(),
+            in_subpath: false,
+        }
+    }
+
+    /// Begin a new subpath at the given point, ending the current one if there is one.
+    #[inline]
+    pub fn move_to(&mut self, to: Point<f32>) -> &mut Self {
+        if self.in_subpath {
+            self.builder.end(false);
+        }
+        self.builder.begin(to);
+        self.current = to;
+        self.start = to;
+        self.in_subpath = true;
+        self
+    }
+
+    /// Add a line from the current point to the given point.
+    #[inline]
+    pub fn line_to(&mut self, to: Point<f32>) -> &mut Self {
+        self.ensure_subpath();
+        self.builder.line_to(to);
+        self.current = to;
+        self
+    }
+
+    /// Add a quadratic bezier curve from the current point to the given point.
+    #[inline]
+    pub fn quad_to(&mut self, ctrl: Point<f32>, to: Point<f32>) -> &mut Self {
+        self.ensure_subpath();
+        self.builder.quadratic_bezier_to(ctrl, to);
+        self.current = to;
+        self
+    }
+
+    /// Add a cubic bezier curve from the current point to the given point.
+    #[inline]
+    pub fn cubic_to(&mut self, ctrl1: Point<f32>, ctrl2: Point<f32>, to: Point<f32>) -> &mut Self {
+        self.ensure_subpath();
+        self.builder.cubic_bezier_to(ctrl1, ctrl2, to);
+        self.current = to;
+        self
+    }
+
+    /// Add an elliptical arc from the current point to the given point, using SVG arc semantics.
+    #[inline]
+    pub fn arc_to(
+        &mut self,
+        radii: Vector<f32>,
+        x_rotation: Angle<f32>,
+        flags: ArcFlags,
+        to: Point<f32>,
+    ) -> &mut Self {
+        self.ensure_subpath();
+        let arc = SvgArc {
+            from: self.current,
+            to,
+            radii,
+            x_rotation,
+            flags,
+        };
+
+        if arc.is_straight_line() {
+            self.builder.line_to(to);
+        } else {
+            let builder = &mut self.builder;
+            arc.for_each_quadratic_bezier(&mut |q: &QuadraticBezierSegment<f32>| {
+                builder.quadratic_bezier_to(q.ctrl, q.to);
+            });
+        }
+
+        self.current = to;
+        self
+    }
+
+    /// Close the current subpath, returning to its starting point.
+    #[inline]
+    pub fn close(&mut self) -> &mut Self {
+        if self.in_subpath {
+            self.builder.close();
+            self.current = self.start;
+            self.in_subpath = false;
+        }
+        self
+    }
+
+    /// Finish building the path.
+    #[inline]
+    pub fn build(mut self) -> Path {
+        if self.in_subpath {
+            self.builder.end(false);
+        }
+        self.builder.build()
+    }
+
+    #[inline]
+    fn ensure_subpath(&mut self) {
+        if !self.in_subpath {
+            let at = self.current;
+            self.move_to(at);
+        }
+    }
+}
+
+/// Extension methods for paths.
+pub trait PathExt {
+    /// Get the bounding rectangle of this path, including its control points' influence.
+    fn bounds(&self) -> Rect<f32>;
+
+    /// Create a new path with the given transform applied to every point.
+    fn transform(&self, m: Transform) -> Path;
+
+    /// Tell whether or not a point lies inside of this path when it is filled using the given fill mode. Open
+    /// subpaths are treated as though they were closed, and points lying on the outline are always inside.
+    fn contains(&self, p: Point<f32>, mode: FillMode) -> bool;
+}
+
+impl PathExt for Path {
+    #[inline]
+    fn bounds(&self) -> Rect<f32> {
+        self.as_slice().bounds()
+    }
+
+    #[inline]
+    fn transform(&self, m: Transform) -> Path {
+        self.as_slice().transform(m)
+    }
+
+    #[inline]
+    fn contains(&self, p: Point<f32>, mode: FillMode) -> bool {
+        self.as_slice().contains(p, mode)
+    }
+}
+
+impl PathExt for PathSlice<'_> {
+    #[inline]
+    fn bounds(&self) -> Rect<f32> {
+        self.iter()
+            .filter_map(|pe| match pe {
+                PathEvent::Begin { at } => Some(Rect::new(at, Default::default())),
+                PathEvent::Line { from, to } => Some(LineSegment { from, to }.bounding_rect()),
+                PathEvent::Quadratic { from, ctrl, to } => {
+                    Some(QuadraticBezierSegment { from, ctrl, to }.bounding_rect())
+                }
+                PathEvent::Cubic {
+                    from,
+                    ctrl1,
+                    ctrl2,
+                    to,
+                } => Some(
+                    CubicBezierSegment {
+                        from,
+                        ctrl1,
+                        ctrl2,
+                        to,
+                    }
+                    .bounding_rect(),
+                ),
+                PathEvent::End { .. } => None,
+            })
+            // don't use Rect::union, since it discards empty rectangles like straight lines
+            .fold(None, |acc: Option<(Point<f32>, Point<f32>)>, rect| {
+                Some(match acc {
+                    Some((min, max)) => (min.min(rect.min()), max.max(rect.max())),
+                    None => (rect.min(), rect.max()),
+                })
+            })
+            .map_or_else(Rect::zero, |(min, max)| Rect::from_points([min, max]))
+    }
+
+    #[inline]
+    fn transform(&self, m: Transform) -> Path {
+        self.iter().transformed(&m.into_inner()).collect()
+    }
+
+    #[inline]
+    fn contains(&self, p: Point<f32>, mode: FillMode) -> bool {
+        let mut crossings = 0usize;
+        let mut winding = 0isize;
+
+        // filling implicitly closes every subpath
+        let on_edge = self
+            .iter()
+            .flattened(DEFAULT_TOLERANCE)
+            .filter_map(|pe| match pe {
+                PathEvent::Begin { .. } => None,
+                PathEvent::Line { from, to } => Some((from, to)),
+                PathEvent::End { last, first, .. } => Some((last, first)),
+                _ => unreachable!(),
+            })
+            .any(|(a, b)| {
+                if point_on_segment(p, a, b) {
+                    return true;
+                }
+
+                // count the edges crossing a ray cast from the point towards positive x
+                let side = (b - a).cross(p - a);
+                if a.y <= p.y && b.y > p.y && side > 0.0 {
+                    crossings += 1;
+                    winding += 1;
+                } else if a.y > p.y && b.y <= p.y && side < 0.0 {
+                    crossings += 1;
+                    winding -= 1;
+                }
+
+                false
+            });
+
+        on_edge
+            || match mode {
+                FillMode::EvenOdd => crossings % 2 == 1,
+                FillMode::NonZero => winding != 0,
+            }
+    }
+}
+
+/// How far a point can be from an edge of a path while still being considered on it.
+const ON_EDGE_EPSILON: f32 = 1e-4;
+
+/// Tell whether or not a point lies on the line segment between `a` and `b`.
+#[inline]
+fn point_on_segment(p: Point<f32>, a: Point<f32>, b: Point<f32>) -> bool {
+    let edge = b - a;
+    let offset = p - a;
+    let length = edge.length();
+
+    if length <= ON_EDGE_EPSILON {
+        return offset.length() <= ON_EDGE_EPSILON;
+    }
+
+    let along = edge.dot(offset) / length;
+    (edge.cross(offset) / length).abs() <= ON_EDGE_EPSILON
+        && along >= -ON_EDGE_EPSILON
+        && along <= length + ON_EDGE_EPSILON
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lyon_geom::{point, Size};
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> Rect<f32> {
+        Rect::new(point(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn zero_radius_round_rect_is_rectangle() {
+        let r = rect(1.0, 2.0, 4.0, 3.0);
+        let path = path_from_round_rect(r, 0.0, 0.0, DEFAULT_TOLERANCE);
+
+        let mut builder = Path::builder();
+        builder.begin(point(1.0, 2.0));
+        builder.line_to(point(5.0, 2.0));
+        builder.line_to(point(5.0, 5.0));
+        builder.line_to(point(1.0, 5.0));
+        builder.close();
+        let plain = builder.build();
+
+        assert!(path.iter().eq(plain.iter()));
+    }
+
+    #[test]
+    fn oversized_radii_clamp_to_stadium() {
+        let r = rect(0.0, 0.0, 20.0, 4.0);
+        assert_eq!(clamp_round_rect_radii(r, 2.0, 50.0), (2.0, 2.0));
+        assert_eq!(clamp_round_rect_radii(r, -1.0, 1.0), (0.0, 1.0));
+
+        // the ends are semicircles joined by straight edges, and the shape stays inside the rectangle
+        let path = path_from_round_rect(r, 50.0, 50.0, DEFAULT_TOLERANCE);
+        let bounds = path.bounds();
+        assert!((bounds.min() - r.min()).length() < 1e-3);
+        assert!((bounds.max() - r.max()).length() < 1e-3);
+
+        let path = path_from_round_rect(r, 2.0, 50.0, DEFAULT_TOLERANCE);
+        assert!(path_to_lines(path.iter(), DEFAULT_TOLERANCE).any(|line| {
+            (line.from - point(2.0, 0.0)).length() < 1e-4
+                && (line.to - point(18.0, 0.0)).length() < 1e-4
+        }));
+        assert!(path.contains(point(1.0, 2.0), FillMode::NonZero));
+        assert!(!path.contains(point(0.2, 0.2), FillMode::NonZero));
+    }
+
+    #[test]
+    fn triangle_builder_events() {
+        let mut builder = PathBuilder::new();
+        builder
+            .move_to(point(0.0, 0.0))
+            .line_to(point(4.0, 0.0))
+            .line_to(point(2.0, 3.0))
+            .close();
+        let path = builder.build();
+
+        let events: Vec<_> = path.iter().collect();
+        assert_eq!(
+            events,
+            vec![
+                PathEvent::Begin {
+                    at: point(0.0, 0.0)
+                },
+                PathEvent::Line {
+                    from: point(0.0, 0.0),
+                    to: point(4.0, 0.0)
+                },
+                PathEvent::Line {
+                    from: point(4.0, 0.0),
+                    to: point(2.0, 3.0)
+                },
+                PathEvent::End {
+                    last: point(2.0, 3.0),
+                    first: point(0.0, 0.0),
+                    close: true
+                },
+            ]
+        );
+        assert_eq!(path.bounds(), rect(0.0, 0.0, 4.0, 3.0));
+    }
+
+    #[test]
+    fn unit_square_bounds_and_translation() {
+        let mut builder = PathBuilder::new();
+        builder
+            .move_to(point(0.0, 0.0))
+            .line_to(point(1.0, 0.0))
+            .line_to(point(1.0, 1.0))
+            .line_to(point(0.0, 1.0))
+            .close();
+        let path = builder.build();
+        assert_eq!(path.bounds(), rect(0.0, 0.0, 1.0, 1.0));
+
+        let moved = path.transform(Transform::translation(3.0, -2.0));
+        assert_eq!(moved.bounds(), rect(3.0, -2.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn pentagram_center_depends_on_fill_mode() {
+        // a concave, self-intersecting star, whose center is wound around twice
+        let mut builder = PathBuilder::new();
+        (0..5).for_each(|i| {
+            let angle = Angle::degrees(-90.0 + 144.0 * i as f32);
+            let pt = point(10.0 * angle.radians.cos(), 10.0 * angle.radians.sin());
+            if i == 0 {
+                builder.move_to(pt);
+            } else {
+                builder.line_to(pt);
+            }
+        });
+        builder.close();
+        let star = builder.build();
+
+        let center = point(0.0, 0.0);
+        assert!(!star.contains(center, FillMode::EvenOdd));
+        assert!(star.contains(center, FillMode::NonZero));
+
+        // the tips are only wound around once, so both modes agree on them
+        let tip = point(0.0, -8.0);
+        assert!(star.contains(tip, FillMode::EvenOdd));
+        assert!(star.contains(tip, FillMode::NonZero));
+        let outside = point(9.0, 9.0);
+        assert!(!star.contains(outside, FillMode::EvenOdd));
+        assert!(!star.contains(outside, FillMode::NonZero));
+    }
+
+    #[test]
+    fn stroked_segment_is_a_rectangle() {
+        let mut builder = PathBuilder::new();
+        builder.move_to(point(0.0, 0.0)).line_to(point(10.0, 0.0));
+        let outline = stroke(builder.build().as_slice(), 2.0, DEFAULT_TOLERANCE);
+
+        // sum the signed area of each closed ring with the shoelace formula
+        let area: f32 = outline
+            .iter()
+            .map(|pe| match pe {
+                PathEvent::Line { from, to }
+                | PathEvent::End {
+                    last: from,
+                    first: to,
+                    close: true,
+                } => from.to_vector().cross(to.to_vector()) / 2.0,
+                _ => 0.0,
+            })
+            .sum();
+        assert!((area.abs() - 20.0).abs() < 1.0e-3, "area is {}", area);
+        assert_eq!(outline.bounds(), rect(0.0, -1.0, 10.0, 2.0));
+    }
+
+    #[test]
+    fn acute_corner_is_beveled() {
+        let corner = |to| {
+            let mut builder = PathBuilder::new();
+            builder
+                .move_to(point(0.0, 0.0))
+                .line_to(point(10.0, 0.0))
+                .line_to(to);
+            stroke(builder.build().as_slice(), 2.0, DEFAULT_TOLERANCE)
+        };
+
+        // a right angle is well within the miter limit, so its outer corner comes to a point
+        let square = corner(point(10.0, 10.0));
+        assert!(square.contains(point(10.9, -0.9), FillMode::NonZero));
+
+        // mitering this corner would put its tip about ten pixels past the vertex, so it is beveled instead
+        let acute = corner(point(0.0, 2.0));
+        let bounds = acute.bounds();
+        assert!(bounds.max_x() < 11.0, "{:?}", bounds);
+        assert!(acute.contains(point(10.0, 0.0), FillMode::NonZero));
+    }
+}
@@ -0,0 +1,173 @@
+// MIT/Apache2 License
+
+//! Splitting self-intersecting polygons into simple ones.
+
+use lyon_geom::{LineSegment, Point};
+use std::cmp::Ordering;
+
+/// How close a crossing can be to the end of an edge before it is treated as being at that end.
+const ENDPOINT_EPSILON: f32 = 1e-5;
+/// How close two corners can be before they are treated as the same corner.
+const CORNER_EPSILON: f32 = 1e-4;
+
+/// Split a polygon that may intersect itself into simple polygons, which only meet each other at their
+/// corners. Filling the pieces together with the even-odd rule gives the same result as filling the original
+/// polygon with it, so backends can use this to fill self-intersecting polygons consistently.
+///
+/// Crossings are found by sweeping across the edges of the polygon from left to right. Edges that overlap each
+/// other along a line are not split.
+#[inline]
+pub fn repair_polygon(points: &[Point<f32>]) -> Vec<Vec<Point<f32>>> {
+    // a closing point that repeats the first one would otherwise look like a crossing
+    let mut points = points.to_vec();
+    points.dedup_by(|a, b| same_corner(*a, *b));
+    while points.len() > 1 && same_corner(points[0], points[points.len() - 1]) {
+        points.pop();
+    }
+    if points.len() < 3 {
+        return vec![];
+    }
+
+    let len = points.len();
+    let edges: Vec<LineSegment<f32>> = (0..len)
+        .map(|i| LineSegment {
+            from: points[i],
+            to: points[(i + 1) % len],
+        })
+        .collect();
+    let min_x = |i: usize| edges[i].from.x.min(edges[i].to.x);
+    let max_x = |i: usize| edges[i].from.x.max(edges[i].to.x);
+
+    // the crossings on each edge, along with how far along the edge they are
+    let mut crossings: Vec<Vec<(f32, Point<f32>)>> = vec![vec![]; len];
+
+    // sweep from left to right, only testing the edges that overlap horizontally
+    let mut order: Vec<usize> = (0..len).collect();
+    order.sort_by(|&a, &b| min_x(a).partial_cmp(&min_x(b)).unwrap_or(Ordering::Equal));
+    let mut active: Vec<usize> = vec![];
+    for i in order {
+        active.retain(|&j| max_x(j) >= min_x(i));
+
+        // this ignores edges that share an end point, including neighboring edges
+        active.iter().for_each(|&j| {
+            if let Some((ti, tj)) = edges[i].intersection_t(&edges[j]) {
+                let point = crossing_point(&edges[i], ti, &edges[j], tj);
+                add_crossing(&mut crossings[i], ti, point);
+                add_crossing(&mut crossings[j], tj, point);
+            }
+        });
+
+        active.push(i);
+    }
+
+    // insert the crossings into the polygon, so that every crossing is a corner
+    let mut ring = Vec::with_capacity(len);
+    crossings
+        .into_iter()
+        .enumerate()
+        .for_each(|(i, mut edge_crossings)| {
+            ring.push(points[i]);
+            edge_crossings.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+            ring.extend(edge_crossings.into_iter().map(|(_, point)| point));
+        });
+    ring.dedup_by(|a, b| same_corner(*a, *b));
+
+    // every time the outline comes back to a corner it has already visited, it has closed a loop
+    let mut loops = vec![];
+    let mut stack: Vec<Point<f32>> = Vec::with_capacity(ring.len());
+    ring.into_iter().for_each(|point| {
+        if let Some(start) = stack.iter().position(|&p| same_corner(p, point)) {
+            let piece = stack.split_off(start);
+            if piece.len() >= 3 {
+                loops.push(piece);
+            }
+        }
+        stack.push(point);
+    });
+
+    if stack.len() > 1 && same_corner(stack[0], stack[stack.len() - 1]) {
+        stack.pop();
+    }
+    if stack.len() >= 3 {
+        loops.push(stack);
+    }
+
+    loops
+}
+
+/// Tell if two corners are close enough to be treated as the same one.
+#[inline]
+fn same_corner(a: Point<f32>, b: Point<f32>) -> bool {
+    (a - b).square_length() <= CORNER_EPSILON * CORNER_EPSILON
+}
+
+/// Get the point where two edges cross, snapping it to the end of an edge if it is close enough to one. This
+/// way, an edge that touches a corner is split at exactly that corner.
+#[inline]
+fn crossing_point(a: &LineSegment<f32>, ta: f32, b: &LineSegment<f32>, tb: f32) -> Point<f32> {
+    if ta <= ENDPOINT_EPSILON {
+        a.from
+    } else if ta >= 1.0 - ENDPOINT_EPSILON {
+        a.to
+    } else if tb <= ENDPOINT_EPSILON {
+        b.from
+    } else if tb >= 1.0 - ENDPOINT_EPSILON {
+        b.to
+    } else {
+        a.sample(ta)
+    }
+}
+
+/// Record a crossing on an edge, unless it lies at one of the edge's ends.
+#[inline]
+fn add_crossing(crossings: &mut Vec<(f32, Point<f32>)>, t: f32, point: Point<f32>) {
+    if t > ENDPOINT_EPSILON && t < 1.0 - ENDPOINT_EPSILON {
+        crossings.push((t, point));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lyon_geom::point;
+
+    #[test]
+    fn figure_eight_splits_into_two_loops() {
+        let loops = repair_polygon(&[
+            point(0.0, 0.0),
+            point(10.0, 10.0),
+            point(10.0, 0.0),
+            point(0.0, 10.0),
+        ]);
+        assert_eq!(loops.len(), 2);
+        assert!(loops.iter().all(|piece| piece.len() == 3));
+        assert!(loops
+            .iter()
+            .all(|piece| piece.iter().any(|&p| same_corner(p, point(5.0, 5.0)))));
+    }
+
+    #[test]
+    fn nearly_touching_corners_split() {
+        // the outline comes back to almost, but not exactly, the same corner
+        let loops = repair_polygon(&[
+            point(0.0, 0.0),
+            point(5.0, 5.0),
+            point(10.0, 0.0),
+            point(10.0, 10.0),
+            point(5.0, 5.000_001),
+            point(0.0, 10.0),
+        ]);
+        assert_eq!(loops.len(), 2);
+    }
+
+    #[test]
+    fn simple_polygon_is_unchanged() {
+        let square = [
+            point(0.0, 0.0),
+            point(10.0, 0.0),
+            point(10.0, 10.0),
+            point(0.0, 10.0),
+        ];
+        assert_eq!(repair_polygon(&square), vec![square.to_vec()]);
+    }
+}
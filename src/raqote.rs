@@ -0,0 +1,705 @@
+// MIT/Apache2 License
+
+//! A software renderer that draws onto a `raqote::DrawTarget`, for programs that already use raqote.
+
+use crate::{
+    fill::{FillMode, FillRule, ImagePattern, TileMode},
+    image::{check_image_region, check_len, to_rgba},
+    stroke::{LineCap, LineJoin},
+    surface::{
+        FallbackPolicy, Surface, SurfaceFeatures, DEFAULT_MITER_LIMIT, DEFAULT_TOLERANCE,
+        MIN_TOLERANCE,
+    },
+    transform::Transform,
+    Color, Image, ImageFormat,
+};
+use lyon_geom::{LineSegment, Point, Rect, Size, Vector};
+use lyon_path::{Path, PathEvent, PathSlice};
+use raqote::{
+    AntialiasMode, DrawOptions, DrawTarget, ExtendMode, FilterMode, Gradient, GradientStop,
+    Image as RqImage, LineCap as RqLineCap, LineJoin as RqLineJoin, Path as RqPath, PathBuilder,
+    PathOp, SolidSource, Source, Spread, StrokeStyle, Winding,
+};
+use std::{collections::HashMap, num::NonZeroUsize};
+
+const FEATURES: SurfaceFeatures = SurfaceFeatures {
+    transparency: true,
+    gradients: true,
+    floats: true,
+    clipping: true,
+    transforms: true,
+    paths: true,
+    text: false,
+    images: true,
+    patterns: true,
+    size: true,
+    antialiasing: true,
+    dirty_tracking: false,
+};
+
+/// A surface that draws onto a `raqote::DrawTarget`.
+pub struct RaqoteSurface<'a> {
+    target: &'a mut DrawTarget,
+    state: State,
+    saved: Vec<State>,
+    images: HashMap<Image, ImageData>,
+    next_image: usize,
+    tolerance: f32,
+    fallback: FallbackPolicy,
+}
+
+/// Drawing state that is saved by `save()`.
+#[derive(Debug, Clone)]
+struct State {
+    stroke: Color,
+    fill: FillRule,
+    fill_mode: FillMode,
+    alpha: f32,
+    line_width: usize,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    miter_limit: f32,
+    dash: Vec<f32>,
+    dash_offset: f32,
+    antialias: bool,
+    clip_depth: usize,
+    transform: Transform,
+}
+
+/// An image, in the premultiplied ARGB format that raqote uses.
+#[derive(Debug)]
+struct ImageData {
+    width: u32,
+    height: u32,
+    data: Vec<u32>,
+}
+
+impl ImageData {
+    #[inline]
+    fn as_raqote(&self) -> RqImage<'_> {
+        RqImage {
+            width: self.width as i32,
+            height: self.height as i32,
+            data: &self.data,
+        }
+    }
+}
+
+impl<'a> RaqoteSurface<'a> {
+    /// Create a new `RaqoteSurface` that draws onto the given target.
+    #[inline]
+    pub fn from_target(target: &'a mut DrawTarget) -> RaqoteSurface<'a> {
+        RaqoteSurface {
+            target,
+            state: State {
+                stroke: Color::BLACK,
+                fill: FillRule::SolidColor(Color::BLACK),
+                fill_mode: FillMode::default(),
+                alpha: 1.0,
+                line_width: 1,
+                line_cap: LineCap::default(),
+                line_join: LineJoin::default(),
+                miter_limit: DEFAULT_MITER_LIMIT,
+                dash: vec![],
+                dash_offset: 0.0,
+                antialias: true,
+                clip_depth: 0,
+                transform: Transform::identity(),
+            },
+            saved: vec![],
+            images: HashMap::new(),
+            next_image: 1,
+            tolerance: DEFAULT_TOLERANCE,
+            fallback: FallbackPolicy::default(),
+        }
+    }
+
+    /// The options that shapes are currently drawn with.
+    #[inline]
+    fn draw_options(&self) -> DrawOptions {
+        DrawOptions {
+            antialias: if self.state.antialias {
+                AntialiasMode::Gray
+            } else {
+                AntialiasMode::None
+            },
+            alpha: self.state.alpha,
+            ..DrawOptions::new()
+        }
+    }
+
+    /// The style that lines are currently stroked with.
+    #[inline]
+    fn stroke_style(&self) -> StrokeStyle {
+        StrokeStyle {
+            width: self.state.line_width as f32,
+            cap: match self.state.line_cap {
+                LineCap::Butt => RqLineCap::Butt,
+                LineCap::Round => RqLineCap::Round,
+                LineCap::Square => RqLineCap::Square,
+            },
+            join: match self.state.line_join {
+                LineJoin::Miter => RqLineJoin::Miter,
+                LineJoin::Round => RqLineJoin::Round,
+                LineJoin::Bevel => RqLineJoin::Bevel,
+            },
+            miter_limit: self.state.miter_limit,
+            dash_array: self.state.dash.iter().copied().map(f32::abs).collect(),
+            dash_offset: self.state.dash_offset,
+            ..StrokeStyle::default()
+        }
+    }
+
+    /// Fill a path with the current fill rule.
+    #[inline]
+    fn fill_rq_path(&mut self, mut path: RqPath) -> crate::Result {
+        path.winding = match self.state.fill_mode {
+            FillMode::EvenOdd => Winding::EvenOdd,
+            FillMode::NonZero => Winding::NonZero,
+        };
+
+        // gradients are spread across the bounding box of the shape, like on other surfaces
+        let bounds = path_bounds(&path);
+        let raster;
+        let source = match self.state.fill {
+            FillRule::SolidColor(color) => Source::Solid(solid_source(color)),
+            FillRule::LinearGradient(ref gradient, angle) => {
+                let direction = Vector::new(angle.radians.cos(), angle.radians.sin());
+                let half = (direction.x.abs() * bounds.size.width
+                    + direction.y.abs() * bounds.size.height)
+                    / 2.0;
+                let center = bounds.center();
+                let (start, end) = (center - direction * half, center + direction * half);
+                Source::new_linear_gradient(
+                    gradient_stops(&gradient.srgb_stops()),
+                    raqote::Point::new(start.x, start.y),
+                    raqote::Point::new(end.x, end.y),
+                    Spread::Pad,
+                )
+            }
+            FillRule::RadialGradient(ref gradient) => {
+                let center = bounds.center();
+                Source::new_radial_gradient(
+                    gradient_stops(&gradient.srgb_stops()),
+                    raqote::Point::new(center.x, center.y),
+                    bounds.size.width.max(bounds.size.height) / 2.0,
+                    Spread::Pad,
+                )
+            }
+            // conic gradients are rendered into an image, so that they start at the same angle as they do
+            // on other surfaces
+            FillRule::ConicalGradient(_) => {
+                let rect = bounds.round_out();
+                let pixels = self
+                    .state
+                    .fill
+                    .rasterize(rect)
+                    .ok_or(crate::Error::NotSupported(crate::NSOpType::Gradients))?;
+                raster = image_data(&pixels, rect.size.width as u32, rect.size.height as u32);
+                Source::Image(
+                    raster.as_raqote(),
+                    ExtendMode::Pad,
+                    FilterMode::Nearest,
+                    rq_transform(Transform::translation(-rect.origin.x, -rect.origin.y)),
+                )
+            }
+            FillRule::Pattern(ImagePattern {
+                image,
+                tiling,
+                transform,
+            }) => {
+                let data = self
+                    .images
+                    .get(&image)
+                    .ok_or(crate::Error::ImageNotAvailable)?;
+                // mirrored patterns are rejected by set_fill
+                let extend = match tiling {
+                    TileMode::Clamp => ExtendMode::Pad,
+                    _ => ExtendMode::Repeat,
+                };
+
+                // raqote maps points on the surface to points on the image
+                let inverse = transform.unwrap_or_default().inverse().unwrap_or_default();
+                Source::Image(
+                    data.as_raqote(),
+                    extend,
+                    FilterMode::Nearest,
+                    rq_transform(inverse),
+                )
+            }
+        };
+
+        self.target
+            .set_transform(&rq_transform(self.state.transform));
+        self.target.fill(&path, &source, &self.draw_options());
+        Ok(())
+    }
+
+    /// Stroke a path with the current stroke color and line style.
+    #[inline]
+    fn stroke_rq_path(&mut self, path: &RqPath) -> crate::Result {
+        let style = self.stroke_style();
+        self.target
+            .set_transform(&rq_transform(self.state.transform));
+        self.target.stroke(
+            path,
+            &Source::Solid(solid_source(self.state.stroke)),
+            &style,
+            &self.draw_options(),
+        );
+        Ok(())
+    }
+}
+
+impl<'a> Surface for RaqoteSurface<'a> {
+    #[inline]
+    fn features(&self) -> SurfaceFeatures {
+        FEATURES
+    }
+
+    #[inline]
+    fn set_stroke(&mut self, color: Color) -> crate::Result {
+        self.state.stroke = color;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_fill(&mut self, rule: FillRule) -> crate::Result {
+        // raqote can't mirror images
+        if let FillRule::Pattern(ImagePattern {
+            tiling: TileMode::Mirror,
+            ..
+        }) = rule
+        {
+            return Err(crate::Error::NotSupported(crate::NSOpType::Pattern));
+        }
+
+        // raqote has no conic gradients, so they are rasterized in software
+        if let (FillRule::ConicalGradient(_), FallbackPolicy::Reject) = (&rule, self.fallback) {
+            return Err(crate::Error::NotSupported(crate::NSOpType::Gradients));
+        }
+
+        self.state.fill = rule;
+        Ok(())
+    }
+
+    #[inline]
+    fn fill_rule(&self) -> Option<&FillRule> {
+        Some(&self.state.fill)
+    }
+
+    #[inline]
+    fn set_fill_mode(&mut self, mode: FillMode) -> crate::Result {
+        self.state.fill_mode = mode;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_line_width(&mut self, width: usize) -> crate::Result {
+        self.state.line_width = width;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_line_cap(&mut self, cap: LineCap) -> crate::Result {
+        self.state.line_cap = cap;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_line_join(&mut self, join: LineJoin) -> crate::Result {
+        self.state.line_join = join;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_miter_limit(&mut self, limit: f32) -> crate::Result {
+        self.state.miter_limit = limit.max(1.0);
+        Ok(())
+    }
+
+    #[inline]
+    fn set_dash(&mut self, pattern: &[f32], offset: f32) -> crate::Result {
+        self.state.dash = pattern.to_vec();
+        self.state.dash_offset = offset;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_antialias(&mut self, on: bool) -> crate::Result {
+        self.state.antialias = on;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_global_alpha(&mut self, alpha: f32) -> crate::Result {
+        self.state.alpha = alpha.clamp(0.0, 1.0);
+        Ok(())
+    }
+
+    #[inline]
+    fn push_clip(&mut self, rect: Rect<f32>) -> crate::Result {
+        let mut builder = PathBuilder::new();
+        builder.rect(
+            rect.origin.x,
+            rect.origin.y,
+            rect.size.width,
+            rect.size.height,
+        );
+
+        // raqote transforms the clipping path by the current transform
+        self.target
+            .set_transform(&rq_transform(self.state.transform));
+        self.target.push_clip(&builder.finish());
+        self.state.clip_depth += 1;
+        Ok(())
+    }
+
+    #[inline]
+    fn pop_clip(&mut self) -> crate::Result {
+        if self.state.clip_depth == 0 {
+            return Err(crate::Error::NoClip);
+        }
+
+        self.target.pop_clip();
+        self.state.clip_depth -= 1;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_transform(&mut self, transform: Transform) -> crate::Result {
+        self.state.transform = transform;
+        Ok(())
+    }
+
+    #[inline]
+    fn transform(&mut self, transform: Transform) -> crate::Result {
+        self.state.transform = transform.then(&self.state.transform);
+        Ok(())
+    }
+
+    #[inline]
+    fn save(&mut self) -> crate::Result {
+        self.saved.push(self.state.clone());
+        Ok(())
+    }
+
+    #[inline]
+    fn restore(&mut self) -> crate::Result {
+        let state = self
+            .saved
+            .pop()
+            .ok_or(crate::Error::StaticMsg("No saved state to restore"))?;
+        // pop the clips that were pushed since the state was saved
+        (state.clip_depth..self.state.clip_depth).for_each(|_| self.target.pop_clip());
+        self.state = state;
+        Ok(())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> crate::Result {
+        Ok(())
+    }
+
+    #[inline]
+    fn tolerance(&self) -> f32 {
+        self.tolerance
+    }
+
+    #[inline]
+    fn set_tolerance(&mut self, tolerance: f32) -> crate::Result {
+        self.tolerance = tolerance.max(MIN_TOLERANCE);
+        Ok(())
+    }
+
+    #[inline]
+    fn set_fallback_policy(&mut self, policy: FallbackPolicy) -> crate::Result {
+        self.fallback = policy;
+        Ok(())
+    }
+
+    #[inline]
+    fn size(&self) -> crate::Result<Size<f32>> {
+        Ok(Size::new(
+            self.target.width() as f32,
+            self.target.height() as f32,
+        ))
+    }
+
+    #[inline]
+    fn create_image(
+        &mut self,
+        image_bytes: &[u8],
+        width: u32,
+        height: u32,
+        image_format: ImageFormat,
+    ) -> crate::Result<Image> {
+        check_len(image_bytes, width, height, image_format)?;
+        let rgba = to_rgba(image_bytes, width, height, image_format);
+
+        let image =
+            Image::from_raw(NonZeroUsize::new(self.next_image).expect("Image ID overflowed"));
+        self.next_image += 1;
+        self.images.insert(image, image_data(&rgba, width, height));
+        Ok(image)
+    }
+
+    #[inline]
+    fn destroy_image(&mut self, image: Image) -> crate::Result {
+        self.images.remove(&image);
+        Ok(())
+    }
+
+    #[inline]
+    fn draw_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> crate::Result {
+        self.draw_lines(&[LineSegment {
+            from: Point::new(x1, y1),
+            to: Point::new(x2, y2),
+        }])
+    }
+
+    #[inline]
+    fn draw_lines(&mut self, lines: &[LineSegment<f32>]) -> crate::Result {
+        let mut builder = PathBuilder::new();
+        lines.iter().for_each(|line| {
+            builder.move_to(line.from.x, line.from.y);
+            builder.line_to(line.to.x, line.to.y);
+        });
+        self.stroke_rq_path(&builder.finish())
+    }
+
+    #[inline]
+    fn draw_polyline(&mut self, pts: &[Point<f32>]) -> crate::Result {
+        self.stroke_rq_path(&polygon_path(pts, false))
+    }
+
+    #[inline]
+    fn draw_path(&mut self, path: PathSlice<'_>) -> crate::Result {
+        self.stroke_rq_path(&rq_path(path.iter()))
+    }
+
+    #[inline]
+    fn draw_path_owned(&mut self, path: Path) -> crate::Result {
+        self.draw_path(path.as_slice())
+    }
+
+    #[inline]
+    fn fill_polygon(&mut self, points: &[Point<f32>]) -> crate::Result {
+        self.fill_rq_path(polygon_path(points, true))
+    }
+
+    #[inline]
+    fn fill_path(&mut self, path: PathSlice<'_>) -> crate::Result {
+        self.fill_rq_path(rq_path(path.iter()))
+    }
+
+    #[inline]
+    fn fill_path_owned(&mut self, path: Path) -> crate::Result {
+        self.fill_path(path.as_slice())
+    }
+
+    #[inline]
+    fn fill_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> crate::Result {
+        let mut builder = PathBuilder::new();
+        builder.rect(x, y, width, height);
+        self.fill_rq_path(builder.finish())
+    }
+
+    #[inline]
+    fn copy_image(
+        &mut self,
+        src: Image,
+        src_x: i32,
+        src_y: i32,
+        dst_x: i32,
+        dst_y: i32,
+        width: u32,
+        height: u32,
+    ) -> crate::Result {
+        let size = Size::new(width as f32, height as f32);
+        self.draw_image_region(
+            &src,
+            Rect::new(Point::new(src_x as f32, src_y as f32), size),
+            Rect::new(Point::new(dst_x as f32, dst_y as f32), size),
+        )
+    }
+
+    #[inline]
+    fn draw_image(&mut self, image: &Image, dst: Rect<f32>) -> crate::Result {
+        let data = self
+            .images
+            .get(image)
+            .ok_or(crate::Error::ImageNotAvailable)?;
+        let size = Size::new(data.width as f32, data.height as f32);
+        self.draw_image_region(image, Rect::new(Point::origin(), size), dst)
+    }
+
+    #[inline]
+    fn draw_image_region(
+        &mut self,
+        image: &Image,
+        src: Rect<f32>,
+        dst: Rect<f32>,
+    ) -> crate::Result {
+        let data = self
+            .images
+            .get(image)
+            .ok_or(crate::Error::ImageNotAvailable)?;
+        check_image_region(src, Size::new(data.width as f32, data.height as f32))?;
+        if src.is_empty() || dst.is_empty() {
+            return Ok(());
+        }
+
+        // map the destination rectangle back onto the source region of the image
+        let inverse = Transform::translation(-dst.origin.x, -dst.origin.y)
+            .then(&Transform::scale(
+                src.size.width / dst.size.width,
+                src.size.height / dst.size.height,
+            ))
+            .then(&Transform::translation(src.origin.x, src.origin.y));
+        let source = Source::Image(
+            data.as_raqote(),
+            ExtendMode::Pad,
+            FilterMode::Bilinear,
+            rq_transform(inverse),
+        );
+
+        let mut builder = PathBuilder::new();
+        builder.rect(dst.origin.x, dst.origin.y, dst.size.width, dst.size.height);
+        self.target
+            .set_transform(&rq_transform(self.state.transform));
+        self.target
+            .fill(&builder.finish(), &source, &self.draw_options());
+        Ok(())
+    }
+}
+
+/// Convert a lyon path into a raqote path.
+#[inline]
+fn rq_path(events: impl IntoIterator<Item = PathEvent>) -> RqPath {
+    let mut builder = PathBuilder::new();
+    events.into_iter().for_each(|event| match event {
+        PathEvent::Begin { at } => builder.move_to(at.x, at.y),
+        PathEvent::Line { to, .. } => builder.line_to(to.x, to.y),
+        PathEvent::Quadratic { ctrl, to, .. } => builder.quad_to(ctrl.x, ctrl.y, to.x, to.y),
+        PathEvent::Cubic {
+            ctrl1, ctrl2, to, ..
+        } => builder.cubic_to(ctrl1.x, ctrl1.y, ctrl2.x, ctrl2.y, to.x, to.y),
+        PathEvent::End { close: true, .. } => builder.close(),
+        PathEvent::End { .. } => {}
+    });
+    builder.finish()
+}
+
+/// Create a path running through a series of points.
+#[inline]
+fn polygon_path(points: &[Point<f32>], close: bool) -> RqPath {
+    let mut builder = PathBuilder::new();
+    if let Some((first, rest)) = points.split_first() {
+        builder.move_to(first.x, first.y);
+        rest.iter()
+            .for_each(|point| builder.line_to(point.x, point.y));
+        if close {
+            builder.close();
+        }
+    }
+    builder.finish()
+}
+
+/// Get a rectangle that contains every point of a path, including its control points.
+#[inline]
+fn path_bounds(path: &RqPath) -> Rect<f32> {
+    Rect::from_points(path.ops.iter().flat_map(|op| {
+        let points = match *op {
+            PathOp::MoveTo(p) | PathOp::LineTo(p) => vec![p],
+            PathOp::QuadTo(c, p) => vec![c, p],
+            PathOp::CubicTo(c1, c2, p) => vec![c1, c2, p],
+            PathOp::Close => vec![],
+        };
+        points.into_iter().map(|p| Point::new(p.x, p.y))
+    }))
+}
+
+/// Convert straight-alpha RGBA pixels to premultiplied ARGB pixels.
+#[inline]
+fn image_data(rgba: &[u8], width: u32, height: u32) -> ImageData {
+    let data = rgba
+        .chunks_exact(4)
+        .map(|p| {
+            let a = p[3] as u32;
+            let premultiply = |c: u8| (c as u32 * a + 127) / 255;
+            (a << 24) | (premultiply(p[0]) << 16) | (premultiply(p[1]) << 8) | premultiply(p[2])
+        })
+        .collect();
+    ImageData {
+        width,
+        height,
+        data,
+    }
+}
+
+#[inline]
+fn gradient_stops(stops: &[crate::gradient::ColorStop]) -> Gradient {
+    Gradient {
+        stops: stops
+            .iter()
+            .map(|stop| {
+                let (r, g, b, a) = stop.color.clamp_u8();
+                GradientStop {
+                    position: stop.position.into_inner(),
+                    color: raqote::Color::new(a, r, g, b),
+                }
+            })
+            .collect(),
+    }
+}
+
+#[inline]
+fn solid_source(color: Color) -> SolidSource {
+    let (r, g, b, a) = color.clamp_u8();
+    SolidSource::from_unpremultiplied_argb(a, r, g, b)
+}
+
+#[inline]
+fn rq_transform(transform: Transform) -> raqote::Transform {
+    let m = transform.into_inner();
+    raqote::Transform::new(m.m11, m.m12, m.m21, m.m22, m.m31, m.m32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::consts::RED;
+
+    #[test]
+    fn stroke_style_follows_state() {
+        let mut target = DrawTarget::new(4, 4);
+        let mut surface = RaqoteSurface::from_target(&mut target);
+        surface.set_line_width(3).unwrap();
+        surface.set_line_cap(LineCap::Round).unwrap();
+        surface.set_line_join(LineJoin::Bevel).unwrap();
+
+        let style = surface.stroke_style();
+        assert_eq!(style.width, 3.0);
+        assert!(matches!(style.cap, RqLineCap::Round));
+        assert!(matches!(style.join, RqLineJoin::Bevel));
+    }
+
+    #[test]
+    fn solid_fill_is_solid_source() {
+        let source = solid_source(RED);
+        assert_eq!((source.r, source.g, source.b, source.a), (255, 0, 0, 255));
+        // raqote's solid sources are premultiplied
+        let source = solid_source(RED.with_alpha(0.5));
+        assert_eq!((source.r, source.g, source.b), (source.a, 0, 0));
+        assert!((127..=128).contains(&source.a));
+
+        let mut target = DrawTarget::new(4, 4);
+        let mut surface = RaqoteSurface::from_target(&mut target);
+        surface.set_fill_color(RED).unwrap();
+        surface.fill_rectangle(0.0, 0.0, 4.0, 4.0).unwrap();
+        drop(surface);
+        // raqote stores premultiplied ARGB
+        assert!(target.get_data().iter().all(|&pixel| pixel == 0xffff_0000));
+    }
+}
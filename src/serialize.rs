@@ -0,0 +1,282 @@
+// MIT/Apache2 License
+
+//! `serde` support for the crate's types, enabled by the `serde` feature.
+
+use crate::{Color, ColorInterpolation, ColorStop, Ellipse, Gradient, Intensity};
+use lyon_geom::{point, vector};
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    ser::SerializeTuple,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt;
+
+impl Serialize for Intensity {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f32(self.into_inner())
+    }
+}
+
+impl<'de> Deserialize<'de> for Intensity {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Intensity, D::Error> {
+        let inner = f32::deserialize(deserializer)?;
+        Intensity::new(inner)
+            .ok_or_else(|| de::Error::custom("intensity must be a number between 0 and 1"))
+    }
+}
+
+/// Colors are written as `#rrggbbaa` strings in human-readable formats, and as four floats otherwise.
+impl Serialize for Color {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_hex())
+        } else {
+            let mut tuple = serializer.serialize_tuple(4)?;
+            tuple.serialize_element(&self.red())?;
+            tuple.serialize_element(&self.green())?;
+            tuple.serialize_element(&self.blue())?;
+            tuple.serialize_element(&self.alpha())?;
+            tuple.end()
+        }
+    }
+}
+
+/// Human-readable formats accept either a hex string or four floats, so that colors which need more
+/// precision than a hex string can hold still round-trip.
+impl<'de> Deserialize<'de> for Color {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(ColorVisitor)
+        } else {
+            deserializer.deserialize_tuple(4, ColorVisitor)
+        }
+    }
+}
+
+struct ColorVisitor;
+
+impl<'de> Visitor<'de> for ColorVisitor {
+    type Value = Color;
+
+    #[inline]
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a hex color string or four floats between 0 and 1")
+    }
+
+    #[inline]
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Color, E> {
+        Color::from_hex(s).map_err(E::custom)
+    }
+
+    #[inline]
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Color, A::Error> {
+        let mut channel = |i: usize| -> Result<f32, A::Error> {
+            seq.next_element::<f32>()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))
+        };
+        let (r, g, b, a) = (channel(0)?, channel(1)?, channel(2)?, channel(3)?);
+        Color::new(r, g, b, a)
+            .ok_or_else(|| de::Error::custom("color channels must be numbers between 0 and 1"))
+    }
+}
+
+impl<'a> Serialize for Gradient<'a> {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GradientRepr {
+            colors: self.as_slice().to_vec(),
+            interpolation: self.interpolation(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Deserialized gradients are normalized, so the stops don't need to be written in order.
+impl<'de, 'a> Deserialize<'de> for Gradient<'a> {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Gradient<'a>, D::Error> {
+        let GradientRepr {
+            colors,
+            interpolation,
+        } = GradientRepr::deserialize(deserializer)?;
+        if colors.is_empty() {
+            return Err(de::Error::custom("gradients need at least one color stop"));
+        }
+
+        // SAFETY: the stops are not empty, and normalizing sorts them
+        let mut gradient = unsafe { Gradient::new_unchecked(colors.into()) };
+        gradient.normalize();
+        Ok(gradient.with_interpolation(interpolation))
+    }
+}
+
+/// The on-disk form of a `Gradient`.
+#[derive(Serialize, Deserialize)]
+struct GradientRepr {
+    colors: Vec<ColorStop>,
+    #[serde(default)]
+    interpolation: ColorInterpolation,
+}
+
+impl Serialize for Ellipse {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        EllipseRepr {
+            cx: self.center.x,
+            cy: self.center.y,
+            rx: self.radii.x,
+            ry: self.radii.y,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Ellipse {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Ellipse, D::Error> {
+        let EllipseRepr { cx, cy, rx, ry } = EllipseRepr::deserialize(deserializer)?;
+        Ok(Ellipse {
+            center: point(cx, cy),
+            radii: vector(rx, ry),
+        })
+    }
+}
+
+/// The on-disk form of an `Ellipse`.
+#[derive(Serialize, Deserialize)]
+struct EllipseRepr {
+    cx: f32,
+    cy: f32,
+    rx: f32,
+    ry: f32,
+}
+
+/// (De)serialize a `lyon_geom::Arc<f32>` as `{center, radii, start, sweep, rotation}`, with the angles in
+/// radians. Since the arc type comes from `lyon_geom`, use this through `#[serde(with = "...")]`.
+pub mod arc {
+    use lyon_geom::{point, vector, Angle, Arc};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    /// Serialize an arc.
+    #[inline]
+    pub fn serialize<S: Serializer>(arc: &Arc<f32>, serializer: S) -> Result<S::Ok, S::Error> {
+        ArcRepr {
+            center: [arc.center.x, arc.center.y],
+            radii: [arc.radii.x, arc.radii.y],
+            start: arc.start_angle.radians,
+            sweep: arc.sweep_angle.radians,
+            rotation: arc.x_rotation.radians,
+        }
+        .serialize(serializer)
+    }
+
+    /// Deserialize an arc.
+    #[inline]
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Arc<f32>, D::Error> {
+        let ArcRepr {
+            center,
+            radii,
+            start,
+            sweep,
+            rotation,
+        } = ArcRepr::deserialize(deserializer)?;
+        Ok(Arc {
+            center: point(center[0], center[1]),
+            radii: vector(radii[0], radii[1]),
+            start_angle: Angle::radians(start),
+            sweep_angle: Angle::radians(sweep),
+            x_rotation: Angle::radians(rotation),
+        })
+    }
+
+    /// The on-disk form of an `Arc`.
+    #[derive(Serialize, Deserialize)]
+    struct ArcRepr {
+        center: [f32; 2],
+        radii: [f32; 2],
+        start: f32,
+        sweep: f32,
+        #[serde(default)]
+        rotation: f32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        color::consts::{BLUE, RED, WHITE},
+        ColorInterpolation, ColorStop, Ellipse, FillRule, Gradient, Intensity,
+    };
+    use lyon_geom::{point, vector, Angle, Arc};
+    use serde::{Deserialize, Serialize};
+
+    #[test]
+    fn gradient_fill_rule_round_trip() {
+        let gradient = Gradient::try_new(vec![
+            ColorStop {
+                color: RED,
+                position: Intensity::new(0.0).unwrap(),
+            },
+            ColorStop {
+                color: WHITE,
+                position: Intensity::new(0.25).unwrap(),
+            },
+            ColorStop {
+                color: BLUE,
+                position: Intensity::new(1.0).unwrap(),
+            },
+        ])
+        .unwrap()
+        .with_interpolation(ColorInterpolation::Hsl);
+        let rule = FillRule::LinearGradient(gradient, Angle::degrees(30.0));
+
+        let json = serde_json::to_string(&rule).unwrap();
+        let rule2: FillRule = serde_json::from_str(&json).unwrap();
+        assert_eq!(rule, rule2);
+    }
+
+    #[test]
+    fn ellipse_round_trip() {
+        let ellipse = Ellipse {
+            center: point(12.5, -3.0),
+            radii: vector(4.0, 0.75),
+        };
+
+        let json = serde_json::to_string(&ellipse).unwrap();
+        assert_eq!(json, r#"{"cx":12.5,"cy":-3.0,"rx":4.0,"ry":0.75}"#);
+        let ellipse2: Ellipse = serde_json::from_str(&json).unwrap();
+        assert_eq!(ellipse2.center, ellipse.center);
+        assert_eq!(ellipse2.radii, ellipse.radii);
+    }
+
+    #[test]
+    fn arc_round_trip() {
+        #[derive(Serialize, Deserialize)]
+        struct Scene {
+            #[serde(with = "crate::serialize::arc")]
+            arc: Arc<f32>,
+        }
+
+        let arc = Arc {
+            center: point(1.0, 2.0),
+            radii: vector(3.0, 4.0),
+            start_angle: Angle::radians(0.5),
+            sweep_angle: Angle::radians(-1.5),
+            x_rotation: Angle::radians(0.25),
+        };
+        let json = serde_json::to_string(&Scene { arc }).unwrap();
+        let Scene { arc: arc2 } = serde_json::from_str(&json).unwrap();
+        assert_eq!(arc2, arc);
+
+        // the rotation can be left out
+        let Scene { arc } = serde_json::from_str(
+            r#"{"arc":{"center":[0.0,0.0],"radii":[1.0,1.0],"start":0.0,"sweep":1.0}}"#,
+        )
+        .unwrap();
+        assert_eq!(arc.x_rotation, Angle::zero());
+    }
+}
@@ -0,0 +1,111 @@
+// MIT/Apache2 License
+
+//! Presents drawings to a window through `softbuffer`. Drawing is done in software by an `ImageSurface`, so
+//! this works anywhere that `softbuffer` does, without needing GDI or X11.
+
+use crate::surface::ImageSurface;
+use softbuffer::GraphicsContext;
+use std::{
+    convert::TryFrom,
+    ops::{Deref, DerefMut},
+};
+
+/// An `ImageSurface` that can be presented to a `softbuffer` window.
+///
+/// This dereferences to the `ImageSurface` that it draws onto, so drawing is done through that.
+#[derive(Debug)]
+pub struct SoftbufferSurface {
+    inner: ImageSurface,
+    pixels: Vec<u32>,
+}
+
+impl SoftbufferSurface {
+    /// Create a new `SoftbufferSurface` of the given size, filled with transparent pixels.
+    #[inline]
+    pub fn new(width: u32, height: u32) -> SoftbufferSurface {
+        SoftbufferSurface {
+            inner: ImageSurface::new(width, height),
+            pixels: vec![],
+        }
+    }
+
+    /// Get the `ImageSurface` that this surface draws onto.
+    #[inline]
+    pub fn into_inner(self) -> ImageSurface {
+        self.inner
+    }
+
+    /// Copy what has been drawn onto the window behind a `softbuffer` graphics context. `softbuffer` takes
+    /// its dimensions as `u16`s, so this fails if the surface is wider or taller than that.
+    #[inline]
+    pub fn present(&mut self, context: &mut GraphicsContext) -> crate::Result {
+        let too_large =
+            |_| crate::Error::StaticMsg("Surface is too large to present through softbuffer");
+        let width = u16::try_from(self.inner.width()).map_err(too_large)?;
+        let height = u16::try_from(self.inner.height()).map_err(too_large)?;
+
+        self.pixels.resize(width as usize * height as usize, 0);
+        present_pixels(&self.inner, &mut self.pixels)?;
+        context.set_buffer(&self.pixels, width, height);
+        Ok(())
+    }
+}
+
+impl Deref for SoftbufferSurface {
+    type Target = ImageSurface;
+
+    #[inline]
+    fn deref(&self) -> &ImageSurface {
+        &self.inner
+    }
+}
+
+impl DerefMut for SoftbufferSurface {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut ImageSurface {
+        &mut self.inner
+    }
+}
+
+/// Convert RGBA pixels into the `0RGB` pixels that `softbuffer` expects. Windows have no alpha channel, so
+/// pixels are composited over black.
+#[inline]
+fn present_pixels(surface: &ImageSurface, buffer: &mut [u32]) -> crate::Result {
+    if buffer.len() != surface.width() as usize * surface.height() as usize {
+        return Err(crate::Error::StaticMsg(
+            "Buffer length does not match the surface's dimensions",
+        ));
+    }
+
+    buffer
+        .iter_mut()
+        .zip(surface.data().chunks_exact(4))
+        .for_each(|(dst, src)| {
+            let a = src[3] as u32;
+            let [r, g, b] = [src[0], src[1], src[2]].map(|c| (c as u32 * a + 127) / 255);
+            *dst = (r << 16) | (g << 8) | b;
+        });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{surface::Surface, Color};
+
+    #[test]
+    fn present_copies_channels_in_order() {
+        let mut surface = ImageSurface::new(2, 1);
+        surface
+            .set_fill_color(Color::from_rgb_u8(0x12, 0x34, 0x56))
+            .unwrap();
+        surface.fill_rectangle(0.0, 0.0, 1.0, 1.0).unwrap();
+
+        let mut buffer = [0xFFFF_FFFF; 2];
+        present_pixels(&surface, &mut buffer).unwrap();
+        // the untouched pixel is transparent, so it comes out black
+        assert_eq!(buffer, [0x0012_3456, 0]);
+
+        assert!(present_pixels(&surface, &mut [0; 3]).is_err());
+    }
+}
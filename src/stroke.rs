@@ -0,0 +1,171 @@
+// MIT/Apache2 License
+
+use lyon_geom::{LineSegment, Point};
+
+/// The shape used at the ends of lines.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LineCap {
+    /// The line ends exactly at its endpoint.
+    Butt,
+    /// The line ends in a semicircle centered on its endpoint.
+    Round,
+    /// The line ends in a square extending half of the line width past its endpoint.
+    Square,
+}
+
+impl Default for LineCap {
+    #[inline]
+    fn default() -> Self {
+        Self::Butt
+    }
+}
+
+/// The shape used at the corners where two lines meet.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LineJoin {
+    /// The outer edges of the lines are extended until they meet.
+    Miter,
+    /// The corner is rounded off.
+    Round,
+    /// The corner is cut off.
+    Bevel,
+}
+
+impl Default for LineJoin {
+    #[inline]
+    fn default() -> Self {
+        Self::Miter
+    }
+}
+
+/// Split a series of lines into the segments that should be drawn for the given dash pattern. The pattern
+/// alternates between lengths that are drawn and lengths that are skipped, starting `offset` units into the
+/// pattern. Patterns with an odd number of entries are repeated to make them even. The position in the pattern
+/// carries over between lines that are connected end-to-start.
+#[inline]
+pub fn dash_lines(
+    lines: impl IntoIterator<Item = LineSegment<f32>>,
+    pattern: &[f32],
+    offset: f32,
+) -> Vec<LineSegment<f32>> {
+    let mut pattern: Vec<f32> = pattern.iter().copied().map(f32::abs).collect();
+    if pattern.len() % 2 != 0 {
+        pattern.extend_from_within(..);
+    }
+
+    let total: f32 = pattern.iter().sum();
+    if total <= 0.0 || !total.is_finite() {
+        return lines.into_iter().collect();
+    }
+
+    // figure out where in the pattern the offset puts us
+    let mut start_index = 0;
+    let mut start_left = offset.rem_euclid(total);
+    while start_left >= pattern[start_index] && start_index + 1 < pattern.len() {
+        start_left -= pattern[start_index];
+        start_index += 1;
+    }
+    let start_left = pattern[start_index] - start_left;
+
+    let mut index = start_index;
+    let mut left = start_left;
+    let mut last_end: Option<Point<f32>> = None;
+    let mut segments = vec![];
+
+    lines.into_iter().for_each(|line| {
+        // lines that aren't connected to the previous one restart the pattern
+        if last_end != Some(line.from) {
+            index = start_index;
+            left = start_left;
+        }
+        last_end = Some(line.to);
+
+        let length = line.length();
+        let mut traveled = 0.0;
+
+        while traveled < length {
+            let step = left.min(length - traveled);
+
+            if index % 2 == 0 {
+                segments.push(LineSegment {
+                    from: line.sample(traveled / length),
+                    to: line.sample((traveled + step) / length),
+                });
+            }
+
+            traveled += step;
+            left -= step;
+            if left <= 0.0 {
+                index = (index + 1) % pattern.len();
+                left = pattern[index];
+            }
+        }
+    });
+
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(x1: f32, y1: f32, x2: f32, y2: f32) -> LineSegment<f32> {
+        LineSegment {
+            from: Point::new(x1, y1),
+            to: Point::new(x2, y2),
+        }
+    }
+
+    fn assert_segments_eq(actual: &[LineSegment<f32>], expected: &[LineSegment<f32>]) {
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                (a.from - e.from).length() < 1e-4 && (a.to - e.to).length() < 1e-4,
+                "{:?} != {:?}",
+                a,
+                e
+            );
+        }
+    }
+
+    #[test]
+    fn dashed_rectangle() {
+        let rect = [
+            line(0.0, 0.0, 10.0, 0.0),
+            line(10.0, 0.0, 10.0, 10.0),
+            line(10.0, 10.0, 0.0, 10.0),
+            line(0.0, 10.0, 0.0, 0.0),
+        ];
+        let segments = dash_lines(rect.iter().copied(), &[2.0, 3.0], 0.0);
+        assert_segments_eq(
+            &segments,
+            &[
+                line(0.0, 0.0, 2.0, 0.0),
+                line(5.0, 0.0, 7.0, 0.0),
+                line(10.0, 0.0, 10.0, 2.0),
+                line(10.0, 5.0, 10.0, 7.0),
+                line(10.0, 10.0, 8.0, 10.0),
+                line(5.0, 10.0, 3.0, 10.0),
+                line(0.0, 10.0, 0.0, 8.0),
+                line(0.0, 5.0, 0.0, 3.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn dash_carries_over_corners() {
+        // the gap starts before the corner and finishes after it
+        let lines = [line(0.0, 0.0, 4.0, 0.0), line(4.0, 0.0, 4.0, 4.0)];
+        let segments = dash_lines(lines.iter().copied(), &[3.0], 0.0);
+        assert_segments_eq(
+            &segments,
+            &[line(0.0, 0.0, 3.0, 0.0), line(4.0, 2.0, 4.0, 4.0)],
+        );
+    }
+
+    #[test]
+    fn empty_pattern_is_solid() {
+        let lines = [line(0.0, 0.0, 4.0, 0.0)];
+        assert_segments_eq(&dash_lines(lines.iter().copied(), &[], 0.0), &lines);
+    }
+}
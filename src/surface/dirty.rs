@@ -0,0 +1,62 @@
+// MIT/Apache2 License
+
+use lyon_geom::{Point, Rect};
+
+/// Keeps track of the area of a surface that has been drawn to. Surfaces that support dirty-region tracking
+/// can embed this and report each primitive's bounds to it.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct DirtyRegion {
+    tracking: bool,
+    // the minimum and maximum points of the area drawn to
+    region: Option<(Point<f32>, Point<f32>)>,
+}
+
+impl DirtyRegion {
+    /// Create a new `DirtyRegion` that isn't tracking anything yet.
+    #[inline]
+    pub fn new() -> DirtyRegion {
+        DirtyRegion::default()
+    }
+
+    /// Start tracking, discarding the area that has been drawn to so far.
+    #[inline]
+    pub fn begin(&mut self) {
+        self.tracking = true;
+        self.region = None;
+    }
+
+    /// Tell whether or not this region is tracking what is drawn.
+    #[inline]
+    pub fn is_tracking(&self) -> bool {
+        self.tracking
+    }
+
+    /// Add a rectangle to the area that has been drawn to.
+    #[inline]
+    pub fn add_rect(&mut self, rect: Rect<f32>) {
+        self.add_points(&[rect.min(), rect.max()]);
+    }
+
+    /// Add the bounding box of a set of points to the area that has been drawn to.
+    #[inline]
+    pub fn add_points(&mut self, points: &[Point<f32>]) {
+        if !self.tracking {
+            return;
+        }
+
+        // Rect::union ignores empty rectangles, but a horizontal line still touches the surface
+        self.region = points.iter().fold(self.region, |region, &pt| match region {
+            Some((min, max)) => Some((min.min(pt), max.max(pt))),
+            None => Some((pt, pt)),
+        });
+    }
+
+    /// Take the area that has been drawn to since tracking began, or since this was last called. Returns `None`
+    /// if nothing has been drawn or this region isn't tracking.
+    #[inline]
+    pub fn take(&mut self) -> Option<Rect<f32>> {
+        self.region
+            .take()
+            .map(|(min, max)| Rect::from_points([min, max].iter().copied()))
+    }
+}
@@ -0,0 +1,1095 @@
+// MIT/Apache2 License
+
+//! A software renderer that draws into an in-memory buffer of pixels. This doesn't need a server, which makes
+//! it useful for headless rendering and for checking what other surfaces should draw.
+
+use super::{
+    read_intensity_with, DirtyRegion, Surface, SurfaceFeatures, DEFAULT_TOLERANCE, MIN_TOLERANCE,
+};
+use crate::{
+    fill::{FillMode, FillRule, ImagePattern, TileMode},
+    image::{check_image_region, check_len, to_rgba},
+    stroke::{dash_lines, LineCap, LineJoin},
+    transform::Transform,
+    util::intersect_clip,
+    Color, Image, ImageBuffer, ImageFormat, Intensity,
+};
+use lyon_geom::{LineSegment, Point, Rect, Size, Vector};
+use std::{cmp::Ordering, collections::HashMap, iter, num::NonZeroUsize};
+
+#[cfg(feature = "png")]
+use std::{fs::File, io::BufWriter, path::Path};
+
+const FEATURES: SurfaceFeatures = SurfaceFeatures {
+    transparency: true,
+    gradients: true,
+    floats: true,
+    clipping: true,
+    transforms: true,
+    paths: true,
+    text: false,
+    images: true,
+    patterns: true,
+    size: true,
+    antialiasing: false,
+    dirty_tracking: true,
+};
+
+// number of segments used to approximate round line caps
+const ROUND_CAP_SEGMENTS: usize = 16;
+
+/// A surface that draws onto a buffer of RGBA pixels in memory.
+///
+/// Shapes are not antialiased; a pixel is drawn if its center lies within the shape. Lines are drawn as
+/// separate segments, so line joins are not applied.
+#[derive(Debug)]
+pub struct ImageSurface {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+    state: State,
+    saved: Vec<State>,
+    images: HashMap<Image, ImageBuffer>,
+    next_image: usize,
+    tolerance: f32,
+    dirty: DirtyRegion,
+}
+
+/// Drawing state that is saved by `save()`.
+#[derive(Debug, Clone)]
+struct State {
+    stroke: Color,
+    fill: FillRule,
+    fill_mode: FillMode,
+    alpha: f32,
+    line_width: usize,
+    line_cap: LineCap,
+    dash: Vec<f32>,
+    dash_offset: f32,
+    clips: Vec<Rect<f32>>,
+    transform: Transform,
+}
+
+impl ImageSurface {
+    /// Create a new `ImageSurface` of the given size, filled with transparent pixels.
+    #[inline]
+    pub fn new(width: u32, height: u32) -> ImageSurface {
+        ImageSurface {
+            width,
+            height,
+            data: vec![0; width as usize * height as usize * 4],
+            state: State {
+                stroke: Color::BLACK,
+                fill: FillRule::SolidColor(Color::BLACK),
+                fill_mode: FillMode::default(),
+                alpha: 1.0,
+                line_width: 1,
+                line_cap: LineCap::default(),
+                dash: vec![],
+                dash_offset: 0.0,
+                clips: vec![],
+                transform: Transform::identity(),
+            },
+            saved: vec![],
+            images: HashMap::new(),
+            next_image: 1,
+            tolerance: DEFAULT_TOLERANCE,
+            dirty: DirtyRegion::new(),
+        }
+    }
+
+    /// The width of the surface, in pixels.
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the surface, in pixels.
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The RGBA pixels that have been drawn onto this surface.
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Get the color of the pixel at the given position, if it lies within the surface.
+    #[inline]
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Color> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let p = &self.data[i..i + 4];
+        Color::new(
+            p[0] as f32 / 255.0,
+            p[1] as f32 / 255.0,
+            p[2] as f32 / 255.0,
+            p[3] as f32 / 255.0,
+        )
+    }
+
+    /// Copy the contents of this surface into an `ImageBuffer`.
+    #[inline]
+    pub fn to_image_buffer(&self) -> ImageBuffer {
+        ImageBuffer::from_raw(
+            self.width,
+            self.height,
+            ImageFormat::Rgba,
+            self.data.clone(),
+        )
+        .expect("Surface buffer should always match its size")
+    }
+
+    /// Write the contents of this surface to a PNG file.
+    #[cfg(feature = "png")]
+    #[inline]
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> crate::Result {
+        let encode_err = |e: png::EncodingError| crate::Error::Msg(e.to_string());
+
+        let file = File::create(path).map_err(|e| crate::Error::Msg(e.to_string()))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(encode_err)?;
+        writer.write_image_data(&self.data).map_err(encode_err)?;
+        writer.finish().map_err(encode_err)
+    }
+
+    /// The area that drawing is currently restricted to, in pixels.
+    #[inline]
+    fn bounds(&self) -> Rect<f32> {
+        let surface = Rect::new(
+            Point::origin(),
+            Size::new(self.width as f32, self.height as f32),
+        );
+        intersect_clip(self.state.clips.last(), surface)
+    }
+
+    /// Fill a polygon, whose points are already transformed, with the given fill rule.
+    #[inline]
+    fn fill_device_polygon(&mut self, points: &[Point<f32>], rule: &FillRule, mode: FillMode) {
+        self.fill_device_polygons(&[points], rule, mode)
+    }
+
+    /// Fill several polygons at once, whose points are already transformed, so that they can cut holes in each
+    /// other according to the fill mode.
+    #[inline]
+    fn fill_device_polygons(&mut self, polys: &[&[Point<f32>]], rule: &FillRule, mode: FillMode) {
+        let polys: Vec<&[Point<f32>]> = polys
+            .iter()
+            .copied()
+            .filter(|points| points.len() > 2)
+            .collect();
+        if polys.is_empty() {
+            return;
+        }
+
+        let shape =
+            Rect::from_points(polys.iter().flat_map(|points| points.iter().copied())).round_out();
+        let bounds = match shape.intersection(&self.bounds()) {
+            Some(bounds) => bounds.round_out(),
+            None => return,
+        };
+        self.dirty.add_rect(bounds);
+
+        let images = &self.images;
+        let paint: Box<dyn Fn(u32, u32) -> [u8; 4] + '_> = match rule {
+            FillRule::SolidColor(color) => {
+                let (r, g, b, a) = color.clamp_u8();
+                Box::new(move |_, _| [r, g, b, a])
+            }
+            FillRule::Pattern(pattern) => match images.get(&pattern.image) {
+                Some(image) => {
+                    let inverse = pattern
+                        .transform
+                        .unwrap_or_default()
+                        .inverse()
+                        .unwrap_or_default();
+                    let pattern = *pattern;
+                    Box::new(move |x, y| sample_pattern(image, &pattern, &inverse, x, y))
+                }
+                None => return,
+            },
+            // gradients are laid out across the shape's bounding box, but only the visible part is shaded
+            rule => {
+                let pixels = rule.rasterize_region(shape, bounds).unwrap_or_default();
+                let (ox, oy, stride) = (
+                    bounds.origin.x as i64,
+                    bounds.origin.y as i64,
+                    bounds.size.width as usize,
+                );
+                Box::new(move |x, y| {
+                    let i = ((y as i64 - oy) as usize * stride + (x as i64 - ox) as usize) * 4;
+                    [pixels[i], pixels[i + 1], pixels[i + 2], pixels[i + 3]]
+                })
+            }
+        };
+
+        let width = self.width as usize;
+        let alpha = self.state.alpha;
+        let data = &mut self.data;
+        let (min_x, max_x) = (bounds.min_x() as u32, bounds.max_x() as u32);
+        let mut crossings: Vec<(f32, i32)> = vec![];
+
+        (bounds.min_y() as u32..bounds.max_y() as u32).for_each(|y| {
+            // find the edges that cross the center of this row
+            let yc = y as f32 + 0.5;
+            crossings.clear();
+            crossings.extend(
+                polys
+                    .iter()
+                    .flat_map(|points| points.iter().zip(points.iter().cycle().skip(1)))
+                    .filter(|(a, b)| (a.y <= yc) != (b.y <= yc))
+                    .map(|(a, b)| {
+                        let x = a.x + (yc - a.y) * (b.x - a.x) / (b.y - a.y);
+                        (x, if b.y > a.y { 1 } else { -1 })
+                    }),
+            );
+            crossings.sort_unstable_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+            let mut winding = 0;
+            crossings.windows(2).enumerate().for_each(|(i, pair)| {
+                winding += pair[0].1;
+                let inside = match mode {
+                    FillMode::EvenOdd => i % 2 == 0,
+                    FillMode::NonZero => winding != 0,
+                };
+                if !inside {
+                    return;
+                }
+
+                // fill in the pixels whose centers lie between these crossings
+                let start = (pair[0].0 - 0.5).ceil().max(min_x as f32) as u32;
+                let end = (pair[1].0 - 0.5).ceil().min(max_x as f32) as u32;
+                (start..end).for_each(|x| {
+                    let i = (y as usize * width + x as usize) * 4;
+                    blend(&mut data[i..i + 4], paint(x, y), alpha);
+                });
+            });
+        });
+    }
+
+    /// Transform lines and stroke them with the current stroke color, ignoring the dash pattern.
+    #[inline]
+    fn stroke_lines(&mut self, lines: Vec<LineSegment<f32>>) {
+        let transform = self.state.transform;
+        let lines: Vec<LineSegment<f32>> = lines
+            .into_iter()
+            .map(|line| LineSegment {
+                from: transform.transform_point(line.from),
+                to: transform.transform_point(line.to),
+            })
+            .collect();
+        self.stroke_device_lines(&lines);
+    }
+
+    /// Stroke lines, whose points are already transformed, with the current stroke color.
+    #[inline]
+    fn stroke_device_lines(&mut self, lines: &[LineSegment<f32>]) {
+        let rule = FillRule::SolidColor(self.state.stroke);
+        let half = self.state.line_width.max(1) as f32 / 2.0;
+        let cap = self.state.line_cap;
+
+        lines.iter().for_each(|line| {
+            let direction = (line.to - line.from)
+                .try_normalize()
+                .unwrap_or_else(|| Vector::new(1.0, 0.0));
+            let normal = Vector::new(-direction.y, direction.x) * half;
+            let extend = match cap {
+                LineCap::Square => direction * half,
+                _ => Vector::zero(),
+            };
+
+            let (from, to) = (line.from - extend, line.to + extend);
+            self.fill_device_polygon(
+                &[from + normal, to + normal, to - normal, from - normal],
+                &rule,
+                FillMode::NonZero,
+            );
+
+            if let LineCap::Round = cap {
+                [line.from, line.to].iter().for_each(|&center| {
+                    let circle: Vec<Point<f32>> = (0..ROUND_CAP_SEGMENTS)
+                        .map(|i| {
+                            let angle =
+                                i as f32 * std::f32::consts::PI * 2.0 / ROUND_CAP_SEGMENTS as f32;
+                            center + Vector::new(angle.cos(), angle.sin()) * half
+                        })
+                        .collect();
+                    self.fill_device_polygon(&circle, &rule, FillMode::NonZero);
+                });
+            }
+        });
+    }
+
+    /// Draw a region of an image onto a region of the surface, using the nearest pixel when scaling.
+    #[inline]
+    fn blit(&mut self, image: &ImageBuffer, src: Rect<f32>, dst: Rect<f32>) {
+        if dst.is_empty() || src.is_empty() {
+            return;
+        }
+
+        let bounds = match dst.intersection(&self.bounds()) {
+            Some(bounds) => bounds.round_out(),
+            None => return,
+        };
+        self.dirty.add_rect(bounds);
+        let scale_x = src.size.width / dst.size.width;
+        let scale_y = src.size.height / dst.size.height;
+        let max_x = image.width().saturating_sub(1) as f32;
+        let max_y = image.height().saturating_sub(1) as f32;
+
+        let width = self.width as usize;
+        let alpha = self.state.alpha;
+        (bounds.min_y() as u32..bounds.max_y() as u32).for_each(|y| {
+            let sy = (src.origin.y + (y as f32 + 0.5 - dst.origin.y) * scale_y)
+                .floor()
+                .clamp(0.0, max_y) as usize;
+            (bounds.min_x() as u32..bounds.max_x() as u32).for_each(|x| {
+                let sx = (src.origin.x + (x as f32 + 0.5 - dst.origin.x) * scale_x)
+                    .floor()
+                    .clamp(0.0, max_x) as usize;
+                let si = (sy * image.width() as usize + sx) * 4;
+                let di = (y as usize * width + x as usize) * 4;
+                let data = image.data();
+                blend(
+                    &mut self.data[di..di + 4],
+                    [data[si], data[si + 1], data[si + 2], data[si + 3]],
+                    alpha,
+                );
+            });
+        });
+    }
+}
+
+impl Surface for ImageSurface {
+    #[inline]
+    fn features(&self) -> SurfaceFeatures {
+        FEATURES
+    }
+
+    #[inline]
+    fn set_stroke(&mut self, color: Color) -> crate::Result {
+        self.state.stroke = color;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_fill(&mut self, rule: FillRule) -> crate::Result {
+        self.state.fill = rule;
+        Ok(())
+    }
+
+    #[inline]
+    fn fill_rule(&self) -> Option<&FillRule> {
+        Some(&self.state.fill)
+    }
+
+    #[inline]
+    fn set_fill_mode(&mut self, mode: FillMode) -> crate::Result {
+        self.state.fill_mode = mode;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_line_width(&mut self, width: usize) -> crate::Result {
+        self.state.line_width = width;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_line_cap(&mut self, cap: LineCap) -> crate::Result {
+        self.state.line_cap = cap;
+        Ok(())
+    }
+
+    /// Lines are drawn as separate segments, so this has no effect.
+    #[inline]
+    fn set_line_join(&mut self, _join: LineJoin) -> crate::Result {
+        Ok(())
+    }
+
+    /// Lines are drawn as separate segments, so this has no effect.
+    #[inline]
+    fn set_miter_limit(&mut self, _limit: f32) -> crate::Result {
+        Ok(())
+    }
+
+    #[inline]
+    fn set_dash(&mut self, pattern: &[f32], offset: f32) -> crate::Result {
+        self.state.dash = pattern.to_vec();
+        self.state.dash_offset = offset;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_global_alpha(&mut self, alpha: f32) -> crate::Result {
+        self.state.alpha = alpha.clamp(0.0, 1.0);
+        Ok(())
+    }
+
+    #[inline]
+    fn push_clip(&mut self, rect: Rect<f32>) -> crate::Result {
+        let rect = self.state.transform.transform_rect(&rect);
+        let clip = intersect_clip(self.state.clips.last(), rect);
+        self.state.clips.push(clip);
+        Ok(())
+    }
+
+    #[inline]
+    fn pop_clip(&mut self) -> crate::Result {
+        self.state.clips.pop().ok_or(crate::Error::NoClip)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_transform(&mut self, transform: Transform) -> crate::Result {
+        self.state.transform = transform;
+        Ok(())
+    }
+
+    #[inline]
+    fn transform(&mut self, transform: Transform) -> crate::Result {
+        self.state.transform = transform.then(&self.state.transform);
+        Ok(())
+    }
+
+    #[inline]
+    fn save(&mut self) -> crate::Result {
+        self.saved.push(self.state.clone());
+        Ok(())
+    }
+
+    #[inline]
+    fn restore(&mut self) -> crate::Result {
+        self.state = self
+            .saved
+            .pop()
+            .ok_or(crate::Error::StaticMsg("No saved state to restore"))?;
+        Ok(())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> crate::Result {
+        Ok(())
+    }
+
+    #[inline]
+    fn tolerance(&self) -> f32 {
+        self.tolerance
+    }
+
+    #[inline]
+    fn set_tolerance(&mut self, tolerance: f32) -> crate::Result {
+        self.tolerance = tolerance.max(MIN_TOLERANCE);
+        Ok(())
+    }
+
+    #[inline]
+    fn size(&self) -> crate::Result<Size<f32>> {
+        Ok(Size::new(self.width as f32, self.height as f32))
+    }
+
+    #[inline]
+    fn read_intensity(&self, rect: Rect<f32>) -> crate::Result<Vec<Intensity>> {
+        read_intensity_with(rect, self.size()?, |x, y| self.pixel(x, y))
+    }
+
+    #[inline]
+    fn begin_dirty_tracking(&mut self) -> crate::Result {
+        self.dirty.begin();
+        Ok(())
+    }
+
+    #[inline]
+    fn take_dirty_region(&mut self) -> crate::Result<Option<Rect<f32>>> {
+        Ok(self.dirty.take())
+    }
+
+    #[inline]
+    fn create_image(
+        &mut self,
+        image_bytes: &[u8],
+        width: u32,
+        height: u32,
+        image_format: ImageFormat,
+    ) -> crate::Result<Image> {
+        check_len(image_bytes, width, height, image_format)?;
+        let buffer = ImageBuffer::from_raw(
+            width,
+            height,
+            ImageFormat::Rgba,
+            to_rgba(image_bytes, width, height, image_format),
+        )?;
+
+        let image =
+            Image::from_raw(NonZeroUsize::new(self.next_image).expect("Image ID overflowed"));
+        self.next_image += 1;
+        self.images.insert(image, buffer);
+        Ok(image)
+    }
+
+    #[inline]
+    fn destroy_image(&mut self, image: Image) -> crate::Result {
+        self.images.remove(&image);
+        Ok(())
+    }
+
+    #[inline]
+    fn draw_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> crate::Result {
+        self.draw_lines(&[LineSegment {
+            from: Point::new(x1, y1),
+            to: Point::new(x2, y2),
+        }])
+    }
+
+    #[inline]
+    fn draw_lines(&mut self, lines: &[LineSegment<f32>]) -> crate::Result {
+        let lines = if self.state.dash.is_empty() {
+            lines.to_vec()
+        } else {
+            dash_lines(
+                lines.iter().copied(),
+                &self.state.dash,
+                self.state.dash_offset,
+            )
+        };
+        self.stroke_lines(lines);
+        Ok(())
+    }
+
+    #[inline]
+    fn draw_dashed_line(
+        &mut self,
+        from: Point<f32>,
+        to: Point<f32>,
+        pattern: &[f32],
+        offset: f32,
+    ) -> crate::Result {
+        self.stroke_lines(dash_lines(
+            iter::once(LineSegment { from, to }),
+            pattern,
+            offset,
+        ));
+        Ok(())
+    }
+
+    #[inline]
+    fn fill_polygon(&mut self, points: &[Point<f32>]) -> crate::Result {
+        let transform = self.state.transform;
+        let points: Vec<Point<f32>> = points
+            .iter()
+            .map(|&point| transform.transform_point(point))
+            .collect();
+        let rule = self.state.fill.clone();
+        self.fill_device_polygon(&points, &rule, self.state.fill_mode);
+        Ok(())
+    }
+
+    #[inline]
+    fn fill_polygons(&mut self, polys: &[&[Point<f32>]]) -> crate::Result {
+        let transform = self.state.transform;
+        let polys: Vec<Vec<Point<f32>>> = polys
+            .iter()
+            .map(|points| {
+                points
+                    .iter()
+                    .map(|&point| transform.transform_point(point))
+                    .collect()
+            })
+            .collect();
+        let polys: Vec<&[Point<f32>]> = polys.iter().map(Vec::as_slice).collect();
+        let rule = self.state.fill.clone();
+        self.fill_device_polygons(&polys, &rule, self.state.fill_mode);
+        Ok(())
+    }
+
+    #[inline]
+    fn copy_image(
+        &mut self,
+        src: Image,
+        src_x: i32,
+        src_y: i32,
+        dst_x: i32,
+        dst_y: i32,
+        width: u32,
+        height: u32,
+    ) -> crate::Result {
+        let size = Size::new(width as f32, height as f32);
+        self.draw_image_region(
+            &src,
+            Rect::new(Point::new(src_x as f32, src_y as f32), size),
+            Rect::new(Point::new(dst_x as f32, dst_y as f32), size),
+        )
+    }
+
+    #[inline]
+    fn draw_image(&mut self, image: &Image, dst: Rect<f32>) -> crate::Result {
+        let buffer = self
+            .images
+            .get(image)
+            .ok_or(crate::Error::ImageNotAvailable)?;
+        let size = Size::new(buffer.width() as f32, buffer.height() as f32);
+        self.draw_image_region(image, Rect::new(Point::origin(), size), dst)
+    }
+
+    /// Like other surfaces, this only moves the destination rectangle by the current transform.
+    #[inline]
+    fn draw_image_region(
+        &mut self,
+        image: &Image,
+        src: Rect<f32>,
+        dst: Rect<f32>,
+    ) -> crate::Result {
+        // take the image out of the map so we can draw it onto ourselves
+        let buffer = self
+            .images
+            .remove(image)
+            .ok_or(crate::Error::ImageNotAvailable)?;
+
+        let res = check_image_region(
+            src,
+            Size::new(buffer.width() as f32, buffer.height() as f32),
+        );
+        if res.is_ok() {
+            let dst = Rect::new(self.state.transform.transform_point(dst.origin), dst.size);
+            self.blit(&buffer, src, dst);
+        }
+
+        self.images.insert(*image, buffer);
+        res
+    }
+
+    #[inline]
+    fn draw_image_masked(&mut self, image: &Image, mask: &Image, dst: Rect<f32>) -> crate::Result {
+        let image = self
+            .images
+            .get(image)
+            .ok_or(crate::Error::ImageNotAvailable)?;
+        let mask = self
+            .images
+            .get(mask)
+            .ok_or(crate::Error::ImageNotAvailable)?;
+        let masked = image.apply_mask(mask)?;
+
+        let src = Rect::new(
+            Point::origin(),
+            Size::new(masked.width() as f32, masked.height() as f32),
+        );
+        let dst = Rect::new(self.state.transform.transform_point(dst.origin), dst.size);
+        self.blit(&masked, src, dst);
+        Ok(())
+    }
+}
+
+/// Blend a straight-alpha RGBA pixel over another one, after multiplying its alpha by `alpha`.
+#[inline]
+fn blend(dst: &mut [u8], src: [u8; 4], alpha: f32) {
+    match (src[3] as f32 * alpha).round() as u8 {
+        0 => {}
+        255 => dst.copy_from_slice(&src),
+        alpha => {
+            let sa = alpha as f32 / 255.0;
+            let da = dst[3] as f32 / 255.0 * (1.0 - sa);
+            let out_a = sa + da;
+
+            (0..3).for_each(|i| {
+                let c = (src[i] as f32 * sa + dst[i] as f32 * da) / out_a;
+                dst[i] = c.round() as u8;
+            });
+            dst[3] = (out_a * 255.0).round() as u8;
+        }
+    }
+}
+
+/// Sample the pixel of an image pattern that covers the given surface pixel.
+#[inline]
+fn sample_pattern(
+    image: &ImageBuffer,
+    pattern: &ImagePattern,
+    inverse: &Transform,
+    x: u32,
+    y: u32,
+) -> [u8; 4] {
+    let point = inverse.transform_point(Point::new(x as f32 + 0.5, y as f32 + 0.5));
+    let tile = |c: f32, len: u32| -> usize {
+        let len = len as f32;
+        let c = match pattern.tiling {
+            TileMode::Tile => c.rem_euclid(len),
+            TileMode::Clamp => c.clamp(0.0, len - 1.0),
+            TileMode::Mirror => {
+                let c = c.rem_euclid(len * 2.0);
+                if c >= len {
+                    len * 2.0 - c
+                } else {
+                    c
+                }
+            }
+        };
+        (c.floor().max(0.0) as usize).min(len as usize - 1)
+    };
+
+    if image.width() == 0 || image.height() == 0 {
+        return [0; 4];
+    }
+
+    let i =
+        (tile(point.y, image.height()) * image.width() as usize + tile(point.x, image.width())) * 4;
+    let data = image.data();
+    [data[i], data[i + 1], data[i + 2], data[i + 3]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        color::consts::{BLUE, GREEN, RED},
+        gradient::{ColorStop, Gradient},
+    };
+    use lyon_geom::Angle;
+
+    #[test]
+    fn nested_save_restore() {
+        let mut surface = ImageSurface::new(4, 4);
+        surface.set_fill_color(RED).unwrap();
+        surface.set_line_width(2).unwrap();
+        surface.save().unwrap();
+        surface.set_fill_color(GREEN).unwrap();
+        surface.save().unwrap();
+        surface.set_fill_color(BLUE).unwrap();
+        surface.set_line_width(5).unwrap();
+
+        surface.restore().unwrap();
+        assert_eq!(surface.fill_rule(), Some(&FillRule::SolidColor(GREEN)));
+        assert_eq!(surface.state.line_width, 2);
+        surface.restore().unwrap();
+        assert_eq!(surface.fill_rule(), Some(&FillRule::SolidColor(RED)));
+    }
+
+    #[test]
+    fn golden_filled_rectangle() {
+        let mut surface = ImageSurface::new(4, 3);
+        surface.set_fill_color(RED).unwrap();
+        surface.fill_rectangle(1.0, 1.0, 2.0, 1.0).unwrap();
+
+        let mut expected = vec![0; 4 * 3 * 4];
+        [(1, 1), (2, 1)].iter().for_each(|&(x, y)| {
+            let i = (y * 4 + x) * 4;
+            expected[i..i + 4].copy_from_slice(&[255, 0, 0, 255]);
+        });
+        assert_eq!(surface.data(), &expected[..]);
+    }
+
+    #[test]
+    fn create_image_rejects_short_buffer() {
+        let mut surface = ImageSurface::new(4, 4);
+        assert!(matches!(
+            surface.create_image(&[0; 10], 2, 2, ImageFormat::Rgb),
+            Err(crate::Error::StaticMsg(_))
+        ));
+    }
+
+    #[test]
+    fn huge_gradient_only_shades_visible_pixels() {
+        let gradient = Gradient::try_new(vec![
+            ColorStop {
+                color: RED,
+                position: Intensity::new(0.0).unwrap(),
+            },
+            ColorStop {
+                color: BLUE,
+                position: Intensity::new(1.0).unwrap(),
+            },
+        ])
+        .unwrap();
+
+        let mut surface = ImageSurface::new(4, 4);
+        surface
+            .set_fill(FillRule::LinearGradient(gradient, Angle::zero()))
+            .unwrap();
+        surface
+            .fill_rectangle(-1.0e6, -1.0e6, 2.0e6, 2.0e6)
+            .unwrap();
+        assert_eq!(surface.pixel(0, 0).map(|c| c.alpha()), Some(1.0));
+    }
+
+    #[test]
+    fn chord_and_pie_close_differently() {
+        let quarter = |pie| {
+            let mut surface = ImageSurface::new(20, 20);
+            surface.set_fill_color(RED).unwrap();
+            if pie {
+                surface
+                    .fill_pie(
+                        Point::new(2.0, 2.0),
+                        Vector::new(16.0, 16.0),
+                        Angle::zero(),
+                        Angle::degrees(90.0),
+                    )
+                    .unwrap();
+            } else {
+                surface
+                    .fill_arc(2.0, 2.0, 16.0, 16.0, Angle::zero(), Angle::degrees(90.0))
+                    .unwrap();
+            }
+            surface
+        };
+        let chord = quarter(false);
+        let pie = quarter(true);
+
+        // the chord closes straight from the end of the arc to its start, cutting off the corner at the center
+        assert_ne!(chord.pixel(4, 4), Some(RED));
+        assert_eq!(pie.pixel(4, 4), Some(RED));
+
+        // both of them cover the area between that line and the arc
+        assert_eq!(chord.pixel(12, 12), Some(RED));
+        assert_eq!(pie.pixel(12, 12), Some(RED));
+        assert_ne!(chord.pixel(18, 18), Some(RED));
+        assert_ne!(pie.pixel(18, 18), Some(RED));
+    }
+
+    #[test]
+    fn global_alpha_blends_opaque_fills() {
+        let mut surface = ImageSurface::new(4, 4);
+        surface.clear(BLUE).unwrap();
+        surface.set_fill_color(RED).unwrap();
+        surface.set_global_alpha(0.5).unwrap();
+        surface.fill_rectangle(0.0, 0.0, 2.0, 4.0).unwrap();
+        assert_eq!(&surface.data()[..4], &[128, 0, 127, 255]);
+
+        // at full opacity, the same fill replaces the background instead
+        surface.set_global_alpha(1.0).unwrap();
+        surface.fill_rectangle(2.0, 0.0, 2.0, 4.0).unwrap();
+        assert_eq!(surface.pixel(3, 0), Some(RED));
+    }
+
+    #[test]
+    fn clear_then_read_pixel() {
+        let mut surface = ImageSurface::new(4, 4);
+        surface.set_fill_color(BLUE).unwrap();
+        surface.clear(GREEN).unwrap();
+        assert_eq!(surface.pixel(2, 3), Some(GREEN));
+        // the fill is left alone, and clearing doesn't leave any saved state behind
+        assert_eq!(surface.fill_rule(), Some(&FillRule::SolidColor(BLUE)));
+        assert!(surface.restore().is_err());
+    }
+
+    fn square(builder: &mut lyon_path::path::Builder, x: f32, y: f32, size: f32) {
+        builder.begin(Point::new(x, y));
+        builder.line_to(Point::new(x + size, y));
+        builder.line_to(Point::new(x + size, y + size));
+        builder.line_to(Point::new(x, y + size));
+        builder.end(true);
+    }
+
+    #[test]
+    fn fill_path_keeps_subpaths_apart() {
+        let mut builder = lyon_path::Path::builder();
+        square(&mut builder, 0.0, 0.0, 8.0);
+        square(&mut builder, 2.0, 2.0, 4.0);
+        square(&mut builder, 10.0, 0.0, 2.0);
+        let path = builder.build();
+
+        let mut surface = ImageSurface::new(12, 8);
+        surface.set_fill_color(RED).unwrap();
+        surface.fill_path(path.as_slice()).unwrap();
+
+        assert_eq!(surface.pixel(1, 1), Some(RED));
+        assert_eq!(surface.pixel(11, 1), Some(RED));
+        // the inner square is a hole, and nothing bridges the gap between the shapes
+        assert_eq!(surface.pixel(4, 4).map(|c| c.alpha()), Some(0.0));
+        assert_eq!(surface.pixel(9, 1).map(|c| c.alpha()), Some(0.0));
+    }
+
+    #[test]
+    fn restore_without_save_fails() {
+        let mut surface = ImageSurface::new(4, 4);
+        assert!(matches!(surface.restore(), Err(crate::Error::StaticMsg(_))));
+    }
+
+    #[test]
+    fn fill_under_clip_only_touches_clip() {
+        let mut surface = ImageSurface::new(8, 8);
+        surface.set_fill_color(RED).unwrap();
+        surface
+            .push_clip(Rect::new(Point::new(2.0, 2.0), Size::new(4.0, 4.0)))
+            .unwrap();
+        surface
+            .push_clip(Rect::new(Point::new(4.0, 0.0), Size::new(4.0, 8.0)))
+            .unwrap();
+        surface.fill_rectangle(0.0, 0.0, 8.0, 8.0).unwrap();
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let inside = (4..6).contains(&x) && (2..6).contains(&y);
+                let expected = if inside { 1.0 } else { 0.0 };
+                assert_eq!(
+                    surface.pixel(x, y).unwrap().alpha(),
+                    expected,
+                    "{}, {}",
+                    x,
+                    y
+                );
+            }
+        }
+
+        // popping both clips lets the whole surface be drawn to again
+        surface.pop_clip().unwrap();
+        surface.pop_clip().unwrap();
+        surface.fill_rectangle(0.0, 0.0, 8.0, 8.0).unwrap();
+        assert_eq!(surface.pixel(0, 0), Some(RED));
+    }
+
+    #[test]
+    fn rotated_rectangle_fill() {
+        let mut surface = ImageSurface::new(8, 8);
+        surface.set_fill_color(RED).unwrap();
+        // rotating a quarter turn maps (x, y) to (-y, x)
+        surface
+            .set_transform(
+                Transform::rotation(Angle::frac_pi_2()).then(&Transform::translation(6.0, 0.0)),
+            )
+            .unwrap();
+        surface.fill_rectangle(1.0, 1.0, 4.0, 2.0).unwrap();
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let inside = (3..5).contains(&x) && (1..5).contains(&y);
+                let expected = if inside { 1.0 } else { 0.0 };
+                assert_eq!(
+                    surface.pixel(x, y).unwrap().alpha(),
+                    expected,
+                    "{}, {}",
+                    x,
+                    y
+                );
+            }
+        }
+
+        // resetting to the identity draws in surface coordinates again
+        surface.set_transform(Transform::identity()).unwrap();
+        surface.fill_rectangle(0.0, 7.0, 1.0, 1.0).unwrap();
+        assert_eq!(surface.pixel(0, 7), Some(RED));
+    }
+
+    #[test]
+    fn star_under_both_fill_modes() {
+        // a pentagram, whose center is wound around twice
+        let star: Vec<Point<f32>> = (0..5)
+            .map(|i| {
+                let angle = Angle::degrees(-90.0 + 144.0 * i as f32);
+                Point::new(
+                    10.0 + 9.0 * angle.radians.cos(),
+                    10.0 + 9.0 * angle.radians.sin(),
+                )
+            })
+            .collect();
+
+        let mut surface = ImageSurface::new(20, 20);
+        surface.set_fill_color(RED).unwrap();
+        surface.fill_polygon(&star).unwrap();
+        assert_eq!(surface.pixel(10, 10).map(|c| c.alpha()), Some(0.0));
+        assert_eq!(surface.pixel(10, 4), Some(RED));
+
+        let mut surface = ImageSurface::new(20, 20);
+        surface.set_fill_color(RED).unwrap();
+        surface.set_fill_mode(FillMode::NonZero).unwrap();
+        surface.fill_polygon(&star).unwrap();
+        assert_eq!(surface.pixel(10, 10), Some(RED));
+        assert_eq!(surface.pixel(10, 4), Some(RED));
+    }
+
+    #[test]
+    fn one_pixel_image_fills_destination() {
+        let mut surface = ImageSurface::new(4, 4);
+        assert!(surface.features().images);
+        let image = surface
+            .create_image(&[0, 0, 255, 255], 1, 1, ImageFormat::Rgba)
+            .unwrap();
+        surface
+            .draw_image(&image, Rect::new(Point::new(1.0, 1.0), Size::new(2.0, 3.0)))
+            .unwrap();
+
+        (0..4)
+            .flat_map(|y| (0..4).map(move |x| (x, y)))
+            .for_each(|(x, y)| {
+                let inside = (1..3).contains(&x) && (1..4).contains(&y);
+                let expected = if inside {
+                    BLUE
+                } else {
+                    Color::from_rgba_u8(0, 0, 0, 0)
+                };
+                assert_eq!(surface.pixel(x, y), Some(expected), "({}, {})", x, y);
+            });
+    }
+
+    #[test]
+    fn bottom_right_quadrant_of_image() {
+        let mut surface = ImageSurface::new(2, 2);
+        #[rustfmt::skip]
+        let pixels = [
+            255, 0, 0, 255,  0, 255, 0, 255,
+            0, 0, 255, 255,  255, 255, 255, 255,
+        ];
+        let image = surface
+            .create_image(&pixels, 2, 2, ImageFormat::Rgba)
+            .unwrap();
+        surface
+            .draw_image_region(
+                &image,
+                Rect::new(Point::new(1.0, 1.0), Size::new(1.0, 1.0)),
+                Rect::new(Point::origin(), Size::new(2.0, 2.0)),
+            )
+            .unwrap();
+        assert_eq!(surface.data(), &[255; 16][..]);
+
+        assert!(matches!(
+            surface.draw_image_region(
+                &image,
+                Rect::new(Point::new(1.0, 1.0), Size::new(2.0, 1.0)),
+                Rect::new(Point::origin(), Size::new(1.0, 1.0)),
+            ),
+            Err(crate::Error::RegionOutOfBounds)
+        ));
+    }
+
+    #[test]
+    fn masked_image_is_half_transparent() {
+        let mut surface = ImageSurface::new(1, 1);
+        let image = surface
+            .create_image(&[255, 0, 0], 1, 1, ImageFormat::Rgb)
+            .unwrap();
+        let mask = surface
+            .create_image(&[128], 1, 1, ImageFormat::Grayscale)
+            .unwrap();
+        surface
+            .draw_image_masked(
+                &image,
+                &mask,
+                Rect::new(Point::origin(), Size::new(1.0, 1.0)),
+            )
+            .unwrap();
+        assert_eq!(surface.data(), &[255, 0, 0, 128][..]);
+    }
+
+    #[test]
+    fn dirty_region_covers_both_rectangles() {
+        let mut surface = ImageSurface::new(16, 16);
+        surface.fill_rectangle(0.0, 0.0, 1.0, 1.0).unwrap();
+        surface.begin_dirty_tracking().unwrap();
+        surface.set_fill_color(RED).unwrap();
+        surface.fill_rectangle(2.0, 3.0, 4.0, 2.0).unwrap();
+        surface.fill_rectangle(10.0, 1.0, 2.0, 2.0).unwrap();
+
+        assert_eq!(
+            surface.take_dirty_region().unwrap(),
+            Some(Rect::new(Point::new(2.0, 1.0), Size::new(10.0, 4.0)))
+        );
+        assert_eq!(surface.take_dirty_region().unwrap(), None);
+    }
+}
@@ -0,0 +1,1881 @@
+// MIT/Apache2 License
+
+use crate::{
+    fill::{FillMode, FillRule},
+    gradient::{ConicGradient, LinearGradient, RadialGradient},
+    path_from_arc, path_from_arc_closed, path_from_curve, path_from_round_rect, path_to_lines,
+    path_to_polygons,
+    stroke::{dash_lines, LineCap, LineJoin},
+    transform::Transform,
+    util::flatten_arc,
+    Color, Ellipse, Font, GlyphId, Image, ImageFormat, Intensity, NSOpType,
+};
+use lyon_geom::{
+    Angle, Arc, CubicBezierSegment, LineSegment, Point, QuadraticBezierSegment, Rect, Size, Vector,
+};
+use lyon_path::{builder::PathBuilder, Path, PathBuffer, PathBufferSlice, PathSlice};
+use std::{
+    iter,
+    ops::{Deref, DerefMut},
+};
+
+#[cfg(feature = "async")]
+use crate::util::GenericResult;
+#[cfg(feature = "async")]
+use futures_lite::future;
+
+mod dirty;
+pub use dirty::*;
+pub mod image_surface;
+pub use image_surface::*;
+pub mod null;
+pub use null::*;
+pub mod recording;
+pub use recording::*;
+pub mod scaled;
+pub use scaled::*;
+pub mod tee;
+pub use tee::*;
+
+/// The default tolerance used when flattening curves into line segments.
+pub const DEFAULT_TOLERANCE: f32 = 1.0;
+/// The default limit on the ratio between the length of a miter join and the width of the line.
+pub const DEFAULT_MITER_LIMIT: f32 = 10.0;
+/// The smallest tolerance that a surface accepts, to keep curves from being split into too many segments.
+pub const MIN_TOLERANCE: f32 = 0.01;
+
+/// Features that a surface can support.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct SurfaceFeatures {
+    /// Does this surface support transparancy?
+    pub transparency: bool,
+    /// Does this surface support drawing gradients?
+    pub gradients: bool,
+    /// Does this surface support floats? If not, all numbers will be rounded
+    /// down.
+    pub floats: bool,
+    /// Does this surface support clipping regions?
+    pub clipping: bool,
+    /// Does this surface support transforms?
+    pub transforms: bool,
+    /// Does this surface support drawing and filling paths?
+    pub paths: bool,
+    /// Does this surface support drawing text?
+    pub text: bool,
+    /// Does this surface support drawing images?
+    pub images: bool,
+    /// Does this surface support filling shapes with image patterns?
+    pub patterns: bool,
+    /// Can this surface report its size?
+    pub size: bool,
+    /// Can this surface antialias the edges of shapes?
+    pub antialiasing: bool,
+    /// Can this surface track the area that is drawn to?
+    pub dirty_tracking: bool,
+}
+
+impl SurfaceFeatures {
+    /// Does this set of features include the given operation?
+    #[inline]
+    pub fn supports(&self, op: NSOpType) -> bool {
+        match op {
+            NSOpType::Gradients => self.gradients,
+            NSOpType::Size => self.size,
+            NSOpType::Pattern => self.patterns,
+            NSOpType::Image => self.images,
+            NSOpType::Text => self.text,
+            NSOpType::Antialiasing => self.antialiasing,
+            NSOpType::Clipping => self.clipping,
+            NSOpType::Transform => self.transforms,
+            NSOpType::Path => self.paths,
+            NSOpType::Transparency => self.transparency,
+            NSOpType::DirtyTracking => self.dirty_tracking,
+        }
+    }
+}
+
+/// What a surface does when asked to perform an operation that it can only emulate in software.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FallbackPolicy {
+    /// Return `Error::NotSupported` instead of emulating the operation.
+    Reject,
+    /// Emulate the operation, even if it is slow.
+    Emulate,
+}
+
+impl Default for FallbackPolicy {
+    #[inline]
+    fn default() -> Self {
+        Self::Emulate
+    }
+}
+
+/// Specifications for copying an image to the surface.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ImageCopySpecs {
+    /// The image to copy from.
+    pub image: Image,
+    /// The X-coordinate to start copying from in the image.
+    pub src_x: i32,
+    /// The Y-coordinate to start copying at in the image.
+    pub src_y: i32,
+    /// The X-coordinate in the surface to put the image.
+    pub dst_x: i32,
+    /// The Y-coordinate in the surface to put the image.
+    pub dst_y: i32,
+    /// The width of the area of the image to copy.
+    pub width: u32,
+    /// The height of the area of the image to copy.
+    pub height: u32,
+}
+
+/// Flushes a surface when it is dropped. Returned by `Surface::frame`.
+#[derive(Debug)]
+pub struct FlushGuard<'a, S: Surface + ?Sized> {
+    surface: &'a mut S,
+}
+
+impl<'a, S: Surface + ?Sized> Deref for FlushGuard<'a, S> {
+    type Target = S;
+
+    #[inline]
+    fn deref(&self) -> &S {
+        self.surface
+    }
+}
+
+impl<'a, S: Surface + ?Sized> DerefMut for FlushGuard<'a, S> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut S {
+        self.surface
+    }
+}
+
+impl<'a, S: Surface + ?Sized> Drop for FlushGuard<'a, S> {
+    #[inline]
+    fn drop(&mut self) {
+        if let Err(e) = self.surface.flush() {
+            log::error!("Failed to flush surface: {}", e);
+        }
+    }
+}
+
+/// A surface which drawing commands can be applied to.
+pub trait Surface {
+    /// The set of features this surface supports.
+    fn features(&self) -> SurfaceFeatures;
+    /// Does this surface support the given operation?
+    #[inline]
+    fn supports(&self, op: NSOpType) -> bool {
+        self.features().supports(op)
+    }
+    /// Set the color used to draw lines.
+    fn set_stroke(&mut self, color: Color) -> crate::Result;
+    /// Set the rule used to fill shapes. Surfaces return `NotSupported` here if they can't fill shapes with the
+    /// rule, rather than waiting until something is filled.
+    fn set_fill(&mut self, rule: FillRule) -> crate::Result;
+    /// Fill shapes with a solid color. This is the same as calling `set_fill` with `FillRule::SolidColor`.
+    #[inline]
+    fn set_fill_color(&mut self, color: Color) -> crate::Result {
+        self.set_fill(FillRule::SolidColor(color))
+    }
+    /// Get the rule used to fill shapes, or `None` if this surface doesn't keep track of it.
+    #[inline]
+    fn fill_rule(&self) -> Option<&FillRule> {
+        None
+    }
+    /// Set the rule used to decide which parts of a self-intersecting polygon or path are filled.
+    fn set_fill_mode(&mut self, mode: FillMode) -> crate::Result;
+    /// Set the width used to draw lines.
+    fn set_line_width(&mut self, width: usize) -> crate::Result;
+    /// Set the shape used at the ends of lines. Defaults to `LineCap::Butt`.
+    fn set_line_cap(&mut self, cap: LineCap) -> crate::Result;
+    /// Set the shape used at the corners where lines meet. Defaults to `LineJoin::Miter`.
+    fn set_line_join(&mut self, join: LineJoin) -> crate::Result;
+    /// Set the limit on the ratio between the length of a miter join and the width of the line, past which the
+    /// join is beveled instead. Defaults to `DEFAULT_MITER_LIMIT`, and is clamped to at least one.
+    fn set_miter_limit(&mut self, limit: f32) -> crate::Result;
+    /// Set the dash pattern used to draw lines, as alternating lengths of drawn and skipped space starting
+    /// `offset` units into the pattern. An empty pattern draws solid lines.
+    fn set_dash(&mut self, pattern: &[f32], offset: f32) -> crate::Result;
+    /// Set whether operations that this surface can only emulate in software are emulated or rejected. Defaults
+    /// to `FallbackPolicy::Emulate`. Surfaces that emulate nothing ignore this.
+    #[inline]
+    fn set_fallback_policy(&mut self, _policy: FallbackPolicy) -> crate::Result {
+        Ok(())
+    }
+    /// Set whether or not the edges of shapes are antialiased. Surfaces that can't antialias only accept
+    /// `false`.
+    #[inline]
+    fn set_antialias(&mut self, on: bool) -> crate::Result {
+        if on {
+            Err(crate::Error::NotSupported(NSOpType::Antialiasing))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Set an opacity, between 0.0 and 1.0, that is multiplied into everything drawn afterwards. Defaults to 1.0.
+    /// Surfaces that can't blend only accept 1.0.
+    #[inline]
+    fn set_global_alpha(&mut self, alpha: f32) -> crate::Result {
+        if alpha.clamp(0.0, 1.0) < 1.0 {
+            Err(crate::Error::NotSupported(NSOpType::Transparency))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Restrict drawing to the given rectangle, intersected with the currently active clip.
+    fn push_clip(&mut self, rect: Rect<f32>) -> crate::Result;
+    /// Restore the clip that was active before the last call to `push_clip`.
+    fn pop_clip(&mut self) -> crate::Result;
+
+    /// Replace the current transform. Passing `Transform::identity()` resets it. Backends that cannot transform
+    /// images will only move them when they are copied.
+    fn set_transform(&mut self, transform: Transform) -> crate::Result;
+    /// Apply a transform to coordinates before the current transform is applied to them.
+    fn transform(&mut self, transform: Transform) -> crate::Result;
+
+    /// Save the current stroke, fill, line width, line cap, line join, miter limit, dash pattern, antialiasing,
+    /// global alpha, clip and transform.
+    fn save(&mut self) -> crate::Result;
+    /// Restore the state saved by the last call to `save`. This returns `Error::StaticMsg` if there is no saved
+    /// state to restore.
+    fn restore(&mut self) -> crate::Result;
+
+    /// Flush all commands passed to this surface to its target.
+    fn flush(&mut self) -> crate::Result;
+    /// Begin a scope of drawing commands, which are flushed when the returned guard is dropped. Errors that
+    /// occur while flushing are logged.
+    #[inline]
+    fn frame(&mut self) -> FlushGuard<'_, Self>
+    where
+        Self: Sized,
+    {
+        FlushGuard { surface: self }
+    }
+
+    /// The maximum distance between a curve and the line segments used to approximate it, for surfaces that
+    /// need to flatten curves before drawing them.
+    #[inline]
+    fn tolerance(&self) -> f32 {
+        DEFAULT_TOLERANCE
+    }
+    /// Set the tolerance used to flatten curves. Smaller tolerances make smoother curves out of more segments.
+    /// This is clamped to at least `MIN_TOLERANCE`.
+    fn set_tolerance(&mut self, tolerance: f32) -> crate::Result;
+
+    /// Get the size of the area this surface draws onto.
+    #[inline]
+    fn size(&self) -> crate::Result<Size<f32>> {
+        Err(crate::Error::NotSupported(crate::NSOpType::Size))
+    }
+
+    /// Start tracking the area of the surface that is drawn to. Surfaces that can't track what they draw return
+    /// `NotSupported`.
+    #[inline]
+    fn begin_dirty_tracking(&mut self) -> crate::Result {
+        Err(crate::Error::NotSupported(crate::NSOpType::DirtyTracking))
+    }
+
+    /// Take the area of the surface that has been drawn to since tracking began, or since this was last
+    /// called. Returns `None` if nothing has been drawn, and `NotSupported` if the surface can't track what it
+    /// draws.
+    #[inline]
+    fn take_dirty_region(&mut self) -> crate::Result<Option<Rect<f32>>> {
+        Err(crate::Error::NotSupported(crate::NSOpType::DirtyTracking))
+    }
+
+    /// Fill the entire surface with a color. This ignores the current transform, but not the clipping region.
+    #[inline]
+    fn clear(&mut self, color: Color) -> crate::Result {
+        let size = self.size()?;
+        self.save()?;
+        let res = (|| {
+            self.set_transform(Transform::identity())?;
+            self.set_global_alpha(1.0)?;
+            self.set_fill_color(color)?;
+            self.fill_rectangle(0.0, 0.0, size.width, size.height)
+        })();
+        let restored = self.restore();
+        res.and(restored)
+    }
+
+    /// Get the size of the area that a string of text would cover if it were drawn with the given font.
+    #[inline]
+    fn measure_text(&self, _text: &str, _font: &Font) -> crate::Result<Size<f32>> {
+        Err(crate::Error::NotSupported(crate::NSOpType::Text))
+    }
+
+    /// Draw a string of text with the given font. `x` and `y` are the top left corner of the area that the text
+    /// covers, as measured by `measure_text`.
+    #[inline]
+    fn draw_text(&mut self, _text: &str, _x: f32, _y: f32, _font: &Font) -> crate::Result {
+        Err(crate::Error::NotSupported(crate::NSOpType::Text))
+    }
+
+    /// Draw glyphs that have already been shaped and positioned, such as the output of a shaping engine. Each
+    /// glyph is drawn with the origin of its baseline at the given point, in the order they are given.
+    #[inline]
+    fn draw_glyphs(&mut self, _glyphs: &[(GlyphId, Point<f32>)], _font: &Font) -> crate::Result {
+        Err(crate::Error::NotSupported(crate::NSOpType::Text))
+    }
+
+    /// Draw a string of text along a path, rotating each character to follow the direction of the path.
+    /// Characters are centered on the path, and any that would run past its end are left out. By default, this
+    /// measures and draws each character separately using `measure_text` and `draw_text`, so surfaces that
+    /// can't draw text return `NotSupported` here as well.
+    #[inline]
+    fn draw_text_on_path(&mut self, text: &str, path: PathSlice<'_>, font: &Font) -> crate::Result {
+        let mut buf = [0; 4];
+        let sizes = text
+            .chars()
+            .map(|c| self.measure_text(c.encode_utf8(&mut buf), font))
+            .collect::<crate::Result<Vec<_>>>()?;
+        let transforms = text_on_path(sizes.iter().copied(), path, self.tolerance());
+
+        text.chars()
+            .zip(sizes)
+            .zip(transforms)
+            .try_for_each(|((c, size), transform)| {
+                self.save()?;
+                let res = self.transform(transform).and_then(|()| {
+                    self.draw_text(
+                        c.encode_utf8(&mut buf),
+                        -size.width / 2.0,
+                        -size.height / 2.0,
+                        font,
+                    )
+                });
+                self.restore()?;
+                res
+            })
+    }
+
+    /// Read back the pixels that have been drawn in a region of the surface, converting each one into its
+    /// perceived brightness. The region is rounded out to whole pixels and ignores the current transform, and
+    /// the pixels are returned row by row.
+    #[inline]
+    fn read_intensity(&self, _rect: Rect<f32>) -> crate::Result<Vec<Intensity>> {
+        Err(crate::Error::NotSupported(crate::NSOpType::Image))
+    }
+
+    /// Create a new `Image`. This `Image` can be used with other `Surface`s of the same type as this one.
+    fn create_image(
+        &mut self,
+        image_bytes: &[u8],
+        width: u32,
+        height: u32,
+        image_format: ImageFormat,
+    ) -> crate::Result<Image>;
+    /// Deallocate the server-side memory for an `Image`.
+    fn destroy_image(&mut self, image: Image) -> crate::Result;
+
+    /// Draw a single point, using the stroke color.
+    #[inline]
+    fn draw_point(&mut self, x: f32, y: f32) -> crate::Result {
+        self.draw_points(&[Point::new(x, y)])
+    }
+    /// Draw several points. By default, this draws a line one unit long starting at each point.
+    #[inline]
+    fn draw_points(&mut self, points: &[Point<f32>]) -> crate::Result {
+        let lines: Vec<LineSegment<f32>> = points
+            .iter()
+            .copied()
+            .map(|point| LineSegment {
+                from: point,
+                to: point + Vector::new(1.0, 0.0),
+            })
+            .collect();
+        self.draw_lines(&lines)
+    }
+
+    /// Draw a single line.
+    fn draw_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> crate::Result;
+    /// Draw a single line with a dash pattern, as if by `set_dash`, without changing the dash pattern that
+    /// other lines are drawn with. Moving `offset` over time makes the dashes march along the line. By default,
+    /// the dashes are drawn as separate solid lines.
+    #[inline]
+    fn draw_dashed_line(
+        &mut self,
+        from: Point<f32>,
+        to: Point<f32>,
+        pattern: &[f32],
+        offset: f32,
+    ) -> crate::Result {
+        let lines = dash_lines(iter::once(LineSegment { from, to }), pattern, offset);
+        self.save()?;
+        let res = self
+            .set_dash(&[], 0.0)
+            .and_then(|()| self.draw_lines(&lines));
+        self.restore()?;
+        res
+    }
+    /// Draw several lines. In many cases this is more efficient than drawing a single line in a loop.
+    #[inline]
+    fn draw_lines(&mut self, lines: &[LineSegment<f32>]) -> crate::Result {
+        lines.iter().copied().try_for_each(
+            |LineSegment {
+                 from: Point { x: x1, y: y1, .. },
+                 to: Point { x: x2, y: y2, .. },
+             }| self.draw_line(x1, y1, x2, y2),
+        )
+    }
+
+    /// Draw a series of connected lines through the given points.
+    #[inline]
+    fn draw_polyline(&mut self, pts: &[Point<f32>]) -> crate::Result {
+        let lines: Vec<LineSegment<f32>> = pts
+            .windows(2)
+            .map(|w| LineSegment {
+                from: w[0],
+                to: w[1],
+            })
+            .collect();
+        self.draw_lines(&lines)
+    }
+
+    /// Draw a path.
+    #[inline]
+    fn draw_path(&mut self, path: PathSlice<'_>) -> crate::Result {
+        let lines: Vec<LineSegment<f32>> = path_to_lines(path.iter(), self.tolerance()).collect();
+        self.draw_lines(&lines)
+    }
+    /// Draw an owned path.
+    #[inline]
+    fn draw_path_owned(&mut self, path: Path) -> crate::Result {
+        let lines: Vec<LineSegment<f32>> = path_to_lines(path.iter(), self.tolerance()).collect();
+        self.draw_lines(&lines)
+    }
+    /// Draw several paths.
+    #[inline]
+    fn draw_paths(&mut self, paths: PathBufferSlice<'_>) -> crate::Result {
+        let tolerance = self.tolerance();
+        let lines: Vec<LineSegment<f32>> = paths
+            .indices()
+            .flat_map(|index| path_to_lines(paths.get(index).iter(), tolerance))
+            .collect();
+        self.draw_lines(&lines)
+    }
+    /// Draw several paths, if we own the paths.
+    #[inline]
+    fn draw_paths_owned(&mut self, paths: PathBuffer) -> crate::Result {
+        let tolerance = self.tolerance();
+        let lines: Vec<LineSegment<f32>> = paths
+            .indices()
+            .flat_map(|index| path_to_lines(paths.get(index).iter(), tolerance))
+            .collect();
+        self.draw_lines(&lines)
+    }
+
+    /// Draw a bezier curve.
+    #[inline]
+    fn draw_bezier_curve(&mut self, curve: CubicBezierSegment<f32>) -> crate::Result {
+        // Although we could implement this in terms of draw_lines(), most Surfaces that are actually used have
+        // optimizations to make drawing paths faster. For the ones that don't, the allocation incurred here is
+        // likely not much of a big deal since they use IPC, and the overhead here isn't comparable to the
+        // overhead of that.
+        let path = path_from_curve(curve);
+        self.draw_path_owned(path)
+    }
+    /// Draw a quadratic bezier curve. By default, this is converted to a cubic curve.
+    #[inline]
+    fn draw_quadratic_bezier(
+        &mut self,
+        from: Point<f32>,
+        ctrl: Point<f32>,
+        to: Point<f32>,
+    ) -> crate::Result {
+        self.draw_bezier_curve(QuadraticBezierSegment { from, ctrl, to }.to_cubic())
+    }
+    /// Draw a cubic bezier curve.
+    #[inline]
+    fn draw_cubic_bezier(
+        &mut self,
+        from: Point<f32>,
+        ctrl1: Point<f32>,
+        ctrl2: Point<f32>,
+        to: Point<f32>,
+    ) -> crate::Result {
+        self.draw_bezier_curve(CubicBezierSegment {
+            from,
+            ctrl1,
+            ctrl2,
+            to,
+        })
+    }
+    /// Draw several bezier curves. In many cases this is more efficient than drawing a single curve in a loop.
+    #[inline]
+    fn draw_bezier_curves(&mut self, curves: &[CubicBezierSegment<f32>]) -> crate::Result {
+        let mut paths = PathBuffer::new();
+        curves.iter().copied().for_each(|curve| {
+            let mut builder = paths.builder();
+            builder.begin(curve.from);
+            builder.cubic_bezier_to(curve.ctrl1, curve.ctrl2, curve.to);
+            builder.end(false);
+            builder.build();
+        });
+        self.draw_paths_owned(paths)
+    }
+
+    /// Draw a rectangle.
+    #[inline]
+    fn draw_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> crate::Result {
+        let mut builder = Path::builder();
+        builder.begin(Point::new(x, y));
+        builder.line_to(Point::new(x + width, y));
+        builder.line_to(Point::new(x + width, y + height));
+        builder.line_to(Point::new(x, y + height));
+        builder.close();
+
+        self.draw_path_owned(builder.build())
+    }
+
+    /// Draw several rectangles. In many cases this is more efficient than drawing a single rectangle in a loop.
+    #[inline]
+    fn draw_rectangles(&mut self, rects: &[Rect<f32>]) -> crate::Result {
+        let mut paths = PathBuffer::new();
+        rects.iter().copied().for_each(
+            |Rect {
+                 origin: Point { x, y, .. },
+                 size: Size { width, height, .. },
+             }| {
+                let mut builder = paths.builder();
+                builder.begin(Point::new(x, y));
+                builder.line_to(Point::new(x + width, y));
+                builder.line_to(Point::new(x + width, y + height));
+                builder.line_to(Point::new(x, y + height));
+                builder.close();
+
+                builder.build();
+            },
+        );
+
+        self.draw_paths_owned(paths)
+    }
+
+    /// Draw a rectangle with rounded corners. The radii of the corners are clamped to half of the width and
+    /// height of the rectangle.
+    #[inline]
+    fn draw_round_rectangle(&mut self, rect: Rect<f32>, rx: f32, ry: f32) -> crate::Result {
+        let path = path_from_round_rect(rect, rx, ry, self.tolerance());
+        self.draw_path_owned(path)
+    }
+
+    /// Draw an arc. Angles are measured with the Y axis pointing down, so positive sweep angles travel
+    /// clockwise on the screen and negative ones travel counter-clockwise.
+    #[inline]
+    fn draw_arc(
+        &mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+        start_angle: Angle<f32>,
+        sweep_angle: Angle<f32>,
+    ) -> crate::Result {
+        match path_from_arc(
+            Arc {
+                center: Point::new(xcenter, ycenter),
+                radii: Vector::new(xradius, yradius),
+                start_angle,
+                sweep_angle,
+                x_rotation: Angle { radians: 0.0 },
+            },
+            self.tolerance(),
+        ) {
+            Some(arc) => self.draw_path_owned(arc),
+            None => Ok(()),
+        }
+    }
+
+    /// Draw several arcs.
+    #[inline]
+    fn draw_arcs(&mut self, arcs: &[Arc<f32>]) -> crate::Result {
+        let tolerance = self.tolerance();
+        let mut buffer = PathBuffer::new();
+        arcs.iter()
+            .copied()
+            .filter_map(|arc| path_from_arc(arc, tolerance))
+            .for_each(|path| {
+                path.iter()
+                    .fold(buffer.builder(), |mut builder, event| {
+                        builder.path_event(event);
+                        builder
+                    })
+                    .build();
+            });
+        self.draw_paths_owned(buffer)
+    }
+
+    /// Draw an ellipse.
+    #[inline]
+    fn draw_ellipse(
+        &mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+    ) -> crate::Result {
+        self.draw_arc(
+            xcenter,
+            ycenter,
+            xradius,
+            yradius,
+            Angle { radians: 0.0 },
+            Angle {
+                radians: std::f32::consts::PI * 2.0,
+            },
+        )
+    }
+
+    /// Draw a circle.
+    #[inline]
+    fn draw_circle(&mut self, center: Point<f32>, radius: f32) -> crate::Result {
+        self.draw_ellipse(center.x, center.y, radius, radius)
+    }
+
+    /// Draw several ellipses.
+    #[inline]
+    fn draw_ellipses(&mut self, rects: &[Ellipse]) -> crate::Result {
+        let arcs: Vec<Arc<f32>> = rects
+            .iter()
+            .copied()
+            .map(|Ellipse { center, radii }| Arc {
+                center,
+                radii,
+                start_angle: Angle { radians: 0.0 },
+                sweep_angle: Angle {
+                    radians: 2.0 * std::f32::consts::PI,
+                },
+                x_rotation: Angle { radians: 0.0 },
+            })
+            .collect();
+        self.draw_arcs(&arcs)
+    }
+
+    /// Fill in a polygon defined by the given set of points.
+    fn fill_polygon(&mut self, points: &[Point<f32>]) -> crate::Result;
+
+    /// Fill in several polygons.
+    #[inline]
+    fn fill_polygons(&mut self, polys: &[&[Point<f32>]]) -> crate::Result {
+        polys
+            .iter()
+            .try_for_each(|points| self.fill_polygon(points))
+    }
+
+    /// Fill in an owned path.
+    #[inline]
+    fn fill_path_owned(&mut self, path: Path) -> crate::Result {
+        let polys = path_to_polygons(path.iter(), self.tolerance());
+        let polys: Vec<&[Point<f32>]> = polys.iter().map(Vec::as_slice).collect();
+        self.fill_polygons(&polys)
+    }
+
+    /// Fill in a path slice.
+    #[inline]
+    fn fill_path(&mut self, path: PathSlice<'_>) -> crate::Result {
+        let polys = path_to_polygons(path.iter(), self.tolerance());
+        let polys: Vec<&[Point<f32>]> = polys.iter().map(Vec::as_slice).collect();
+        self.fill_polygons(&polys)
+    }
+
+    /// Fill in a series of paths in a path buffer.
+    #[inline]
+    fn fill_paths_owned(&mut self, paths: PathBuffer) -> crate::Result {
+        paths
+            .indices()
+            .try_for_each(|index| self.fill_path(paths.get(index)))
+    }
+
+    /// Fill in a series of paths in a path buffer.
+    #[inline]
+    fn fill_paths(&mut self, paths: PathBufferSlice<'_>) -> crate::Result {
+        paths
+            .indices()
+            .try_for_each(|index| self.fill_path(paths.get(index)))
+    }
+
+    /// Fill in a rectangle.
+    #[inline]
+    fn fill_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> crate::Result {
+        let x1 = x;
+        let y1 = y;
+        let x2 = x + width;
+        let y2 = y + height;
+
+        self.fill_polygon(&[
+            Point::new(x1, y1),
+            Point::new(x2, y1),
+            Point::new(x2, y2),
+            Point::new(x1, y2),
+        ])
+    }
+
+    /// Fill in several rectangles.
+    #[inline]
+    fn fill_rectangles(&mut self, rects: &[Rect<f32>]) -> crate::Result {
+        rects.iter().copied().try_for_each(
+            |Rect {
+                 origin: Point { x, y, .. },
+                 size: Size { width, height, .. },
+                 ..
+             }| self.fill_rectangle(x, y, width, height),
+        )
+    }
+
+    /// Fill in a rectangle with rounded corners. The radii of the corners are clamped to half of the width
+    /// and height of the rectangle.
+    #[inline]
+    fn fill_round_rectangle(&mut self, rect: Rect<f32>, rx: f32, ry: f32) -> crate::Result {
+        let path = path_from_round_rect(rect, rx, ry, self.tolerance());
+        self.fill_path_owned(path)
+    }
+
+    /// Fill in the chord of an arc, which is the area between the arc and the line connecting its ends.
+    #[inline]
+    fn fill_arc(
+        &mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+        start_angle: Angle<f32>,
+        sweep_angle: Angle<f32>,
+    ) -> crate::Result {
+        let arc = Arc {
+            center: Point::new(xcenter, ycenter),
+            radii: Vector::new(xradius, yradius),
+            start_angle,
+            sweep_angle,
+            x_rotation: Angle { radians: 0.0 },
+        };
+        match path_from_arc_closed(arc, self.tolerance()) {
+            Some(path) => self.fill_path(path.as_slice()),
+            None => Ok(()),
+        }
+    }
+
+    /// Fill in a pie slice, which is the area between an arc and lines from its ends to its center.
+    #[inline]
+    fn fill_pie(
+        &mut self,
+        center: Point<f32>,
+        radii: Vector<f32>,
+        start_angle: Angle<f32>,
+        sweep_angle: Angle<f32>,
+    ) -> crate::Result {
+        let arc = Arc {
+            center,
+            radii,
+            start_angle,
+            sweep_angle,
+            x_rotation: Angle { radians: 0.0 },
+        };
+        let points: Vec<Point<f32>> = iter::once(center)
+            .chain(flatten_arc(&arc, self.tolerance()))
+            .collect();
+        self.fill_polygon(&points)
+    }
+
+    /// Fill in several arcs.
+    #[inline]
+    fn fill_arcs(&mut self, arcs: &[Arc<f32>]) -> crate::Result {
+        let tolerance = self.tolerance();
+        let mut buffer = PathBuffer::new();
+        arcs.iter()
+            .copied()
+            .filter_map(|arc| path_from_arc_closed(arc, tolerance))
+            .for_each(|path| {
+                path.iter()
+                    .fold(buffer.builder(), |mut builder, event| {
+                        builder.path_event(event);
+                        builder
+                    })
+                    .build();
+            });
+        self.fill_paths_owned(buffer)
+    }
+
+    /// Fill in an ellipse.
+    #[inline]
+    fn fill_ellipse(
+        &mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+    ) -> crate::Result {
+        self.fill_arc(
+            xcenter,
+            ycenter,
+            xradius,
+            yradius,
+            Angle { radians: 0.0 },
+            Angle {
+                radians: 2.0 * std::f32::consts::PI,
+            },
+        )
+    }
+
+    /// Fill in a circle.
+    #[inline]
+    fn fill_circle(&mut self, center: Point<f32>, radius: f32) -> crate::Result {
+        self.fill_ellipse(center.x, center.y, radius, radius)
+    }
+
+    /// Fill in several ellipses.
+    #[inline]
+    fn fill_ellipses(&mut self, rects: &[Ellipse]) -> crate::Result {
+        let arcs: Vec<Arc<f32>> = rects
+            .iter()
+            .copied()
+            .map(|Ellipse { center, radii }| Arc {
+                center,
+                radii,
+                start_angle: Angle { radians: 0.0 },
+                sweep_angle: Angle {
+                    radians: 2.0 * std::f32::consts::PI,
+                },
+                x_rotation: Angle { radians: 0.0 },
+            })
+            .collect();
+        self.fill_arcs(&arcs)
+    }
+
+    /// Fill in a rectangle and then draw its outline. The fill, stroke and line width are only used for this
+    /// rectangle; the surface's previous state is restored afterwards.
+    #[inline]
+    fn paint_rectangle(
+        &mut self,
+        rect: Rect<f32>,
+        fill: FillRule,
+        stroke: Color,
+        width: usize,
+    ) -> crate::Result {
+        let Rect { origin, size } = rect;
+        paint_shape(
+            self,
+            fill,
+            stroke,
+            width,
+            |s| s.fill_rectangle(origin.x, origin.y, size.width, size.height),
+            |s| s.draw_rectangle(origin.x, origin.y, size.width, size.height),
+        )
+    }
+
+    /// Fill in a rectangle with rounded corners and then draw its outline, restoring the surface's previous
+    /// state afterwards.
+    #[inline]
+    fn paint_round_rectangle(
+        &mut self,
+        rect: Rect<f32>,
+        rx: f32,
+        ry: f32,
+        fill: FillRule,
+        stroke: Color,
+        width: usize,
+    ) -> crate::Result {
+        let path = path_from_round_rect(rect, rx, ry, self.tolerance());
+        self.paint_path(path.as_slice(), fill, stroke, width)
+    }
+
+    /// Fill in a polygon and then draw its outline, restoring the surface's previous state afterwards.
+    #[inline]
+    fn paint_polygon(
+        &mut self,
+        points: &[Point<f32>],
+        fill: FillRule,
+        stroke: Color,
+        width: usize,
+    ) -> crate::Result {
+        paint_shape(
+            self,
+            fill,
+            stroke,
+            width,
+            |s| s.fill_polygon(points),
+            |s| {
+                // close the outline by returning to the first point
+                let outline: Vec<Point<f32>> =
+                    points.iter().chain(points.first()).copied().collect();
+                s.draw_polyline(&outline)
+            },
+        )
+    }
+
+    /// Fill in an ellipse and then draw its outline, restoring the surface's previous state afterwards.
+    #[inline]
+    fn paint_ellipse(
+        &mut self,
+        ellipse: Ellipse,
+        fill: FillRule,
+        stroke: Color,
+        width: usize,
+    ) -> crate::Result {
+        let Ellipse { center, radii } = ellipse;
+        paint_shape(
+            self,
+            fill,
+            stroke,
+            width,
+            |s| s.fill_ellipse(center.x, center.y, radii.x, radii.y),
+            |s| s.draw_ellipse(center.x, center.y, radii.x, radii.y),
+        )
+    }
+
+    /// Fill in a path and then draw its outline, restoring the surface's previous state afterwards.
+    #[inline]
+    fn paint_path(
+        &mut self,
+        path: PathSlice<'_>,
+        fill: FillRule,
+        stroke: Color,
+        width: usize,
+    ) -> crate::Result {
+        paint_shape(
+            self,
+            fill,
+            stroke,
+            width,
+            |s| s.fill_path(path),
+            |s| s.draw_path(path),
+        )
+    }
+
+    /// Copy the contents of an image to this surface.
+    fn copy_image(
+        &mut self,
+        src: Image,
+        src_x: i32,
+        src_y: i32,
+        dst_x: i32,
+        dst_y: i32,
+        width: u32,
+        height: u32,
+    ) -> crate::Result;
+
+    /// Draw an image, scaling it to fill the destination rectangle.
+    #[inline]
+    fn draw_image(&mut self, _image: &Image, _dst: Rect<f32>) -> crate::Result {
+        Err(crate::Error::NotSupported(crate::NSOpType::Image))
+    }
+
+    /// Draw the `src` area of an image, scaling it to fill the destination rectangle. By default, this can only
+    /// copy the area without scaling it.
+    #[inline]
+    fn draw_image_region(
+        &mut self,
+        image: &Image,
+        src: Rect<f32>,
+        dst: Rect<f32>,
+    ) -> crate::Result {
+        let (src, dst) = (src.round(), dst.round());
+        if src.size != dst.size {
+            return Err(crate::Error::NotSupported(crate::NSOpType::Image));
+        }
+
+        self.copy_image(
+            *image,
+            src.origin.x as i32,
+            src.origin.y as i32,
+            dst.origin.x as i32,
+            dst.origin.y as i32,
+            src.size.width as u32,
+            src.size.height as u32,
+        )
+    }
+
+    /// Draw an image, scaling it to fill the destination rectangle, with its alpha scaled by the brightness of
+    /// `mask`. The mask must be the same size as the image.
+    #[inline]
+    fn draw_image_masked(
+        &mut self,
+        _image: &Image,
+        _mask: &Image,
+        _dst: Rect<f32>,
+    ) -> crate::Result {
+        Err(crate::Error::NotSupported(crate::NSOpType::Image))
+    }
+
+    /// Fill in a rectangle with a linear gradient. By default, this renders the gradient into an image and
+    /// copies it onto the surface, which ignores the current transform.
+    #[inline]
+    fn fill_linear_gradient(
+        &mut self,
+        gradient: &LinearGradient,
+        rect: Rect<f32>,
+    ) -> crate::Result {
+        let rect = rect.round_out();
+        let pixels = gradient.rasterize(rect);
+        copy_pixels(self, &pixels, rect)
+    }
+
+    /// Fill in a rectangle with a conic gradient. By default, this renders the gradient into an image and
+    /// copies it onto the surface, which ignores the current transform.
+    #[inline]
+    fn fill_conic_gradient(&mut self, gradient: &ConicGradient, rect: Rect<f32>) -> crate::Result {
+        let rect = rect.round_out();
+        let pixels = gradient.rasterize(rect);
+        copy_pixels(self, &pixels, rect)
+    }
+
+    /// Fill in a rectangle with a radial gradient. By default, this renders the gradient into an image and
+    /// copies it onto the surface, which ignores the current transform.
+    #[inline]
+    fn fill_radial_gradient(
+        &mut self,
+        gradient: &RadialGradient,
+        rect: Rect<f32>,
+    ) -> crate::Result {
+        let rect = rect.round_out();
+        let pixels = gradient.rasterize(rect);
+        copy_pixels(self, &pixels, rect)
+    }
+
+    /// Copy several images to this surface.
+    fn copy_images(&mut self, images: &[ImageCopySpecs]) -> crate::Result {
+        images.iter().copied().try_for_each(
+            |ImageCopySpecs {
+                 image,
+                 src_x,
+                 src_y,
+                 dst_x,
+                 dst_y,
+                 width,
+                 height,
+             }| self.copy_image(image, src_x, src_y, dst_x, dst_y, width, height),
+        )
+    }
+}
+
+/// Copy a buffer of RGBA pixels covering `rect` onto a surface, using a temporary image.
+#[inline]
+pub(crate) fn copy_pixels<S: Surface + ?Sized>(
+    surface: &mut S,
+    pixels: &[u8],
+    rect: Rect<f32>,
+) -> crate::Result {
+    let (width, height) = (rect.size.width as u32, rect.size.height as u32);
+    if width == 0 || height == 0 {
+        return Ok(());
+    }
+
+    let image = surface.create_image(pixels, width, height, ImageFormat::Rgba)?;
+    let res = surface.copy_image(
+        image,
+        0,
+        0,
+        rect.origin.x as i32,
+        rect.origin.y as i32,
+        width,
+        height,
+    );
+    surface.destroy_image(image)?;
+    res
+}
+
+/// Lay out characters of the given sizes along a path, one after the other. Each transform moves the origin to
+/// the center of a character and rotates it to follow the path. Characters that would run past the end of the
+/// path are left out.
+#[inline]
+pub(crate) fn text_on_path(
+    sizes: impl IntoIterator<Item = Size<f32>>,
+    path: PathSlice<'_>,
+    tolerance: f32,
+) -> Vec<Transform> {
+    let mut lines = path_to_lines(path.iter(), tolerance).filter(|l| l.length() > 0.0);
+    let mut current = lines.next();
+    // the distance along the path that the current line starts at, and that the next character starts at
+    let mut start = 0.0;
+    let mut distance = 0.0;
+
+    let mut transforms = vec![];
+    for size in sizes {
+        let middle = distance + size.width / 2.0;
+        distance += size.width;
+
+        // find the line that the middle of the character lies on
+        while let Some(line) = current {
+            if middle <= start + line.length() {
+                break;
+            }
+            start += line.length();
+            current = lines.next();
+        }
+        let line = match current {
+            Some(line) => line,
+            None => break,
+        };
+
+        let center = line.sample((middle - start) / line.length());
+        let angle = line.to_vector().angle_from_x_axis();
+        let transform =
+            Transform::rotation(angle).then(&Transform::translation(center.x, center.y));
+        transforms.push(transform);
+    }
+    transforms
+}
+
+/// Read back the brightness of the pixels in a region of a surface, given a way to get the color of each pixel.
+#[inline]
+pub(crate) fn read_intensity_with(
+    rect: Rect<f32>,
+    size: Size<f32>,
+    pixel: impl Fn(u32, u32) -> Option<Color>,
+) -> crate::Result<Vec<Intensity>> {
+    let rect = rect.round_out();
+    crate::image::check_image_region(rect, size)?;
+
+    let (x, y) = (rect.origin.x as u32, rect.origin.y as u32);
+    let (width, height) = (rect.size.width as u32, rect.size.height as u32);
+    (y..y + height)
+        .flat_map(|py| (x..x + width).map(move |px| (px, py)))
+        .map(|(px, py)| {
+            pixel(px, py)
+                .map(Intensity::from_color)
+                .ok_or(crate::Error::RegionOutOfBounds)
+        })
+        .collect()
+}
+
+/// Fill in a shape and then stroke it, restoring the surface's state afterwards even if drawing fails.
+#[inline]
+fn paint_shape<S: Surface + ?Sized>(
+    surface: &mut S,
+    fill: FillRule,
+    stroke: Color,
+    width: usize,
+    fill_shape: impl FnOnce(&mut S) -> crate::Result,
+    stroke_shape: impl FnOnce(&mut S) -> crate::Result,
+) -> crate::Result {
+    surface.save()?;
+    let res = (|| {
+        surface.set_fill(fill)?;
+        fill_shape(surface)?;
+        surface.set_stroke(stroke)?;
+        surface.set_line_width(width)?;
+        stroke_shape(surface)
+    })();
+    let restored = surface.restore();
+    res.and(restored)
+}
+
+/// A surface which drawing commands can be applied to, in a non-blocking way.
+///
+/// This mirrors the `Surface` trait, but every operation returns a future instead of blocking.
+#[cfg(feature = "async")]
+pub trait AsyncSurface: Send {
+    /// The set of features this surface supports.
+    fn features(&self) -> SurfaceFeatures;
+    /// Does this surface support the given operation?
+    #[inline]
+    fn supports(&self, op: NSOpType) -> bool {
+        self.features().supports(op)
+    }
+    /// Set the color used to draw lines.
+    fn set_stroke_async<'future>(&'future mut self, color: Color) -> GenericResult<'future>;
+    /// Set the rule used to fill shapes.
+    fn set_fill_async<'future>(&'future mut self, rule: FillRule) -> GenericResult<'future>;
+    /// Set the width used to draw lines.
+    fn set_line_width_async<'future>(&'future mut self, width: usize) -> GenericResult<'future>;
+
+    /// Flush all commands passed to this surface to its target.
+    fn flush_async<'future>(&'future mut self) -> GenericResult<'future>;
+
+    /// Create a new `Image`. This `Image` can be used with other `AsyncSurface`s of the same type as this one.
+    fn create_image_async<'future, 'a, 'b>(
+        &'a mut self,
+        image_bytes: &'b [u8],
+        width: u32,
+        height: u32,
+        image_format: ImageFormat,
+    ) -> GenericResult<'future, Image>
+    where
+        'a: 'future,
+        'b: 'future;
+    /// Deallocate the server-side memory for an `Image`.
+    fn destroy_image_async<'future>(&'future mut self, image: Image) -> GenericResult<'future>;
+
+    /// Draw a single line.
+    fn draw_line_async<'future>(
+        &'future mut self,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+    ) -> GenericResult<'future>;
+    /// Draw several lines. In many cases this is more efficient than drawing a single line in a loop.
+    #[inline]
+    fn draw_lines_async<'future, 'a, 'b>(
+        &'a mut self,
+        lines: &'b [LineSegment<f32>],
+    ) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        Box::pin(async move {
+            for line in lines {
+                self.draw_line_async(line.from.x, line.from.y, line.to.x, line.to.y)
+                    .await?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Draw a path.
+    #[inline]
+    fn draw_path_async<'future, 'a, 'b>(&'a mut self, path: PathSlice<'b>) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        let lines: Vec<LineSegment<f32>> = path_to_lines(path.iter(), DEFAULT_TOLERANCE).collect();
+        Box::pin(async move { self.draw_lines_async(&lines).await })
+    }
+    /// Draw an owned path.
+    #[inline]
+    fn draw_path_owned_async<'future>(&'future mut self, path: Path) -> GenericResult<'future> {
+        let lines: Vec<LineSegment<f32>> = path_to_lines(path.iter(), DEFAULT_TOLERANCE).collect();
+        Box::pin(async move { self.draw_lines_async(&lines).await })
+    }
+    /// Draw several paths, if we own the paths.
+    #[inline]
+    fn draw_paths_owned_async<'future>(
+        &'future mut self,
+        paths: PathBuffer,
+    ) -> GenericResult<'future> {
+        let lines: Vec<LineSegment<f32>> = paths
+            .indices()
+            .flat_map(|index| path_to_lines(paths.get(index).iter(), DEFAULT_TOLERANCE))
+            .collect();
+        Box::pin(async move { self.draw_lines_async(&lines).await })
+    }
+
+    /// Draw a bezier curve.
+    #[inline]
+    fn draw_bezier_curve_async<'future>(
+        &'future mut self,
+        curve: CubicBezierSegment<f32>,
+    ) -> GenericResult<'future> {
+        let path = path_from_curve(curve);
+        self.draw_path_owned_async(path)
+    }
+    /// Draw several bezier curves. In many cases this is more efficient than drawing a single curve in a loop.
+    #[inline]
+    fn draw_bezier_curves_async<'future>(
+        &'future mut self,
+        curves: &[CubicBezierSegment<f32>],
+    ) -> GenericResult<'future> {
+        let mut paths = PathBuffer::new();
+        curves.iter().copied().for_each(|curve| {
+            let mut builder = paths.builder();
+            builder.begin(curve.from);
+            builder.cubic_bezier_to(curve.ctrl1, curve.ctrl2, curve.to);
+            builder.end(false);
+            builder.build();
+        });
+        self.draw_paths_owned_async(paths)
+    }
+
+    /// Draw a rectangle.
+    #[inline]
+    fn draw_rectangle_async<'future>(
+        &'future mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    ) -> GenericResult<'future> {
+        let mut builder = Path::builder();
+        builder.begin(Point::new(x, y));
+        builder.line_to(Point::new(x + width, y));
+        builder.line_to(Point::new(x + width, y + height));
+        builder.line_to(Point::new(x, y + height));
+        builder.close();
+
+        self.draw_path_owned_async(builder.build())
+    }
+    /// Draw several rectangles. In many cases this is more efficient than drawing a single rectangle in a loop.
+    #[inline]
+    fn draw_rectangles_async<'future, 'a, 'b>(
+        &'a mut self,
+        rects: &'b [Rect<f32>],
+    ) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        Box::pin(async move {
+            for rect in rects {
+                self.draw_rectangle_async(
+                    rect.origin.x,
+                    rect.origin.y,
+                    rect.size.width,
+                    rect.size.height,
+                )
+                .await?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Draw an arc.
+    #[inline]
+    fn draw_arc_async<'future>(
+        &'future mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+        start_angle: Angle<f32>,
+        sweep_angle: Angle<f32>,
+    ) -> GenericResult<'future> {
+        match path_from_arc(
+            Arc {
+                center: Point::new(xcenter, ycenter),
+                radii: Vector::new(xradius, yradius),
+                start_angle,
+                sweep_angle,
+                x_rotation: Angle { radians: 0.0 },
+            },
+            DEFAULT_TOLERANCE,
+        ) {
+            Some(arc) => self.draw_path_owned_async(arc),
+            None => Box::pin(future::ready(Ok(()))),
+        }
+    }
+    /// Draw several arcs.
+    #[inline]
+    fn draw_arcs_async<'future, 'a, 'b>(
+        &'a mut self,
+        arcs: &'b [Arc<f32>],
+    ) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        let mut buffer = PathBuffer::new();
+        arcs.iter()
+            .copied()
+            .filter_map(|arc| path_from_arc(arc, DEFAULT_TOLERANCE))
+            .for_each(|path| {
+                path.iter()
+                    .fold(buffer.builder(), |mut builder, event| {
+                        builder.path_event(event);
+                        builder
+                    })
+                    .build();
+            });
+        self.draw_paths_owned_async(buffer)
+    }
+
+    /// Draw an ellipse.
+    #[inline]
+    fn draw_ellipse_async<'future>(
+        &'future mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+    ) -> GenericResult<'future> {
+        self.draw_arc_async(
+            xcenter,
+            ycenter,
+            xradius,
+            yradius,
+            Angle { radians: 0.0 },
+            Angle {
+                radians: std::f32::consts::PI * 2.0,
+            },
+        )
+    }
+    /// Draw a circle.
+    #[inline]
+    fn draw_circle_async<'future>(
+        &'future mut self,
+        center: Point<f32>,
+        radius: f32,
+    ) -> GenericResult<'future> {
+        self.draw_ellipse_async(center.x, center.y, radius, radius)
+    }
+    /// Draw several ellipses.
+    #[inline]
+    fn draw_ellipses_async<'future, 'a, 'b>(
+        &'a mut self,
+        rects: &'b [Ellipse],
+    ) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        let arcs: Vec<Arc<f32>> = rects
+            .iter()
+            .copied()
+            .map(|Ellipse { center, radii }| Arc {
+                center,
+                radii,
+                start_angle: Angle { radians: 0.0 },
+                sweep_angle: Angle {
+                    radians: 2.0 * std::f32::consts::PI,
+                },
+                x_rotation: Angle { radians: 0.0 },
+            })
+            .collect();
+        Box::pin(async move { self.draw_arcs_async(&arcs).await })
+    }
+
+    /// Fill in a polygon defined by the given set of points.
+    fn fill_polygon_async<'future, 'a, 'b>(
+        &'a mut self,
+        points: &'b [Point<f32>],
+    ) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future;
+
+    /// Fill in several polygons.
+    #[inline]
+    fn fill_polygons_async<'future, 'a, 'b, 'c>(
+        &'a mut self,
+        polys: &'b [&'c [Point<f32>]],
+    ) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+        'c: 'future,
+    {
+        Box::pin(async move {
+            for points in polys {
+                self.fill_polygon_async(points).await?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Fill in a path slice.
+    #[inline]
+    fn fill_path_async<'future, 'a, 'b>(&'a mut self, path: PathSlice<'b>) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        let polys = path_to_polygons(path.iter(), DEFAULT_TOLERANCE);
+        Box::pin(async move {
+            let polys: Vec<&[Point<f32>]> = polys.iter().map(Vec::as_slice).collect();
+            self.fill_polygons_async(&polys).await
+        })
+    }
+    /// Fill in an owned path.
+    #[inline]
+    fn fill_path_owned_async<'future>(&'future mut self, path: Path) -> GenericResult<'future> {
+        let polys = path_to_polygons(path.iter(), DEFAULT_TOLERANCE);
+        Box::pin(async move {
+            let polys: Vec<&[Point<f32>]> = polys.iter().map(Vec::as_slice).collect();
+            self.fill_polygons_async(&polys).await
+        })
+    }
+
+    /// Fill in a rectangle.
+    #[inline]
+    fn fill_rectangle_async<'future>(
+        &'future mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    ) -> GenericResult<'future> {
+        let x1 = x;
+        let y1 = y;
+        let x2 = x + width;
+        let y2 = y + height;
+
+        Box::pin(async move {
+            self.fill_polygon_async(&[
+                Point::new(x1, y1),
+                Point::new(x2, y1),
+                Point::new(x2, y2),
+                Point::new(x1, y2),
+            ])
+            .await
+        })
+    }
+    /// Fill in several rectangles.
+    #[inline]
+    fn fill_rectangles_async<'future, 'a, 'b>(
+        &'a mut self,
+        rects: &'b [Rect<f32>],
+    ) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        Box::pin(async move {
+            for rect in rects {
+                self.fill_rectangle_async(
+                    rect.origin.x,
+                    rect.origin.y,
+                    rect.size.width,
+                    rect.size.height,
+                )
+                .await?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Fill in the chord of an arc, which is the area between the arc and the line connecting its ends.
+    #[inline]
+    fn fill_arc_async<'future>(
+        &'future mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+        start_angle: Angle<f32>,
+        sweep_angle: Angle<f32>,
+    ) -> GenericResult<'future> {
+        let arc = Arc {
+            center: Point::new(xcenter, ycenter),
+            radii: Vector::new(xradius, yradius),
+            start_angle,
+            sweep_angle,
+            x_rotation: Angle { radians: 0.0 },
+        };
+        match path_from_arc_closed(arc, DEFAULT_TOLERANCE) {
+            Some(path) => self.fill_path_owned_async(path),
+            None => Box::pin(future::ready(Ok(()))),
+        }
+    }
+    /// Fill in several arcs.
+    #[inline]
+    fn fill_arcs_async<'future, 'a, 'b>(
+        &'a mut self,
+        arcs: &'b [Arc<f32>],
+    ) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        Box::pin(async move {
+            for arc in arcs {
+                self.fill_arc_async(
+                    arc.center.x,
+                    arc.center.y,
+                    arc.radii.x,
+                    arc.radii.y,
+                    arc.start_angle,
+                    arc.sweep_angle,
+                )
+                .await?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Fill in an ellipse.
+    #[inline]
+    fn fill_ellipse_async<'future>(
+        &'future mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+    ) -> GenericResult<'future> {
+        self.fill_arc_async(
+            xcenter,
+            ycenter,
+            xradius,
+            yradius,
+            Angle { radians: 0.0 },
+            Angle {
+                radians: 2.0 * std::f32::consts::PI,
+            },
+        )
+    }
+    /// Fill in a circle.
+    #[inline]
+    fn fill_circle_async<'future>(
+        &'future mut self,
+        center: Point<f32>,
+        radius: f32,
+    ) -> GenericResult<'future> {
+        self.fill_ellipse_async(center.x, center.y, radius, radius)
+    }
+    /// Fill in several ellipses.
+    #[inline]
+    fn fill_ellipses_async<'future, 'a, 'b>(
+        &'a mut self,
+        rects: &'b [Ellipse],
+    ) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        Box::pin(async move {
+            for Ellipse { center, radii } in rects.iter().copied() {
+                self.fill_ellipse_async(center.x, center.y, radii.x, radii.y)
+                    .await?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Copy the contents of an image to this surface.
+    fn copy_image_async<'future>(
+        &'future mut self,
+        src: Image,
+        src_x: i32,
+        src_y: i32,
+        dst_x: i32,
+        dst_y: i32,
+        width: u32,
+        height: u32,
+    ) -> GenericResult<'future>;
+    /// Copy several images to this surface.
+    #[inline]
+    fn copy_images_async<'future, 'a, 'b>(
+        &'a mut self,
+        images: &'b [ImageCopySpecs],
+    ) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        Box::pin(async move {
+            for spec in images {
+                self.copy_image_async(
+                    spec.image,
+                    spec.src_x,
+                    spec.src_y,
+                    spec.dst_x,
+                    spec.dst_y,
+                    spec.width,
+                    spec.height,
+                )
+                .await?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path_to_points;
+    use lyon_geom::point;
+
+    /// The points of every path drawn onto a recording `NullSurface`, flattened.
+    fn drawn_points(surface: &mut NullSurface) -> Vec<Point<f32>> {
+        surface
+            .take_operations()
+            .into_iter()
+            .flat_map(|op| match op {
+                DrawOp::DrawPath(path) => path_to_points(path.iter(), 0.01).collect(),
+                _ => vec![],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn straight_beziers_flatten_to_collinear_points() {
+        let mut surface = NullSurface::recording();
+        surface
+            .draw_cubic_bezier(
+                point(0.0, 0.0),
+                point(1.0, 2.0),
+                point(3.0, 6.0),
+                point(4.0, 8.0),
+            )
+            .unwrap();
+        let points = drawn_points(&mut surface);
+        assert!(points.len() >= 2);
+        points
+            .iter()
+            .for_each(|p| assert!((p.y - 2.0 * p.x).abs() < 1e-4, "{:?}", p));
+
+        surface
+            .draw_quadratic_bezier(point(0.0, 0.0), point(2.0, 4.0), point(4.0, 8.0))
+            .unwrap();
+        let points = drawn_points(&mut surface);
+        assert!(points.len() >= 2);
+        points
+            .iter()
+            .for_each(|p| assert!((p.y - 2.0 * p.x).abs() < 1e-4, "{:?}", p));
+    }
+
+    #[test]
+    fn tolerance_is_clamped() {
+        let mut null = NullSurface::new();
+        let mut image = ImageSurface::new(1, 1);
+        assert_eq!(Surface::tolerance(&null), DEFAULT_TOLERANCE);
+
+        Surface::set_tolerance(&mut null, 0.5).unwrap();
+        image.set_tolerance(0.5).unwrap();
+        assert_eq!(Surface::tolerance(&null), 0.5);
+        assert_eq!(image.tolerance(), 0.5);
+
+        Surface::set_tolerance(&mut null, 0.0).unwrap();
+        image.set_tolerance(-1.0).unwrap();
+        assert_eq!(Surface::tolerance(&null), MIN_TOLERANCE);
+        assert_eq!(image.tolerance(), MIN_TOLERANCE);
+    }
+
+    #[test]
+    fn supports_matches_feature_flags() {
+        let image = ImageSurface::new(1, 1);
+        let features = image.features();
+        assert_eq!(image.supports(NSOpType::Gradients), features.gradients);
+        assert_eq!(image.supports(NSOpType::Clipping), features.clipping);
+        assert_eq!(image.supports(NSOpType::Text), features.text);
+
+        let no_text = SurfaceFeatures {
+            text: false,
+            gradients: true,
+            ..features
+        };
+        assert!(!no_text.supports(NSOpType::Text));
+        assert!(no_text.supports(NSOpType::Gradients));
+    }
+
+    #[test]
+    fn dropping_frame_guard_flushes() {
+        let mut surface = NullSurface::recording();
+        {
+            let mut frame = surface.frame();
+            Surface::draw_line(&mut *frame, 0.0, 0.0, 4.0, 4.0).unwrap();
+            assert!(!frame
+                .operations()
+                .iter()
+                .any(|op| matches!(op, DrawOp::Flush)));
+        }
+
+        assert!(matches!(surface.operations().last(), Some(DrawOp::Flush)));
+    }
+
+    #[test]
+    fn painted_rectangle_fills_then_strokes() {
+        let mut surface = NullSurface::recording();
+        let rect = Rect::new(point(1.0, 2.0), Size::new(3.0, 4.0));
+        Surface::paint_rectangle(
+            &mut surface,
+            rect,
+            FillRule::SolidColor(Color::WHITE),
+            Color::BLACK,
+            2,
+        )
+        .unwrap();
+
+        let ops = surface.take_operations();
+        let fill = ops
+            .iter()
+            .position(|op| matches!(op, DrawOp::FillRectangle(r) if *r == rect))
+            .expect("the rectangle should be filled");
+        let stroke = ops
+            .iter()
+            .position(|op| matches!(op, DrawOp::DrawRectangle(r) if *r == rect))
+            .expect("the rectangle should be stroked");
+        assert!(fill < stroke);
+    }
+
+    #[test]
+    fn fill_circle_is_an_equal_radius_ellipse() {
+        let mut surface = NullSurface::recording();
+        surface.fill_circle(point(5.0, 6.0), 3.0).unwrap();
+        surface.fill_ellipse(5.0, 6.0, 3.0, 3.0).unwrap();
+
+        let arcs: Vec<Arc<f32>> = surface
+            .take_operations()
+            .into_iter()
+            .map(|op| match op {
+                DrawOp::FillArc(arc) => arc,
+                op => panic!("unexpected operation {:?}", op),
+            })
+            .collect();
+        assert_eq!(arcs.len(), 2);
+        assert_eq!(arcs[0], arcs[1]);
+    }
+
+    #[test]
+    fn quarter_pie_is_a_sector_through_the_center() {
+        let mut surface = NullSurface::recording();
+        let center = point(10.0, 10.0);
+        surface
+            .fill_pie(
+                center,
+                Vector::new(5.0, 5.0),
+                Angle::zero(),
+                Angle::degrees(90.0),
+            )
+            .unwrap();
+
+        let ops = surface.take_operations();
+        let points = match &ops[..] {
+            [DrawOp::FillPolygon(points)] => points,
+            ops => panic!("unexpected operations {:?}", ops),
+        };
+        assert!(points.len() > 3);
+        assert_eq!(points[0], center);
+
+        // the rest of the points run along the arc, from its start to its end
+        points[1..]
+            .iter()
+            .for_each(|p| assert!(((*p - center).length() - 5.0).abs() < 1.0e-3, "{:?}", p));
+        assert!((points[1] - point(15.0, 10.0)).length() < 1.0e-3);
+        assert!((points[points.len() - 1] - point(10.0, 15.0)).length() < 1.0e-3);
+    }
+
+    #[test]
+    fn dash_offset_shifts_the_drawn_segments() {
+        let dashes = |offset| {
+            let mut surface = NullSurface::recording();
+            surface
+                .draw_dashed_line(point(0.0, 0.0), point(10.0, 0.0), &[2.0, 2.0], offset)
+                .unwrap();
+            surface
+                .take_operations()
+                .into_iter()
+                .filter_map(|op| match op {
+                    DrawOp::DrawLine(line) => Some((line.from.x, line.to.x)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(dashes(0.0), [(0.0, 2.0), (4.0, 6.0), (8.0, 10.0)]);
+        assert_eq!(dashes(1.0), [(0.0, 1.0), (3.0, 5.0), (7.0, 9.0)]);
+    }
+
+    #[test]
+    fn text_origins_advance_along_a_straight_path() {
+        let mut builder = Path::builder();
+        builder.begin(point(10.0, 20.0));
+        builder.line_to(point(110.0, 20.0));
+        builder.end(false);
+        let path = builder.build();
+
+        let sizes = iter::repeat(Size::new(6.0, 10.0)).take(5);
+        let origins: Vec<Point<f32>> = text_on_path(sizes, path.as_slice(), DEFAULT_TOLERANCE)
+            .iter()
+            .map(|transform| transform.transform_point(Point::zero()))
+            .collect();
+
+        assert_eq!(origins.len(), 5);
+        assert!((origins[0] - point(13.0, 20.0)).length() < 1.0e-3);
+        origins.windows(2).for_each(|w| {
+            assert!(w[1].x > w[0].x);
+            assert!((w[1].y - 20.0).abs() < 1.0e-3);
+        });
+
+        // characters whose middle runs off the end are left out
+        let sizes = iter::repeat(Size::new(30.0, 10.0)).take(5);
+        assert_eq!(
+            text_on_path(sizes, path.as_slice(), DEFAULT_TOLERANCE).len(),
+            3
+        );
+    }
+}
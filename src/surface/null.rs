@@ -0,0 +1,775 @@
+// MIT/Apache2 License
+
+//! A surface that doesn't draw anything. It counts the operations applied to it and can optionally record them,
+//! which makes it useful for testing drawing code without a display.
+
+use super::{
+    DirtyRegion, FallbackPolicy, ImageCopySpecs, Surface, SurfaceFeatures, DEFAULT_TOLERANCE,
+    MIN_TOLERANCE,
+};
+use crate::{
+    fill::{FillMode, FillRule},
+    path::PathExt,
+    stroke::{LineCap, LineJoin},
+    transform::Transform,
+    Color, Font, GlyphId, Image, ImageFormat,
+};
+use lyon_geom::{Angle, Arc, LineSegment, Point, Rect, Size, Vector};
+use lyon_path::{Path, PathSlice};
+use std::{collections::HashMap, num::NonZeroUsize};
+
+#[cfg(feature = "async")]
+use super::AsyncSurface;
+#[cfg(feature = "async")]
+use crate::util::GenericResult;
+#[cfg(feature = "async")]
+use futures_lite::future;
+
+const FEATURES: SurfaceFeatures = SurfaceFeatures {
+    transparency: true,
+    gradients: true,
+    floats: true,
+    clipping: true,
+    transforms: true,
+    paths: true,
+    text: false,
+    images: true,
+    patterns: true,
+    size: false,
+    antialiasing: true,
+    dirty_tracking: true,
+};
+
+/// An operation applied to a `NullSurface`.
+#[derive(Debug, Clone)]
+pub enum DrawOp {
+    SetStroke(Color),
+    SetFill(FillRule),
+    SetFillMode(FillMode),
+    SetLineWidth(usize),
+    SetLineCap(LineCap),
+    SetLineJoin(LineJoin),
+    SetMiterLimit(f32),
+    SetDash(Vec<f32>, f32),
+    SetFallbackPolicy(FallbackPolicy),
+    SetAntialias(bool),
+    SetGlobalAlpha(f32),
+    SetTolerance(f32),
+    PushClip(Rect<f32>),
+    PopClip,
+    SetTransform(Transform),
+    Transform(Transform),
+    Save,
+    Restore,
+    Flush,
+    Clear(Color),
+    CreateImage {
+        image: Image,
+        data: Vec<u8>,
+        width: u32,
+        height: u32,
+        format: ImageFormat,
+    },
+    DestroyImage(Image),
+    DrawLine(LineSegment<f32>),
+    DrawPath(Path),
+    DrawRectangle(Rect<f32>),
+    DrawArc(Arc<f32>),
+    FillPolygon(Vec<Point<f32>>),
+    FillPath(Path),
+    FillRectangle(Rect<f32>),
+    FillArc(Arc<f32>),
+    CopyImage(ImageCopySpecs),
+    DrawImage {
+        image: Image,
+        src: Rect<f32>,
+        dst: Rect<f32>,
+    },
+    DrawGlyphs {
+        glyphs: Vec<(GlyphId, Point<f32>)>,
+        font: Font,
+    },
+}
+
+impl DrawOp {
+    /// The area that this operation draws to, not accounting for line width or the current transform. Returns
+    /// `None` if this operation doesn't draw anything, or if it draws to the entire surface.
+    #[inline]
+    pub fn bounds(&self) -> Option<Rect<f32>> {
+        match self {
+            DrawOp::DrawLine(line) => Some(line.bounding_rect()),
+            DrawOp::DrawPath(path) | DrawOp::FillPath(path) => Some(path.bounds()),
+            DrawOp::DrawRectangle(rect) | DrawOp::FillRectangle(rect) => Some(*rect),
+            DrawOp::DrawArc(arc) | DrawOp::FillArc(arc) => Some(arc.bounding_rect()),
+            DrawOp::FillPolygon(points) => Some(Rect::from_points(points)),
+            DrawOp::CopyImage(specs) => Some(Rect::new(
+                Point::new(specs.dst_x as f32, specs.dst_y as f32),
+                Size::new(specs.width as f32, specs.height as f32),
+            )),
+            DrawOp::DrawImage { dst, .. } => Some(*dst),
+            _ => None,
+        }
+    }
+}
+
+/// A surface that doesn't draw anything.
+#[derive(Debug)]
+pub struct NullSurface {
+    count: usize,
+    // `None` if we aren't recording operations
+    operations: Option<Vec<DrawOp>>,
+    images: HashMap<Image, Size<f32>>,
+    next_image: usize,
+    clip_depth: usize,
+    saved: usize,
+    tolerance: f32,
+    dirty: DirtyRegion,
+}
+
+impl NullSurface {
+    /// Create a new `NullSurface` that only counts the operations applied to it.
+    #[inline]
+    pub fn new() -> NullSurface {
+        NullSurface {
+            count: 0,
+            operations: None,
+            images: HashMap::new(),
+            next_image: 1,
+            clip_depth: 0,
+            saved: 0,
+            tolerance: DEFAULT_TOLERANCE,
+            dirty: DirtyRegion::new(),
+        }
+    }
+
+    /// Create a new `NullSurface` that records the operations applied to it.
+    #[inline]
+    pub fn recording() -> NullSurface {
+        NullSurface {
+            operations: Some(vec![]),
+            ..NullSurface::new()
+        }
+    }
+
+    /// The number of operations that have been applied to this surface.
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The operations that have been applied to this surface, in order. This is empty if the surface isn't
+    /// recording.
+    #[inline]
+    pub fn operations(&self) -> &[DrawOp] {
+        self.operations.as_deref().unwrap_or(&[])
+    }
+
+    /// Take the operations that have been recorded so far, and reset the operation count.
+    #[inline]
+    pub fn take_operations(&mut self) -> Vec<DrawOp> {
+        self.count = 0;
+        self.operations
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    fn record(&mut self, op: DrawOp) -> crate::Result {
+        self.count += 1;
+        if self.dirty.is_tracking() {
+            if let Some(bounds) = op.bounds() {
+                self.dirty.add_rect(bounds);
+            }
+        }
+        if let Some(ref mut operations) = self.operations {
+            operations.push(op);
+        }
+        Ok(())
+    }
+}
+
+#[inline]
+fn arc(
+    xcenter: f32,
+    ycenter: f32,
+    xradius: f32,
+    yradius: f32,
+    start_angle: Angle<f32>,
+    sweep_angle: Angle<f32>,
+) -> Arc<f32> {
+    Arc {
+        center: Point::new(xcenter, ycenter),
+        radii: Vector::new(xradius, yradius),
+        start_angle,
+        sweep_angle,
+        x_rotation: Angle { radians: 0.0 },
+    }
+}
+
+impl Default for NullSurface {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Surface for NullSurface {
+    #[inline]
+    fn features(&self) -> SurfaceFeatures {
+        FEATURES
+    }
+
+    #[inline]
+    fn set_stroke(&mut self, color: Color) -> crate::Result {
+        self.record(DrawOp::SetStroke(color))
+    }
+
+    #[inline]
+    fn set_fill(&mut self, rule: FillRule) -> crate::Result {
+        self.record(DrawOp::SetFill(rule))
+    }
+
+    #[inline]
+    fn set_fill_mode(&mut self, mode: FillMode) -> crate::Result {
+        self.record(DrawOp::SetFillMode(mode))
+    }
+
+    #[inline]
+    fn set_line_width(&mut self, width: usize) -> crate::Result {
+        self.record(DrawOp::SetLineWidth(width))
+    }
+
+    #[inline]
+    fn set_line_cap(&mut self, cap: LineCap) -> crate::Result {
+        self.record(DrawOp::SetLineCap(cap))
+    }
+
+    #[inline]
+    fn set_line_join(&mut self, join: LineJoin) -> crate::Result {
+        self.record(DrawOp::SetLineJoin(join))
+    }
+
+    #[inline]
+    fn set_miter_limit(&mut self, limit: f32) -> crate::Result {
+        self.record(DrawOp::SetMiterLimit(limit.max(1.0)))
+    }
+
+    #[inline]
+    fn set_dash(&mut self, pattern: &[f32], offset: f32) -> crate::Result {
+        self.record(DrawOp::SetDash(pattern.to_vec(), offset))
+    }
+
+    #[inline]
+    fn set_fallback_policy(&mut self, policy: FallbackPolicy) -> crate::Result {
+        self.record(DrawOp::SetFallbackPolicy(policy))
+    }
+
+    #[inline]
+    fn set_antialias(&mut self, on: bool) -> crate::Result {
+        self.record(DrawOp::SetAntialias(on))
+    }
+
+    #[inline]
+    fn set_global_alpha(&mut self, alpha: f32) -> crate::Result {
+        self.record(DrawOp::SetGlobalAlpha(alpha))
+    }
+
+    #[inline]
+    fn push_clip(&mut self, rect: Rect<f32>) -> crate::Result {
+        self.clip_depth += 1;
+        self.record(DrawOp::PushClip(rect))
+    }
+
+    #[inline]
+    fn pop_clip(&mut self) -> crate::Result {
+        self.clip_depth = self.clip_depth.checked_sub(1).ok_or(crate::Error::NoClip)?;
+        self.record(DrawOp::PopClip)
+    }
+
+    #[inline]
+    fn set_transform(&mut self, transform: Transform) -> crate::Result {
+        self.record(DrawOp::SetTransform(transform))
+    }
+
+    #[inline]
+    fn transform(&mut self, transform: Transform) -> crate::Result {
+        self.record(DrawOp::Transform(transform))
+    }
+
+    #[inline]
+    fn save(&mut self) -> crate::Result {
+        self.saved += 1;
+        self.record(DrawOp::Save)
+    }
+
+    #[inline]
+    fn restore(&mut self) -> crate::Result {
+        self.saved = self
+            .saved
+            .checked_sub(1)
+            .ok_or(crate::Error::StaticMsg("No saved state to restore"))?;
+        self.record(DrawOp::Restore)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> crate::Result {
+        self.record(DrawOp::Flush)
+    }
+
+    #[inline]
+    fn tolerance(&self) -> f32 {
+        self.tolerance
+    }
+
+    #[inline]
+    fn set_tolerance(&mut self, tolerance: f32) -> crate::Result {
+        self.tolerance = tolerance.max(MIN_TOLERANCE);
+        self.record(DrawOp::SetTolerance(self.tolerance))
+    }
+
+    #[inline]
+    fn begin_dirty_tracking(&mut self) -> crate::Result {
+        self.dirty.begin();
+        Ok(())
+    }
+
+    #[inline]
+    fn take_dirty_region(&mut self) -> crate::Result<Option<Rect<f32>>> {
+        Ok(self.dirty.take())
+    }
+
+    #[inline]
+    fn clear(&mut self, color: Color) -> crate::Result {
+        self.record(DrawOp::Clear(color))
+    }
+
+    #[inline]
+    fn create_image(
+        &mut self,
+        image_bytes: &[u8],
+        width: u32,
+        height: u32,
+        format: ImageFormat,
+    ) -> crate::Result<Image> {
+        let image =
+            Image::from_raw(NonZeroUsize::new(self.next_image).expect("Image IDs start at one"));
+        self.next_image += 1;
+        self.images
+            .insert(image, Size::new(width as f32, height as f32));
+
+        // only copy the image data if someone is going to look at it
+        let data = match self.operations {
+            Some(_) => image_bytes.to_vec(),
+            None => vec![],
+        };
+        self.record(DrawOp::CreateImage {
+            image,
+            data,
+            width,
+            height,
+            format,
+        })?;
+        Ok(image)
+    }
+
+    #[inline]
+    fn destroy_image(&mut self, image: Image) -> crate::Result {
+        self.images
+            .remove(&image)
+            .ok_or(crate::Error::ImageNotAvailable)?;
+        self.record(DrawOp::DestroyImage(image))
+    }
+
+    #[inline]
+    fn draw_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> crate::Result {
+        self.record(DrawOp::DrawLine(LineSegment {
+            from: Point::new(x1, y1),
+            to: Point::new(x2, y2),
+        }))
+    }
+
+    #[inline]
+    fn draw_lines(&mut self, lines: &[LineSegment<f32>]) -> crate::Result {
+        lines
+            .iter()
+            .try_for_each(|&line| self.record(DrawOp::DrawLine(line)))
+    }
+
+    #[inline]
+    fn draw_path(&mut self, path: PathSlice<'_>) -> crate::Result {
+        self.record(DrawOp::DrawPath(path.iter().collect()))
+    }
+
+    #[inline]
+    fn draw_path_owned(&mut self, path: Path) -> crate::Result {
+        self.record(DrawOp::DrawPath(path))
+    }
+
+    #[inline]
+    fn draw_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> crate::Result {
+        self.record(DrawOp::DrawRectangle(Rect::new(
+            Point::new(x, y),
+            Size::new(width, height),
+        )))
+    }
+
+    #[inline]
+    fn draw_rectangles(&mut self, rects: &[Rect<f32>]) -> crate::Result {
+        rects
+            .iter()
+            .try_for_each(|&rect| self.record(DrawOp::DrawRectangle(rect)))
+    }
+
+    #[inline]
+    fn draw_arc(
+        &mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+        start_angle: Angle<f32>,
+        sweep_angle: Angle<f32>,
+    ) -> crate::Result {
+        self.draw_arcs(&[arc(
+            xcenter,
+            ycenter,
+            xradius,
+            yradius,
+            start_angle,
+            sweep_angle,
+        )])
+    }
+
+    #[inline]
+    fn draw_arcs(&mut self, arcs: &[Arc<f32>]) -> crate::Result {
+        arcs.iter()
+            .try_for_each(|&arc| self.record(DrawOp::DrawArc(arc)))
+    }
+
+    #[inline]
+    fn fill_polygon(&mut self, points: &[Point<f32>]) -> crate::Result {
+        self.record(DrawOp::FillPolygon(points.to_vec()))
+    }
+
+    #[inline]
+    fn fill_path(&mut self, path: PathSlice<'_>) -> crate::Result {
+        self.record(DrawOp::FillPath(path.iter().collect()))
+    }
+
+    #[inline]
+    fn fill_path_owned(&mut self, path: Path) -> crate::Result {
+        self.record(DrawOp::FillPath(path))
+    }
+
+    #[inline]
+    fn fill_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> crate::Result {
+        self.record(DrawOp::FillRectangle(Rect::new(
+            Point::new(x, y),
+            Size::new(width, height),
+        )))
+    }
+
+    #[inline]
+    fn fill_rectangles(&mut self, rects: &[Rect<f32>]) -> crate::Result {
+        rects
+            .iter()
+            .try_for_each(|&rect| self.record(DrawOp::FillRectangle(rect)))
+    }
+
+    #[inline]
+    fn fill_arc(
+        &mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+        start_angle: Angle<f32>,
+        sweep_angle: Angle<f32>,
+    ) -> crate::Result {
+        self.fill_arcs(&[arc(
+            xcenter,
+            ycenter,
+            xradius,
+            yradius,
+            start_angle,
+            sweep_angle,
+        )])
+    }
+
+    #[inline]
+    fn fill_arcs(&mut self, arcs: &[Arc<f32>]) -> crate::Result {
+        arcs.iter()
+            .try_for_each(|&arc| self.record(DrawOp::FillArc(arc)))
+    }
+
+    #[inline]
+    fn copy_image(
+        &mut self,
+        image: Image,
+        src_x: i32,
+        src_y: i32,
+        dst_x: i32,
+        dst_y: i32,
+        width: u32,
+        height: u32,
+    ) -> crate::Result {
+        self.record(DrawOp::CopyImage(ImageCopySpecs {
+            image,
+            src_x,
+            src_y,
+            dst_x,
+            dst_y,
+            width,
+            height,
+        }))
+    }
+
+    #[inline]
+    fn draw_image(&mut self, image: &Image, dst: Rect<f32>) -> crate::Result {
+        let size = *self
+            .images
+            .get(image)
+            .ok_or(crate::Error::ImageNotAvailable)?;
+        self.draw_image_region(image, Rect::new(Point::origin(), size), dst)
+    }
+
+    #[inline]
+    fn draw_image_region(
+        &mut self,
+        image: &Image,
+        src: Rect<f32>,
+        dst: Rect<f32>,
+    ) -> crate::Result {
+        let size = *self
+            .images
+            .get(image)
+            .ok_or(crate::Error::ImageNotAvailable)?;
+        crate::image::check_image_region(src, size)?;
+        self.record(DrawOp::DrawImage {
+            image: *image,
+            src,
+            dst,
+        })
+    }
+
+    #[inline]
+    fn draw_glyphs(&mut self, glyphs: &[(GlyphId, Point<f32>)], font: &Font) -> crate::Result {
+        self.record(DrawOp::DrawGlyphs {
+            glyphs: glyphs.to_vec(),
+            font: font.clone(),
+        })
+    }
+}
+
+/// The async operations run synchronously, so a `NullSurface` can stand in for async surfaces in tests.
+#[cfg(feature = "async")]
+impl AsyncSurface for NullSurface {
+    #[inline]
+    fn features(&self) -> SurfaceFeatures {
+        FEATURES
+    }
+
+    #[inline]
+    fn set_stroke_async<'future>(&'future mut self, color: Color) -> GenericResult<'future> {
+        Box::pin(future::ready(self.set_stroke(color)))
+    }
+
+    #[inline]
+    fn set_fill_async<'future>(&'future mut self, rule: FillRule) -> GenericResult<'future> {
+        Box::pin(future::ready(self.set_fill(rule)))
+    }
+
+    #[inline]
+    fn set_line_width_async<'future>(&'future mut self, width: usize) -> GenericResult<'future> {
+        Box::pin(future::ready(self.set_line_width(width)))
+    }
+
+    #[inline]
+    fn flush_async<'future>(&'future mut self) -> GenericResult<'future> {
+        Box::pin(future::ready(self.flush()))
+    }
+
+    #[inline]
+    fn create_image_async<'future, 'a, 'b>(
+        &'a mut self,
+        image_bytes: &'b [u8],
+        width: u32,
+        height: u32,
+        image_format: ImageFormat,
+    ) -> GenericResult<'future, Image>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        Box::pin(future::ready(self.create_image(
+            image_bytes,
+            width,
+            height,
+            image_format,
+        )))
+    }
+
+    #[inline]
+    fn destroy_image_async<'future>(&'future mut self, image: Image) -> GenericResult<'future> {
+        Box::pin(future::ready(self.destroy_image(image)))
+    }
+
+    #[inline]
+    fn draw_line_async<'future>(
+        &'future mut self,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+    ) -> GenericResult<'future> {
+        Box::pin(future::ready(self.draw_line(x1, y1, x2, y2)))
+    }
+
+    #[inline]
+    fn fill_polygon_async<'future, 'a, 'b>(
+        &'a mut self,
+        points: &'b [Point<f32>],
+    ) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        Box::pin(future::ready(self.fill_polygon(points)))
+    }
+
+    #[inline]
+    fn copy_image_async<'future>(
+        &'future mut self,
+        src: Image,
+        src_x: i32,
+        src_y: i32,
+        dst_x: i32,
+        dst_y: i32,
+        width: u32,
+        height: u32,
+    ) -> GenericResult<'future> {
+        Box::pin(future::ready(
+            self.copy_image(src, src_x, src_y, dst_x, dst_y, width, height),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "async")]
+    use crate::color::consts::RED;
+
+    #[test]
+    fn nested_save_restore() {
+        let mut surface = NullSurface::new();
+        surface.save().unwrap();
+        surface.save().unwrap();
+        surface.restore().unwrap();
+        surface.restore().unwrap();
+        assert!(matches!(surface.restore(), Err(crate::Error::StaticMsg(_))));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_surface_records_operations() {
+        let mut surface = NullSurface::recording();
+        future::block_on(async {
+            surface.set_stroke_async(RED).await.unwrap();
+            surface
+                .fill_rectangle_async(0.0, 0.0, 4.0, 2.0)
+                .await
+                .unwrap();
+            surface
+                .draw_lines_async(&[LineSegment {
+                    from: Point::new(0.0, 0.0),
+                    to: Point::new(3.0, 3.0),
+                }])
+                .await
+                .unwrap();
+            let image = surface
+                .create_image_async(&[0; 16], 2, 2, ImageFormat::Rgba)
+                .await
+                .unwrap();
+            surface
+                .copy_image_async(image, 0, 0, 1, 1, 2, 2)
+                .await
+                .unwrap();
+            surface.destroy_image_async(image).await.unwrap();
+            surface.flush_async().await.unwrap();
+        });
+
+        let ops = surface.take_operations();
+        assert_eq!(ops.len(), 7);
+        assert!(matches!(ops[0], DrawOp::SetStroke(c) if c == RED));
+        assert!(matches!(&ops[1], DrawOp::FillPolygon(points) if points.len() == 4));
+        assert!(matches!(ops[2], DrawOp::DrawLine(_)));
+        assert!(matches!(
+            ops[3],
+            DrawOp::CreateImage {
+                width: 2,
+                height: 2,
+                ..
+            }
+        ));
+        assert!(matches!(
+            ops[4],
+            DrawOp::CopyImage(ImageCopySpecs { dst_x: 1, .. })
+        ));
+        assert!(matches!(ops[5], DrawOp::DestroyImage(_)));
+        assert!(matches!(ops[6], DrawOp::Flush));
+    }
+
+    #[test]
+    fn polyline_falls_back_to_lines() {
+        let mut surface = NullSurface::recording();
+        let pts = [
+            Point::new(0.0, 0.0),
+            Point::new(1.0, 0.0),
+            Point::new(1.0, 1.0),
+            Point::new(0.0, 1.0),
+        ];
+        surface.draw_polyline(&pts).unwrap();
+
+        let lines: Vec<LineSegment<f32>> = surface
+            .operations()
+            .iter()
+            .map(|op| match op {
+                DrawOp::DrawLine(line) => *line,
+                op => panic!("unexpected operation {:?}", op),
+            })
+            .collect();
+        assert_eq!(lines.len(), 3);
+        lines.iter().zip(pts.windows(2)).for_each(|(line, w)| {
+            assert_eq!((line.from, line.to), (w[0], w[1]));
+        });
+    }
+
+    #[test]
+    fn text_is_not_supported_by_default() {
+        let mut surface = NullSurface::new();
+        let font = crate::Font::new("Arial", 12.0);
+        assert!(!Surface::features(&surface).text);
+        assert!(matches!(
+            surface.measure_text("", &font),
+            Err(crate::Error::NotSupported(crate::NSOpType::Text))
+        ));
+        assert!(matches!(
+            surface.draw_text("hello", 0.0, 0.0, &font),
+            Err(crate::Error::NotSupported(crate::NSOpType::Text))
+        ));
+
+        // drawing text along a path falls back on draw_text, so it isn't supported either
+        let mut builder = Path::builder();
+        builder.begin(Point::new(0.0, 0.0));
+        builder.line_to(Point::new(100.0, 0.0));
+        builder.end(false);
+        let path = builder.build();
+        assert!(matches!(
+            surface.draw_text_on_path("hello", path.as_slice(), &font),
+            Err(crate::Error::NotSupported(crate::NSOpType::Text))
+        ));
+        assert!(surface.operations().is_empty());
+    }
+}
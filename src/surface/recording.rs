@@ -0,0 +1,467 @@
+// MIT/Apache2 License
+
+//! A surface that records the operations applied to it, so that they can be replayed onto another surface
+//! later.
+
+use super::{DrawOp, FallbackPolicy, NullSurface, Surface, SurfaceFeatures};
+use crate::{
+    fill::{FillMode, FillRule},
+    stroke::{LineCap, LineJoin},
+    transform::Transform,
+    Color, Font, GlyphId, Image, ImageFormat,
+};
+use lyon_geom::{Angle, Arc, LineSegment, Point, Rect};
+use lyon_path::{Path, PathSlice};
+use std::collections::HashMap;
+
+/// A surface that records the operations applied to it. The recorded operations can be replayed onto any
+/// other surface using the `replay` method.
+///
+/// Images created on this surface are recreated on the target surface every time the recording is replayed.
+#[derive(Debug)]
+pub struct RecordingSurface {
+    inner: NullSurface,
+}
+
+impl RecordingSurface {
+    /// Create a new, empty `RecordingSurface`.
+    #[inline]
+    pub fn new() -> RecordingSurface {
+        RecordingSurface {
+            inner: NullSurface::recording(),
+        }
+    }
+
+    /// The operations that have been recorded so far, in order.
+    #[inline]
+    pub fn operations(&self) -> &[DrawOp] {
+        self.inner.operations()
+    }
+
+    /// Take the operations that have been recorded so far, leaving this surface empty.
+    #[inline]
+    pub fn take_operations(&mut self) -> Vec<DrawOp> {
+        self.inner.take_operations()
+    }
+
+    /// Apply the recorded operations, in order, to another surface.
+    #[inline]
+    pub fn replay<S: Surface + ?Sized>(&self, target: &mut S) -> crate::Result {
+        replay(self.operations(), target)
+    }
+}
+
+impl Default for RecordingSurface {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Apply a list of operations, in order, to a surface.
+#[inline]
+pub fn replay<S: Surface + ?Sized>(operations: &[DrawOp], target: &mut S) -> crate::Result {
+    // maps the images in the recording to the images on the target
+    let mut images: HashMap<Image, Image> = HashMap::new();
+    let lookup = |images: &HashMap<Image, Image>, image: &Image| {
+        images
+            .get(image)
+            .copied()
+            .ok_or(crate::Error::ImageNotAvailable)
+    };
+
+    operations.iter().try_for_each(|op| match op {
+        DrawOp::SetStroke(color) => target.set_stroke(*color),
+        DrawOp::SetFill(rule) => target.set_fill(rule.clone()),
+        DrawOp::SetFillMode(mode) => target.set_fill_mode(*mode),
+        DrawOp::SetLineWidth(width) => target.set_line_width(*width),
+        DrawOp::SetLineCap(cap) => target.set_line_cap(*cap),
+        DrawOp::SetLineJoin(join) => target.set_line_join(*join),
+        DrawOp::SetMiterLimit(limit) => target.set_miter_limit(*limit),
+        DrawOp::SetDash(pattern, offset) => target.set_dash(pattern, *offset),
+        DrawOp::SetFallbackPolicy(policy) => target.set_fallback_policy(*policy),
+        DrawOp::SetAntialias(on) => target.set_antialias(*on),
+        DrawOp::SetGlobalAlpha(alpha) => target.set_global_alpha(*alpha),
+        DrawOp::SetTolerance(tolerance) => target.set_tolerance(*tolerance),
+        DrawOp::PushClip(rect) => target.push_clip(*rect),
+        DrawOp::PopClip => target.pop_clip(),
+        DrawOp::SetTransform(transform) => target.set_transform(*transform),
+        DrawOp::Transform(transform) => target.transform(*transform),
+        DrawOp::Save => target.save(),
+        DrawOp::Restore => target.restore(),
+        DrawOp::Flush => target.flush(),
+        DrawOp::Clear(color) => target.clear(*color),
+        DrawOp::CreateImage {
+            image,
+            data,
+            width,
+            height,
+            format,
+        } => {
+            let new_image = target.create_image(data, *width, *height, *format)?;
+            images.insert(*image, new_image);
+            Ok(())
+        }
+        DrawOp::DestroyImage(image) => {
+            let image = lookup(&images, image)?;
+            target.destroy_image(image)
+        }
+        DrawOp::DrawLine(line) => target.draw_line(line.from.x, line.from.y, line.to.x, line.to.y),
+        DrawOp::DrawPath(path) => target.draw_path(path.as_slice()),
+        DrawOp::DrawRectangle(rect) => target.draw_rectangles(&[*rect]),
+        DrawOp::DrawArc(arc) => target.draw_arcs(&[*arc]),
+        DrawOp::FillPolygon(points) => target.fill_polygon(points),
+        DrawOp::FillPath(path) => target.fill_path(path.as_slice()),
+        DrawOp::FillRectangle(rect) => target.fill_rectangles(&[*rect]),
+        DrawOp::FillArc(arc) => target.fill_arcs(&[*arc]),
+        DrawOp::CopyImage(specs) => target.copy_image(
+            lookup(&images, &specs.image)?,
+            specs.src_x,
+            specs.src_y,
+            specs.dst_x,
+            specs.dst_y,
+            specs.width,
+            specs.height,
+        ),
+        DrawOp::DrawImage { image, src, dst } => {
+            target.draw_image_region(&lookup(&images, image)?, *src, *dst)
+        }
+        DrawOp::DrawGlyphs { glyphs, font } => target.draw_glyphs(glyphs, font),
+    })
+}
+
+impl Surface for RecordingSurface {
+    #[inline]
+    fn features(&self) -> SurfaceFeatures {
+        self.inner.features()
+    }
+
+    #[inline]
+    fn set_stroke(&mut self, color: Color) -> crate::Result {
+        self.inner.set_stroke(color)
+    }
+
+    #[inline]
+    fn set_fill(&mut self, rule: FillRule) -> crate::Result {
+        self.inner.set_fill(rule)
+    }
+
+    #[inline]
+    fn fill_rule(&self) -> Option<&FillRule> {
+        self.inner.fill_rule()
+    }
+
+    #[inline]
+    fn set_fill_mode(&mut self, mode: FillMode) -> crate::Result {
+        self.inner.set_fill_mode(mode)
+    }
+
+    #[inline]
+    fn set_line_width(&mut self, width: usize) -> crate::Result {
+        self.inner.set_line_width(width)
+    }
+
+    #[inline]
+    fn set_line_cap(&mut self, cap: LineCap) -> crate::Result {
+        self.inner.set_line_cap(cap)
+    }
+
+    #[inline]
+    fn set_line_join(&mut self, join: LineJoin) -> crate::Result {
+        self.inner.set_line_join(join)
+    }
+
+    #[inline]
+    fn set_miter_limit(&mut self, limit: f32) -> crate::Result {
+        self.inner.set_miter_limit(limit)
+    }
+
+    #[inline]
+    fn set_dash(&mut self, pattern: &[f32], offset: f32) -> crate::Result {
+        self.inner.set_dash(pattern, offset)
+    }
+
+    #[inline]
+    fn set_fallback_policy(&mut self, policy: FallbackPolicy) -> crate::Result {
+        self.inner.set_fallback_policy(policy)
+    }
+
+    #[inline]
+    fn set_antialias(&mut self, on: bool) -> crate::Result {
+        self.inner.set_antialias(on)
+    }
+
+    #[inline]
+    fn set_global_alpha(&mut self, alpha: f32) -> crate::Result {
+        self.inner.set_global_alpha(alpha)
+    }
+
+    #[inline]
+    fn push_clip(&mut self, rect: Rect<f32>) -> crate::Result {
+        self.inner.push_clip(rect)
+    }
+
+    #[inline]
+    fn pop_clip(&mut self) -> crate::Result {
+        self.inner.pop_clip()
+    }
+
+    #[inline]
+    fn set_transform(&mut self, transform: Transform) -> crate::Result {
+        self.inner.set_transform(transform)
+    }
+
+    #[inline]
+    fn transform(&mut self, transform: Transform) -> crate::Result {
+        self.inner.transform(transform)
+    }
+
+    #[inline]
+    fn save(&mut self) -> crate::Result {
+        self.inner.save()
+    }
+
+    #[inline]
+    fn restore(&mut self) -> crate::Result {
+        self.inner.restore()
+    }
+
+    #[inline]
+    fn flush(&mut self) -> crate::Result {
+        self.inner.flush()
+    }
+
+    #[inline]
+    fn tolerance(&self) -> f32 {
+        self.inner.tolerance()
+    }
+
+    #[inline]
+    fn set_tolerance(&mut self, tolerance: f32) -> crate::Result {
+        self.inner.set_tolerance(tolerance)
+    }
+
+    #[inline]
+    fn begin_dirty_tracking(&mut self) -> crate::Result {
+        self.inner.begin_dirty_tracking()
+    }
+
+    #[inline]
+    fn take_dirty_region(&mut self) -> crate::Result<Option<Rect<f32>>> {
+        self.inner.take_dirty_region()
+    }
+
+    #[inline]
+    fn clear(&mut self, color: Color) -> crate::Result {
+        self.inner.clear(color)
+    }
+
+    #[inline]
+    fn create_image(
+        &mut self,
+        image_bytes: &[u8],
+        width: u32,
+        height: u32,
+        format: ImageFormat,
+    ) -> crate::Result<Image> {
+        self.inner.create_image(image_bytes, width, height, format)
+    }
+
+    #[inline]
+    fn destroy_image(&mut self, image: Image) -> crate::Result {
+        self.inner.destroy_image(image)
+    }
+
+    #[inline]
+    fn draw_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> crate::Result {
+        self.inner.draw_line(x1, y1, x2, y2)
+    }
+
+    #[inline]
+    fn draw_lines(&mut self, lines: &[LineSegment<f32>]) -> crate::Result {
+        self.inner.draw_lines(lines)
+    }
+
+    #[inline]
+    fn draw_path(&mut self, path: PathSlice<'_>) -> crate::Result {
+        self.inner.draw_path(path)
+    }
+
+    #[inline]
+    fn draw_path_owned(&mut self, path: Path) -> crate::Result {
+        self.inner.draw_path_owned(path)
+    }
+
+    #[inline]
+    fn draw_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> crate::Result {
+        self.inner.draw_rectangle(x, y, width, height)
+    }
+
+    #[inline]
+    fn draw_rectangles(&mut self, rects: &[Rect<f32>]) -> crate::Result {
+        self.inner.draw_rectangles(rects)
+    }
+
+    #[inline]
+    fn draw_arc(
+        &mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+        start_angle: Angle<f32>,
+        sweep_angle: Angle<f32>,
+    ) -> crate::Result {
+        self.inner
+            .draw_arc(xcenter, ycenter, xradius, yradius, start_angle, sweep_angle)
+    }
+
+    #[inline]
+    fn draw_arcs(&mut self, arcs: &[Arc<f32>]) -> crate::Result {
+        self.inner.draw_arcs(arcs)
+    }
+
+    #[inline]
+    fn fill_polygon(&mut self, points: &[Point<f32>]) -> crate::Result {
+        self.inner.fill_polygon(points)
+    }
+
+    #[inline]
+    fn fill_path(&mut self, path: PathSlice<'_>) -> crate::Result {
+        self.inner.fill_path(path)
+    }
+
+    #[inline]
+    fn fill_path_owned(&mut self, path: Path) -> crate::Result {
+        self.inner.fill_path_owned(path)
+    }
+
+    #[inline]
+    fn fill_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> crate::Result {
+        self.inner.fill_rectangle(x, y, width, height)
+    }
+
+    #[inline]
+    fn fill_rectangles(&mut self, rects: &[Rect<f32>]) -> crate::Result {
+        self.inner.fill_rectangles(rects)
+    }
+
+    #[inline]
+    fn fill_arc(
+        &mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+        start_angle: Angle<f32>,
+        sweep_angle: Angle<f32>,
+    ) -> crate::Result {
+        self.inner
+            .fill_arc(xcenter, ycenter, xradius, yradius, start_angle, sweep_angle)
+    }
+
+    #[inline]
+    fn fill_arcs(&mut self, arcs: &[Arc<f32>]) -> crate::Result {
+        self.inner.fill_arcs(arcs)
+    }
+
+    #[inline]
+    fn copy_image(
+        &mut self,
+        image: Image,
+        src_x: i32,
+        src_y: i32,
+        dst_x: i32,
+        dst_y: i32,
+        width: u32,
+        height: u32,
+    ) -> crate::Result {
+        self.inner
+            .copy_image(image, src_x, src_y, dst_x, dst_y, width, height)
+    }
+
+    #[inline]
+    fn draw_image(&mut self, image: &Image, dst: Rect<f32>) -> crate::Result {
+        self.inner.draw_image(image, dst)
+    }
+
+    #[inline]
+    fn draw_image_region(
+        &mut self,
+        image: &Image,
+        src: Rect<f32>,
+        dst: Rect<f32>,
+    ) -> crate::Result {
+        self.inner.draw_image_region(image, src, dst)
+    }
+
+    #[inline]
+    fn draw_glyphs(&mut self, glyphs: &[(GlyphId, Point<f32>)], font: &Font) -> crate::Result {
+        self.inner.draw_glyphs(glyphs, font)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::consts::{BLUE, RED};
+    use std::mem;
+
+    #[test]
+    fn replay_matches_recording() {
+        let mut recording = RecordingSurface::new();
+        recording.set_stroke(RED).unwrap();
+        recording.set_fill_color(BLUE).unwrap();
+        recording.save().unwrap();
+        recording.draw_line(0.0, 0.0, 4.0, 4.0).unwrap();
+        recording.fill_rectangle(1.0, 1.0, 2.0, 2.0).unwrap();
+        recording.restore().unwrap();
+        let image = recording
+            .create_image(&[0; 16], 2, 2, ImageFormat::Rgba)
+            .unwrap();
+        recording.copy_image(image, 0, 0, 1, 1, 2, 2).unwrap();
+        recording.destroy_image(image).unwrap();
+
+        let mut target = NullSurface::recording();
+        recording.replay(&mut target).unwrap();
+
+        assert_eq!(target.count(), recording.operations().len());
+        recording
+            .operations()
+            .iter()
+            .zip(target.operations())
+            .for_each(|(recorded, replayed)| {
+                assert_eq!(mem::discriminant(recorded), mem::discriminant(replayed));
+            });
+
+        // replaying again recreates the image on the target
+        recording.replay(&mut target).unwrap();
+        assert_eq!(target.count(), recording.operations().len() * 2);
+    }
+
+    #[test]
+    fn glyphs_are_forwarded_in_order() {
+        let glyphs = [
+            (GlyphId(36), Point::new(0.0, 10.0)),
+            (GlyphId(72), Point::new(7.5, 10.0)),
+            (GlyphId(36), Point::new(15.0, 10.0)),
+        ];
+        let font = Font::builder().size(10.0).build();
+
+        let mut recording = RecordingSurface::new();
+        recording.draw_glyphs(&glyphs, &font).unwrap();
+        let mut target = RecordingSurface::new();
+        recording.replay(&mut target).unwrap();
+
+        match target.operations() {
+            [DrawOp::DrawGlyphs {
+                glyphs: forwarded,
+                font: forwarded_font,
+            }] => {
+                assert_eq!(forwarded, &glyphs);
+                assert_eq!(forwarded_font, &font);
+            }
+            ops => panic!("unexpected operations: {:?}", ops),
+        }
+    }
+}
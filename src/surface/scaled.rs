@@ -0,0 +1,894 @@
+// MIT/Apache2 License
+
+//! A surface that scales everything drawn on it by a constant factor, for drawing in logical pixels onto a
+//! surface measured in physical pixels.
+
+use super::{FallbackPolicy, ImageCopySpecs, Surface, SurfaceFeatures};
+use crate::{
+    fill::{FillMode, FillRule, ImagePattern},
+    gradient::{ConicGradient, LinearGradient, RadialGradient},
+    stroke::{LineCap, LineJoin},
+    transform::Transform,
+    Color, Ellipse, Font, GlyphId, Image, ImageFormat, Intensity,
+};
+use lyon_geom::{Angle, Arc, CubicBezierSegment, LineSegment, Point, Rect, Size, Vector};
+use lyon_path::{
+    builder::PathBuilder, iterator::PathIterator, Path, PathBuffer, PathBufferSlice, PathSlice,
+};
+
+/// A surface that multiplies the coordinates, radii and line widths of everything drawn on it by `scale`
+/// before passing it on to another surface. This is meant for high-DPI displays, where shapes are described in
+/// logical pixels but drawn in physical pixels.
+///
+/// Line widths are rounded, but never below one. Transforms set on this surface are expressed in logical
+/// pixels. The tolerance is passed through unchanged, so curves are flattened in physical pixels. Images
+/// copied with `copy_image` are moved, but not resized; use `draw_image` to scale them.
+#[derive(Debug)]
+pub struct ScaledSurface<S> {
+    inner: S,
+    scale: f32,
+}
+
+impl<S> ScaledSurface<S> {
+    /// Create a new `ScaledSurface` that draws onto `inner`, scaled by `scale`.
+    #[inline]
+    pub fn new(inner: S, scale: f32) -> ScaledSurface<S> {
+        ScaledSurface { inner, scale }
+    }
+
+    /// The factor that everything drawn is scaled by.
+    #[inline]
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Change the factor that everything drawn afterwards is scaled by. This does not affect the line width,
+    /// dash pattern or transform that have already been set.
+    #[inline]
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
+    }
+
+    /// The surface that this surface draws onto.
+    #[inline]
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// The surface that this surface draws onto.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Get the surface that this surface draws onto.
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    #[inline]
+    fn length(&self, length: f32) -> f32 {
+        length * self.scale
+    }
+
+    #[inline]
+    fn point(&self, point: Point<f32>) -> Point<f32> {
+        (point.to_vector() * self.scale).to_point()
+    }
+
+    #[inline]
+    fn points(&self, points: &[Point<f32>]) -> Vec<Point<f32>> {
+        points.iter().map(|&point| self.point(point)).collect()
+    }
+
+    #[inline]
+    fn rect(&self, rect: Rect<f32>) -> Rect<f32> {
+        rect.scale(self.scale, self.scale)
+    }
+
+    #[inline]
+    fn rects(&self, rects: &[Rect<f32>]) -> Vec<Rect<f32>> {
+        rects.iter().map(|&rect| self.rect(rect)).collect()
+    }
+
+    #[inline]
+    fn line_width(&self, width: usize) -> usize {
+        ((width as f32 * self.scale).round() as usize).max(1)
+    }
+
+    #[inline]
+    fn arc(&self, arc: Arc<f32>) -> Arc<f32> {
+        Arc {
+            center: self.point(arc.center),
+            radii: arc.radii * self.scale,
+            ..arc
+        }
+    }
+
+    #[inline]
+    fn ellipse(&self, ellipse: Ellipse) -> Ellipse {
+        Ellipse {
+            center: self.point(ellipse.center),
+            radii: ellipse.radii * self.scale,
+        }
+    }
+
+    #[inline]
+    fn curve(&self, curve: CubicBezierSegment<f32>) -> CubicBezierSegment<f32> {
+        CubicBezierSegment {
+            from: self.point(curve.from),
+            ctrl1: self.point(curve.ctrl1),
+            ctrl2: self.point(curve.ctrl2),
+            to: self.point(curve.to),
+        }
+    }
+
+    #[inline]
+    fn path(&self, path: PathSlice<'_>) -> Path {
+        let matrix = Transform::scale(self.scale, self.scale).into_inner();
+        let mut builder = Path::builder();
+        path.iter()
+            .transformed(&matrix)
+            .for_each(|event| builder.path_event(event));
+        builder.build()
+    }
+
+    #[inline]
+    fn paths(&self, paths: PathBufferSlice<'_>) -> PathBuffer {
+        let matrix = Transform::scale(self.scale, self.scale).into_inner();
+        let mut buffer = PathBuffer::new();
+        paths.indices().for_each(|index| {
+            paths
+                .get(index)
+                .iter()
+                .transformed(&matrix)
+                .fold(buffer.builder(), |mut builder, event| {
+                    builder.path_event(event);
+                    builder
+                })
+                .build();
+        });
+        buffer
+    }
+
+    /// Express a transform in logical pixels in physical pixels instead. Only the translation needs to be
+    /// scaled, since everything else about the transform commutes with a uniform scale.
+    #[inline]
+    fn transform_of(&self, transform: Transform) -> Transform {
+        let m = transform.into_inner();
+        Transform::new(
+            m.m11,
+            m.m12,
+            m.m21,
+            m.m22,
+            m.m31 * self.scale,
+            m.m32 * self.scale,
+        )
+    }
+
+    /// Image patterns are placed in logical pixels, so they need to be scaled as well.
+    #[inline]
+    fn fill_rule_of(&self, rule: FillRule) -> FillRule {
+        match rule {
+            FillRule::Pattern(pattern) => FillRule::Pattern(ImagePattern {
+                transform: Some(
+                    pattern
+                        .transform
+                        .unwrap_or_default()
+                        .then(&Transform::scale(self.scale, self.scale)),
+                ),
+                ..pattern
+            }),
+            rule => rule,
+        }
+    }
+
+    #[inline]
+    fn font(&self, font: &Font) -> Font {
+        Font {
+            size: self.length(font.size),
+            ..font.clone()
+        }
+    }
+}
+
+impl<S: Surface> Surface for ScaledSurface<S> {
+    #[inline]
+    fn features(&self) -> SurfaceFeatures {
+        self.inner.features()
+    }
+
+    #[inline]
+    fn set_stroke(&mut self, color: Color) -> crate::Result {
+        self.inner.set_stroke(color)
+    }
+
+    #[inline]
+    fn set_fill(&mut self, rule: FillRule) -> crate::Result {
+        let rule = self.fill_rule_of(rule);
+        self.inner.set_fill(rule)
+    }
+
+    #[inline]
+    fn fill_rule(&self) -> Option<&FillRule> {
+        self.inner.fill_rule()
+    }
+
+    #[inline]
+    fn set_fill_mode(&mut self, mode: FillMode) -> crate::Result {
+        self.inner.set_fill_mode(mode)
+    }
+
+    #[inline]
+    fn set_line_width(&mut self, width: usize) -> crate::Result {
+        let width = self.line_width(width);
+        self.inner.set_line_width(width)
+    }
+
+    #[inline]
+    fn set_line_cap(&mut self, cap: LineCap) -> crate::Result {
+        self.inner.set_line_cap(cap)
+    }
+
+    #[inline]
+    fn set_line_join(&mut self, join: LineJoin) -> crate::Result {
+        self.inner.set_line_join(join)
+    }
+
+    #[inline]
+    fn set_miter_limit(&mut self, limit: f32) -> crate::Result {
+        self.inner.set_miter_limit(limit)
+    }
+
+    #[inline]
+    fn set_dash(&mut self, pattern: &[f32], offset: f32) -> crate::Result {
+        let pattern: Vec<f32> = pattern.iter().map(|&dash| self.length(dash)).collect();
+        let offset = self.length(offset);
+        self.inner.set_dash(&pattern, offset)
+    }
+
+    #[inline]
+    fn set_fallback_policy(&mut self, policy: FallbackPolicy) -> crate::Result {
+        self.inner.set_fallback_policy(policy)
+    }
+
+    #[inline]
+    fn set_antialias(&mut self, on: bool) -> crate::Result {
+        self.inner.set_antialias(on)
+    }
+
+    #[inline]
+    fn set_global_alpha(&mut self, alpha: f32) -> crate::Result {
+        self.inner.set_global_alpha(alpha)
+    }
+
+    #[inline]
+    fn push_clip(&mut self, rect: Rect<f32>) -> crate::Result {
+        let rect = self.rect(rect);
+        self.inner.push_clip(rect)
+    }
+
+    #[inline]
+    fn pop_clip(&mut self) -> crate::Result {
+        self.inner.pop_clip()
+    }
+
+    #[inline]
+    fn set_transform(&mut self, transform: Transform) -> crate::Result {
+        let transform = self.transform_of(transform);
+        self.inner.set_transform(transform)
+    }
+
+    #[inline]
+    fn transform(&mut self, transform: Transform) -> crate::Result {
+        let transform = self.transform_of(transform);
+        self.inner.transform(transform)
+    }
+
+    #[inline]
+    fn save(&mut self) -> crate::Result {
+        self.inner.save()
+    }
+
+    #[inline]
+    fn restore(&mut self) -> crate::Result {
+        self.inner.restore()
+    }
+
+    #[inline]
+    fn flush(&mut self) -> crate::Result {
+        self.inner.flush()
+    }
+
+    #[inline]
+    fn tolerance(&self) -> f32 {
+        self.inner.tolerance()
+    }
+
+    #[inline]
+    fn set_tolerance(&mut self, tolerance: f32) -> crate::Result {
+        self.inner.set_tolerance(tolerance)
+    }
+
+    #[inline]
+    fn size(&self) -> crate::Result<Size<f32>> {
+        Ok(self.inner.size()? / self.scale)
+    }
+
+    #[inline]
+    fn begin_dirty_tracking(&mut self) -> crate::Result {
+        self.inner.begin_dirty_tracking()
+    }
+
+    #[inline]
+    fn take_dirty_region(&mut self) -> crate::Result<Option<Rect<f32>>> {
+        let region = self.inner.take_dirty_region()?;
+        Ok(region.map(|region| region.scale(1.0 / self.scale, 1.0 / self.scale)))
+    }
+
+    #[inline]
+    fn clear(&mut self, color: Color) -> crate::Result {
+        self.inner.clear(color)
+    }
+
+    #[inline]
+    fn measure_text(&self, text: &str, font: &Font) -> crate::Result<Size<f32>> {
+        Ok(self.inner.measure_text(text, &self.font(font))? / self.scale)
+    }
+
+    #[inline]
+    fn draw_text(&mut self, text: &str, x: f32, y: f32, font: &Font) -> crate::Result {
+        let font = self.font(font);
+        let (x, y) = (self.length(x), self.length(y));
+        self.inner.draw_text(text, x, y, &font)
+    }
+
+    #[inline]
+    fn draw_glyphs(&mut self, glyphs: &[(GlyphId, Point<f32>)], font: &Font) -> crate::Result {
+        let font = self.font(font);
+        let glyphs: Vec<(GlyphId, Point<f32>)> = glyphs
+            .iter()
+            .map(|&(glyph, point)| (glyph, self.point(point)))
+            .collect();
+        self.inner.draw_glyphs(&glyphs, &font)
+    }
+
+    #[inline]
+    fn read_intensity(&self, rect: Rect<f32>) -> crate::Result<Vec<Intensity>> {
+        self.inner.read_intensity(self.rect(rect))
+    }
+
+    #[inline]
+    fn create_image(
+        &mut self,
+        image_bytes: &[u8],
+        width: u32,
+        height: u32,
+        image_format: ImageFormat,
+    ) -> crate::Result<Image> {
+        self.inner
+            .create_image(image_bytes, width, height, image_format)
+    }
+
+    #[inline]
+    fn destroy_image(&mut self, image: Image) -> crate::Result {
+        self.inner.destroy_image(image)
+    }
+
+    #[inline]
+    fn draw_point(&mut self, x: f32, y: f32) -> crate::Result {
+        let (x, y) = (self.length(x), self.length(y));
+        self.inner.draw_point(x, y)
+    }
+
+    #[inline]
+    fn draw_points(&mut self, points: &[Point<f32>]) -> crate::Result {
+        let points = self.points(points);
+        self.inner.draw_points(&points)
+    }
+
+    #[inline]
+    fn draw_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> crate::Result {
+        let (x1, y1, x2, y2) = (
+            self.length(x1),
+            self.length(y1),
+            self.length(x2),
+            self.length(y2),
+        );
+        self.inner.draw_line(x1, y1, x2, y2)
+    }
+
+    #[inline]
+    fn draw_dashed_line(
+        &mut self,
+        from: Point<f32>,
+        to: Point<f32>,
+        pattern: &[f32],
+        offset: f32,
+    ) -> crate::Result {
+        let (from, to) = (self.point(from), self.point(to));
+        let pattern: Vec<f32> = pattern.iter().map(|&dash| self.length(dash)).collect();
+        let offset = self.length(offset);
+        self.inner.draw_dashed_line(from, to, &pattern, offset)
+    }
+
+    #[inline]
+    fn draw_lines(&mut self, lines: &[LineSegment<f32>]) -> crate::Result {
+        let lines: Vec<LineSegment<f32>> = lines
+            .iter()
+            .map(|line| LineSegment {
+                from: self.point(line.from),
+                to: self.point(line.to),
+            })
+            .collect();
+        self.inner.draw_lines(&lines)
+    }
+
+    #[inline]
+    fn draw_polyline(&mut self, pts: &[Point<f32>]) -> crate::Result {
+        let pts = self.points(pts);
+        self.inner.draw_polyline(&pts)
+    }
+
+    #[inline]
+    fn draw_path(&mut self, path: PathSlice<'_>) -> crate::Result {
+        let path = self.path(path);
+        self.inner.draw_path_owned(path)
+    }
+
+    #[inline]
+    fn draw_path_owned(&mut self, path: Path) -> crate::Result {
+        self.draw_path(path.as_slice())
+    }
+
+    #[inline]
+    fn draw_paths(&mut self, paths: PathBufferSlice<'_>) -> crate::Result {
+        let paths = self.paths(paths);
+        self.inner.draw_paths_owned(paths)
+    }
+
+    #[inline]
+    fn draw_paths_owned(&mut self, paths: PathBuffer) -> crate::Result {
+        self.draw_paths(paths.as_slice())
+    }
+
+    #[inline]
+    fn draw_bezier_curve(&mut self, curve: CubicBezierSegment<f32>) -> crate::Result {
+        let curve = self.curve(curve);
+        self.inner.draw_bezier_curve(curve)
+    }
+
+    #[inline]
+    fn draw_quadratic_bezier(
+        &mut self,
+        from: Point<f32>,
+        ctrl: Point<f32>,
+        to: Point<f32>,
+    ) -> crate::Result {
+        let (from, ctrl, to) = (self.point(from), self.point(ctrl), self.point(to));
+        self.inner.draw_quadratic_bezier(from, ctrl, to)
+    }
+
+    #[inline]
+    fn draw_cubic_bezier(
+        &mut self,
+        from: Point<f32>,
+        ctrl1: Point<f32>,
+        ctrl2: Point<f32>,
+        to: Point<f32>,
+    ) -> crate::Result {
+        let (from, ctrl1, ctrl2, to) = (
+            self.point(from),
+            self.point(ctrl1),
+            self.point(ctrl2),
+            self.point(to),
+        );
+        self.inner.draw_cubic_bezier(from, ctrl1, ctrl2, to)
+    }
+
+    #[inline]
+    fn draw_bezier_curves(&mut self, curves: &[CubicBezierSegment<f32>]) -> crate::Result {
+        let curves: Vec<CubicBezierSegment<f32>> =
+            curves.iter().map(|&curve| self.curve(curve)).collect();
+        self.inner.draw_bezier_curves(&curves)
+    }
+
+    #[inline]
+    fn draw_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> crate::Result {
+        let Rect { origin, size } =
+            self.rect(Rect::new(Point::new(x, y), Size::new(width, height)));
+        self.inner
+            .draw_rectangle(origin.x, origin.y, size.width, size.height)
+    }
+
+    #[inline]
+    fn draw_rectangles(&mut self, rects: &[Rect<f32>]) -> crate::Result {
+        let rects = self.rects(rects);
+        self.inner.draw_rectangles(&rects)
+    }
+
+    #[inline]
+    fn draw_round_rectangle(&mut self, rect: Rect<f32>, rx: f32, ry: f32) -> crate::Result {
+        let (rect, rx, ry) = (self.rect(rect), self.length(rx), self.length(ry));
+        self.inner.draw_round_rectangle(rect, rx, ry)
+    }
+
+    #[inline]
+    fn draw_arc(
+        &mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+        start_angle: Angle<f32>,
+        sweep_angle: Angle<f32>,
+    ) -> crate::Result {
+        let (xcenter, ycenter, xradius, yradius) = (
+            self.length(xcenter),
+            self.length(ycenter),
+            self.length(xradius),
+            self.length(yradius),
+        );
+        self.inner
+            .draw_arc(xcenter, ycenter, xradius, yradius, start_angle, sweep_angle)
+    }
+
+    #[inline]
+    fn draw_arcs(&mut self, arcs: &[Arc<f32>]) -> crate::Result {
+        let arcs: Vec<Arc<f32>> = arcs.iter().map(|&arc| self.arc(arc)).collect();
+        self.inner.draw_arcs(&arcs)
+    }
+
+    #[inline]
+    fn draw_ellipse(
+        &mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+    ) -> crate::Result {
+        let (xcenter, ycenter, xradius, yradius) = (
+            self.length(xcenter),
+            self.length(ycenter),
+            self.length(xradius),
+            self.length(yradius),
+        );
+        self.inner.draw_ellipse(xcenter, ycenter, xradius, yradius)
+    }
+
+    #[inline]
+    fn draw_circle(&mut self, center: Point<f32>, radius: f32) -> crate::Result {
+        let (center, radius) = (self.point(center), self.length(radius));
+        self.inner.draw_circle(center, radius)
+    }
+
+    #[inline]
+    fn draw_ellipses(&mut self, rects: &[Ellipse]) -> crate::Result {
+        let rects: Vec<Ellipse> = rects.iter().map(|&rect| self.ellipse(rect)).collect();
+        self.inner.draw_ellipses(&rects)
+    }
+
+    #[inline]
+    fn fill_polygon(&mut self, points: &[Point<f32>]) -> crate::Result {
+        let points = self.points(points);
+        self.inner.fill_polygon(&points)
+    }
+
+    #[inline]
+    fn fill_polygons(&mut self, polys: &[&[Point<f32>]]) -> crate::Result {
+        let polys: Vec<Vec<Point<f32>>> = polys.iter().map(|poly| self.points(poly)).collect();
+        let polys: Vec<&[Point<f32>]> = polys.iter().map(|poly| poly.as_slice()).collect();
+        self.inner.fill_polygons(&polys)
+    }
+
+    #[inline]
+    fn fill_path_owned(&mut self, path: Path) -> crate::Result {
+        self.fill_path(path.as_slice())
+    }
+
+    #[inline]
+    fn fill_path(&mut self, path: PathSlice<'_>) -> crate::Result {
+        let path = self.path(path);
+        self.inner.fill_path_owned(path)
+    }
+
+    #[inline]
+    fn fill_paths_owned(&mut self, paths: PathBuffer) -> crate::Result {
+        self.fill_paths(paths.as_slice())
+    }
+
+    #[inline]
+    fn fill_paths(&mut self, paths: PathBufferSlice<'_>) -> crate::Result {
+        let paths = self.paths(paths);
+        self.inner.fill_paths_owned(paths)
+    }
+
+    #[inline]
+    fn fill_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> crate::Result {
+        let Rect { origin, size } =
+            self.rect(Rect::new(Point::new(x, y), Size::new(width, height)));
+        self.inner
+            .fill_rectangle(origin.x, origin.y, size.width, size.height)
+    }
+
+    #[inline]
+    fn fill_rectangles(&mut self, rects: &[Rect<f32>]) -> crate::Result {
+        let rects = self.rects(rects);
+        self.inner.fill_rectangles(&rects)
+    }
+
+    #[inline]
+    fn fill_round_rectangle(&mut self, rect: Rect<f32>, rx: f32, ry: f32) -> crate::Result {
+        let (rect, rx, ry) = (self.rect(rect), self.length(rx), self.length(ry));
+        self.inner.fill_round_rectangle(rect, rx, ry)
+    }
+
+    #[inline]
+    fn fill_arc(
+        &mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+        start_angle: Angle<f32>,
+        sweep_angle: Angle<f32>,
+    ) -> crate::Result {
+        let (xcenter, ycenter, xradius, yradius) = (
+            self.length(xcenter),
+            self.length(ycenter),
+            self.length(xradius),
+            self.length(yradius),
+        );
+        self.inner
+            .fill_arc(xcenter, ycenter, xradius, yradius, start_angle, sweep_angle)
+    }
+
+    #[inline]
+    fn fill_pie(
+        &mut self,
+        center: Point<f32>,
+        radii: Vector<f32>,
+        start_angle: Angle<f32>,
+        sweep_angle: Angle<f32>,
+    ) -> crate::Result {
+        let (center, radii) = (self.point(center), radii * self.scale);
+        self.inner.fill_pie(center, radii, start_angle, sweep_angle)
+    }
+
+    #[inline]
+    fn fill_arcs(&mut self, arcs: &[Arc<f32>]) -> crate::Result {
+        let arcs: Vec<Arc<f32>> = arcs.iter().map(|&arc| self.arc(arc)).collect();
+        self.inner.fill_arcs(&arcs)
+    }
+
+    #[inline]
+    fn fill_ellipse(
+        &mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+    ) -> crate::Result {
+        let (xcenter, ycenter, xradius, yradius) = (
+            self.length(xcenter),
+            self.length(ycenter),
+            self.length(xradius),
+            self.length(yradius),
+        );
+        self.inner.fill_ellipse(xcenter, ycenter, xradius, yradius)
+    }
+
+    #[inline]
+    fn fill_circle(&mut self, center: Point<f32>, radius: f32) -> crate::Result {
+        let (center, radius) = (self.point(center), self.length(radius));
+        self.inner.fill_circle(center, radius)
+    }
+
+    #[inline]
+    fn fill_ellipses(&mut self, rects: &[Ellipse]) -> crate::Result {
+        let rects: Vec<Ellipse> = rects.iter().map(|&rect| self.ellipse(rect)).collect();
+        self.inner.fill_ellipses(&rects)
+    }
+
+    #[inline]
+    fn paint_rectangle(
+        &mut self,
+        rect: Rect<f32>,
+        fill: FillRule,
+        stroke: Color,
+        width: usize,
+    ) -> crate::Result {
+        let (rect, fill, width) = (
+            self.rect(rect),
+            self.fill_rule_of(fill),
+            self.line_width(width),
+        );
+        self.inner.paint_rectangle(rect, fill, stroke, width)
+    }
+
+    #[inline]
+    fn paint_round_rectangle(
+        &mut self,
+        rect: Rect<f32>,
+        rx: f32,
+        ry: f32,
+        fill: FillRule,
+        stroke: Color,
+        width: usize,
+    ) -> crate::Result {
+        let (rect, rx, ry, fill, width) = (
+            self.rect(rect),
+            self.length(rx),
+            self.length(ry),
+            self.fill_rule_of(fill),
+            self.line_width(width),
+        );
+        self.inner
+            .paint_round_rectangle(rect, rx, ry, fill, stroke, width)
+    }
+
+    #[inline]
+    fn paint_polygon(
+        &mut self,
+        points: &[Point<f32>],
+        fill: FillRule,
+        stroke: Color,
+        width: usize,
+    ) -> crate::Result {
+        let (points, fill, width) = (
+            self.points(points),
+            self.fill_rule_of(fill),
+            self.line_width(width),
+        );
+        self.inner.paint_polygon(&points, fill, stroke, width)
+    }
+
+    #[inline]
+    fn paint_ellipse(
+        &mut self,
+        ellipse: Ellipse,
+        fill: FillRule,
+        stroke: Color,
+        width: usize,
+    ) -> crate::Result {
+        let (ellipse, fill, width) = (
+            self.ellipse(ellipse),
+            self.fill_rule_of(fill),
+            self.line_width(width),
+        );
+        self.inner.paint_ellipse(ellipse, fill, stroke, width)
+    }
+
+    #[inline]
+    fn paint_path(
+        &mut self,
+        path: PathSlice<'_>,
+        fill: FillRule,
+        stroke: Color,
+        width: usize,
+    ) -> crate::Result {
+        let (path, fill, width) = (
+            self.path(path),
+            self.fill_rule_of(fill),
+            self.line_width(width),
+        );
+        self.inner.paint_path(path.as_slice(), fill, stroke, width)
+    }
+
+    #[inline]
+    fn copy_image(
+        &mut self,
+        src: Image,
+        src_x: i32,
+        src_y: i32,
+        dst_x: i32,
+        dst_y: i32,
+        width: u32,
+        height: u32,
+    ) -> crate::Result {
+        let dst_x = self.length(dst_x as f32).round() as i32;
+        let dst_y = self.length(dst_y as f32).round() as i32;
+        self.inner
+            .copy_image(src, src_x, src_y, dst_x, dst_y, width, height)
+    }
+
+    #[inline]
+    fn draw_image(&mut self, image: &Image, dst: Rect<f32>) -> crate::Result {
+        let dst = self.rect(dst);
+        self.inner.draw_image(image, dst)
+    }
+
+    #[inline]
+    fn draw_image_region(
+        &mut self,
+        image: &Image,
+        src: Rect<f32>,
+        dst: Rect<f32>,
+    ) -> crate::Result {
+        let dst = self.rect(dst);
+        self.inner.draw_image_region(image, src, dst)
+    }
+
+    #[inline]
+    fn draw_image_masked(&mut self, image: &Image, mask: &Image, dst: Rect<f32>) -> crate::Result {
+        let dst = self.rect(dst);
+        self.inner.draw_image_masked(image, mask, dst)
+    }
+
+    #[inline]
+    fn fill_linear_gradient(
+        &mut self,
+        gradient: &LinearGradient,
+        rect: Rect<f32>,
+    ) -> crate::Result {
+        let gradient = LinearGradient {
+            start: self.point(gradient.start),
+            end: self.point(gradient.end),
+            ..gradient.clone()
+        };
+        let rect = self.rect(rect);
+        self.inner.fill_linear_gradient(&gradient, rect)
+    }
+
+    #[inline]
+    fn fill_conic_gradient(&mut self, gradient: &ConicGradient, rect: Rect<f32>) -> crate::Result {
+        let gradient = ConicGradient {
+            center: self.point(gradient.center),
+            ..gradient.clone()
+        };
+        let rect = self.rect(rect);
+        self.inner.fill_conic_gradient(&gradient, rect)
+    }
+
+    #[inline]
+    fn fill_radial_gradient(
+        &mut self,
+        gradient: &RadialGradient,
+        rect: Rect<f32>,
+    ) -> crate::Result {
+        let gradient = RadialGradient {
+            center: self.point(gradient.center),
+            radius: self.length(gradient.radius),
+            ..gradient.clone()
+        };
+        let rect = self.rect(rect);
+        self.inner.fill_radial_gradient(&gradient, rect)
+    }
+
+    #[inline]
+    fn copy_images(&mut self, images: &[ImageCopySpecs]) -> crate::Result {
+        let images: Vec<ImageCopySpecs> = images
+            .iter()
+            .map(|specs| ImageCopySpecs {
+                dst_x: self.length(specs.dst_x as f32).round() as i32,
+                dst_y: self.length(specs.dst_y as f32).round() as i32,
+                ..*specs
+            })
+            .collect();
+        self.inner.copy_images(&images)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::surface::{DrawOp, RecordingSurface};
+
+    #[test]
+    fn rectangles_are_doubled() {
+        let mut surface = ScaledSurface::new(RecordingSurface::new(), 2.0);
+        surface.set_line_width(3).unwrap();
+        surface.draw_rectangle(1.0, 2.0, 3.0, 4.0).unwrap();
+        surface.fill_rectangle(0.5, 0.5, 1.0, 1.0).unwrap();
+
+        let expected = [
+            Rect::new(Point::new(2.0, 4.0), Size::new(6.0, 8.0)),
+            Rect::new(Point::new(1.0, 1.0), Size::new(2.0, 2.0)),
+        ];
+        let ops = surface.inner().operations();
+        assert!(matches!(ops[0], DrawOp::SetLineWidth(6)));
+        assert!(matches!(ops[1], DrawOp::DrawRectangle(rect) if rect == expected[0]));
+        assert!(matches!(ops[2], DrawOp::FillRectangle(rect) if rect == expected[1]));
+    }
+}
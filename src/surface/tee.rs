@@ -0,0 +1,913 @@
+// MIT/Apache2 License
+
+//! A surface that passes every operation on to two other surfaces.
+
+use super::{FallbackPolicy, ImageCopySpecs, Surface, SurfaceFeatures};
+use crate::{
+    fill::{FillMode, FillRule, ImagePattern},
+    gradient::{ConicGradient, LinearGradient, RadialGradient},
+    stroke::{LineCap, LineJoin},
+    transform::Transform,
+    Color, Ellipse, Font, GlyphId, Image, ImageFormat, Intensity,
+};
+use lyon_geom::{Angle, Arc, CubicBezierSegment, LineSegment, Point, Rect, Size, Vector};
+use lyon_path::{Path, PathBuffer, PathBufferSlice, PathSlice};
+use std::collections::HashMap;
+
+/// A surface that applies every operation to two other surfaces, such as a window and a `RecordingSurface` that
+/// captures what is drawn on it. Both surfaces see the same operations in the same order.
+///
+/// An operation is applied to the second surface even if it fails on the first one. If it fails on both, the
+/// errors are returned together as `Error::Multiple`. Queries like `size` and `tolerance` are answered by the
+/// first surface. Images created on this surface are created on both, and are identified by the image that the
+/// first surface created.
+#[derive(Debug)]
+pub struct TeeSurface<A, B> {
+    first: A,
+    second: B,
+    // maps the images created on the first surface to the same images on the second surface
+    images: HashMap<Image, Image>,
+}
+
+impl<A, B> TeeSurface<A, B> {
+    /// Create a new `TeeSurface` that draws onto both `first` and `second`.
+    #[inline]
+    pub fn new(first: A, second: B) -> TeeSurface<A, B> {
+        TeeSurface {
+            first,
+            second,
+            images: HashMap::new(),
+        }
+    }
+
+    /// The first surface that this surface draws onto.
+    #[inline]
+    pub fn first(&self) -> &A {
+        &self.first
+    }
+
+    /// The first surface that this surface draws onto.
+    #[inline]
+    pub fn first_mut(&mut self) -> &mut A {
+        &mut self.first
+    }
+
+    /// The second surface that this surface draws onto.
+    #[inline]
+    pub fn second(&self) -> &B {
+        &self.second
+    }
+
+    /// The second surface that this surface draws onto.
+    #[inline]
+    pub fn second_mut(&mut self) -> &mut B {
+        &mut self.second
+    }
+
+    /// Get the two surfaces that this surface draws onto.
+    #[inline]
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+
+    /// Get the image on the second surface that corresponds to an image on the first one.
+    #[inline]
+    fn second_image(&self, image: &Image) -> crate::Result<Image> {
+        self.images
+            .get(image)
+            .copied()
+            .ok_or(crate::Error::ImageNotAvailable)
+    }
+
+    /// Get the fill rule to use on the second surface, whose image patterns use its own images.
+    #[inline]
+    fn second_fill_rule(&self, rule: &FillRule) -> crate::Result<FillRule> {
+        Ok(match rule {
+            FillRule::Pattern(pattern) => FillRule::Pattern(ImagePattern {
+                image: self.second_image(&pattern.image)?,
+                ..*pattern
+            }),
+            rule => rule.clone(),
+        })
+    }
+}
+
+/// Combine the results of applying an operation to both surfaces.
+#[inline]
+fn join(first: crate::Result, second: crate::Result) -> crate::Result {
+    match (first, second) {
+        (Ok(()), Ok(())) => Ok(()),
+        (Err(e), Ok(())) | (Ok(()), Err(e)) => Err(e),
+        (Err(e1), Err(e2)) => Err(crate::Error::Multiple(vec![e1, e2])),
+    }
+}
+
+impl<A: Surface, B: Surface> Surface for TeeSurface<A, B> {
+    /// Only the features that both surfaces support.
+    #[inline]
+    fn features(&self) -> SurfaceFeatures {
+        let (a, b) = (self.first.features(), self.second.features());
+        SurfaceFeatures {
+            transparency: a.transparency && b.transparency,
+            gradients: a.gradients && b.gradients,
+            floats: a.floats && b.floats,
+            clipping: a.clipping && b.clipping,
+            transforms: a.transforms && b.transforms,
+            paths: a.paths && b.paths,
+            text: a.text && b.text,
+            images: a.images && b.images,
+            patterns: a.patterns && b.patterns,
+            size: a.size && b.size,
+            antialiasing: a.antialiasing && b.antialiasing,
+            dirty_tracking: a.dirty_tracking && b.dirty_tracking,
+        }
+    }
+
+    #[inline]
+    fn set_stroke(&mut self, color: Color) -> crate::Result {
+        join(self.first.set_stroke(color), self.second.set_stroke(color))
+    }
+
+    #[inline]
+    fn set_fill(&mut self, rule: FillRule) -> crate::Result {
+        let second = self.second_fill_rule(&rule);
+        join(
+            self.first.set_fill(rule),
+            second.and_then(|rule| self.second.set_fill(rule)),
+        )
+    }
+
+    #[inline]
+    fn fill_rule(&self) -> Option<&FillRule> {
+        self.first.fill_rule()
+    }
+
+    #[inline]
+    fn set_fill_mode(&mut self, mode: FillMode) -> crate::Result {
+        join(
+            self.first.set_fill_mode(mode),
+            self.second.set_fill_mode(mode),
+        )
+    }
+
+    #[inline]
+    fn set_line_width(&mut self, width: usize) -> crate::Result {
+        join(
+            self.first.set_line_width(width),
+            self.second.set_line_width(width),
+        )
+    }
+
+    #[inline]
+    fn set_line_cap(&mut self, cap: LineCap) -> crate::Result {
+        join(self.first.set_line_cap(cap), self.second.set_line_cap(cap))
+    }
+
+    #[inline]
+    fn set_line_join(&mut self, line_join: LineJoin) -> crate::Result {
+        join(
+            self.first.set_line_join(line_join),
+            self.second.set_line_join(line_join),
+        )
+    }
+
+    #[inline]
+    fn set_miter_limit(&mut self, limit: f32) -> crate::Result {
+        join(
+            self.first.set_miter_limit(limit),
+            self.second.set_miter_limit(limit),
+        )
+    }
+
+    #[inline]
+    fn set_dash(&mut self, pattern: &[f32], offset: f32) -> crate::Result {
+        join(
+            self.first.set_dash(pattern, offset),
+            self.second.set_dash(pattern, offset),
+        )
+    }
+
+    #[inline]
+    fn set_fallback_policy(&mut self, policy: FallbackPolicy) -> crate::Result {
+        join(
+            self.first.set_fallback_policy(policy),
+            self.second.set_fallback_policy(policy),
+        )
+    }
+
+    #[inline]
+    fn set_antialias(&mut self, on: bool) -> crate::Result {
+        join(self.first.set_antialias(on), self.second.set_antialias(on))
+    }
+
+    #[inline]
+    fn set_global_alpha(&mut self, alpha: f32) -> crate::Result {
+        join(
+            self.first.set_global_alpha(alpha),
+            self.second.set_global_alpha(alpha),
+        )
+    }
+
+    #[inline]
+    fn push_clip(&mut self, rect: Rect<f32>) -> crate::Result {
+        join(self.first.push_clip(rect), self.second.push_clip(rect))
+    }
+
+    #[inline]
+    fn pop_clip(&mut self) -> crate::Result {
+        join(self.first.pop_clip(), self.second.pop_clip())
+    }
+
+    #[inline]
+    fn set_transform(&mut self, transform: Transform) -> crate::Result {
+        join(
+            self.first.set_transform(transform),
+            self.second.set_transform(transform),
+        )
+    }
+
+    #[inline]
+    fn transform(&mut self, transform: Transform) -> crate::Result {
+        join(
+            self.first.transform(transform),
+            self.second.transform(transform),
+        )
+    }
+
+    #[inline]
+    fn save(&mut self) -> crate::Result {
+        join(self.first.save(), self.second.save())
+    }
+
+    #[inline]
+    fn restore(&mut self) -> crate::Result {
+        join(self.first.restore(), self.second.restore())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> crate::Result {
+        join(self.first.flush(), self.second.flush())
+    }
+
+    #[inline]
+    fn tolerance(&self) -> f32 {
+        self.first.tolerance()
+    }
+
+    #[inline]
+    fn set_tolerance(&mut self, tolerance: f32) -> crate::Result {
+        join(
+            self.first.set_tolerance(tolerance),
+            self.second.set_tolerance(tolerance),
+        )
+    }
+
+    #[inline]
+    fn size(&self) -> crate::Result<Size<f32>> {
+        self.first.size()
+    }
+
+    #[inline]
+    fn begin_dirty_tracking(&mut self) -> crate::Result {
+        let res = self.first.begin_dirty_tracking();
+        res.and(self.second.begin_dirty_tracking())
+    }
+
+    #[inline]
+    fn take_dirty_region(&mut self) -> crate::Result<Option<Rect<f32>>> {
+        let second = self.second.take_dirty_region();
+        Ok(self.first.take_dirty_region()?.or(second?))
+    }
+
+    #[inline]
+    fn clear(&mut self, color: Color) -> crate::Result {
+        join(self.first.clear(color), self.second.clear(color))
+    }
+
+    #[inline]
+    fn measure_text(&self, text: &str, font: &Font) -> crate::Result<Size<f32>> {
+        self.first.measure_text(text, font)
+    }
+
+    #[inline]
+    fn draw_text(&mut self, text: &str, x: f32, y: f32, font: &Font) -> crate::Result {
+        join(
+            self.first.draw_text(text, x, y, font),
+            self.second.draw_text(text, x, y, font),
+        )
+    }
+
+    #[inline]
+    fn draw_glyphs(&mut self, glyphs: &[(GlyphId, Point<f32>)], font: &Font) -> crate::Result {
+        join(
+            self.first.draw_glyphs(glyphs, font),
+            self.second.draw_glyphs(glyphs, font),
+        )
+    }
+
+    #[inline]
+    fn draw_text_on_path(&mut self, text: &str, path: PathSlice<'_>, font: &Font) -> crate::Result {
+        join(
+            self.first.draw_text_on_path(text, path, font),
+            self.second.draw_text_on_path(text, path, font),
+        )
+    }
+
+    #[inline]
+    fn read_intensity(&self, rect: Rect<f32>) -> crate::Result<Vec<Intensity>> {
+        self.first.read_intensity(rect)
+    }
+
+    #[inline]
+    fn create_image(
+        &mut self,
+        image_bytes: &[u8],
+        width: u32,
+        height: u32,
+        image_format: ImageFormat,
+    ) -> crate::Result<Image> {
+        let first = self
+            .first
+            .create_image(image_bytes, width, height, image_format)?;
+        match self
+            .second
+            .create_image(image_bytes, width, height, image_format)
+        {
+            Ok(second) => {
+                self.images.insert(first, second);
+                Ok(first)
+            }
+            Err(e) => {
+                // don't leave the image behind on the first surface
+                let _ = self.first.destroy_image(first);
+                Err(e)
+            }
+        }
+    }
+
+    #[inline]
+    fn destroy_image(&mut self, image: Image) -> crate::Result {
+        let second = self
+            .images
+            .remove(&image)
+            .ok_or(crate::Error::ImageNotAvailable);
+        join(
+            self.first.destroy_image(image),
+            second.and_then(|image| self.second.destroy_image(image)),
+        )
+    }
+
+    #[inline]
+    fn draw_point(&mut self, x: f32, y: f32) -> crate::Result {
+        join(self.first.draw_point(x, y), self.second.draw_point(x, y))
+    }
+
+    #[inline]
+    fn draw_points(&mut self, points: &[Point<f32>]) -> crate::Result {
+        join(
+            self.first.draw_points(points),
+            self.second.draw_points(points),
+        )
+    }
+
+    #[inline]
+    fn draw_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> crate::Result {
+        join(
+            self.first.draw_line(x1, y1, x2, y2),
+            self.second.draw_line(x1, y1, x2, y2),
+        )
+    }
+
+    #[inline]
+    fn draw_dashed_line(
+        &mut self,
+        from: Point<f32>,
+        to: Point<f32>,
+        pattern: &[f32],
+        offset: f32,
+    ) -> crate::Result {
+        join(
+            self.first.draw_dashed_line(from, to, pattern, offset),
+            self.second.draw_dashed_line(from, to, pattern, offset),
+        )
+    }
+
+    #[inline]
+    fn draw_lines(&mut self, lines: &[LineSegment<f32>]) -> crate::Result {
+        join(self.first.draw_lines(lines), self.second.draw_lines(lines))
+    }
+
+    #[inline]
+    fn draw_polyline(&mut self, pts: &[Point<f32>]) -> crate::Result {
+        join(
+            self.first.draw_polyline(pts),
+            self.second.draw_polyline(pts),
+        )
+    }
+
+    #[inline]
+    fn draw_path(&mut self, path: PathSlice<'_>) -> crate::Result {
+        join(self.first.draw_path(path), self.second.draw_path(path))
+    }
+
+    #[inline]
+    fn draw_path_owned(&mut self, path: Path) -> crate::Result {
+        join(
+            self.first.draw_path(path.as_slice()),
+            self.second.draw_path_owned(path),
+        )
+    }
+
+    #[inline]
+    fn draw_paths(&mut self, paths: PathBufferSlice<'_>) -> crate::Result {
+        join(
+            self.first.draw_paths(paths.clone()),
+            self.second.draw_paths(paths),
+        )
+    }
+
+    #[inline]
+    fn draw_paths_owned(&mut self, paths: PathBuffer) -> crate::Result {
+        join(
+            self.first.draw_paths(paths.as_slice()),
+            self.second.draw_paths_owned(paths),
+        )
+    }
+
+    #[inline]
+    fn draw_bezier_curve(&mut self, curve: CubicBezierSegment<f32>) -> crate::Result {
+        join(
+            self.first.draw_bezier_curve(curve),
+            self.second.draw_bezier_curve(curve),
+        )
+    }
+
+    #[inline]
+    fn draw_quadratic_bezier(
+        &mut self,
+        from: Point<f32>,
+        ctrl: Point<f32>,
+        to: Point<f32>,
+    ) -> crate::Result {
+        join(
+            self.first.draw_quadratic_bezier(from, ctrl, to),
+            self.second.draw_quadratic_bezier(from, ctrl, to),
+        )
+    }
+
+    #[inline]
+    fn draw_cubic_bezier(
+        &mut self,
+        from: Point<f32>,
+        ctrl1: Point<f32>,
+        ctrl2: Point<f32>,
+        to: Point<f32>,
+    ) -> crate::Result {
+        join(
+            self.first.draw_cubic_bezier(from, ctrl1, ctrl2, to),
+            self.second.draw_cubic_bezier(from, ctrl1, ctrl2, to),
+        )
+    }
+
+    #[inline]
+    fn draw_bezier_curves(&mut self, curves: &[CubicBezierSegment<f32>]) -> crate::Result {
+        join(
+            self.first.draw_bezier_curves(curves),
+            self.second.draw_bezier_curves(curves),
+        )
+    }
+
+    #[inline]
+    fn draw_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> crate::Result {
+        join(
+            self.first.draw_rectangle(x, y, width, height),
+            self.second.draw_rectangle(x, y, width, height),
+        )
+    }
+
+    #[inline]
+    fn draw_rectangles(&mut self, rects: &[Rect<f32>]) -> crate::Result {
+        join(
+            self.first.draw_rectangles(rects),
+            self.second.draw_rectangles(rects),
+        )
+    }
+
+    #[inline]
+    fn draw_round_rectangle(&mut self, rect: Rect<f32>, rx: f32, ry: f32) -> crate::Result {
+        join(
+            self.first.draw_round_rectangle(rect, rx, ry),
+            self.second.draw_round_rectangle(rect, rx, ry),
+        )
+    }
+
+    #[inline]
+    fn draw_arc(
+        &mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+        start_angle: Angle<f32>,
+        sweep_angle: Angle<f32>,
+    ) -> crate::Result {
+        join(
+            self.first
+                .draw_arc(xcenter, ycenter, xradius, yradius, start_angle, sweep_angle),
+            self.second
+                .draw_arc(xcenter, ycenter, xradius, yradius, start_angle, sweep_angle),
+        )
+    }
+
+    #[inline]
+    fn draw_arcs(&mut self, arcs: &[Arc<f32>]) -> crate::Result {
+        join(self.first.draw_arcs(arcs), self.second.draw_arcs(arcs))
+    }
+
+    #[inline]
+    fn draw_ellipse(
+        &mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+    ) -> crate::Result {
+        join(
+            self.first.draw_ellipse(xcenter, ycenter, xradius, yradius),
+            self.second.draw_ellipse(xcenter, ycenter, xradius, yradius),
+        )
+    }
+
+    #[inline]
+    fn draw_circle(&mut self, center: Point<f32>, radius: f32) -> crate::Result {
+        join(
+            self.first.draw_circle(center, radius),
+            self.second.draw_circle(center, radius),
+        )
+    }
+
+    #[inline]
+    fn draw_ellipses(&mut self, rects: &[Ellipse]) -> crate::Result {
+        join(
+            self.first.draw_ellipses(rects),
+            self.second.draw_ellipses(rects),
+        )
+    }
+
+    #[inline]
+    fn fill_polygon(&mut self, points: &[Point<f32>]) -> crate::Result {
+        join(
+            self.first.fill_polygon(points),
+            self.second.fill_polygon(points),
+        )
+    }
+
+    #[inline]
+    fn fill_polygons(&mut self, polys: &[&[Point<f32>]]) -> crate::Result {
+        join(
+            self.first.fill_polygons(polys),
+            self.second.fill_polygons(polys),
+        )
+    }
+
+    #[inline]
+    fn fill_path_owned(&mut self, path: Path) -> crate::Result {
+        join(
+            self.first.fill_path(path.as_slice()),
+            self.second.fill_path_owned(path),
+        )
+    }
+
+    #[inline]
+    fn fill_path(&mut self, path: PathSlice<'_>) -> crate::Result {
+        join(self.first.fill_path(path), self.second.fill_path(path))
+    }
+
+    #[inline]
+    fn fill_paths_owned(&mut self, paths: PathBuffer) -> crate::Result {
+        join(
+            self.first.fill_paths(paths.as_slice()),
+            self.second.fill_paths_owned(paths),
+        )
+    }
+
+    #[inline]
+    fn fill_paths(&mut self, paths: PathBufferSlice<'_>) -> crate::Result {
+        join(
+            self.first.fill_paths(paths.clone()),
+            self.second.fill_paths(paths),
+        )
+    }
+
+    #[inline]
+    fn fill_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> crate::Result {
+        join(
+            self.first.fill_rectangle(x, y, width, height),
+            self.second.fill_rectangle(x, y, width, height),
+        )
+    }
+
+    #[inline]
+    fn fill_rectangles(&mut self, rects: &[Rect<f32>]) -> crate::Result {
+        join(
+            self.first.fill_rectangles(rects),
+            self.second.fill_rectangles(rects),
+        )
+    }
+
+    #[inline]
+    fn fill_round_rectangle(&mut self, rect: Rect<f32>, rx: f32, ry: f32) -> crate::Result {
+        join(
+            self.first.fill_round_rectangle(rect, rx, ry),
+            self.second.fill_round_rectangle(rect, rx, ry),
+        )
+    }
+
+    #[inline]
+    fn fill_arc(
+        &mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+        start_angle: Angle<f32>,
+        sweep_angle: Angle<f32>,
+    ) -> crate::Result {
+        join(
+            self.first
+                .fill_arc(xcenter, ycenter, xradius, yradius, start_angle, sweep_angle),
+            self.second
+                .fill_arc(xcenter, ycenter, xradius, yradius, start_angle, sweep_angle),
+        )
+    }
+
+    #[inline]
+    fn fill_pie(
+        &mut self,
+        center: Point<f32>,
+        radii: Vector<f32>,
+        start_angle: Angle<f32>,
+        sweep_angle: Angle<f32>,
+    ) -> crate::Result {
+        join(
+            self.first.fill_pie(center, radii, start_angle, sweep_angle),
+            self.second
+                .fill_pie(center, radii, start_angle, sweep_angle),
+        )
+    }
+
+    #[inline]
+    fn fill_arcs(&mut self, arcs: &[Arc<f32>]) -> crate::Result {
+        join(self.first.fill_arcs(arcs), self.second.fill_arcs(arcs))
+    }
+
+    #[inline]
+    fn fill_ellipse(
+        &mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+    ) -> crate::Result {
+        join(
+            self.first.fill_ellipse(xcenter, ycenter, xradius, yradius),
+            self.second.fill_ellipse(xcenter, ycenter, xradius, yradius),
+        )
+    }
+
+    #[inline]
+    fn fill_circle(&mut self, center: Point<f32>, radius: f32) -> crate::Result {
+        join(
+            self.first.fill_circle(center, radius),
+            self.second.fill_circle(center, radius),
+        )
+    }
+
+    #[inline]
+    fn fill_ellipses(&mut self, rects: &[Ellipse]) -> crate::Result {
+        join(
+            self.first.fill_ellipses(rects),
+            self.second.fill_ellipses(rects),
+        )
+    }
+
+    #[inline]
+    fn paint_rectangle(
+        &mut self,
+        rect: Rect<f32>,
+        fill: FillRule,
+        stroke: Color,
+        width: usize,
+    ) -> crate::Result {
+        let second = self.second_fill_rule(&fill);
+        join(
+            self.first.paint_rectangle(rect, fill, stroke, width),
+            second.and_then(|fill| self.second.paint_rectangle(rect, fill, stroke, width)),
+        )
+    }
+
+    #[inline]
+    fn paint_round_rectangle(
+        &mut self,
+        rect: Rect<f32>,
+        rx: f32,
+        ry: f32,
+        fill: FillRule,
+        stroke: Color,
+        width: usize,
+    ) -> crate::Result {
+        let second = self.second_fill_rule(&fill);
+        join(
+            self.first
+                .paint_round_rectangle(rect, rx, ry, fill, stroke, width),
+            second.and_then(|fill| {
+                self.second
+                    .paint_round_rectangle(rect, rx, ry, fill, stroke, width)
+            }),
+        )
+    }
+
+    #[inline]
+    fn paint_polygon(
+        &mut self,
+        points: &[Point<f32>],
+        fill: FillRule,
+        stroke: Color,
+        width: usize,
+    ) -> crate::Result {
+        let second = self.second_fill_rule(&fill);
+        join(
+            self.first.paint_polygon(points, fill, stroke, width),
+            second.and_then(|fill| self.second.paint_polygon(points, fill, stroke, width)),
+        )
+    }
+
+    #[inline]
+    fn paint_ellipse(
+        &mut self,
+        ellipse: Ellipse,
+        fill: FillRule,
+        stroke: Color,
+        width: usize,
+    ) -> crate::Result {
+        let second = self.second_fill_rule(&fill);
+        join(
+            self.first.paint_ellipse(ellipse, fill, stroke, width),
+            second.and_then(|fill| self.second.paint_ellipse(ellipse, fill, stroke, width)),
+        )
+    }
+
+    #[inline]
+    fn paint_path(
+        &mut self,
+        path: PathSlice<'_>,
+        fill: FillRule,
+        stroke: Color,
+        width: usize,
+    ) -> crate::Result {
+        let second = self.second_fill_rule(&fill);
+        join(
+            self.first.paint_path(path, fill, stroke, width),
+            second.and_then(|fill| self.second.paint_path(path, fill, stroke, width)),
+        )
+    }
+
+    #[inline]
+    fn copy_image(
+        &mut self,
+        src: Image,
+        src_x: i32,
+        src_y: i32,
+        dst_x: i32,
+        dst_y: i32,
+        width: u32,
+        height: u32,
+    ) -> crate::Result {
+        let second = self.second_image(&src);
+        join(
+            self.first
+                .copy_image(src, src_x, src_y, dst_x, dst_y, width, height),
+            second.and_then(|src| {
+                self.second
+                    .copy_image(src, src_x, src_y, dst_x, dst_y, width, height)
+            }),
+        )
+    }
+
+    #[inline]
+    fn draw_image(&mut self, image: &Image, dst: Rect<f32>) -> crate::Result {
+        let second = self.second_image(image);
+        join(
+            self.first.draw_image(image, dst),
+            second.and_then(|image| self.second.draw_image(&image, dst)),
+        )
+    }
+
+    #[inline]
+    fn draw_image_region(
+        &mut self,
+        image: &Image,
+        src: Rect<f32>,
+        dst: Rect<f32>,
+    ) -> crate::Result {
+        let second = self.second_image(image);
+        join(
+            self.first.draw_image_region(image, src, dst),
+            second.and_then(|image| self.second.draw_image_region(&image, src, dst)),
+        )
+    }
+
+    #[inline]
+    fn draw_image_masked(&mut self, image: &Image, mask: &Image, dst: Rect<f32>) -> crate::Result {
+        let second = self
+            .second_image(image)
+            .and_then(|image| Ok((image, self.second_image(mask)?)));
+        join(
+            self.first.draw_image_masked(image, mask, dst),
+            second.and_then(|(image, mask)| self.second.draw_image_masked(&image, &mask, dst)),
+        )
+    }
+
+    #[inline]
+    fn fill_linear_gradient(
+        &mut self,
+        gradient: &LinearGradient,
+        rect: Rect<f32>,
+    ) -> crate::Result {
+        join(
+            self.first.fill_linear_gradient(gradient, rect),
+            self.second.fill_linear_gradient(gradient, rect),
+        )
+    }
+
+    #[inline]
+    fn fill_conic_gradient(&mut self, gradient: &ConicGradient, rect: Rect<f32>) -> crate::Result {
+        join(
+            self.first.fill_conic_gradient(gradient, rect),
+            self.second.fill_conic_gradient(gradient, rect),
+        )
+    }
+
+    #[inline]
+    fn fill_radial_gradient(
+        &mut self,
+        gradient: &RadialGradient,
+        rect: Rect<f32>,
+    ) -> crate::Result {
+        join(
+            self.first.fill_radial_gradient(gradient, rect),
+            self.second.fill_radial_gradient(gradient, rect),
+        )
+    }
+
+    #[inline]
+    fn copy_images(&mut self, images: &[ImageCopySpecs]) -> crate::Result {
+        let second = images
+            .iter()
+            .map(|specs| {
+                Ok(ImageCopySpecs {
+                    image: self.second_image(&specs.image)?,
+                    ..*specs
+                })
+            })
+            .collect::<crate::Result<Vec<ImageCopySpecs>>>();
+        join(
+            self.first.copy_images(images),
+            second.and_then(|images| self.second.copy_images(&images)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color::consts::GREEN, surface::NullSurface};
+    use std::mem;
+
+    #[test]
+    fn both_surfaces_see_the_same_operations() {
+        let mut surface = TeeSurface::new(NullSurface::recording(), NullSurface::recording());
+        surface.set_stroke(GREEN).unwrap();
+        surface
+            .push_clip(Rect::new(Point::new(0.0, 0.0), Size::new(8.0, 8.0)))
+            .unwrap();
+        surface.draw_line(0.0, 0.0, 8.0, 8.0).unwrap();
+        surface.fill_rectangle(1.0, 1.0, 2.0, 2.0).unwrap();
+        surface.pop_clip().unwrap();
+        let image = surface
+            .create_image(&[0; 16], 2, 2, ImageFormat::Rgba)
+            .unwrap();
+        surface.copy_image(image, 0, 0, 1, 1, 2, 2).unwrap();
+        surface.destroy_image(image).unwrap();
+        surface.flush().unwrap();
+
+        let (first, second) = surface.into_inner();
+        assert_eq!(first.count(), 9);
+        assert_eq!(first.count(), second.count());
+        first
+            .operations()
+            .iter()
+            .zip(second.operations())
+            .for_each(|(a, b)| assert_eq!(mem::discriminant(a), mem::discriminant(b)));
+    }
+}
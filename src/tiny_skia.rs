@@ -0,0 +1,901 @@
+// MIT/Apache2 License
+
+//! A software renderer built on top of `tiny-skia`. Unlike `ImageSurface`, this antialiases everything it
+//! draws, which makes it a good reference renderer for headless use.
+
+use crate::{
+    fill::{FillMode, FillRule, TileMode},
+    image::{check_image_region, check_len, to_rgba},
+    stroke::{LineCap, LineJoin},
+    surface::{
+        read_intensity_with, FallbackPolicy, Surface, SurfaceFeatures, DEFAULT_MITER_LIMIT,
+        DEFAULT_TOLERANCE, MIN_TOLERANCE,
+    },
+    transform::Transform,
+    Color, Image, ImageFormat, Intensity,
+};
+use lyon_geom::{Angle, LineSegment, Point, Rect, Size, Vector};
+use lyon_path::{Path, PathEvent, PathSlice};
+use std::{collections::HashMap, num::NonZeroUsize};
+use tiny_skia::{
+    ClipMask, FillRule as SkFillRule, FilterQuality, GradientStop, LineCap as SkLineCap,
+    LineJoin as SkLineJoin, LinearGradient, Paint, Path as SkPath, PathBuilder, Pattern, Pixmap,
+    RadialGradient, Rect as SkRect, Shader, SpreadMode, Stroke, StrokeDash,
+};
+
+const FEATURES: SurfaceFeatures = SurfaceFeatures {
+    transparency: true,
+    gradients: true,
+    floats: true,
+    clipping: true,
+    transforms: true,
+    paths: true,
+    text: false,
+    images: true,
+    patterns: true,
+    size: true,
+    antialiasing: true,
+    dirty_tracking: false,
+};
+
+/// A surface that draws onto a `tiny_skia::Pixmap`.
+#[derive(Debug)]
+pub struct TinySkiaSurface {
+    pixmap: Pixmap,
+    state: State,
+    saved: Vec<State>,
+    images: HashMap<Image, Pixmap>,
+    next_image: usize,
+    tolerance: f32,
+    fallback: FallbackPolicy,
+}
+
+/// Drawing state that is saved by `save()`.
+#[derive(Debug, Clone)]
+struct State {
+    stroke: Color,
+    fill: FillRule,
+    fill_mode: FillMode,
+    alpha: f32,
+    line_width: usize,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    miter_limit: f32,
+    dash: Vec<f32>,
+    dash_offset: f32,
+    antialias: bool,
+    // `None` means that the clipping rectangles don't overlap, so nothing is drawn
+    clips: Vec<Option<ClipMask>>,
+    transform: Transform,
+}
+
+impl TinySkiaSurface {
+    /// Create a new `TinySkiaSurface` of the given size, filled with transparent pixels.
+    #[inline]
+    pub fn new(width: u32, height: u32) -> crate::Result<TinySkiaSurface> {
+        let pixmap = Pixmap::new(width, height).ok_or(crate::Error::StaticMsg(
+            "Surface cannot have a size of zero",
+        ))?;
+        Ok(Self::from_pixmap(pixmap))
+    }
+
+    /// Create a new `TinySkiaSurface` that draws on top of an existing pixmap.
+    #[inline]
+    pub fn from_pixmap(pixmap: Pixmap) -> TinySkiaSurface {
+        TinySkiaSurface {
+            pixmap,
+            state: State {
+                stroke: Color::BLACK,
+                fill: FillRule::SolidColor(Color::BLACK),
+                fill_mode: FillMode::default(),
+                alpha: 1.0,
+                line_width: 1,
+                line_cap: LineCap::default(),
+                line_join: LineJoin::default(),
+                miter_limit: DEFAULT_MITER_LIMIT,
+                dash: vec![],
+                dash_offset: 0.0,
+                antialias: true,
+                clips: vec![],
+                transform: Transform::identity(),
+            },
+            saved: vec![],
+            images: HashMap::new(),
+            next_image: 1,
+            tolerance: DEFAULT_TOLERANCE,
+            fallback: FallbackPolicy::default(),
+        }
+    }
+
+    /// The pixmap that this surface draws onto.
+    #[inline]
+    pub fn pixmap(&self) -> &Pixmap {
+        &self.pixmap
+    }
+
+    /// Get the pixmap that this surface has drawn onto.
+    #[inline]
+    pub fn into_pixmap(self) -> Pixmap {
+        self.pixmap
+    }
+
+    /// Get the color of the pixel at the given position, if it lies within the surface.
+    #[inline]
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Color> {
+        let pixel = self.pixmap.pixel(x, y)?.demultiply();
+        Color::new(
+            pixel.red() as f32 / 255.0,
+            pixel.green() as f32 / 255.0,
+            pixel.blue() as f32 / 255.0,
+            pixel.alpha() as f32 / 255.0,
+        )
+    }
+
+    /// Fill a path with the current fill rule.
+    #[inline]
+    fn fill_sk_path(&mut self, path: &SkPath) -> crate::Result {
+        let clip = match active_clip(&self.state.clips) {
+            Some(clip) => clip,
+            None => return Ok(()),
+        };
+
+        let bounds = path.bounds();
+        let alpha = self.state.alpha;
+        let raster;
+        let shader = match self.state.fill {
+            FillRule::SolidColor(color) => Shader::SolidColor(sk_color(color.scale_alpha(alpha))),
+            FillRule::LinearGradient(ref gradient, angle) => {
+                let bounds = lyon_rect(bounds);
+                let direction = Vector::new(angle.radians.cos(), angle.radians.sin());
+                let half = (direction.x.abs() * bounds.size.width
+                    + direction.y.abs() * bounds.size.height)
+                    / 2.0;
+                let center = bounds.center();
+                match LinearGradient::new(
+                    sk_point(center - direction * half),
+                    sk_point(center + direction * half),
+                    gradient_stops(&gradient.srgb_stops(), alpha),
+                    SpreadMode::Pad,
+                    tiny_skia::Transform::identity(),
+                ) {
+                    Some(shader) => shader,
+                    None => return Ok(()),
+                }
+            }
+            FillRule::RadialGradient(ref gradient) => {
+                let bounds = lyon_rect(bounds);
+                let center = sk_point(bounds.center());
+                match RadialGradient::new(
+                    center,
+                    center,
+                    bounds.size.width.max(bounds.size.height) / 2.0,
+                    gradient_stops(&gradient.srgb_stops(), alpha),
+                    SpreadMode::Pad,
+                    tiny_skia::Transform::identity(),
+                ) {
+                    Some(shader) => shader,
+                    None => return Ok(()),
+                }
+            }
+            // tiny-skia can't draw conic gradients, so they are rendered into a pixmap and used as a pattern
+            FillRule::ConicalGradient(_) => {
+                raster = rasterize(&self.state.fill, bounds)?;
+                Pattern::new(
+                    raster.as_ref(),
+                    SpreadMode::Pad,
+                    FilterQuality::Nearest,
+                    alpha,
+                    sk_transform(Transform::translation(
+                        bounds.left().floor(),
+                        bounds.top().floor(),
+                    )),
+                )
+            }
+            FillRule::Pattern(ref pattern) => {
+                let image = self
+                    .images
+                    .get(&pattern.image)
+                    .ok_or(crate::Error::ImageNotAvailable)?;
+                Pattern::new(
+                    image.as_ref(),
+                    spread_mode(pattern.tiling),
+                    FilterQuality::Nearest,
+                    alpha,
+                    sk_transform(pattern.transform.unwrap_or_default()),
+                )
+            }
+        };
+
+        let paint = Paint {
+            shader,
+            anti_alias: self.state.antialias,
+            ..Paint::default()
+        };
+        let fill_rule = match self.state.fill_mode {
+            FillMode::EvenOdd => SkFillRule::EvenOdd,
+            FillMode::NonZero => SkFillRule::Winding,
+        };
+
+        // tiny-skia refuses to draw shapes that end up outside of the pixmap, which isn't an error for us
+        let _ = self.pixmap.fill_path(
+            path,
+            &paint,
+            fill_rule,
+            sk_transform(self.state.transform),
+            clip,
+        );
+        Ok(())
+    }
+
+    /// Stroke a path with the current stroke color and line style.
+    #[inline]
+    fn stroke_sk_path(&mut self, path: &SkPath) -> crate::Result {
+        let clip = match active_clip(&self.state.clips) {
+            Some(clip) => clip,
+            None => return Ok(()),
+        };
+
+        let mut paint = Paint {
+            anti_alias: self.state.antialias,
+            ..Paint::default()
+        };
+        paint.set_color(sk_color(self.state.stroke.scale_alpha(self.state.alpha)));
+
+        // tiny-skia needs an even number of dashes
+        let dash = if self.state.dash.is_empty() {
+            None
+        } else {
+            let mut dash: Vec<f32> = self.state.dash.iter().copied().map(f32::abs).collect();
+            if dash.len() % 2 != 0 {
+                dash.extend_from_within(..);
+            }
+            StrokeDash::new(dash, self.state.dash_offset)
+        };
+
+        let stroke = Stroke {
+            width: self.state.line_width as f32,
+            line_cap: match self.state.line_cap {
+                LineCap::Butt => SkLineCap::Butt,
+                LineCap::Round => SkLineCap::Round,
+                LineCap::Square => SkLineCap::Square,
+            },
+            line_join: match self.state.line_join {
+                LineJoin::Miter => SkLineJoin::Miter,
+                LineJoin::Round => SkLineJoin::Round,
+                LineJoin::Bevel => SkLineJoin::Bevel,
+            },
+            miter_limit: self.state.miter_limit,
+            dash,
+        };
+
+        let _ = self.pixmap.stroke_path(
+            path,
+            &paint,
+            &stroke,
+            sk_transform(self.state.transform),
+            clip,
+        );
+        Ok(())
+    }
+}
+
+impl Surface for TinySkiaSurface {
+    #[inline]
+    fn features(&self) -> SurfaceFeatures {
+        FEATURES
+    }
+
+    #[inline]
+    fn set_stroke(&mut self, color: Color) -> crate::Result {
+        self.state.stroke = color;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_fill(&mut self, rule: FillRule) -> crate::Result {
+        // tiny-skia has no conic gradients, so they are rasterized in software
+        if let (FillRule::ConicalGradient(_), FallbackPolicy::Reject) = (&rule, self.fallback) {
+            return Err(crate::Error::NotSupported(crate::NSOpType::Gradients));
+        }
+
+        self.state.fill = rule;
+        Ok(())
+    }
+
+    #[inline]
+    fn fill_rule(&self) -> Option<&FillRule> {
+        Some(&self.state.fill)
+    }
+
+    #[inline]
+    fn set_fill_mode(&mut self, mode: FillMode) -> crate::Result {
+        self.state.fill_mode = mode;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_line_width(&mut self, width: usize) -> crate::Result {
+        self.state.line_width = width;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_line_cap(&mut self, cap: LineCap) -> crate::Result {
+        self.state.line_cap = cap;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_line_join(&mut self, join: LineJoin) -> crate::Result {
+        self.state.line_join = join;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_miter_limit(&mut self, limit: f32) -> crate::Result {
+        self.state.miter_limit = limit.max(1.0);
+        Ok(())
+    }
+
+    #[inline]
+    fn set_dash(&mut self, pattern: &[f32], offset: f32) -> crate::Result {
+        self.state.dash = pattern.to_vec();
+        self.state.dash_offset = offset;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_antialias(&mut self, on: bool) -> crate::Result {
+        self.state.antialias = on;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_global_alpha(&mut self, alpha: f32) -> crate::Result {
+        self.state.alpha = alpha.clamp(0.0, 1.0);
+        Ok(())
+    }
+
+    #[inline]
+    fn push_clip(&mut self, rect: Rect<f32>) -> crate::Result {
+        let path = sk_rect(rect)
+            .map(PathBuilder::from_rect)
+            .and_then(|path| path.transform(sk_transform(self.state.transform)));
+
+        let clip = match (path, self.state.clips.last()) {
+            (None, _) | (_, Some(None)) => None,
+            (Some(path), Some(Some(mask))) => {
+                let mut mask = mask.clone();
+                mask.intersect_path(&path, SkFillRule::Winding, true)
+                    .map(|()| mask)
+            }
+            (Some(path), None) => {
+                let mut mask = ClipMask::new();
+                mask.set_path(
+                    self.pixmap.width(),
+                    self.pixmap.height(),
+                    &path,
+                    SkFillRule::Winding,
+                    true,
+                )
+                .map(|()| mask)
+            }
+        };
+
+        self.state.clips.push(clip);
+        Ok(())
+    }
+
+    #[inline]
+    fn pop_clip(&mut self) -> crate::Result {
+        self.state.clips.pop().ok_or(crate::Error::NoClip)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_transform(&mut self, transform: Transform) -> crate::Result {
+        self.state.transform = transform;
+        Ok(())
+    }
+
+    #[inline]
+    fn transform(&mut self, transform: Transform) -> crate::Result {
+        self.state.transform = transform.then(&self.state.transform);
+        Ok(())
+    }
+
+    #[inline]
+    fn save(&mut self) -> crate::Result {
+        self.saved.push(self.state.clone());
+        Ok(())
+    }
+
+    #[inline]
+    fn restore(&mut self) -> crate::Result {
+        self.state = self
+            .saved
+            .pop()
+            .ok_or(crate::Error::StaticMsg("No saved state to restore"))?;
+        Ok(())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> crate::Result {
+        Ok(())
+    }
+
+    #[inline]
+    fn tolerance(&self) -> f32 {
+        self.tolerance
+    }
+
+    #[inline]
+    fn set_tolerance(&mut self, tolerance: f32) -> crate::Result {
+        self.tolerance = tolerance.max(MIN_TOLERANCE);
+        Ok(())
+    }
+
+    #[inline]
+    fn set_fallback_policy(&mut self, policy: FallbackPolicy) -> crate::Result {
+        self.fallback = policy;
+        Ok(())
+    }
+
+    #[inline]
+    fn size(&self) -> crate::Result<Size<f32>> {
+        Ok(Size::new(
+            self.pixmap.width() as f32,
+            self.pixmap.height() as f32,
+        ))
+    }
+
+    #[inline]
+    fn read_intensity(&self, rect: Rect<f32>) -> crate::Result<Vec<Intensity>> {
+        read_intensity_with(rect, self.size()?, |x, y| self.pixel(x, y))
+    }
+
+    #[inline]
+    fn create_image(
+        &mut self,
+        image_bytes: &[u8],
+        width: u32,
+        height: u32,
+        image_format: ImageFormat,
+    ) -> crate::Result<Image> {
+        check_len(image_bytes, width, height, image_format)?;
+        let rgba = to_rgba(image_bytes, width, height, image_format);
+        let pixmap = pixmap_from_rgba(&rgba, width, height)?;
+
+        let image =
+            Image::from_raw(NonZeroUsize::new(self.next_image).expect("Image ID overflowed"));
+        self.next_image += 1;
+        self.images.insert(image, pixmap);
+        Ok(image)
+    }
+
+    #[inline]
+    fn destroy_image(&mut self, image: Image) -> crate::Result {
+        self.images.remove(&image);
+        Ok(())
+    }
+
+    #[inline]
+    fn draw_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> crate::Result {
+        self.draw_lines(&[LineSegment {
+            from: Point::new(x1, y1),
+            to: Point::new(x2, y2),
+        }])
+    }
+
+    #[inline]
+    fn draw_lines(&mut self, lines: &[LineSegment<f32>]) -> crate::Result {
+        let mut builder = PathBuilder::new();
+        lines.iter().for_each(|line| {
+            builder.move_to(line.from.x, line.from.y);
+            builder.line_to(line.to.x, line.to.y);
+        });
+
+        match builder.finish() {
+            Some(path) => self.stroke_sk_path(&path),
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn draw_polyline(&mut self, pts: &[Point<f32>]) -> crate::Result {
+        match polygon_path(pts, false) {
+            Some(path) => self.stroke_sk_path(&path),
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn draw_path(&mut self, path: PathSlice<'_>) -> crate::Result {
+        match sk_path(path.iter()) {
+            Some(path) => self.stroke_sk_path(&path),
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn draw_path_owned(&mut self, path: Path) -> crate::Result {
+        self.draw_path(path.as_slice())
+    }
+
+    #[inline]
+    fn fill_polygon(&mut self, points: &[Point<f32>]) -> crate::Result {
+        match polygon_path(points, true) {
+            Some(path) => self.fill_sk_path(&path),
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn fill_polygons(&mut self, polys: &[&[Point<f32>]]) -> crate::Result {
+        // filling every polygon at once lets them cut holes in each other
+        let mut builder = PathBuilder::new();
+        polys
+            .iter()
+            .filter(|points| points.len() > 2)
+            .for_each(|points| {
+                builder.move_to(points[0].x, points[0].y);
+                points[1..]
+                    .iter()
+                    .for_each(|point| builder.line_to(point.x, point.y));
+                builder.close();
+            });
+
+        match builder.finish() {
+            Some(path) => self.fill_sk_path(&path),
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn fill_path(&mut self, path: PathSlice<'_>) -> crate::Result {
+        match sk_path(path.iter()) {
+            Some(path) => self.fill_sk_path(&path),
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn fill_path_owned(&mut self, path: Path) -> crate::Result {
+        self.fill_path(path.as_slice())
+    }
+
+    #[inline]
+    fn fill_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> crate::Result {
+        match SkRect::from_xywh(x, y, width, height) {
+            Some(rect) => self.fill_sk_path(&PathBuilder::from_rect(rect)),
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn fill_ellipse(
+        &mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+    ) -> crate::Result {
+        // circles are drawn with real curves instead of being flattened
+        if xradius != yradius {
+            return self.fill_arc(
+                xcenter,
+                ycenter,
+                xradius,
+                yradius,
+                Angle { radians: 0.0 },
+                Angle {
+                    radians: 2.0 * std::f32::consts::PI,
+                },
+            );
+        }
+
+        match PathBuilder::from_circle(xcenter, ycenter, xradius) {
+            Some(path) => self.fill_sk_path(&path),
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn copy_image(
+        &mut self,
+        src: Image,
+        src_x: i32,
+        src_y: i32,
+        dst_x: i32,
+        dst_y: i32,
+        width: u32,
+        height: u32,
+    ) -> crate::Result {
+        let size = Size::new(width as f32, height as f32);
+        self.draw_image_region(
+            &src,
+            Rect::new(Point::new(src_x as f32, src_y as f32), size),
+            Rect::new(Point::new(dst_x as f32, dst_y as f32), size),
+        )
+    }
+
+    #[inline]
+    fn draw_image(&mut self, image: &Image, dst: Rect<f32>) -> crate::Result {
+        let pixmap = self
+            .images
+            .get(image)
+            .ok_or(crate::Error::ImageNotAvailable)?;
+        let size = Size::new(pixmap.width() as f32, pixmap.height() as f32);
+        self.draw_image_region(image, Rect::new(Point::origin(), size), dst)
+    }
+
+    #[inline]
+    fn draw_image_region(
+        &mut self,
+        image: &Image,
+        src: Rect<f32>,
+        dst: Rect<f32>,
+    ) -> crate::Result {
+        let pixmap = self
+            .images
+            .get(image)
+            .ok_or(crate::Error::ImageNotAvailable)?;
+        check_image_region(
+            src,
+            Size::new(pixmap.width() as f32, pixmap.height() as f32),
+        )?;
+
+        let clip = match active_clip(&self.state.clips) {
+            Some(clip) => clip,
+            None => return Ok(()),
+        };
+        let (rect, scale) = match sk_rect(dst) {
+            Some(rect) if !src.is_empty() => (
+                rect,
+                Vector::new(
+                    dst.size.width / src.size.width,
+                    dst.size.height / src.size.height,
+                ),
+            ),
+            _ => return Ok(()),
+        };
+
+        // the image is used as a pattern that maps the source region onto the destination rectangle
+        let pattern_transform = Transform::translation(-src.origin.x, -src.origin.y)
+            .then(&Transform::scale(scale.x, scale.y))
+            .then(&Transform::translation(dst.origin.x, dst.origin.y));
+        let paint = Paint {
+            shader: Pattern::new(
+                pixmap.as_ref(),
+                SpreadMode::Pad,
+                FilterQuality::Bilinear,
+                self.state.alpha,
+                sk_transform(pattern_transform),
+            ),
+            anti_alias: self.state.antialias,
+            ..Paint::default()
+        };
+
+        let _ = self
+            .pixmap
+            .fill_rect(rect, &paint, sk_transform(self.state.transform), clip);
+        Ok(())
+    }
+}
+
+/// Get the mask to clip drawing with, or `None` if everything is clipped out.
+#[inline]
+fn active_clip(clips: &[Option<ClipMask>]) -> Option<Option<&ClipMask>> {
+    match clips.last() {
+        Some(Some(mask)) => Some(Some(mask)),
+        Some(None) => None,
+        None => Some(None),
+    }
+}
+
+/// Render a fill rule over the pixels covered by a rectangle into a pixmap.
+#[inline]
+fn rasterize(rule: &FillRule, bounds: SkRect) -> crate::Result<Pixmap> {
+    let rect = lyon_rect(bounds).round_out();
+    let pixels = rule
+        .rasterize(rect)
+        .ok_or(crate::Error::NotSupported(crate::NSOpType::Gradients))?;
+    pixmap_from_rgba(&pixels, rect.size.width as u32, rect.size.height as u32)
+}
+
+/// Create a pixmap out of straight-alpha RGBA pixels. Pixmaps hold premultiplied pixels.
+#[inline]
+fn pixmap_from_rgba(rgba: &[u8], width: u32, height: u32) -> crate::Result<Pixmap> {
+    let mut pixmap = Pixmap::new(width, height).ok_or(crate::Error::ImageNotAvailable)?;
+    if rgba.len() != pixmap.data().len() {
+        return Err(crate::Error::StaticMsg(
+            "Buffer length does not match the image's dimensions",
+        ));
+    }
+
+    pixmap
+        .data_mut()
+        .chunks_exact_mut(4)
+        .zip(rgba.chunks_exact(4))
+        .for_each(|(dst, src)| {
+            let a = src[3] as u16;
+            (0..3).for_each(|i| dst[i] = ((src[i] as u16 * a + 127) / 255) as u8);
+            dst[3] = src[3];
+        });
+    Ok(pixmap)
+}
+
+/// Convert a lyon path into a tiny-skia path. Returns `None` if the path is empty.
+#[inline]
+fn sk_path(events: impl IntoIterator<Item = PathEvent>) -> Option<SkPath> {
+    let mut builder = PathBuilder::new();
+    events.into_iter().for_each(|event| match event {
+        PathEvent::Begin { at } => builder.move_to(at.x, at.y),
+        PathEvent::Line { to, .. } => builder.line_to(to.x, to.y),
+        PathEvent::Quadratic { ctrl, to, .. } => builder.quad_to(ctrl.x, ctrl.y, to.x, to.y),
+        PathEvent::Cubic {
+            ctrl1, ctrl2, to, ..
+        } => builder.cubic_to(ctrl1.x, ctrl1.y, ctrl2.x, ctrl2.y, to.x, to.y),
+        PathEvent::End { close: true, .. } => builder.close(),
+        PathEvent::End { .. } => {}
+    });
+    builder.finish()
+}
+
+/// Create a path running through a series of points.
+#[inline]
+fn polygon_path(points: &[Point<f32>], close: bool) -> Option<SkPath> {
+    let (first, rest) = points.split_first()?;
+    let mut builder = PathBuilder::new();
+    builder.move_to(first.x, first.y);
+    rest.iter()
+        .for_each(|point| builder.line_to(point.x, point.y));
+    if close {
+        builder.close();
+    }
+    builder.finish()
+}
+
+#[inline]
+fn gradient_stops(stops: &[crate::gradient::ColorStop], alpha: f32) -> Vec<GradientStop> {
+    stops
+        .iter()
+        .map(|stop| {
+            GradientStop::new(
+                stop.position.into_inner(),
+                sk_color(stop.color.scale_alpha(alpha)),
+            )
+        })
+        .collect()
+}
+
+#[inline]
+fn spread_mode(tiling: TileMode) -> SpreadMode {
+    match tiling {
+        TileMode::Tile => SpreadMode::Repeat,
+        TileMode::Clamp => SpreadMode::Pad,
+        TileMode::Mirror => SpreadMode::Reflect,
+    }
+}
+
+#[inline]
+fn sk_color(color: Color) -> tiny_skia::Color {
+    tiny_skia::Color::from_rgba(color.red(), color.green(), color.blue(), color.alpha())
+        .expect("Color elements are always between zero and one")
+}
+
+#[inline]
+fn sk_point(point: Point<f32>) -> tiny_skia::Point {
+    tiny_skia::Point::from_xy(point.x, point.y)
+}
+
+#[inline]
+fn sk_rect(rect: Rect<f32>) -> Option<SkRect> {
+    SkRect::from_xywh(
+        rect.origin.x,
+        rect.origin.y,
+        rect.size.width,
+        rect.size.height,
+    )
+}
+
+#[inline]
+fn lyon_rect(rect: SkRect) -> Rect<f32> {
+    Rect::new(
+        Point::new(rect.left(), rect.top()),
+        Size::new(rect.width(), rect.height()),
+    )
+}
+
+#[inline]
+fn sk_transform(transform: Transform) -> tiny_skia::Transform {
+    let m = transform.into_inner();
+    tiny_skia::Transform::from_row(m.m11, m.m12, m.m21, m.m22, m.m31, m.m32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        color::consts::{BLUE, RED},
+        gradient::{ColorStop, Gradient},
+    };
+
+    #[test]
+    fn filled_circle_coverage() {
+        let mut surface = TinySkiaSurface::new(20, 20).unwrap();
+        surface.set_fill_color(RED).unwrap();
+        surface.fill_circle(Point::new(10.0, 10.0), 5.0).unwrap();
+
+        // with antialiasing, the partially covered pixels add up to the area of the circle
+        let coverage: f32 = surface
+            .pixmap()
+            .data()
+            .chunks_exact(4)
+            .map(|pixel| pixel[3] as f32 / 255.0)
+            .sum();
+        let area = std::f32::consts::PI * 25.0;
+        assert!((coverage - area).abs() < 1.0, "{} != {}", coverage, area);
+        assert_eq!(surface.pixel(10, 10), Some(RED));
+        assert_eq!(surface.pixel(0, 0).map(|c| c.alpha()), Some(0.0));
+    }
+
+    #[test]
+    fn stroked_line_covers_its_width() {
+        let mut surface = TinySkiaSurface::new(20, 20).unwrap();
+        surface.set_stroke(BLUE).unwrap();
+        surface.set_line_width(4).unwrap();
+        surface.draw_line(2.0, 10.0, 18.0, 10.0).unwrap();
+
+        // a butt-capped line four pixels wide covers rows 8 through 11 between its endpoints
+        for y in 8..12 {
+            assert_eq!(surface.pixel(10, y), Some(BLUE));
+        }
+        assert_eq!(surface.pixel(10, 6).map(|c| c.alpha()), Some(0.0));
+        assert_eq!(surface.pixel(10, 13).map(|c| c.alpha()), Some(0.0));
+        assert_eq!(surface.pixel(0, 10).map(|c| c.alpha()), Some(0.0));
+    }
+
+    #[test]
+    fn linear_gradient_endpoints() {
+        let gradient = Gradient::try_new(vec![
+            ColorStop {
+                color: RED,
+                position: Intensity::new(0.0).unwrap(),
+            },
+            ColorStop {
+                color: BLUE,
+                position: Intensity::new(1.0).unwrap(),
+            },
+        ])
+        .unwrap();
+
+        let mut surface = TinySkiaSurface::new(16, 4).unwrap();
+        surface
+            .set_fill(FillRule::LinearGradient(gradient, Angle::zero()))
+            .unwrap();
+        surface.fill_rectangle(0.0, 0.0, 16.0, 4.0).unwrap();
+
+        let left = surface.pixel(0, 1).unwrap();
+        assert!(left.red() > 0.9 && left.blue() < 0.1, "{:?}", left);
+        let right = surface.pixel(15, 1).unwrap();
+        assert!(right.red() < 0.1 && right.blue() > 0.9, "{:?}", right);
+        let middle = surface.pixel(8, 1).unwrap();
+        assert!(middle.red() > 0.2 && middle.blue() > 0.2, "{:?}", middle);
+    }
+
+    #[test]
+    fn dirty_tracking_is_not_supported() {
+        let mut surface = TinySkiaSurface::new(4, 4).unwrap();
+        assert!(!surface.supports(crate::NSOpType::DirtyTracking));
+        assert!(matches!(
+            surface.begin_dirty_tracking(),
+            Err(crate::Error::NotSupported(crate::NSOpType::DirtyTracking))
+        ));
+        surface.fill_rectangle(0.0, 0.0, 2.0, 2.0).unwrap();
+        assert!(matches!(
+            surface.take_dirty_region(),
+            Err(crate::Error::NotSupported(crate::NSOpType::DirtyTracking))
+        ));
+    }
+}
@@ -0,0 +1,177 @@
+// MIT/Apache2 License
+
+use lyon_geom::{Angle, Point, Rect, Transform as Transform2D, Vector};
+
+/// An affine transformation, applied to coordinates before they are drawn onto a surface.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(transparent)]
+pub struct Transform {
+    inner: Transform2D<f32>,
+}
+
+impl Transform {
+    /// Create a new `Transform` from the components of its matrix.
+    #[must_use]
+    #[inline]
+    pub fn new(m11: f32, m12: f32, m21: f32, m22: f32, m31: f32, m32: f32) -> Transform {
+        Transform {
+            inner: Transform2D::new(m11, m12, m21, m22, m31, m32),
+        }
+    }
+
+    /// The transform that leaves coordinates unchanged.
+    #[must_use]
+    #[inline]
+    pub fn identity() -> Transform {
+        Transform {
+            inner: Transform2D::identity(),
+        }
+    }
+
+    /// A transform that moves coordinates by the given amount.
+    #[must_use]
+    #[inline]
+    pub fn translation(x: f32, y: f32) -> Transform {
+        Transform {
+            inner: Transform2D::translation(x, y),
+        }
+    }
+
+    /// A transform that scales coordinates by the given factors.
+    #[must_use]
+    #[inline]
+    pub fn scale(x: f32, y: f32) -> Transform {
+        Transform {
+            inner: Transform2D::scale(x, y),
+        }
+    }
+
+    /// A transform that rotates coordinates around the origin by the given angle.
+    #[must_use]
+    #[inline]
+    pub fn rotation(angle: Angle<f32>) -> Transform {
+        Transform {
+            inner: Transform2D::rotation(angle),
+        }
+    }
+
+    /// Create a transform that applies this transform, and then the other one.
+    #[must_use]
+    #[inline]
+    pub fn then(&self, other: &Transform) -> Transform {
+        Transform {
+            inner: self.inner.then(&other.inner),
+        }
+    }
+
+    /// Get the transform that undoes this one, if there is one.
+    #[must_use]
+    #[inline]
+    pub fn inverse(&self) -> Option<Transform> {
+        self.inner.inverse().map(|inner| Transform { inner })
+    }
+
+    /// Tell whether or not this transform leaves coordinates unchanged.
+    #[must_use]
+    #[inline]
+    pub fn is_identity(&self) -> bool {
+        self.inner == Transform2D::identity()
+    }
+
+    /// Apply this transform to a point.
+    #[must_use]
+    #[inline]
+    pub fn transform_point(&self, point: Point<f32>) -> Point<f32> {
+        self.inner.transform_point(point)
+    }
+
+    /// Apply this transform to a vector. This ignores the translation part of the transform.
+    #[must_use]
+    #[inline]
+    pub fn transform_vector(&self, vector: Vector<f32>) -> Vector<f32> {
+        self.inner.transform_vector(vector)
+    }
+
+    /// Get the smallest axis-aligned rectangle containing this rectangle after it has been transformed.
+    #[must_use]
+    #[inline]
+    pub fn transform_rect(&self, rect: &Rect<f32>) -> Rect<f32> {
+        self.inner.outer_transformed_rect(rect)
+    }
+
+    /// Get the inner matrix of this transform.
+    #[must_use]
+    #[inline]
+    pub fn into_inner(self) -> Transform2D<f32> {
+        self.inner
+    }
+}
+
+impl Default for Transform {
+    #[inline]
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl From<Transform2D<f32>> for Transform {
+    #[inline]
+    fn from(inner: Transform2D<f32>) -> Self {
+        Self { inner }
+    }
+}
+
+impl From<Transform> for Transform2D<f32> {
+    #[inline]
+    fn from(t: Transform) -> Self {
+        t.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lyon_geom::{point, Size};
+
+    fn assert_close(a: Point<f32>, b: Point<f32>) {
+        assert!((a - b).length() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn rotated_rectangle_corners() {
+        let transform =
+            Transform::rotation(Angle::frac_pi_2()).then(&Transform::translation(10.0, 0.0));
+        let corners = [
+            point(0.0, 0.0),
+            point(2.0, 0.0),
+            point(2.0, 1.0),
+            point(0.0, 1.0),
+        ];
+        let expected = [
+            point(10.0, 0.0),
+            point(10.0, 2.0),
+            point(9.0, 2.0),
+            point(9.0, 0.0),
+        ];
+        corners
+            .iter()
+            .zip(expected.iter())
+            .for_each(|(&corner, &expected)| {
+                assert_close(transform.transform_point(corner), expected)
+            });
+
+        let bounds = transform.transform_rect(&Rect::new(point(0.0, 0.0), Size::new(2.0, 1.0)));
+        assert_close(bounds.min(), point(9.0, 0.0));
+        assert_close(bounds.max(), point(10.0, 2.0));
+    }
+
+    #[test]
+    fn inverse_undoes_transform() {
+        let transform = Transform::scale(2.0, 4.0).then(&Transform::translation(1.0, -3.0));
+        let inverse = transform.inverse().unwrap();
+        let p = point(5.0, 7.0);
+        assert_close(inverse.transform_point(transform.transform_point(p)), p);
+        assert!(Transform::scale(0.0, 1.0).inverse().is_none());
+        assert!(Transform::default().is_identity());
+    }
+}
@@ -1,6 +1,13 @@
 // MIT/Apache2 License
 
+use lyon_geom::{Arc, Point, Rect};
 use num_traits::{AsPrimitive, Bounded};
+#[cfg(any(test, all(windows, feature = "yaww")))]
+use std::{
+    collections::hash_map::{HashMap, IntoIter as HashMapIntoIter},
+    hash::Hash,
+    iter,
+};
 use std::{fmt, ops};
 #[cfg(feature = "async")]
 use std::{future::Future, pin::Pin};
@@ -9,6 +16,9 @@ use std::{future::Future, pin::Pin};
 pub type GenericResult<'future, T = ()> =
     Pin<Box<dyn Future<Output = crate::Result<T>> + Send + 'future>>;
 
+/// The most segments that `flatten_arc` will split an arc into, no matter how small the tolerance is.
+const MAX_ARC_SEGMENTS: usize = 4096;
+
 pub(crate) fn clamp<N: Bounded + Copy + ops::Sub + 'static>(i: f32) -> N
 where
     f32: AsPrimitive<N> + From<N::Output>,
@@ -17,6 +27,147 @@ where
     (bounds * i).as_()
 }
 
+/// Flatten an arc into a polyline, starting at the start of the arc and ending at its end.
+///
+/// The number of segments is chosen from the larger radius, so that the polyline never strays from the arc by
+/// more than `tolerance`. A segment spanning the angle `θ` strays by `r * (1 - cos(θ / 2))`, so large arcs get
+/// more segments than small ones, up to `MAX_ARC_SEGMENTS`.
+#[inline]
+pub(crate) fn flatten_arc(arc: &Arc<f32>, tolerance: f32) -> Vec<Point<f32>> {
+    let radius = arc.radii.x.abs().max(arc.radii.y.abs());
+    let sweep = arc.sweep_angle.radians.abs();
+    if !radius.is_finite() || !sweep.is_finite() || radius <= 0.0 || tolerance <= 0.0 {
+        return vec![arc.from(), arc.to()];
+    }
+
+    // when the tolerance is tiny next to the radius, the step rounds down to zero
+    let step =
+        (2.0 * (1.0 - tolerance / radius).max(-1.0).acos()).max(sweep / MAX_ARC_SEGMENTS as f32);
+    let count = (sweep / step).ceil().max(1.0) as usize;
+    (0..=count)
+        .map(|i| arc.sample(i as f32 / count as f32))
+        .collect()
+}
+
+/// Intersect a new clipping rectangle with the one at the top of a clip stack, if there is one.
+#[inline]
+pub(crate) fn intersect_clip(current: Option<&Rect<f32>>, rect: Rect<f32>) -> Rect<f32> {
+    match current {
+        Some(current) => current.intersection(&rect).unwrap_or_else(Rect::zero),
+        None => rect,
+    }
+}
+
+/// Convert RGBA pixels to BGRA in place, by swapping the red and blue components of each pixel. Any trailing
+/// bytes that don't make up a whole pixel are left alone.
+#[inline]
+pub(crate) fn swizzle_rgba_to_bgra(bytes: &mut [u8]) {
+    bytes.chunks_exact_mut(4).for_each(|pixel| pixel.swap(0, 2));
+}
+
+/// Convert BGRA pixels to RGBA in place. This is the inverse of `swizzle_rgba_to_bgra`.
+#[inline]
+pub(crate) fn swizzle_bgra_to_rgba(bytes: &mut [u8]) {
+    // swapping red and blue is its own inverse
+    swizzle_rgba_to_bgra(bytes)
+}
+
+/// A map that holds at most a certain number of entries, evicting the least recently used entry when it is
+/// full. Only the GDI backend caches its pens and brushes with this.
+#[cfg(any(test, all(windows, feature = "yaww")))]
+#[derive(Debug)]
+pub(crate) struct LruCache<K, V> {
+    map: HashMap<K, (V, u64)>,
+    capacity: usize,
+    clock: u64,
+}
+
+#[cfg(any(test, all(windows, feature = "yaww")))]
+impl<K: Eq + Hash + Clone, V> LruCache<K, V> {
+    #[inline]
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            map: HashMap::new(),
+            capacity: capacity.max(1),
+            clock: 0,
+        }
+    }
+
+    #[inline]
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// Get an entry, marking it as the most recently used.
+    #[inline]
+    pub(crate) fn get(&mut self, key: &K) -> Option<&V> {
+        let now = self.tick();
+        self.map.get_mut(key).map(|(value, used)| {
+            *used = now;
+            &*value
+        })
+    }
+
+    /// Insert an entry, returning the entry it replaced or any entries that were evicted to make room for it.
+    #[inline]
+    pub(crate) fn insert(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        let now = self.tick();
+        if let Some((old, used)) = self.map.get_mut(&key) {
+            *used = now;
+            let old = std::mem::replace(old, value);
+            return vec![(key, old)];
+        }
+
+        let evicted = self.evict_to(self.capacity - 1);
+        self.map.insert(key, (value, now));
+        evicted
+    }
+
+    /// Change the number of entries this cache can hold, returning any entries that no longer fit.
+    #[inline]
+    pub(crate) fn set_capacity(&mut self, capacity: usize) -> Vec<(K, V)> {
+        self.capacity = capacity.max(1);
+        self.evict_to(self.capacity)
+    }
+
+    /// Remove every entry from the cache, returning them.
+    #[inline]
+    pub(crate) fn clear(&mut self) -> Vec<(K, V)> {
+        self.map
+            .drain()
+            .map(|(key, (value, _))| (key, value))
+            .collect()
+    }
+
+    #[inline]
+    fn evict_to(&mut self, len: usize) -> Vec<(K, V)> {
+        let mut evicted = vec![];
+        while self.map.len() > len {
+            let oldest = self
+                .map
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone())
+                .expect("Map cannot be empty");
+            let value = self.map.remove(&oldest).expect("Key was just found").0;
+            evicted.push((oldest, value));
+        }
+        evicted
+    }
+}
+
+#[cfg(any(test, all(windows, feature = "yaww")))]
+impl<K, V> IntoIterator for LruCache<K, V> {
+    type Item = (K, V);
+    type IntoIter = iter::Map<HashMapIntoIter<K, (V, u64)>, fn((K, (V, u64))) -> (K, V)>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.into_iter().map(|(key, (value, _))| (key, value))
+    }
+}
+
 /// Hides a type in order to make #[derive(Debug)] usable.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(transparent)]
@@ -63,3 +214,98 @@ impl<T> From<T> for DebugContainer<T> {
         Self(t)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lyon_geom::{point, vector, Angle, Size};
+
+    fn quarter_circle(radius: f32) -> Arc<f32> {
+        Arc {
+            center: point(0.0, 0.0),
+            radii: vector(radius, radius),
+            start_angle: Angle::zero(),
+            sweep_angle: Angle::frac_pi_2(),
+            x_rotation: Angle::zero(),
+        }
+    }
+
+    #[test]
+    fn flatten_arc_larger_radius_more_segments() {
+        let small = flatten_arc(&quarter_circle(10.0), 0.1);
+        let large = flatten_arc(&quarter_circle(1000.0), 0.1);
+        assert!(large.len() > small.len());
+    }
+
+    #[test]
+    fn flatten_arc_smaller_tolerance_more_segments() {
+        let arc = quarter_circle(100.0);
+        let coarse = flatten_arc(&arc, 0.5);
+        let fine = flatten_arc(&arc, 0.25);
+        assert!(fine.len() > coarse.len());
+    }
+
+    #[test]
+    fn flatten_arc_hits_endpoints() {
+        let arc = quarter_circle(10.0);
+        let points = flatten_arc(&arc, 0.1);
+        assert!((points[0] - arc.from()).length() < 1e-4);
+        assert!((points[points.len() - 1] - arc.to()).length() < 1e-4);
+    }
+
+    #[test]
+    fn flatten_arc_tiny_tolerance_is_bounded() {
+        let points = flatten_arc(&quarter_circle(1.0e9), 1.0e-9);
+        assert!(points.len() <= MAX_ARC_SEGMENTS + 1);
+    }
+
+    #[test]
+    fn swizzle_round_trip() {
+        let mut bytes = [0x11, 0x22, 0x33, 0x44, 0x55];
+        swizzle_rgba_to_bgra(&mut bytes);
+        // the trailing byte isn't a whole pixel, so it is left alone
+        assert_eq!(bytes, [0x33, 0x22, 0x11, 0x44, 0x55]);
+        swizzle_bgra_to_rgba(&mut bytes);
+        assert_eq!(bytes, [0x11, 0x22, 0x33, 0x44, 0x55]);
+    }
+
+    #[test]
+    fn lru_cache_evicts_oldest() {
+        let mut cache = LruCache::new(2);
+        assert!(cache.insert(1, "one").is_empty());
+        assert!(cache.insert(2, "two").is_empty());
+        // using the first entry makes the second one the oldest
+        assert_eq!(cache.get(&1), Some(&"one"));
+        assert_eq!(cache.insert(3, "three"), vec![(2, "two")]);
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.set_capacity(1), vec![(1, "one")]);
+    }
+
+    #[test]
+    fn lru_cache_clear_forgets_entries() {
+        let mut cache = LruCache::new(4);
+        cache.insert("red pen", 1);
+        cache.insert("blue brush", 2);
+        let mut cleared = cache.clear();
+        cleared.sort();
+        assert_eq!(cleared, vec![("blue brush", 2), ("red pen", 1)]);
+
+        // the next lookup misses, so the pen has to be created again
+        assert_eq!(cache.get(&"red pen"), None);
+        assert!(cache.insert("red pen", 3).is_empty());
+        assert_eq!(cache.get(&"red pen"), Some(&3));
+    }
+
+    #[test]
+    fn nested_clips_intersect() {
+        let outer = Rect::new(point(0.0, 0.0), Size::new(10.0, 10.0));
+        let inner = Rect::new(point(5.0, 5.0), Size::new(10.0, 10.0));
+        let clip = intersect_clip(Some(&outer), inner);
+        assert_eq!(clip, Rect::new(point(5.0, 5.0), Size::new(5.0, 5.0)));
+        assert_eq!(intersect_clip(None, inner), inner);
+
+        // disjoint clips leave nothing visible
+        let far = Rect::new(point(20.0, 20.0), Size::new(1.0, 1.0));
+        assert!(intersect_clip(Some(&outer), far).is_empty());
+    }
+}
@@ -0,0 +1,1047 @@
+// MIT/Apache2 License
+
+//! A GPU renderer built on top of `wgpu`. Shapes are tessellated into triangles with lyon and batched on the
+//! CPU, then rendered into a texture when the surface is flushed. Gradients are evaluated in the fragment
+//! shader, using color ramps that are uploaded alongside the triangles.
+
+use crate::{
+    fill::{FillMode, FillRule},
+    gradient::Gradient,
+    stroke::{dash_lines, LineCap, LineJoin},
+    surface::{Surface, SurfaceFeatures, DEFAULT_MITER_LIMIT, DEFAULT_TOLERANCE, MIN_TOLERANCE},
+    transform::Transform,
+    util::{intersect_clip, DebugContainer},
+    Color, Image, ImageFormat,
+};
+use lyon_geom::{LineSegment, Point, Rect, Size, Vector};
+use lyon_path::{Path, PathEvent, PathSlice};
+use lyon_tessellation::{
+    BuffersBuilder, FillOptions, FillRule as LyonFillRule, FillTessellator, FillVertex,
+    FillVertexConstructor, LineCap as LyonLineCap, LineJoin as LyonLineJoin, StrokeOptions,
+    StrokeTessellator, StrokeVertex, StrokeVertexConstructor, VertexBuffers,
+};
+use std::{borrow::Cow, iter, mem, num::NonZeroU32, ops::Range, slice};
+use wgpu::util::DeviceExt;
+
+const FEATURES: SurfaceFeatures = SurfaceFeatures {
+    transparency: true,
+    gradients: true,
+    floats: true,
+    clipping: true,
+    transforms: true,
+    paths: true,
+    text: false,
+    images: false,
+    patterns: false,
+    size: true,
+    antialiasing: false,
+    dirty_tracking: false,
+};
+
+/// The number of texels in each gradient's color ramp.
+const RAMP_WIDTH: u32 = 256;
+/// The number of gradients that can be drawn before the surface has to flush.
+const RAMP_ROWS: u32 = 64;
+
+const SHADER: &str = r#"
+struct VertexInput {
+    [[location(0)]] position: vec2<f32>;
+    [[location(1)]] local: vec2<f32>;
+    [[location(2)]] color: vec4<f32>;
+    [[location(3)]] params: vec4<f32>;
+    [[location(4)]] kind: u32;
+    [[location(5)]] ramp: u32;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0)]] local: vec2<f32>;
+    [[location(1)]] color: vec4<f32>;
+    [[location(2)]] params: vec4<f32>;
+    [[location(3), interpolate(flat)]] kind: u32;
+    [[location(4), interpolate(flat)]] ramp: u32;
+};
+
+[[group(0), binding(0)]]
+var ramps: texture_2d<f32>;
+[[group(0), binding(1)]]
+var ramp_sampler: sampler;
+
+[[stage(vertex)]]
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    output.position = vec4<f32>(input.position, 0.0, 1.0);
+    output.local = input.local;
+    output.color = input.color;
+    output.params = input.params;
+    output.kind = input.kind;
+    output.ramp = input.ramp;
+    return output;
+}
+
+[[stage(fragment)]]
+fn fs_main(input: VertexOutput) -> [[location(0)]] vec4<f32> {
+    if (input.kind == 0u) {
+        return input.color;
+    }
+
+    var t: f32 = 0.0;
+    if (input.kind == 1u) {
+        let direction = input.params.zw - input.params.xy;
+        t = dot(input.local - input.params.xy, direction) / dot(direction, direction);
+    }
+    if (input.kind == 2u) {
+        t = length(input.local - input.params.xy) / input.params.z;
+    }
+    if (input.kind == 3u) {
+        let offset = input.local - input.params.xy;
+        t = fract((atan2(offset.y, offset.x) - input.params.z) / 6.2831853);
+    }
+
+    let row = (f32(input.ramp) + 0.5) / f32(textureDimensions(ramps).y);
+    return textureSampleLevel(ramps, ramp_sampler, vec2<f32>(clamp(t, 0.0, 1.0), row), 0.0) * input.color;
+}
+"#;
+
+const VERTEX_ATTRIBUTES: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
+    0 => Float32x2,
+    1 => Float32x2,
+    2 => Float32x4,
+    3 => Float32x4,
+    4 => Uint32,
+    5 => Uint32,
+];
+
+// kinds of paint understood by the fragment shader
+const PAINT_SOLID: u32 = 0;
+const PAINT_LINEAR: u32 = 1;
+const PAINT_RADIAL: u32 = 2;
+const PAINT_CONIC: u32 = 3;
+
+/// A surface that renders onto a `wgpu` texture.
+///
+/// Drawing commands are batched until `flush()` is called, at which point they are rendered into the
+/// surface's texture. Colors are written to the texture as they are, without any conversion into or out of
+/// sRGB.
+#[derive(Debug)]
+pub struct WgpuSurface<'a> {
+    device: &'a wgpu::Device,
+    queue: &'a wgpu::Queue,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    ramp_texture: wgpu::Texture,
+    target: wgpu::Texture,
+    target_view: wgpu::TextureView,
+    size: Size<u32>,
+
+    // triangles waiting to be rendered
+    batch: VertexBuffers<Vertex, u32>,
+    draws: Vec<DrawCall>,
+    // color ramps used by the gradients in the batch, one row per gradient
+    ramps: Vec<u8>,
+    // the color to clear the texture to before rendering the batch
+    clear: Option<Color>,
+
+    state: State,
+    saved: Vec<State>,
+    tolerance: f32,
+    fill_tessellator: DebugContainer<FillTessellator>,
+    stroke_tessellator: DebugContainer<StrokeTessellator>,
+    scratch: VertexBuffers<Vertex, u32>,
+}
+
+/// Drawing state that is saved by `save()`.
+#[derive(Debug, Clone)]
+struct State {
+    stroke: Color,
+    fill: FillRule,
+    fill_mode: FillMode,
+    alpha: f32,
+    line_width: usize,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    miter_limit: f32,
+    dash: Vec<f32>,
+    dash_offset: f32,
+    // each clipping rectangle is in device space, and intersected with the one below it
+    clips: Vec<Rect<f32>>,
+    transform: Transform,
+}
+
+/// A vertex, as understood by the shader.
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+struct Vertex {
+    // position in normalized device coordinates
+    position: [f32; 2],
+    // position before the transform was applied, which gradients are evaluated against
+    local: [f32; 2],
+    // solid colors, or the color that gradients are multiplied by
+    color: [f32; 4],
+    // linear gradients: start and end; radial gradients: center and radius; conic gradients: center and angle
+    params: [f32; 4],
+    kind: u32,
+    ramp: u32,
+}
+
+/// A range of indices in the batch that are drawn with the same scissor rectangle.
+#[derive(Debug, Clone)]
+struct DrawCall {
+    indices: Range<u32>,
+    scissor: [u32; 4],
+}
+
+/// Turns tessellated points into vertices.
+#[derive(Copy, Clone)]
+struct VertexCtor {
+    transform: Transform,
+    size: Size<f32>,
+}
+
+impl VertexCtor {
+    #[inline]
+    fn vertex(&self, point: Point<f32>) -> Vertex {
+        let device = self.transform.transform_point(point);
+        Vertex {
+            position: [
+                device.x / self.size.width * 2.0 - 1.0,
+                1.0 - device.y / self.size.height * 2.0,
+            ],
+            local: [point.x, point.y],
+            ..Default::default()
+        }
+    }
+}
+
+impl FillVertexConstructor<Vertex> for VertexCtor {
+    #[inline]
+    fn new_vertex(&mut self, vert: FillVertex<'_>) -> Vertex {
+        self.vertex(vert.position())
+    }
+}
+
+impl StrokeVertexConstructor<Vertex> for VertexCtor {
+    #[inline]
+    fn new_vertex(&mut self, vert: StrokeVertex<'_, '_>) -> Vertex {
+        self.vertex(vert.position())
+    }
+}
+
+impl<'a> WgpuSurface<'a> {
+    /// Create a new `WgpuSurface` that renders onto a new texture of the given format and size.
+    #[inline]
+    pub fn new(
+        device: &'a wgpu::Device,
+        queue: &'a wgpu::Queue,
+        target_format: wgpu::TextureFormat,
+        size: Size<u32>,
+    ) -> crate::Result<WgpuSurface<'a>> {
+        if size.width == 0 || size.height == 0 {
+            return Err(crate::Error::StaticMsg(
+                "Surface cannot have a size of zero",
+            ));
+        }
+
+        let target = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("chalkboard target"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: target_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+        });
+        let target_view = target.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let ramp_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("chalkboard gradient ramps"),
+            size: wgpu::Extent3d {
+                width: RAMP_WIDTH,
+                height: RAMP_ROWS,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+        let ramp_view = ramp_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let ramp_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("chalkboard gradient sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("chalkboard bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        filtering: true,
+                        comparison: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("chalkboard bind group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&ramp_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&ramp_sampler),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("chalkboard shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("chalkboard pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("chalkboard pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: mem::size_of::<Vertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &VERTEX_ATTRIBUTES,
+                }],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[wgpu::ColorTargetState {
+                    format: target_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }],
+            }),
+        });
+
+        Ok(WgpuSurface {
+            device,
+            queue,
+            pipeline,
+            bind_group,
+            ramp_texture,
+            target,
+            target_view,
+            size,
+            batch: VertexBuffers::new(),
+            draws: vec![],
+            ramps: vec![],
+            // the texture starts out with undefined contents
+            clear: Some(Color::default()),
+            state: State {
+                stroke: Color::BLACK,
+                fill: FillRule::SolidColor(Color::BLACK),
+                fill_mode: FillMode::default(),
+                alpha: 1.0,
+                line_width: 1,
+                line_cap: LineCap::default(),
+                line_join: LineJoin::default(),
+                miter_limit: DEFAULT_MITER_LIMIT,
+                dash: vec![],
+                dash_offset: 0.0,
+                clips: vec![],
+                transform: Transform::identity(),
+            },
+            saved: vec![],
+            tolerance: DEFAULT_TOLERANCE,
+            fill_tessellator: DebugContainer::new(FillTessellator::new()),
+            stroke_tessellator: DebugContainer::new(StrokeTessellator::new()),
+            scratch: VertexBuffers::new(),
+        })
+    }
+
+    /// The texture that this surface renders onto. This only contains what has been drawn up until the last
+    /// call to `flush()`.
+    #[inline]
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.target
+    }
+
+    /// A view into the texture that this surface renders onto.
+    #[inline]
+    pub fn texture_view(&self) -> &wgpu::TextureView {
+        &self.target_view
+    }
+
+    /// Get the texture that this surface has rendered onto, flushing any pending drawing first.
+    #[inline]
+    pub fn into_texture(mut self) -> crate::Result<wgpu::Texture> {
+        self.flush()?;
+        Ok(self.target)
+    }
+
+    /// The scissor rectangle for the current clip, or `None` if everything is clipped out.
+    #[inline]
+    fn scissor(&self) -> Option<[u32; 4]> {
+        let surface = Rect::new(Point::origin(), self.size.to_f32());
+        let clip = intersect_clip(self.state.clips.last(), surface).round_out();
+        let clip = clip.intersection(&surface)?;
+        if clip.is_empty() {
+            return None;
+        }
+
+        Some([
+            clip.origin.x as u32,
+            clip.origin.y as u32,
+            clip.size.width as u32,
+            clip.size.height as u32,
+        ])
+    }
+
+    #[inline]
+    fn ctor(&self) -> VertexCtor {
+        VertexCtor {
+            transform: self.state.transform,
+            size: self.size.to_f32(),
+        }
+    }
+
+    /// Tessellate the inside of a path into the scratch buffers.
+    #[inline]
+    fn tessellate_fill(&mut self, path: impl IntoIterator<Item = PathEvent>) -> crate::Result {
+        let options = FillOptions::default()
+            .with_tolerance(self.tolerance)
+            .with_fill_rule(match self.state.fill_mode {
+                FillMode::EvenOdd => LyonFillRule::EvenOdd,
+                FillMode::NonZero => LyonFillRule::NonZero,
+            });
+        let ctor = self.ctor();
+
+        self.scratch.vertices.clear();
+        self.scratch.indices.clear();
+        self.fill_tessellator
+            .tessellate(
+                path,
+                &options,
+                &mut BuffersBuilder::new(&mut self.scratch, ctor),
+            )
+            .map_err(|e| {
+                log::error!("Tesselation error occurred: {:?}", e);
+                crate::Error::FailedToTesselate
+            })?;
+        Ok(())
+    }
+
+    /// Tessellate the outline of a path into the scratch buffers.
+    #[inline]
+    fn tessellate_stroke(&mut self, path: impl IntoIterator<Item = PathEvent>) -> crate::Result {
+        let line_cap = match self.state.line_cap {
+            LineCap::Butt => LyonLineCap::Butt,
+            LineCap::Round => LyonLineCap::Round,
+            LineCap::Square => LyonLineCap::Square,
+        };
+        let mut options = StrokeOptions::default().with_tolerance(self.tolerance);
+        options.start_cap = line_cap;
+        options.end_cap = line_cap;
+        options.line_join = match self.state.line_join {
+            LineJoin::Miter => LyonLineJoin::Miter,
+            LineJoin::Round => LyonLineJoin::Round,
+            LineJoin::Bevel => LyonLineJoin::Bevel,
+        };
+        options.line_width = self.state.line_width.max(1) as f32;
+        options.miter_limit = self.state.miter_limit;
+        let ctor = self.ctor();
+
+        self.scratch.vertices.clear();
+        self.scratch.indices.clear();
+        self.stroke_tessellator
+            .tessellate(
+                path,
+                &options,
+                &mut BuffersBuilder::new(&mut self.scratch, ctor),
+            )
+            .map_err(|e| {
+                log::error!("Tesselation error occurred: {:?}", e);
+                crate::Error::FailedToTesselate
+            })?;
+        Ok(())
+    }
+
+    /// Paint the triangles in the scratch buffers with a fill rule, and add them to the batch.
+    #[inline]
+    fn submit_scratch(&mut self, rule: &FillRule) -> crate::Result {
+        if self.scratch.indices.is_empty() {
+            return Ok(());
+        }
+        let scissor = match self.scissor() {
+            Some(scissor) => scissor,
+            None => return Ok(()),
+        };
+
+        // gradients are spread across the shape's bounding box, like the other backends do
+        let bounds = Rect::from_points(
+            self.scratch
+                .vertices
+                .iter()
+                .map(|v| Point::new(v.local[0], v.local[1])),
+        );
+        let (color, params, kind, stops) = match rule {
+            FillRule::SolidColor(color) => (*color, [0.0; 4], PAINT_SOLID, None),
+            FillRule::LinearGradient(gradient, angle) => {
+                let direction = Vector::new(angle.radians.cos(), angle.radians.sin());
+                let half = (direction.x.abs() * bounds.size.width
+                    + direction.y.abs() * bounds.size.height)
+                    / 2.0;
+                let (start, end) = (
+                    bounds.center() - direction * half,
+                    bounds.center() + direction * half,
+                );
+                (
+                    Color::WHITE,
+                    [start.x, start.y, end.x, end.y],
+                    PAINT_LINEAR,
+                    Some(gradient),
+                )
+            }
+            FillRule::RadialGradient(gradient) => {
+                let center = bounds.center();
+                let radius = bounds.size.width.max(bounds.size.height) / 2.0;
+                (
+                    Color::WHITE,
+                    [center.x, center.y, radius.max(f32::EPSILON), 0.0],
+                    PAINT_RADIAL,
+                    Some(gradient),
+                )
+            }
+            FillRule::ConicalGradient(gradient) => {
+                let center = bounds.center();
+                (
+                    Color::WHITE,
+                    [center.x, center.y, 0.0, 0.0],
+                    PAINT_CONIC,
+                    Some(gradient),
+                )
+            }
+            FillRule::Pattern(_) => {
+                return Err(crate::Error::NotSupported(crate::NSOpType::Pattern))
+            }
+        };
+
+        let ramp = match stops {
+            Some(stops) => self.push_ramp(stops)?,
+            None => 0,
+        };
+        let color = color.scale_alpha(self.state.alpha);
+        let color = [color.red(), color.green(), color.blue(), color.alpha()];
+        self.scratch.vertices.iter_mut().for_each(|vertex| {
+            vertex.color = color;
+            vertex.params = params;
+            vertex.kind = kind;
+            vertex.ramp = ramp;
+        });
+
+        let base = self.batch.vertices.len() as u32;
+        let start = self.batch.indices.len() as u32;
+        self.batch.vertices.append(&mut self.scratch.vertices);
+        self.batch
+            .indices
+            .extend(self.scratch.indices.drain(..).map(|index| index + base));
+        let end = self.batch.indices.len() as u32;
+
+        // triangles drawn with the same scissor rectangle can be drawn in one call
+        match self.draws.last_mut() {
+            Some(draw) if draw.scissor == scissor && draw.indices.end == start => {
+                draw.indices.end = end;
+            }
+            _ => self.draws.push(DrawCall {
+                indices: start..end,
+                scissor,
+            }),
+        }
+
+        Ok(())
+    }
+
+    /// Rasterize a gradient into a new row of the ramp texture, returning the row's index.
+    #[inline]
+    fn push_ramp(&mut self, gradient: &Gradient<'_>) -> crate::Result<u32> {
+        let row_len = RAMP_WIDTH as usize * 4;
+        if self.ramps.len() >= row_len * RAMP_ROWS as usize {
+            self.flush()?;
+        }
+
+        let row = (self.ramps.len() / row_len) as u32;
+        self.ramps.extend((0..RAMP_WIDTH).flat_map(|i| {
+            let (r, g, b, a) = gradient
+                .sample(i as f32 / (RAMP_WIDTH - 1) as f32)
+                .clamp_u8();
+            iter::once(r)
+                .chain(iter::once(g))
+                .chain(iter::once(b))
+                .chain(iter::once(a))
+        }));
+        Ok(row)
+    }
+
+    /// Fill a path with the current fill rule.
+    #[inline]
+    fn fill_events(&mut self, path: impl IntoIterator<Item = PathEvent>) -> crate::Result {
+        self.tessellate_fill(path)?;
+        let rule = self.state.fill.clone();
+        self.submit_scratch(&rule)
+    }
+
+    /// Stroke a path with the current stroke color and line style.
+    #[inline]
+    fn stroke_events(&mut self, path: impl IntoIterator<Item = PathEvent>) -> crate::Result {
+        // dashed paths are split into their dashes and stroked as separate lines
+        if !self.state.dash.is_empty() {
+            let lines = dash_lines(
+                crate::path_to_lines(path, self.tolerance),
+                &self.state.dash,
+                self.state.dash_offset,
+            );
+            self.tessellate_stroke(lines_to_events(&lines))?;
+        } else {
+            self.tessellate_stroke(path)?;
+        }
+
+        let rule = FillRule::SolidColor(self.state.stroke);
+        self.submit_scratch(&rule)
+    }
+}
+
+impl<'a> Surface for WgpuSurface<'a> {
+    #[inline]
+    fn features(&self) -> SurfaceFeatures {
+        FEATURES
+    }
+
+    #[inline]
+    fn set_stroke(&mut self, color: Color) -> crate::Result {
+        self.state.stroke = color;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_fill(&mut self, rule: FillRule) -> crate::Result {
+        if let FillRule::Pattern(_) = rule {
+            return Err(crate::Error::NotSupported(crate::NSOpType::Pattern));
+        }
+
+        self.state.fill = rule;
+        Ok(())
+    }
+
+    #[inline]
+    fn fill_rule(&self) -> Option<&FillRule> {
+        Some(&self.state.fill)
+    }
+
+    #[inline]
+    fn set_fill_mode(&mut self, mode: FillMode) -> crate::Result {
+        self.state.fill_mode = mode;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_line_width(&mut self, width: usize) -> crate::Result {
+        self.state.line_width = width;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_line_cap(&mut self, cap: LineCap) -> crate::Result {
+        self.state.line_cap = cap;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_line_join(&mut self, join: LineJoin) -> crate::Result {
+        self.state.line_join = join;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_miter_limit(&mut self, limit: f32) -> crate::Result {
+        self.state.miter_limit = limit.max(1.0);
+        Ok(())
+    }
+
+    #[inline]
+    fn set_dash(&mut self, pattern: &[f32], offset: f32) -> crate::Result {
+        self.state.dash = pattern.to_vec();
+        self.state.dash_offset = offset;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_global_alpha(&mut self, alpha: f32) -> crate::Result {
+        self.state.alpha = alpha.clamp(0.0, 1.0);
+        Ok(())
+    }
+
+    #[inline]
+    fn push_clip(&mut self, rect: Rect<f32>) -> crate::Result {
+        let rect = self.state.transform.transform_rect(&rect);
+        let clip = intersect_clip(self.state.clips.last(), rect);
+        self.state.clips.push(clip);
+        Ok(())
+    }
+
+    #[inline]
+    fn pop_clip(&mut self) -> crate::Result {
+        self.state.clips.pop().ok_or(crate::Error::NoClip)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_transform(&mut self, transform: Transform) -> crate::Result {
+        self.state.transform = transform;
+        Ok(())
+    }
+
+    #[inline]
+    fn transform(&mut self, transform: Transform) -> crate::Result {
+        self.state.transform = transform.then(&self.state.transform);
+        Ok(())
+    }
+
+    #[inline]
+    fn save(&mut self) -> crate::Result {
+        self.saved.push(self.state.clone());
+        Ok(())
+    }
+
+    #[inline]
+    fn restore(&mut self) -> crate::Result {
+        self.state = self
+            .saved
+            .pop()
+            .ok_or(crate::Error::StaticMsg("No saved state to restore"))?;
+        Ok(())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> crate::Result {
+        if self.draws.is_empty() && self.clear.is_none() {
+            return Ok(());
+        }
+
+        if !self.ramps.is_empty() {
+            let rows = (self.ramps.len() / (RAMP_WIDTH as usize * 4)) as u32;
+            self.queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &self.ramp_texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &self.ramps,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(RAMP_WIDTH * 4),
+                    rows_per_image: None,
+                },
+                wgpu::Extent3d {
+                    width: RAMP_WIDTH,
+                    height: rows,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        let buffers = if self.draws.is_empty() {
+            None
+        } else {
+            let vertices = self
+                .device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("chalkboard vertices"),
+                    contents: as_bytes(&self.batch.vertices),
+                    usage: wgpu::BufferUsages::VERTEX,
+                });
+            let indices = self
+                .device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("chalkboard indices"),
+                    contents: as_bytes(&self.batch.indices),
+                    usage: wgpu::BufferUsages::INDEX,
+                });
+            Some((vertices, indices))
+        };
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("chalkboard encoder"),
+            });
+        {
+            let load = match self.clear.take() {
+                Some(color) => wgpu::LoadOp::Clear(wgpu::Color {
+                    r: color.red() as f64,
+                    g: color.green() as f64,
+                    b: color.blue() as f64,
+                    a: color.alpha() as f64,
+                }),
+                None => wgpu::LoadOp::Load,
+            };
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("chalkboard render pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachment {
+                    view: &self.target_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations { load, store: true },
+                }],
+                depth_stencil_attachment: None,
+            });
+
+            if let Some((ref vertices, ref indices)) = buffers {
+                pass.set_pipeline(&self.pipeline);
+                pass.set_bind_group(0, &self.bind_group, &[]);
+                pass.set_vertex_buffer(0, vertices.slice(..));
+                pass.set_index_buffer(indices.slice(..), wgpu::IndexFormat::Uint32);
+                self.draws.iter().for_each(|draw| {
+                    let [x, y, width, height] = draw.scissor;
+                    pass.set_scissor_rect(x, y, width, height);
+                    pass.draw_indexed(draw.indices.clone(), 0, 0..1);
+                });
+            }
+        }
+        self.queue.submit(iter::once(encoder.finish()));
+
+        self.batch.vertices.clear();
+        self.batch.indices.clear();
+        self.draws.clear();
+        self.ramps.clear();
+        Ok(())
+    }
+
+    #[inline]
+    fn tolerance(&self) -> f32 {
+        self.tolerance
+    }
+
+    #[inline]
+    fn set_tolerance(&mut self, tolerance: f32) -> crate::Result {
+        self.tolerance = tolerance.max(MIN_TOLERANCE);
+        Ok(())
+    }
+
+    #[inline]
+    fn size(&self) -> crate::Result<Size<f32>> {
+        Ok(self.size.to_f32())
+    }
+
+    #[inline]
+    fn clear(&mut self, color: Color) -> crate::Result {
+        if !self.state.clips.is_empty() {
+            let size = self.size.to_f32();
+            self.save()?;
+            self.state.transform = Transform::identity();
+            self.state.fill = FillRule::SolidColor(color);
+            self.state.alpha = 1.0;
+            let res = self.fill_rectangle(0.0, 0.0, size.width, size.height);
+            self.restore()?;
+            return res;
+        }
+
+        // everything drawn so far would be covered up, so it doesn't need to be drawn
+        self.batch.vertices.clear();
+        self.batch.indices.clear();
+        self.draws.clear();
+        self.ramps.clear();
+        self.clear = Some(color);
+        Ok(())
+    }
+
+    #[inline]
+    fn create_image(
+        &mut self,
+        _image_bytes: &[u8],
+        _width: u32,
+        _height: u32,
+        _format: ImageFormat,
+    ) -> crate::Result<Image> {
+        Err(crate::Error::NotSupported(crate::NSOpType::Image))
+    }
+
+    #[inline]
+    fn destroy_image(&mut self, _image: Image) -> crate::Result {
+        Err(crate::Error::ImageNotAvailable)
+    }
+
+    #[inline]
+    fn draw_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> crate::Result {
+        self.draw_lines(&[LineSegment {
+            from: Point::new(x1, y1),
+            to: Point::new(x2, y2),
+        }])
+    }
+
+    #[inline]
+    fn draw_lines(&mut self, lines: &[LineSegment<f32>]) -> crate::Result {
+        self.stroke_events(lines_to_events(lines))
+    }
+
+    #[inline]
+    fn draw_path(&mut self, path: PathSlice<'_>) -> crate::Result {
+        self.stroke_events(path.iter())
+    }
+
+    #[inline]
+    fn draw_path_owned(&mut self, path: Path) -> crate::Result {
+        self.stroke_events(path.iter())
+    }
+
+    #[inline]
+    fn fill_polygon(&mut self, points: &[Point<f32>]) -> crate::Result {
+        let (first, rest) = match points.split_first() {
+            Some(split) if points.len() > 2 => split,
+            _ => return Ok(()),
+        };
+
+        let mut last = *first;
+        let events = iter::once(PathEvent::Begin { at: *first })
+            .chain(rest.iter().map(|&to| {
+                let from = mem::replace(&mut last, to);
+                PathEvent::Line { from, to }
+            }))
+            .chain(iter::once(PathEvent::End {
+                last: *points.last().unwrap(),
+                first: *first,
+                close: true,
+            }));
+        self.fill_events(events.collect::<Vec<_>>())
+    }
+
+    #[inline]
+    fn fill_path(&mut self, path: PathSlice<'_>) -> crate::Result {
+        self.fill_events(path.iter())
+    }
+
+    #[inline]
+    fn fill_path_owned(&mut self, path: Path) -> crate::Result {
+        self.fill_events(path.iter())
+    }
+
+    #[inline]
+    fn copy_image(
+        &mut self,
+        _src: Image,
+        _src_x: i32,
+        _src_y: i32,
+        _dst_x: i32,
+        _dst_y: i32,
+        _width: u32,
+        _height: u32,
+    ) -> crate::Result {
+        Err(crate::Error::NotSupported(crate::NSOpType::Image))
+    }
+}
+
+/// Turn a series of lines into path events, with each line as its own subpath.
+#[inline]
+fn lines_to_events(lines: &[LineSegment<f32>]) -> Vec<PathEvent> {
+    lines
+        .iter()
+        .flat_map(|line| {
+            iter::once(PathEvent::Begin { at: line.from })
+                .chain(iter::once(PathEvent::Line {
+                    from: line.from,
+                    to: line.to,
+                }))
+                .chain(iter::once(PathEvent::End {
+                    last: line.to,
+                    first: line.from,
+                    close: false,
+                }))
+        })
+        .collect()
+}
+
+/// View a slice of plain data as bytes, for uploading to the GPU.
+#[inline]
+fn as_bytes<T: Copy>(data: &[T]) -> &[u8] {
+    // SAFETY: T is Copy and only vertices and indices are passed in, which contain no padding
+    unsafe { slice::from_raw_parts(data.as_ptr() as *const u8, mem::size_of_val(data)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::consts::RED;
+    use futures_lite::future;
+
+    /// Get a device from whatever adapter is available, or `None` if there isn't one.
+    fn device() -> Option<(wgpu::Device, wgpu::Queue)> {
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let adapter =
+            future::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))?;
+        future::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None)).ok()
+    }
+
+    #[test]
+    fn filled_triangle_is_batched() {
+        let (device, queue) = match device() {
+            Some(device) => device,
+            None => {
+                eprintln!("no wgpu adapter is available, skipping");
+                return;
+            }
+        };
+
+        let mut surface = WgpuSurface::new(
+            &device,
+            &queue,
+            wgpu::TextureFormat::Rgba8Unorm,
+            Size::new(16, 16),
+        )
+        .unwrap();
+        surface.set_fill(FillRule::SolidColor(RED)).unwrap();
+        surface
+            .fill_polygon(&[
+                Point::new(2.0, 2.0),
+                Point::new(14.0, 2.0),
+                Point::new(8.0, 14.0),
+            ])
+            .unwrap();
+
+        assert!(!surface.batch.vertices.is_empty());
+        assert!(!surface.batch.indices.is_empty());
+        assert_eq!(surface.draws.len(), 1);
+
+        surface.flush().unwrap();
+        assert!(surface.batch.indices.is_empty());
+    }
+}
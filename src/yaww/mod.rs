@@ -3,35 +3,61 @@
 #![cfg(windows)]
 
 use crate::{
-    fill::FillRule,
-    surface::{Surface, SurfaceFeatures},
-    util::DebugContainer,
-    Color, Ellipse, Image, ImageFormat,
+    clamp_round_rect_radii,
+    fill::{FillMode, FillRule, ImagePattern, TileMode},
+    stroke::{dash_lines, LineCap, LineJoin},
+    surface::{FallbackPolicy, Surface, SurfaceFeatures, DEFAULT_TOLERANCE, MIN_TOLERANCE},
+    transform::Transform,
+    util::{flatten_arc, DebugContainer, LruCache},
+    Color, Ellipse, Font, GlyphId, Image, ImageFormat,
 };
-use lyon_geom::{Angle, Arc, LineSegment, Point, Rect, Size, Vector};
+use lyon_geom::{
+    Angle, Arc, CubicBezierSegment, LineSegment, Point, QuadraticBezierSegment, Rect, Size, Vector,
+};
+use lyon_path::{Path, PathBuffer, PathBufferSlice, PathEvent, PathSlice};
 use std::{
     array::IntoIter as ArrayIter,
     cmp,
     collections::hash_map::{Entry, HashMap},
-    mem::drop,
+    iter,
+    mem::{self, drop},
 };
 use yaww::{
     brush::{Brush, BrushFunctions},
     color::Color as YawwColor,
-    dc::{BitBltOp, Dc},
+    dc::{BitBltOp, BkMode, Dc, ExtTextOutOptions, GraphicsMode, PolyFillMode, TextAlign, XForm},
+    font::{FontFunctions, FontWeight},
     gdiobj::{AsGdiObject, GdiFunctions, GdiObject, StockObject},
-    pen::{Pen, PenFunctions, PenStyle},
+    pen::{Pen, PenEndCap, PenFunctions, PenJoin, PenStyle},
+    region::{Region, RegionFunctions, RegionMode},
     task::Task,
     Point as YawwPoint, SendsDirective,
 };
 
+#[cfg(feature = "async")]
+use crate::{surface::AsyncSurface, util::GenericResult};
+#[cfg(feature = "async")]
+use futures_lite::future;
+
 const FEATURES: SurfaceFeatures = SurfaceFeatures {
     transparency: false,
-    gradients: false,
+    gradients: true,
     floats: false,
+    clipping: true,
+    transforms: true,
+    paths: true,
+    text: true,
+    images: true,
+    patterns: true,
+    size: true,
+    antialiasing: false,
+    dirty_tracking: false,
 };
 
 /// Yaww GDI drawing surface. This uses GDI to render on surfaces, even if it is slower than OpenGL or Direct2D.
+///
+/// Note that GDI draws lines with a width of one pixel or less using cosmetic pens, which ignore the line cap
+/// and line join. GDI also has no concept of a dash offset, so dash patterns always start at their beginning.
 #[derive(Debug)]
 pub struct YawwGdiSurface<'thread, S> {
     thread: &'thread S,
@@ -42,23 +68,146 @@ pub struct YawwGdiSurface<'thread, S> {
 #[derive(Debug)]
 pub struct YawwGdiSurfaceResidual {
     pen: Option<Color>,
+    fill: Option<FillRule>,
     brush: Option<Color>,
+    pattern: Option<Image>,
+    gradient: Option<FillRule>,
     clear_brush: Option<Brush>,
+    clear_pen: Option<Pen>,
+    // the pen and brush currently selected into the DC, so that selecting them again can be skipped
+    selected_pen: Option<Pen>,
+    selected_brush: Option<Brush>,
     width: usize,
+    cap: LineCap,
+    join: LineJoin,
+    dash: Vec<u32>,
+    // opacity multiplied into the fill color
+    alpha: f32,
     task_queue: DebugContainer<Vec<Task<yaww::Result<()>>>>,
-    pens: HashMap<(Color, usize), Pen>,
-    brushes: HashMap<Color, Brush>,
+    pens: LruCache<PenKey, Pen>,
+    brushes: LruCache<Color, Brush>,
+    patterns: HashMap<Image, Brush>,
+    evicted: Vec<GdiObject>,
     image_dcs: HashMap<Dc, GdiObject>,
+    image_info: HashMap<Dc, ImageInfo>,
+    clips: Vec<Option<Region>>,
+    transform: Transform,
+    saved: Vec<SavedState>,
+    tolerance: f32,
+    auto_flush: Option<usize>,
+    fallback: FallbackPolicy,
+    rounding: RoundingMode,
+}
+
+type PenKey = (Color, usize, LineCap, LineJoin, Vec<u32>);
+
+/// The size of an image, and whether it needs to be alpha blended onto the surface.
+#[derive(Debug, Copy, Clone)]
+struct ImageInfo {
+    width: u32,
+    height: u32,
+    translucent: bool,
+}
+
+/// Glyphs that share a baseline, which are drawn with a single `ExtTextOut` call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GlyphRun {
+    // the origin of the first glyph
+    x: i32,
+    y: i32,
+    glyphs: Vec<u16>,
+    // the distance from each glyph to the next one
+    dx: Vec<i32>,
+}
+
+/// How floating point coordinates are converted to the integer coordinates that GDI uses.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RoundingMode {
+    /// Round to the nearest integer, rounding halfway cases away from zero.
+    Round,
+    /// Round towards negative infinity.
+    Floor,
+    /// Round towards zero. This is what an `as` cast does, which makes shapes near the origin drift by a pixel
+    /// when their coordinates are negative.
+    Truncate,
+}
+
+impl Default for RoundingMode {
+    #[inline]
+    fn default() -> Self {
+        Self::Round
+    }
+}
+
+impl RoundingMode {
+    /// Convert a coordinate to an integer using this rounding mode.
+    #[inline]
+    pub fn apply(self, value: f32) -> i32 {
+        match self {
+            Self::Round => value.round() as i32,
+            Self::Floor => value.floor() as i32,
+            Self::Truncate => value as i32,
+        }
+    }
+}
+
+/// The default number of pens and brushes that a surface keeps around before deleting the least recently used
+/// ones.
+pub const DEFAULT_CACHE_CAPACITY: usize = 256;
+
+/// The default number of queued tasks a surface allows before it waits on them without an explicit flush.
+pub const DEFAULT_AUTO_FLUSH_THRESHOLD: usize = 1024;
+
+/// The number of queued tasks that `poll_flush` waits on at once.
+pub const POLL_FLUSH_BATCH: usize = 64;
+
+/// Drawing state saved by `save()`. The GDI state itself is saved using `SaveDC`.
+#[derive(Debug)]
+struct SavedState {
+    pen: Option<Color>,
+    fill: Option<FillRule>,
+    brush: Option<Color>,
+    pattern: Option<Image>,
+    gradient: Option<FillRule>,
+    width: usize,
+    cap: LineCap,
+    join: LineJoin,
+    dash: Vec<u32>,
+    alpha: f32,
+    clip_depth: usize,
+    transform: Transform,
 }
 
 impl YawwGdiSurfaceResidual {
+    /// Forget which pen and brush are selected into the DC, so that the next ones used are always selected.
+    #[inline]
+    fn forget_selection(&mut self) {
+        self.selected_pen = None;
+        self.selected_brush = None;
+    }
+
+    #[inline]
+    fn evict_pens(&mut self, pens: Vec<(PenKey, Pen)>) {
+        self.evicted
+            .extend(pens.into_iter().map(|(_, p)| p.into_gdi_object()));
+    }
+
+    #[inline]
+    fn evict_brushes(&mut self, brushes: Vec<(Color, Brush)>) {
+        self.evicted
+            .extend(brushes.into_iter().map(|(_, b)| b.into_gdi_object()));
+    }
+
     #[inline]
     pub fn free<S: SendsDirective>(self, thread: &S) -> crate::Result {
         let YawwGdiSurfaceResidual {
             clear_brush,
             pens,
             brushes,
+            patterns,
+            evicted,
             image_dcs,
+            clips,
             ..
         } = self;
         pens.into_iter()
@@ -72,6 +221,16 @@ impl YawwGdiSurfaceResidual {
                 let _ = b.delete(thread)?;
                 Ok(())
             })?;
+        patterns
+            .into_iter()
+            .try_for_each::<_, crate::Result>(|(_, b)| {
+                let _ = b.delete(thread)?;
+                Ok(())
+            })?;
+        evicted.into_iter().try_for_each::<_, crate::Result>(|o| {
+            let _ = o.delete(thread)?;
+            Ok(())
+        })?;
         image_dcs
             .into_iter()
             .try_for_each::<_, crate::Result>(|(dc, default_image)| {
@@ -79,6 +238,13 @@ impl YawwGdiSurfaceResidual {
                 let _ = dc.delete(thread)?;
                 Ok(())
             })?;
+        clips
+            .into_iter()
+            .flatten()
+            .try_for_each::<_, crate::Result>(|r| {
+                let _ = r.delete(thread)?;
+                Ok(())
+            })?;
 
         Ok(())
     }
@@ -101,13 +267,33 @@ impl<'thread, S> YawwGdiSurface<'thread, S> {
             dc,
             YawwGdiSurfaceResidual {
                 pen: None,
+                fill: None,
                 brush: None,
+                pattern: None,
+                gradient: None,
                 clear_brush: None,
+                clear_pen: None,
+                selected_pen: None,
+                selected_brush: None,
                 width: 0,
+                cap: LineCap::Butt,
+                join: LineJoin::Miter,
+                dash: vec![],
+                alpha: 1.0,
                 task_queue: DebugContainer::new(vec![]),
-                pens: HashMap::new(),
-                brushes: HashMap::new(),
+                pens: LruCache::new(DEFAULT_CACHE_CAPACITY),
+                brushes: LruCache::new(DEFAULT_CACHE_CAPACITY),
+                patterns: HashMap::new(),
+                evicted: vec![],
                 image_dcs: HashMap::new(),
+                image_info: HashMap::new(),
+                clips: vec![],
+                transform: Transform::identity(),
+                saved: vec![],
+                tolerance: DEFAULT_TOLERANCE,
+                auto_flush: Some(DEFAULT_AUTO_FLUSH_THRESHOLD),
+                fallback: FallbackPolicy::default(),
+                rounding: RoundingMode::default(),
             },
         )
     }
@@ -116,8 +302,13 @@ impl<'thread, S> YawwGdiSurface<'thread, S> {
     pub fn into_residual(self) -> YawwGdiSurfaceResidual {
         let mut residual = self.residual.unwrap();
         residual.pen = None;
+        residual.fill = None;
         residual.brush = None;
+        residual.pattern = None;
+        residual.gradient = None;
         residual.clear_brush = None;
+        residual.clear_pen = None;
+        residual.forget_selection();
         residual
     }
 
@@ -125,9 +316,124 @@ impl<'thread, S> YawwGdiSurface<'thread, S> {
     fn residual(&mut self) -> &mut YawwGdiSurfaceResidual {
         self.residual.as_mut().expect("Already dropped?!?!")
     }
+
+    /// Set the number of pens and brushes this surface keeps cached. Pens and brushes that no longer fit are
+    /// deleted on the next flush.
+    #[inline]
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        let residual = self.residual();
+        let pens = residual.pens.set_capacity(capacity);
+        let brushes = residual.brushes.set_capacity(capacity);
+        residual.evict_pens(pens);
+        residual.evict_brushes(brushes);
+    }
+
+    /// Set the number of queued tasks after which the queue is drained automatically, or `None` to only drain it
+    /// on `flush`.
+    #[inline]
+    pub fn set_auto_flush(&mut self, n: Option<usize>) {
+        self.residual().auto_flush = n;
+    }
+
+    /// Set how coordinates are rounded to the integers that GDI uses. This defaults to rounding to the nearest
+    /// integer.
+    #[inline]
+    pub fn set_rounding_mode(&mut self, mode: RoundingMode) {
+        self.residual().rounding = mode;
+    }
+
+    #[inline]
+    fn rounding(&mut self) -> RoundingMode {
+        self.residual().rounding
+    }
 }
 
 impl<'thread, S: SendsDirective> YawwGdiSurface<'thread, S> {
+    /// Queue up a task, draining the queue if it has grown past the auto flush threshold.
+    #[inline]
+    fn push_task(&mut self, t: Task<yaww::Result<()>>) -> crate::Result {
+        self.residual().task_queue.push(t);
+        self.auto_flush()
+    }
+
+    #[inline]
+    fn extend_tasks<I: IntoIterator<Item = Task<yaww::Result<()>>>>(
+        &mut self,
+        t: I,
+    ) -> crate::Result {
+        self.residual().task_queue.extend(t);
+        self.auto_flush()
+    }
+
+    #[inline]
+    fn auto_flush(&mut self) -> crate::Result {
+        let residual = self.residual();
+        if !needs_auto_flush(residual.task_queue.len(), residual.auto_flush) {
+            return Ok(());
+        }
+
+        let errors = crate::Error::collect_errors(residual.task_queue.drain(..).map(|t| t.wait()));
+        crate::Error::from_errors(errors)
+    }
+
+    /// Wait on a bounded batch of the oldest queued tasks, returning the number of tasks that are still pending.
+    /// The GDI thread processes tasks in order, so the oldest tasks are the ones most likely to be done already.
+    #[inline]
+    pub fn poll_flush(&mut self) -> crate::Result<usize> {
+        let residual = self.residual();
+        let batch = cmp::min(residual.task_queue.len(), POLL_FLUSH_BATCH);
+        let mut errors =
+            crate::Error::collect_errors(residual.task_queue.drain(..batch).map(|t| t.wait()));
+        let pending = self.residual().task_queue.len();
+
+        // once the queue is empty, a full flush also deletes the evicted pens and brushes without blocking
+        if pending == 0 {
+            if let Err(e) = self.flush() {
+                errors.push(e);
+            }
+        }
+
+        crate::Error::from_errors(errors).map(|()| pending)
+    }
+
+    /// Delete every cached pen and brush. They are recreated the next time they are needed.
+    #[inline]
+    pub fn clear_caches(&mut self) -> crate::Result {
+        // select the stock objects so that none of the cached objects are in use by the DC
+        let cp = self.clear_pen()?;
+        self.select_pen(cp)?;
+        let cb = self.clear_brush()?;
+        self.select_brush(cb)?;
+
+        let residual = self.residual();
+        let pens = residual.pens.clear();
+        let brushes = residual.brushes.clear();
+        residual.evict_pens(pens);
+        residual.evict_brushes(brushes);
+        let patterns = mem::take(&mut residual.patterns);
+        residual
+            .evicted
+            .extend(patterns.into_iter().map(|(_, b)| b.into_gdi_object()));
+
+        // the evicted objects are deleted once the queued drawing operations are done with them
+        self.flush()
+    }
+
+    /// Wait on every queued task, deselect this surface's pens and brushes from the DC, and get the residual
+    /// back. Unlike `into_residual`, this never leaves drawing operations queued up.
+    #[inline]
+    pub fn finish(mut self) -> crate::Result<YawwGdiSurfaceResidual> {
+        self.flush()?;
+
+        // the DC may outlive the residual's pens and brushes, so it shouldn't hold on to any of them
+        let cp = self.clear_pen()?;
+        self.select_pen(cp)?;
+        let cb = self.clear_brush()?;
+        self.select_brush(cb)?;
+
+        Ok(self.into_residual())
+    }
+
     #[inline]
     fn clear_brush(&mut self) -> crate::Result<Brush> {
         match self.residual().clear_brush {
@@ -146,19 +452,114 @@ impl<'thread, S: SendsDirective> YawwGdiSurface<'thread, S> {
         }
     }
 
+    #[inline]
+    fn clear_pen(&mut self) -> crate::Result<Pen> {
+        match self.residual().clear_pen {
+            Some(cp) => Ok(cp),
+            None => {
+                let cp = self
+                    .thread
+                    .get_stock_object(StockObject::NullPen)?
+                    .wait()
+                    .ok_or(crate::Error::StaticMsg("Could not acquire null pen"))?;
+                Ok(*self.residual().clear_pen.insert(Pen::from_gdi_object(cp)))
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[inline]
+    async fn clear_brush_async(&mut self) -> crate::Result<Brush> {
+        match self.residual().clear_brush {
+            Some(cb) => Ok(cb),
+            None => {
+                let cb = self
+                    .thread
+                    .get_stock_object(StockObject::NullBrush)?
+                    .await
+                    .ok_or(crate::Error::StaticMsg("Could not acquire null brush"))?;
+                Ok(*self
+                    .residual()
+                    .clear_brush
+                    .insert(Brush::from_gdi_object(cb)))
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[inline]
+    async fn clear_pen_async(&mut self) -> crate::Result<Pen> {
+        match self.residual().clear_pen {
+            Some(cp) => Ok(cp),
+            None => {
+                let cp = self
+                    .thread
+                    .get_stock_object(StockObject::NullPen)?
+                    .await
+                    .ok_or(crate::Error::StaticMsg("Could not acquire null pen"))?;
+                Ok(*self.residual().clear_pen.insert(Pen::from_gdi_object(cp)))
+            }
+        }
+    }
+
+    /// Get the pattern brush for an image, creating it if it doesn't exist yet.
+    #[inline]
+    fn get_pattern_brush(&mut self, image: Image) -> crate::Result<Brush> {
+        if let Some(brush) = self.residual().patterns.get(&image) {
+            return Ok(*brush);
+        }
+
+        let dc = Dc::from_raw(image.into_raw());
+        let old_image = *self
+            .residual()
+            .image_dcs
+            .get(&dc)
+            .ok_or(crate::Error::ImageNotAvailable)?;
+
+        // swap the bitmap out of its DC to get a handle to it, then put it back
+        let bitmap = dc.select_object(self.thread, old_image)?.wait()?;
+        dc.select_object(self.thread, bitmap)?.wait()?;
+
+        let brush = self.thread.create_pattern_brush(bitmap)?.wait()?;
+        self.residual().patterns.insert(image, brush);
+        Ok(brush)
+    }
+
     #[inline]
     fn get_pen_from_color(&mut self, color: Color) -> crate::Result<Pen> {
-        let width = self.residual().width;
-        match self.residual().pens.get(&(color, width)) {
+        let residual = self.residual();
+        let key = (
+            color,
+            residual.width,
+            residual.cap,
+            residual.join,
+            residual.dash.clone(),
+        );
+        match residual.pens.get(&key) {
             Some(o) => Ok(*o),
             None => {
                 let (r, g, b, _) = color.clamp_u8();
                 let color2 = YawwColor::from_rgb(r, g, b);
+                let (_, width, cap, join, ref dash) = key;
+                let style = if dash.is_empty() {
+                    PenStyle::Solid
+                } else {
+                    PenStyle::UserStyle
+                };
                 let pen = self
                     .thread
-                    .create_pen(PenStyle::Solid, width as _, color2)?
+                    .ext_create_pen(
+                        style,
+                        width as _,
+                        color2,
+                        gdi_end_cap(cap),
+                        gdi_join(join),
+                        dash,
+                    )?
                     .wait()?;
-                self.residual().pens.insert((color, width), pen);
+                let residual = self.residual();
+                let evicted = residual.pens.insert(key, pen);
+                residual.evict_pens(evicted);
                 Ok(pen)
             }
         }
@@ -167,54 +568,190 @@ impl<'thread, S: SendsDirective> YawwGdiSurface<'thread, S> {
     #[cfg(feature = "async")]
     #[inline]
     async fn get_pen_from_color_async(&mut self, color: Color) -> crate::Result<Pen> {
-        let width = self.residual().width;
-        match self.residual().pens.get(&(color, width)) {
+        let residual = self.residual();
+        let key = (
+            color,
+            residual.width,
+            residual.cap,
+            residual.join,
+            residual.dash.clone(),
+        );
+        match residual.pens.get(&key) {
             Some(o) => Ok(*o),
             None => {
                 let (r, g, b, _) = color.clamp_u8();
                 let color2 = YawwColor::from_rgb(r, g, b);
+                let (_, width, cap, join, ref dash) = key;
+                let style = if dash.is_empty() {
+                    PenStyle::Solid
+                } else {
+                    PenStyle::UserStyle
+                };
                 let pen = self
                     .thread
-                    .create_pen(PenStyle::Solid, width as _, color2)?
+                    .ext_create_pen(
+                        style,
+                        width as _,
+                        color2,
+                        gdi_end_cap(cap),
+                        gdi_join(join),
+                        dash,
+                    )?
                     .await?;
-                self.residual().pens.insert((color, width), pen);
+                let residual = self.residual();
+                let evicted = residual.pens.insert(key, pen);
+                residual.evict_pens(evicted);
                 Ok(pen)
             }
         }
     }
 
+    #[inline]
+    fn get_brush_from_color(&mut self, color: Color) -> crate::Result<Brush> {
+        match self.residual().brushes.get(&color) {
+            Some(brush) => Ok(*brush),
+            None => {
+                let (r, g, b, _) = color.clamp_u8();
+                let brush = self
+                    .thread
+                    .create_solid_brush(YawwColor::from_rgb(r, g, b))?
+                    .wait()?;
+                let residual = self.residual();
+                let evicted = residual.brushes.insert(color, brush);
+                residual.evict_brushes(evicted);
+                Ok(brush)
+            }
+        }
+    }
+
+    /// Select a pen into the DC, unless it is already selected.
+    #[inline]
+    fn select_pen(&mut self, pen: Pen) -> crate::Result {
+        if self.residual().selected_pen != Some(pen) {
+            self.dc.select_object(self.thread, pen)?.wait()?;
+            self.residual().selected_pen = Some(pen);
+        }
+        Ok(())
+    }
+
+    /// Select a brush into the DC, unless it is already selected.
+    #[inline]
+    fn select_brush(&mut self, brush: Brush) -> crate::Result {
+        if self.residual().selected_brush != Some(brush) {
+            self.dc.select_object(self.thread, brush)?.wait()?;
+            self.residual().selected_brush = Some(brush);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[inline]
+    async fn select_pen_async(&mut self, pen: Pen) -> crate::Result {
+        if self.residual().selected_pen != Some(pen) {
+            self.dc.select_object(self.thread, pen)?.await?;
+            self.residual().selected_pen = Some(pen);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[inline]
+    async fn select_brush_async(&mut self, brush: Brush) -> crate::Result {
+        if self.residual().selected_brush != Some(brush) {
+            self.dc.select_object(self.thread, brush)?.await?;
+            self.residual().selected_brush = Some(brush);
+        }
+        Ok(())
+    }
+
+    /// Select a GDI font matching `font` into the DC while running `f`, then select the previous font again and
+    /// delete the new one. Anything `f` queues up runs before the font is deleted.
+    #[inline]
+    fn with_font<T>(
+        &self,
+        font: &Font,
+        f: impl FnOnce(&Self) -> crate::Result<T>,
+    ) -> crate::Result<T> {
+        let weight = if font.bold {
+            FontWeight::Bold
+        } else {
+            FontWeight::Normal
+        };
+        let hfont = self
+            .thread
+            .create_font(
+                font.size as i32,
+                weight,
+                font.italic,
+                font.family.to_string(),
+            )?
+            .wait()?;
+
+        let old_font = match self.dc.select_object(self.thread, hfont)?.wait() {
+            Ok(old_font) => old_font,
+            Err(e) => {
+                let _ = hfont.delete(self.thread)?;
+                return Err(e.into());
+            }
+        };
+        let res = f(self);
+        let _ = self.dc.select_object(self.thread, old_font)?.wait()?;
+        let _ = hfont.delete(self.thread)?;
+        res
+    }
+
+    /// Draw text in the given color without filling in the background behind it, aligned to its reference point
+    /// by `align`.
+    #[inline]
+    fn set_text_style(&self, color: Color, align: TextAlign) -> crate::Result {
+        let (r, g, b, _) = color.clamp_u8();
+        let _ = self
+            .dc
+            .set_text_color(self.thread, YawwColor::from_rgb(r, g, b))?
+            .wait()?;
+        let _ = self
+            .dc
+            .set_bk_mode(self.thread, BkMode::Transparent)?
+            .wait()?;
+        let _ = self.dc.set_text_align(self.thread, align)?.wait()?;
+        Ok(())
+    }
+
     #[inline]
     fn submit(&mut self, draw: DrawType) -> crate::Result {
         match draw {
             DrawType::Stroke => {
                 // clear the fill
                 let cb = self.clear_brush()?;
-                self.dc.select_object(self.thread, cb)?.wait()?;
+                self.select_brush(cb)?;
 
                 // install the stroke
                 if let Some(s) = self.residual().pen.clone() {
                     let pen = self.get_pen_from_color(s)?;
-
-                    self.dc.select_object(self.thread, pen)?.wait()?;
+                    self.select_pen(pen)?;
                 }
             }
             DrawType::Fill => {
+                // only rectangles and polygons can be filled with gradients
+                if self.residual().gradient.is_some() {
+                    return Err(crate::Error::NotSupported(crate::NSOpType::Gradients));
+                }
+
+                // patterns have no color to outline the shape with, so clear the stroke
+                if let Some(image) = self.residual().pattern {
+                    let cp = self.clear_pen()?;
+                    self.select_pen(cp)?;
+                    let brush = self.get_pattern_brush(image)?;
+                    self.select_brush(brush)?;
+                    return Ok(());
+                }
+
                 // replace the stroke with a color
                 if let Some(f) = self.residual().brush.clone() {
-                    self.dc
-                        .select_object(self.thread, self.get_pen_from_color(f)?)?
-                        .wait()?;
-                    let brush = match self.residual().brushes.get(&f) {
-                        Some(o) => *o,
-                        None => {
-                            let (r, g, b, _) = f.clamp_u8();
-                            let color = YawwColor::from_rgb(r, g, b);
-                            let brush = self.thread.create_solid_brush(color)?.wait()?;
-                            self.residual().brushes.insert(f, brush);
-                            brush
-                        }
-                    };
-                    self.dc.select_object(self.thread, brush)?.wait()?;
+                    let pen = self.get_pen_from_color(f)?;
+                    self.select_pen(pen)?;
+                    let brush = self.get_brush_from_color(f)?;
+                    self.select_brush(brush)?;
                 } else {
                     log::warn!("Tried to fill with empty brush?");
                 }
@@ -230,37 +767,49 @@ impl<'thread, S: SendsDirective> YawwGdiSurface<'thread, S> {
         match draw {
             DrawType::Stroke => {
                 // clear the fill
-                if let Some(cf) = self.residual().clear_brush.take() {
-                    self.dc.select_object(self.thread, cf)?.await;
-                }
+                let cb = self.clear_brush_async().await?;
+                self.select_brush_async(cb).await?;
 
                 // install the stroke
                 if let Some(s) = self.residual().pen.clone() {
                     let pen = self.get_pen_from_color_async(s).await?;
-
-                    self.dc.select_object(self.thread, pen)?.await?;
+                    self.select_pen_async(pen).await?;
                 }
             }
             DrawType::Fill => {
+                // only rectangles and polygons can be filled with gradients
+                if self.residual().gradient.is_some() {
+                    return Err(crate::Error::NotSupported(crate::NSOpType::Gradients));
+                }
+
+                // patterns have no color to outline the shape with, so clear the stroke
+                if let Some(image) = self.residual().pattern {
+                    let cp = self.clear_pen_async().await?;
+                    self.select_pen_async(cp).await?;
+                    let brush = self.get_pattern_brush(image)?;
+                    self.select_brush_async(brush).await?;
+                    return Ok(());
+                }
+
                 // replace the stroke with a color
                 if let Some(f) = self.residual().brush.clone() {
-                    self.dc
-                        .select_object(self.thread, self.get_pen_from_color_async(f)?)?
-                        .await?;
+                    let pen = self.get_pen_from_color_async(f).await?;
+                    self.select_pen_async(pen).await?;
                     let brush = match self.residual().brushes.get(&f) {
                         Some(o) => *o,
                         None => {
                             let (r, g, b, _) = f.clamp_u8();
                             let color = YawwColor::from_rgb(r, g, b);
                             let brush = self.thread.create_solid_brush(color)?.await?;
-                            self.residual().brushes.insert(f, brush);
+                            let residual = self.residual();
+                            let evicted = residual.brushes.insert(f, brush);
+                            residual.evict_brushes(evicted);
                             brush
                         }
                     };
-                    let old_brush = self.dc.select_object(self.thread, brush)?.await?;
-                    if self.residual().clear_brush.is_none() {
-                        self.residual().clear_brush = Some(old_brush);
-                    }
+                    self.select_brush_async(brush).await?;
+                } else {
+                    log::warn!("Tried to fill with empty brush?");
                 }
             }
         }
@@ -270,58 +819,77 @@ impl<'thread, S: SendsDirective> YawwGdiSurface<'thread, S> {
 
     #[inline]
     fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> crate::Result {
+        let rounding = self.rounding();
         let t = ArrayIter::new([
-            self.dc.move_to(self.thread, x1 as i32, y1 as i32)?,
-            self.dc.line_to(self.thread, x2 as i32, y2 as i32)?,
+            self.dc
+                .move_to(self.thread, rounding.apply(x1), rounding.apply(y1))?,
+            self.dc
+                .line_to(self.thread, rounding.apply(x2), rounding.apply(y2))?,
         ]);
-        self.residual().task_queue.extend(t);
+        self.extend_tasks(t)?;
         Ok(())
     }
 
     #[inline]
     fn lines(&mut self, lines: &[LineSegment<f32>]) -> crate::Result {
-        self.residual().task_queue.reserve(lines.len() * 2);
-        lines.iter().copied().try_for_each::<_, crate::Result>(
-            |LineSegment {
-                 from: Point { x: x1, y: y1, .. },
-                 to: Point { x: x2, y: y2, .. },
-             }| {
-                let t = ArrayIter::new([
-                    self.dc.move_to(self.thread, x1 as i32, y1 as i32)?,
-                    self.dc.line_to(self.thread, x2 as i32, y2 as i32)?,
-                ]);
-                self.residual().task_queue.extend(t);
-                Ok(())
-            },
-        )
+        let rounding = self.rounding();
+        polyline_runs(rounding, lines)
+            .into_iter()
+            .try_for_each::<_, crate::Result>(|run| {
+                let t = self.dc.polyline(self.thread, run)?;
+                self.push_task(t)
+            })
     }
 
     #[inline]
     fn rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> crate::Result {
-        let x2 = (x + width) as i32;
-        let y2 = (y + height) as i32;
-        let t = self.dc.rectangle(self.thread, x as i32, y as i32, x2, y2)?;
-        self.residual().task_queue.push(t);
+        let rounding = self.rounding();
+        let x2 = rounding.apply(x + width);
+        let y2 = rounding.apply(y + height);
+        let t = self
+            .dc
+            .rectangle(self.thread, rounding.apply(x), rounding.apply(y), x2, y2)?;
+        self.push_task(t)?;
         Ok(())
     }
 
     #[inline]
     fn rectangles(&mut self, rects: &[Rect<f32>]) -> crate::Result {
+        let rounding = self.rounding();
         self.residual().task_queue.reserve(rects.len());
         rects.iter().copied().try_for_each::<_, crate::Result>(
             |Rect {
                  origin: Point { x, y, .. },
                  size: Size { width, height, .. },
              }| {
-                let x2 = (x + width) as i32;
-                let y2 = (y + height) as i32;
-                let t = self.dc.rectangle(self.thread, x as i32, y as i32, x2, y2)?;
-                self.residual().task_queue.push(t);
+                let x2 = rounding.apply(x + width);
+                let y2 = rounding.apply(y + height);
+                let t =
+                    self.dc
+                        .rectangle(self.thread, rounding.apply(x), rounding.apply(y), x2, y2)?;
+                self.push_task(t)?;
                 Ok(())
             },
         )
     }
 
+    #[inline]
+    fn round_rectangle(&mut self, rect: Rect<f32>, rx: f32, ry: f32) -> crate::Result {
+        let rounding = self.rounding();
+        let (rx, ry) = clamp_round_rect_radii(rect, rx, ry);
+        let t = self.dc.round_rect(
+            self.thread,
+            rounding.apply(rect.min_x()),
+            rounding.apply(rect.min_y()),
+            rounding.apply(rect.max_x()),
+            rounding.apply(rect.max_y()),
+            rounding.apply(rx * 2.0),
+            rounding.apply(ry * 2.0),
+        )?;
+        self.push_task(t)?;
+        Ok(())
+    }
+
     #[inline]
     fn arc(
         &mut self,
@@ -331,50 +899,81 @@ impl<'thread, S: SendsDirective> YawwGdiSurface<'thread, S> {
         yradius: f32,
         start_angle: Angle<f32>,
         sweep_angle: Angle<f32>,
+        draw: DrawType,
     ) -> crate::Result {
-        let [x1, y1, x2, y2, asx, asy, aex, aey] = calc_posns(Arc {
-            center: Point::new(xcenter, ycenter),
-            radii: Vector::new(xradius, yradius),
-            start_angle,
-            sweep_angle,
-            x_rotation: Angle { radians: 0.0 },
-        });
-        let t = self
-            .dc
-            .arc(self.thread, x1, y1, x2, y2, asx, asy, aex, aey)?;
-        self.residual().task_queue.push(t);
+        let rounding = self.rounding();
+        let [x1, y1, x2, y2, asx, asy, aex, aey] = calc_posns(
+            rounding,
+            Arc {
+                center: Point::new(xcenter, ycenter),
+                radii: Vector::new(xradius, yradius),
+                start_angle,
+                sweep_angle,
+                x_rotation: Angle { radians: 0.0 },
+            },
+        );
+        // GDI's Arc only outlines the arc, Chord fills it
+        let t = match draw {
+            DrawType::Stroke => self
+                .dc
+                .arc(self.thread, x1, y1, x2, y2, asx, asy, aex, aey)?,
+            DrawType::Fill => self
+                .dc
+                .chord(self.thread, x1, y1, x2, y2, asx, asy, aex, aey)?,
+        };
+        self.push_task(t)?;
         Ok(())
     }
 
     #[inline]
-    fn arcs(&mut self, arcs: &[Arc<f32>]) -> crate::Result {
+    fn arcs(&mut self, arcs: &[Arc<f32>], draw: DrawType) -> crate::Result {
+        let rounding = self.rounding();
+        let tolerance = self.tolerance();
         self.residual().task_queue.reserve(arcs.len());
         arcs.iter()
             .copied()
             .try_for_each::<_, crate::Result>(|arc| {
-                let [x1, y1, x2, y2, asx, asy, aex, aey] = calc_posns(arc);
-                let t = self
-                    .dc
-                    .arc(self.thread, x1, y1, x2, y2, asx, asy, aex, aey)?;
-                self.residual().task_queue.push(t);
+                // GDI can only draw axis-aligned arcs, so rotated ones are flattened
+                let t = if arc.x_rotation.radians != 0.0 {
+                    let points = rotated_arc_points(rounding, &arc, tolerance);
+                    match draw {
+                        DrawType::Stroke => self.dc.polyline(self.thread, points)?,
+                        DrawType::Fill => self.dc.polygon(self.thread, points)?,
+                    }
+                } else {
+                    let [x1, y1, x2, y2, asx, asy, aex, aey] = calc_posns(rounding, arc);
+                    match draw {
+                        DrawType::Stroke => {
+                            self.dc
+                                .arc(self.thread, x1, y1, x2, y2, asx, asy, aex, aey)?
+                        }
+                        DrawType::Fill => {
+                            self.dc
+                                .chord(self.thread, x1, y1, x2, y2, asx, asy, aex, aey)?
+                        }
+                    }
+                };
+                self.push_task(t)?;
                 Ok(())
             })
     }
 
     #[inline]
     fn ellipse(&mut self, xcenter: f32, ycenter: f32, xradius: f32, yradius: f32) -> crate::Result {
-        let x1 = (xcenter - xradius) as i32;
-        let y1 = (ycenter - yradius) as i32;
-        let x2 = x1 + ((xradius * 2.0) as i32);
-        let y2 = y1 + ((yradius * 2.0) as i32);
+        let rounding = self.rounding();
+        let x1 = rounding.apply(xcenter - xradius);
+        let y1 = rounding.apply(ycenter - yradius);
+        let x2 = x1 + rounding.apply(xradius * 2.0);
+        let y2 = y1 + rounding.apply(yradius * 2.0);
 
         let t = self.dc.ellipse(self.thread, x1, y1, x2, y2)?;
-        self.residual().task_queue.push(t);
+        self.push_task(t)?;
         Ok(())
     }
 
     #[inline]
     fn ellipses(&mut self, rects: &[Ellipse]) -> crate::Result {
+        let rounding = self.rounding();
         self.residual().task_queue.reserve(rects.len());
         rects.iter().copied().try_for_each::<_, crate::Result>(
             |Ellipse {
@@ -391,36 +990,231 @@ impl<'thread, S: SendsDirective> YawwGdiSurface<'thread, S> {
                          ..
                      },
              }| {
-                let x1 = (xcenter - xradius) as i32;
-                let y1 = (ycenter - yradius) as i32;
-                let x2 = x1 + ((xradius * 2.0) as i32);
-                let y2 = y1 + ((yradius * 2.0) as i32);
+                let x1 = rounding.apply(xcenter - xradius);
+                let y1 = rounding.apply(ycenter - yradius);
+                let x2 = x1 + rounding.apply(xradius * 2.0);
+                let y2 = y1 + rounding.apply(yradius * 2.0);
 
                 let t = self.dc.ellipse(self.thread, x1, y1, x2, y2)?;
-                self.residual().task_queue.push(t);
+                self.push_task(t)?;
                 Ok(())
             },
         )
     }
 
     #[inline]
-    fn polygon(&mut self, pts: &[Point<f32>]) -> crate::Result {
-        let points: Vec<YawwPoint> = pts
-            .iter()
+    fn bezier_path(&mut self, path: impl IntoIterator<Item = PathEvent>) -> crate::Result {
+        let rounding = self.rounding();
+        // GDI's PolyBezier takes a starting point followed by sets of three points (two control points and an
+        // endpoint), so lines and quadratic curves are raised to cubic curves and every sub-path is submitted
+        // separately
+        let mut points: Vec<YawwPoint> = vec![];
+        path.into_iter().try_for_each::<_, crate::Result>(|event| {
+            match event {
+                PathEvent::Begin { at } => {
+                    points.clear();
+                    points.push(yaww_point(rounding, at));
+                }
+                PathEvent::Line { from, to } => {
+                    points
+                        .extend(ArrayIter::new([from, to, to]).map(|pt| yaww_point(rounding, pt)));
+                }
+                PathEvent::Quadratic { from, ctrl, to } => {
+                    let CubicBezierSegment {
+                        ctrl1, ctrl2, to, ..
+                    } = QuadraticBezierSegment { from, ctrl, to }.to_cubic();
+                    points.extend(
+                        ArrayIter::new([ctrl1, ctrl2, to]).map(|pt| yaww_point(rounding, pt)),
+                    );
+                }
+                PathEvent::Cubic {
+                    ctrl1, ctrl2, to, ..
+                } => {
+                    points.extend(
+                        ArrayIter::new([ctrl1, ctrl2, to]).map(|pt| yaww_point(rounding, pt)),
+                    );
+                }
+                PathEvent::End { last, first, close } => {
+                    if close {
+                        points.extend(
+                            ArrayIter::new([last, first, first]).map(|pt| yaww_point(rounding, pt)),
+                        );
+                    }
+
+                    if points.len() > 1 {
+                        let t = self.dc.poly_bezier(self.thread, mem::take(&mut points))?;
+                        self.push_task(t)?;
+                    }
+                }
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Get the size and translucency of an image.
+    #[inline]
+    fn image_info(&mut self, image: &Image) -> crate::Result<ImageInfo> {
+        self.residual()
+            .image_info
+            .get(&Dc::from_raw(image.into_raw()))
             .copied()
-            .map(|Point { x, y, .. }| YawwPoint {
-                x: x as i32,
-                y: y as i32,
-            })
-            .collect();
-        let t = self.dc.polygon(self.thread, points)?;
-        self.residual().task_queue.push(t);
-        Ok(())
+            .ok_or(crate::Error::ImageNotAvailable)
     }
-}
 
-#[derive(Copy, Clone)]
-enum DrawType {
+    /// Fill the shapes with the current gradient, if there is one. Returns `false` if there is no gradient.
+    #[inline]
+    fn gradient_shapes(
+        &mut self,
+        rects: &[Rect<f32>],
+        polys: &[&[Point<f32>]],
+    ) -> crate::Result<bool> {
+        let gradient = match self.residual().gradient.clone() {
+            Some(gradient) => gradient,
+            None => return Ok(false),
+        };
+
+        rects
+            .iter()
+            .try_for_each(|rect| self.gradient_rectangle(&gradient, *rect))?;
+        if !polys.is_empty() {
+            self.gradient_polygons(&gradient, polys)?;
+        }
+
+        Ok(true)
+    }
+
+    /// GDI has no gradient brushes, so the gradient is rasterized into an image and drawn over the rectangle.
+    #[inline]
+    fn gradient_rectangle(&mut self, gradient: &FillRule, rect: Rect<f32>) -> crate::Result {
+        let rect = rect.round_out();
+        if rect.is_empty() {
+            return Ok(());
+        }
+
+        let pixels = gradient
+            .rasterize(rect)
+            .ok_or(crate::Error::NotSupported(crate::NSOpType::Gradients))?;
+        let image = self.create_image(
+            &pixels,
+            rect.size.width as _,
+            rect.size.height as _,
+            ImageFormat::Rgba,
+        )?;
+        let res = self.draw_image(&image, rect);
+        self.destroy_image(image)?;
+        res
+    }
+
+    /// Fill the polygons with a gradient by drawing it over their bounding box, clipped to the polygons.
+    #[inline]
+    fn gradient_polygons(&mut self, gradient: &FillRule, polys: &[&[Point<f32>]]) -> crate::Result {
+        let rounding = self.rounding();
+        let bounds = Rect::from_points(polys.iter().flat_map(|points| points.iter().copied()));
+
+        // clip regions are in device coordinates, so the world transform is applied to the points here
+        let transform = self.residual().transform;
+        let pts: Vec<YawwPoint> = polys
+            .iter()
+            .flat_map(|points| points.iter().copied())
+            .map(|point| yaww_point(rounding, transform.transform_point(point)))
+            .collect();
+        let counts: Vec<usize> = polys.iter().map(|points| points.len()).collect();
+        let mode = self.dc.get_poly_fill_mode(self.thread)?.wait()?;
+        let region = self
+            .thread
+            .create_poly_polygon_rgn(pts, counts, mode)?
+            .wait()?;
+
+        // SaveDC and RestoreDC take care of bringing back the old clip region
+        let t = self.dc.save_dc(self.thread)?;
+        self.push_task(t)?;
+        let t = self
+            .dc
+            .ext_select_clip_rgn(self.thread, Some(region), RegionMode::And)?;
+        self.push_task(t)?;
+
+        // ExtSelectClipRgn copies the region, so it can be deleted once that's done
+        let _ = region.delete(self.thread)?;
+
+        let res = self.gradient_rectangle(gradient, bounds);
+        let t = self.dc.restore_dc(self.thread, -1)?;
+        self.push_task(t)?;
+        res
+    }
+
+    /// Get the fill color with the global alpha multiplied in, if that isn't fully opaque.
+    #[inline]
+    fn translucent_brush(&mut self) -> Option<Color> {
+        let residual = self.residual();
+        let alpha = residual.alpha;
+        residual
+            .brush
+            .map(|color| color.scale_alpha(alpha))
+            .filter(|color| color.alpha() < 1.0)
+    }
+
+    /// Solid GDI brushes are always opaque, so translucent rectangles are filled by stretching a single pixel of
+    /// the color over them using AlphaBlend.
+    #[inline]
+    fn alpha_rectangles(&mut self, color: Color, rects: &[Rect<f32>]) -> crate::Result {
+        let rounding = self.rounding();
+        let (r, g, b, a) = color.clamp_u8();
+
+        let compat_dc = self.dc.create_compatible_dc(self.thread)?.wait()?;
+        let bitmap = compat_dc
+            .create_compatible_bitmap(self.thread, 1, 1)?
+            .wait()?;
+        let old_image = compat_dc.select_object(self.thread, bitmap)?.wait()?;
+        compat_dc
+            .set_pixel(self.thread, 0, 0, YawwColor::from_rgb(r, g, b))?
+            .wait()?;
+
+        self.residual().task_queue.reserve(rects.len());
+        rects.iter().try_for_each::<_, crate::Result>(|rect| {
+            let t = compat_dc.alpha_blend(
+                self.thread,
+                0,
+                0,
+                1,
+                1,
+                self.dc,
+                rounding.apply(rect.origin.x),
+                rounding.apply(rect.origin.y),
+                rounding.apply(rect.size.width),
+                rounding.apply(rect.size.height),
+                a,
+            )?;
+            self.push_task(t)?;
+            Ok(())
+        })?;
+
+        // these are processed after the blits that use them
+        let _ = compat_dc.select_object(self.thread, old_image)?;
+        let _ = bitmap.delete(self.thread)?;
+        let _ = compat_dc.delete(self.thread)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn polygon(&mut self, pts: &[Point<f32>]) -> crate::Result {
+        let rounding = self.rounding();
+        let points: Vec<YawwPoint> = pts
+            .iter()
+            .copied()
+            .map(|Point { x, y, .. }| YawwPoint {
+                x: rounding.apply(x),
+                y: rounding.apply(y),
+            })
+            .collect();
+        let t = self.dc.polygon(self.thread, points)?;
+        self.push_task(t)?;
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone)]
+enum DrawType {
     Stroke,
     Fill,
 }
@@ -441,13 +1235,43 @@ impl<'thread, S: SendsDirective> Surface for YawwGdiSurface<'thread, S> {
 
     #[inline]
     fn set_fill(&mut self, fill: FillRule) -> crate::Result {
-        match fill {
+        match fill.clone() {
             FillRule::SolidColor(color) => {
-                self.residual().brush = Some(color);
+                let residual = self.residual();
+                residual.brush = Some(color);
+                residual.pattern = None;
+                residual.gradient = None;
                 Ok(())
             }
-            _ => Err(crate::Error::NotSupported(crate::NSOpType::Gradients)),
-        }
+            FillRule::Pattern(pattern) => {
+                let image = pattern_brush_image(&pattern)?;
+                let residual = self.residual();
+                residual.brush = None;
+                residual.pattern = Some(image);
+                residual.gradient = None;
+                Ok(())
+            }
+            // GDI has no gradient brushes, so gradients are rasterized when a shape is filled
+            gradient => {
+                let residual = self.residual();
+                if residual.fallback == FallbackPolicy::Reject {
+                    return Err(crate::Error::NotSupported(crate::NSOpType::Gradients));
+                }
+
+                residual.brush = None;
+                residual.pattern = None;
+                residual.gradient = Some(gradient);
+                Ok(())
+            }
+        }?;
+
+        self.residual().fill = Some(fill);
+        Ok(())
+    }
+
+    #[inline]
+    fn fill_rule(&self) -> Option<&FillRule> {
+        self.residual.as_ref()?.fill.as_ref()
     }
 
     #[inline]
@@ -456,15 +1280,321 @@ impl<'thread, S: SendsDirective> Surface for YawwGdiSurface<'thread, S> {
         Ok(())
     }
 
+    #[inline]
+    fn tolerance(&self) -> f32 {
+        self.residual
+            .as_ref()
+            .expect("Already dropped?!?!")
+            .tolerance
+    }
+
+    #[inline]
+    fn set_tolerance(&mut self, tolerance: f32) -> crate::Result {
+        self.residual().tolerance = tolerance.max(MIN_TOLERANCE);
+        Ok(())
+    }
+
+    #[inline]
+    fn set_fallback_policy(&mut self, policy: FallbackPolicy) -> crate::Result {
+        self.residual().fallback = policy;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_line_cap(&mut self, cap: LineCap) -> crate::Result {
+        self.residual().cap = cap;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_line_join(&mut self, join: LineJoin) -> crate::Result {
+        self.residual().join = join;
+        Ok(())
+    }
+
+    /// The miter limit is part of the DC, so `SaveDC` and `RestoreDC` take care of it.
+    #[inline]
+    fn set_miter_limit(&mut self, limit: f32) -> crate::Result {
+        let t = self.dc.set_miter_limit(self.thread, limit.max(1.0))?;
+        self.push_task(t)
+    }
+
+    #[inline]
+    fn set_fill_mode(&mut self, mode: FillMode) -> crate::Result {
+        // the fill mode is part of the DC, so SaveDC and RestoreDC take care of it
+        let t = self
+            .dc
+            .set_poly_fill_mode(self.thread, gdi_fill_mode(mode))?;
+        self.push_task(t)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_dash(&mut self, pattern: &[f32], _offset: f32) -> crate::Result {
+        let residual = self.residual();
+        residual.dash.clear();
+        residual.dash.extend(
+            pattern
+                .iter()
+                .map(|length| cmp::max(length.abs().round() as u32, 1)),
+        );
+        Ok(())
+    }
+
+    /// GDI brushes are always opaque, so only rectangle fills are blended using the global alpha. Other shapes
+    /// are drawn opaque, just like when the fill color itself is translucent.
+    #[inline]
+    fn set_global_alpha(&mut self, alpha: f32) -> crate::Result {
+        self.residual().alpha = alpha.clamp(0.0, 1.0);
+        Ok(())
+    }
+
+    #[inline]
+    fn push_clip(&mut self, rect: Rect<f32>) -> crate::Result {
+        let rounding = self.rounding();
+        // save the current clip so that it can be restored when this one is popped
+        let saved = self.dc.get_clip_rgn(self.thread)?.wait()?;
+        self.residual().clips.push(saved);
+
+        // IntersectClipRect already combines the rectangle with the current clip
+        let Rect {
+            origin: Point { x, y, .. },
+            size: Size { width, height, .. },
+        } = rect;
+        let t = self.dc.intersect_clip_rect(
+            self.thread,
+            rounding.apply(x),
+            rounding.apply(y),
+            rounding.apply(x + width),
+            rounding.apply(y + height),
+        )?;
+        self.push_task(t)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn pop_clip(&mut self) -> crate::Result {
+        let saved = self.residual().clips.pop().ok_or(crate::Error::NoClip)?;
+
+        // selecting no region removes the clip entirely
+        let t = self.dc.select_clip_rgn(self.thread, saved)?;
+        self.push_task(t)?;
+
+        // SelectClipRgn copies the region, so our saved copy is no longer needed
+        if let Some(region) = saved {
+            let _ = region.delete(self.thread)?;
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn set_transform(&mut self, transform: Transform) -> crate::Result {
+        // world transforms are only respected in the advanced graphics mode
+        let m = transform.into_inner();
+        let t = ArrayIter::new([
+            self.dc
+                .set_graphics_mode(self.thread, GraphicsMode::Advanced)?,
+            self.dc.set_world_transform(
+                self.thread,
+                XForm {
+                    m11: m.m11,
+                    m12: m.m12,
+                    m21: m.m21,
+                    m22: m.m22,
+                    dx: m.m31,
+                    dy: m.m32,
+                },
+            )?,
+        ]);
+        self.residual().transform = transform;
+        self.extend_tasks(t)
+    }
+
+    #[inline]
+    fn transform(&mut self, transform: Transform) -> crate::Result {
+        let transform = transform.then(&self.residual().transform);
+        self.set_transform(transform)
+    }
+
+    #[inline]
+    fn save(&mut self) -> crate::Result {
+        let t = self.dc.save_dc(self.thread)?;
+        let residual = self.residual();
+        let state = SavedState {
+            pen: residual.pen,
+            fill: residual.fill.clone(),
+            brush: residual.brush,
+            pattern: residual.pattern,
+            gradient: residual.gradient.clone(),
+            width: residual.width,
+            cap: residual.cap,
+            join: residual.join,
+            dash: residual.dash.clone(),
+            alpha: residual.alpha,
+            clip_depth: residual.clips.len(),
+            transform: residual.transform,
+        };
+        residual.saved.push(state);
+        self.push_task(t)
+    }
+
+    #[inline]
+    fn restore(&mut self) -> crate::Result {
+        let SavedState {
+            pen,
+            fill,
+            brush,
+            pattern,
+            gradient,
+            width,
+            cap,
+            join,
+            dash,
+            alpha,
+            clip_depth,
+            transform,
+        } = self
+            .residual()
+            .saved
+            .pop()
+            .ok_or(crate::Error::StaticMsg("No saved state to restore"))?;
+
+        // RestoreDC brings back the clip region, so the regions saved since then are no longer needed. It also
+        // brings back the pen and brush that were selected at the time.
+        let t = self.dc.restore_dc(self.thread, -1)?;
+        self.push_task(t)?;
+        self.residual().forget_selection();
+        let stale: Vec<Region> = self
+            .residual()
+            .clips
+            .drain(clip_depth..)
+            .flatten()
+            .collect();
+        stale.into_iter().try_for_each::<_, crate::Result>(|r| {
+            let _ = r.delete(self.thread)?;
+            Ok(())
+        })?;
+
+        let residual = self.residual();
+        residual.pen = pen;
+        residual.fill = fill;
+        residual.brush = brush;
+        residual.pattern = pattern;
+        residual.gradient = gradient;
+        residual.width = width;
+        residual.cap = cap;
+        residual.join = join;
+        residual.dash = dash;
+        residual.alpha = alpha;
+        residual.transform = transform;
+        Ok(())
+    }
+
     #[inline]
     fn flush(&mut self) -> crate::Result {
-        self.residual()
-            .task_queue
-            .drain(..)
-            .try_for_each::<_, crate::Result>(|t| {
-                t.wait()?;
-                Ok(())
-            })
+        // every task is waited on, even if an earlier one failed
+        let mut errors =
+            crate::Error::collect_errors(self.residual().task_queue.drain(..).map(|t| t.wait()));
+
+        // now that nothing is drawing with them, evicted pens and brushes can be deleted
+        let evicted = mem::take(&mut self.residual().evicted);
+        if !evicted.is_empty() {
+            self.residual().forget_selection();
+        }
+        errors.extend(evicted.into_iter().filter_map(|o| {
+            let res: crate::Result = o
+                .delete(self.thread)
+                .and_then(|t| t.wait())
+                .map_err(Into::into);
+            res.err()
+        }));
+
+        crate::Error::from_errors(errors)
+    }
+
+    #[inline]
+    fn size(&self) -> crate::Result<Size<f32>> {
+        let (width, height) = self.dc.bitmap_dimensions(self.thread)?.wait()?;
+        Ok(Size::new(width as _, height as _))
+    }
+
+    #[inline]
+    fn measure_text(&self, text: &str, font: &Font) -> crate::Result<Size<f32>> {
+        // GetTextExtentPoint32 measures using the font selected into the DC
+        let (width, height) = self.with_font(font, |this| {
+            Ok(this
+                .dc
+                .text_extent_point(this.thread, text.to_string())?
+                .wait()?)
+        })?;
+        Ok(Size::new(width as _, height as _))
+    }
+
+    /// Text is drawn in the fill color, or in black if shapes are filled with a gradient or pattern.
+    #[inline]
+    fn draw_text(&mut self, text: &str, x: f32, y: f32, font: &Font) -> crate::Result {
+        let color = self.residual().brush.unwrap_or(Color::BLACK);
+        let rounding = self.rounding();
+        let t = self.with_font(font, |this| {
+            this.set_text_style(color, TextAlign::TOP | TextAlign::LEFT)?;
+            Ok(this.dc.text_out(
+                this.thread,
+                rounding.apply(x),
+                rounding.apply(y),
+                text.to_string(),
+            )?)
+        })?;
+        self.push_task(t)
+    }
+
+    /// Glyphs are drawn in the fill color, or in black if shapes are filled with a gradient or pattern.
+    #[inline]
+    fn draw_glyphs(&mut self, glyphs: &[(GlyphId, Point<f32>)], font: &Font) -> crate::Result {
+        if glyphs.is_empty() {
+            return Ok(());
+        }
+
+        let color = self.residual().brush.unwrap_or(Color::BLACK);
+        let runs = glyph_runs(self.rounding(), glyphs);
+        let t = self.with_font(font, |this| {
+            this.set_text_style(color, TextAlign::BASELINE | TextAlign::LEFT)?;
+            // ETO_GLYPH_INDEX draws the glyphs as they are, instead of mapping characters to glyphs
+            runs.into_iter()
+                .map(|run| {
+                    Ok(this.dc.ext_text_out(
+                        this.thread,
+                        run.x,
+                        run.y,
+                        ExtTextOutOptions::GLYPH_INDEX,
+                        run.glyphs,
+                        run.dx,
+                    )?)
+                })
+                .collect::<crate::Result<Vec<_>>>()
+        })?;
+        self.extend_tasks(t)
+    }
+
+    #[inline]
+    fn clear(&mut self, color: Color) -> crate::Result {
+        let size = self.size()?;
+        let brush = self.get_brush_from_color(color)?;
+
+        // FillRect uses the given brush directly, so the current pen is left alone
+        self.save()?;
+        self.set_transform(Transform::identity())?;
+        self.dc
+            .fill_rect(
+                self.thread,
+                0,
+                0,
+                size.width as i32,
+                size.height as i32,
+                brush,
+            )?
+            .wait()?;
+        self.restore()
     }
 
     #[inline]
@@ -475,36 +1605,55 @@ impl<'thread, S: SendsDirective> Surface for YawwGdiSurface<'thread, S> {
         height: u32,
         format: ImageFormat,
     ) -> crate::Result<Image> {
+        let (image_bytes, format) = crate::image::bgra_to_rgba(image_bytes, format);
+        let image_bytes = &*image_bytes;
+
         // create a compatible DC
         let compat_dc = self.dc.create_compatible_dc(self.thread)?.wait()?;
-
-        // create a bitmap using that dc
-        let bitmap = compat_dc
-            .create_compatible_bitmap(self.thread, width as _, height as _)?
-            .wait()?;
-        let old_image = compat_dc.select_object(self.thread, bitmap)?.wait()?;
-
-        // draw pixels onto it
-        compat_dc
-            .draw_pixels(
-                self.thread,
-                0,
-                0,
-                width as _,
-                crate::image::iterate_pixels(image_bytes, width, height, format).map(|pixel| {
-                    pixel
-                        .iter()
-                        .copied()
-                        .enumerate()
-                        .rfold(0, |color, (i, channel)| {
-                            color | ((channel as u32) << (i * 8))
-                        })
-                }),
-            )?
-            .wait()?;
+        let translucent = is_translucent(image_bytes, width, height, format);
+
+        let old_image = if translucent {
+            // AlphaBlend needs a 32-bit DIB section containing premultiplied pixels
+            let bitmap = compat_dc
+                .create_dib_section(
+                    self.thread,
+                    width as _,
+                    height as _,
+                    premultiplied_pixels(image_bytes, width, height, format),
+                )?
+                .wait()?;
+            compat_dc.select_object(self.thread, bitmap)?.wait()?
+        } else {
+            // create a bitmap using that dc
+            let bitmap = compat_dc
+                .create_compatible_bitmap(self.thread, width as _, height as _)?
+                .wait()?;
+            let old_image = compat_dc.select_object(self.thread, bitmap)?.wait()?;
+
+            // draw pixels onto it
+            compat_dc
+                .draw_pixels(
+                    self.thread,
+                    0,
+                    0,
+                    width as _,
+                    crate::image::iterate_pixels(image_bytes, width, height, format).map(colorref),
+                )?
+                .wait()?;
+            old_image
+        };
 
         // insert into images map
-        self.residual().image_dcs.insert(compat_dc, old_image);
+        let residual = self.residual();
+        residual.image_dcs.insert(compat_dc, old_image);
+        residual.image_info.insert(
+            compat_dc,
+            ImageInfo {
+                width,
+                height,
+                translucent,
+            },
+        );
 
         // return
         Ok(Image::from_raw(compat_dc.into_raw()))
@@ -512,7 +1661,13 @@ impl<'thread, S: SendsDirective> Surface for YawwGdiSurface<'thread, S> {
 
     #[inline]
     fn destroy_image(&mut self, image: Image) -> crate::Result {
+        // the brush may still be in use by queued draws, so it is deleted on the next flush
+        if let Some(brush) = self.residual().patterns.remove(&image) {
+            self.residual().evicted.push(brush.into_gdi_object());
+        }
+
         let dc = Dc::from_raw(image.into_raw());
+        self.residual().image_info.remove(&dc);
         if let Some(old_image) = self.residual().image_dcs.remove(&dc) {
             let bitmap = dc.select_object(self.thread, old_image)?.wait()?;
             let _ = bitmap.delete(self.thread)?;
@@ -522,18 +1677,116 @@ impl<'thread, S: SendsDirective> Surface for YawwGdiSurface<'thread, S> {
         Ok(())
     }
 
+    #[inline]
+    fn draw_point(&mut self, x: f32, y: f32) -> crate::Result {
+        let rounding = self.rounding();
+        let (r, g, b, _) = self.residual().pen.unwrap_or(Color::BLACK).clamp_u8();
+        let t = self.dc.set_pixel(
+            self.thread,
+            rounding.apply(x),
+            rounding.apply(y),
+            YawwColor::from_rgb(r, g, b),
+        )?;
+        self.push_task(t)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn draw_points(&mut self, points: &[Point<f32>]) -> crate::Result {
+        let rounding = self.rounding();
+        // GDI leaves off the last pixel of a line, so a line one pixel long covers exactly one pixel. Sending
+        // them all at once with PolyPolyline uses a single task instead of one per point.
+        self.submit(Stroke)?;
+        let pts = point_strokes(rounding, points);
+        let counts = vec![2; points.len()];
+        let t = self.dc.poly_polyline(self.thread, pts, counts)?;
+        self.push_task(t)?;
+        Ok(())
+    }
+
     #[inline]
     fn draw_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> crate::Result {
         self.submit(Stroke)?;
         self.line(x1, y1, x2, y2)
     }
 
+    /// GDI pens can't start partway into their dash pattern, so the dashes are drawn with a temporary solid pen.
+    #[inline]
+    fn draw_dashed_line(
+        &mut self,
+        from: Point<f32>,
+        to: Point<f32>,
+        pattern: &[f32],
+        offset: f32,
+    ) -> crate::Result {
+        let lines = dash_lines(iter::once(LineSegment { from, to }), pattern, offset);
+        let dash = mem::take(&mut self.residual().dash);
+        let res = self.submit(Stroke).and_then(|()| self.lines(&lines));
+        self.residual().dash = dash;
+        res
+    }
+
     #[inline]
     fn draw_lines(&mut self, lines: &[LineSegment<f32>]) -> crate::Result {
         self.submit(Stroke)?;
         self.lines(lines)
     }
 
+    #[inline]
+    fn draw_polyline(&mut self, pts: &[Point<f32>]) -> crate::Result {
+        let rounding = self.rounding();
+        if pts.len() < 2 {
+            return Ok(());
+        }
+
+        self.submit(Stroke)?;
+        let t = self.dc.polyline(self.thread, yaww_points(rounding, pts))?;
+        self.push_task(t)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn draw_path(&mut self, path: PathSlice<'_>) -> crate::Result {
+        self.submit(Stroke)?;
+        self.bezier_path(path.iter())
+    }
+
+    #[inline]
+    fn draw_path_owned(&mut self, path: Path) -> crate::Result {
+        self.submit(Stroke)?;
+        self.bezier_path(path.iter())
+    }
+
+    #[inline]
+    fn draw_paths(&mut self, paths: PathBufferSlice<'_>) -> crate::Result {
+        self.submit(Stroke)?;
+        paths
+            .indices()
+            .try_for_each(|index| self.bezier_path(paths.get(index).iter()))
+    }
+
+    #[inline]
+    fn draw_paths_owned(&mut self, paths: PathBuffer) -> crate::Result {
+        self.submit(Stroke)?;
+        paths
+            .indices()
+            .try_for_each(|index| self.bezier_path(paths.get(index).iter()))
+    }
+
+    #[inline]
+    fn draw_bezier_curve(&mut self, curve: CubicBezierSegment<f32>) -> crate::Result {
+        let rounding = self.rounding();
+        self.submit(Stroke)?;
+        let t = self.dc.poly_bezier(
+            self.thread,
+            ArrayIter::new([curve.from, curve.ctrl1, curve.ctrl2, curve.to])
+                .map(|pt| yaww_point(rounding, pt))
+                .collect::<Vec<YawwPoint>>(),
+        )?;
+        self.push_task(t)?;
+        Ok(())
+    }
+
     #[inline]
     fn draw_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> crate::Result {
         self.submit(Stroke)?;
@@ -546,6 +1799,12 @@ impl<'thread, S: SendsDirective> Surface for YawwGdiSurface<'thread, S> {
         self.rectangles(rects)
     }
 
+    #[inline]
+    fn draw_round_rectangle(&mut self, rect: Rect<f32>, rx: f32, ry: f32) -> crate::Result {
+        self.submit(Stroke)?;
+        self.round_rectangle(rect, rx, ry)
+    }
+
     #[inline]
     fn draw_arc(
         &mut self,
@@ -557,13 +1816,21 @@ impl<'thread, S: SendsDirective> Surface for YawwGdiSurface<'thread, S> {
         sweep_angle: Angle<f32>,
     ) -> crate::Result {
         self.submit(Stroke)?;
-        self.arc(xcenter, ycenter, xradius, yradius, start_angle, sweep_angle)
+        self.arc(
+            xcenter,
+            ycenter,
+            xradius,
+            yradius,
+            start_angle,
+            sweep_angle,
+            Stroke,
+        )
     }
 
     #[inline]
     fn draw_arcs(&mut self, arcs: &[Arc<f32>]) -> crate::Result {
         self.submit(Stroke)?;
-        self.arcs(arcs)
+        self.arcs(arcs, Stroke)
     }
 
     #[inline]
@@ -586,22 +1853,64 @@ impl<'thread, S: SendsDirective> Surface for YawwGdiSurface<'thread, S> {
 
     #[inline]
     fn fill_polygon(&mut self, points: &[Point<f32>]) -> crate::Result {
+        if self.gradient_shapes(&[], &[points])? {
+            return Ok(());
+        }
+
         self.submit(Fill)?;
         self.polygon(points)
     }
 
+    #[inline]
+    fn fill_polygons(&mut self, polys: &[&[Point<f32>]]) -> crate::Result {
+        let rounding = self.rounding();
+        if self.gradient_shapes(&[], polys)? {
+            return Ok(());
+        }
+
+        // PolyPolygon sends every polygon in a single task
+        self.submit(Fill)?;
+        let (pts, counts) = poly_polygon_points(rounding, polys);
+        let t = self.dc.poly_polygon(self.thread, pts, counts)?;
+        self.push_task(t)?;
+        Ok(())
+    }
+
     #[inline]
     fn fill_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> crate::Result {
+        let rect = Rect::new(Point::new(x, y), Size::new(width, height));
+        if self.gradient_shapes(&[rect], &[])? {
+            return Ok(());
+        }
+
+        if let Some(color) = self.translucent_brush() {
+            return self.alpha_rectangles(color, &[rect]);
+        }
+
         self.submit(Fill)?;
         self.rectangle(x, y, width, height)
     }
 
     #[inline]
     fn fill_rectangles(&mut self, rects: &[Rect<f32>]) -> crate::Result {
+        if self.gradient_shapes(rects, &[])? {
+            return Ok(());
+        }
+
+        if let Some(color) = self.translucent_brush() {
+            return self.alpha_rectangles(color, rects);
+        }
+
         self.submit(Fill)?;
         self.rectangles(rects)
     }
 
+    #[inline]
+    fn fill_round_rectangle(&mut self, rect: Rect<f32>, rx: f32, ry: f32) -> crate::Result {
+        self.submit(Fill)?;
+        self.round_rectangle(rect, rx, ry)
+    }
+
     #[inline]
     fn fill_arc(
         &mut self,
@@ -613,13 +1922,48 @@ impl<'thread, S: SendsDirective> Surface for YawwGdiSurface<'thread, S> {
         sweep_angle: Angle<f32>,
     ) -> crate::Result {
         self.submit(Fill)?;
-        self.arc(xcenter, ycenter, xradius, ycenter, start_angle, sweep_angle)
-    }
-
+        self.arc(
+            xcenter,
+            ycenter,
+            xradius,
+            yradius,
+            start_angle,
+            sweep_angle,
+            Fill,
+        )
+    }
+
+    #[inline]
+    fn fill_pie(
+        &mut self,
+        center: Point<f32>,
+        radii: Vector<f32>,
+        start_angle: Angle<f32>,
+        sweep_angle: Angle<f32>,
+    ) -> crate::Result {
+        let rounding = self.rounding();
+        self.submit(Fill)?;
+        let [x1, y1, x2, y2, asx, asy, aex, aey] = calc_posns(
+            rounding,
+            Arc {
+                center,
+                radii,
+                start_angle,
+                sweep_angle,
+                x_rotation: Angle { radians: 0.0 },
+            },
+        );
+        let t = self
+            .dc
+            .pie(self.thread, x1, y1, x2, y2, asx, asy, aex, aey)?;
+        self.push_task(t)?;
+        Ok(())
+    }
+
     #[inline]
     fn fill_arcs(&mut self, arcs: &[Arc<f32>]) -> crate::Result {
         self.submit(Fill)?;
-        self.arcs(arcs)
+        self.arcs(arcs, Fill)
     }
 
     #[inline]
@@ -668,161 +2012,601 @@ impl<'thread, S: SendsDirective> Surface for YawwGdiSurface<'thread, S> {
             dst_y,
             BitBltOp::SrcCopy,
         )?;
-        self.residual().task_queue.push(t);
+        self.push_task(t)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn draw_image(&mut self, image: &Image, dst: Rect<f32>) -> crate::Result {
+        let ImageInfo { width, height, .. } = self.image_info(image)?;
+        self.draw_image_region(
+            image,
+            Rect::new(Point::origin(), Size::new(width as _, height as _)),
+            dst,
+        )
+    }
+
+    #[inline]
+    fn draw_image_region(
+        &mut self,
+        image: &Image,
+        src: Rect<f32>,
+        dst: Rect<f32>,
+    ) -> crate::Result {
+        let rounding = self.rounding();
+        let ImageInfo {
+            width,
+            height,
+            translucent,
+        } = self.image_info(image)?;
+        crate::image::check_image_region(src, Size::new(width as _, height as _))?;
+
+        let src_dc = Dc::from_raw(image.into_raw());
+        let t = if translucent {
+            src_dc.alpha_blend_per_pixel(
+                self.thread,
+                rounding.apply(src.origin.x),
+                rounding.apply(src.origin.y),
+                rounding.apply(src.size.width),
+                rounding.apply(src.size.height),
+                self.dc,
+                rounding.apply(dst.origin.x),
+                rounding.apply(dst.origin.y),
+                rounding.apply(dst.size.width),
+                rounding.apply(dst.size.height),
+            )?
+        } else {
+            src_dc.stretch_blt(
+                self.thread,
+                rounding.apply(src.origin.x),
+                rounding.apply(src.origin.y),
+                rounding.apply(src.size.width),
+                rounding.apply(src.size.height),
+                self.dc,
+                rounding.apply(dst.origin.x),
+                rounding.apply(dst.origin.y),
+                rounding.apply(dst.size.width),
+                rounding.apply(dst.size.height),
+                BitBltOp::SrcCopy,
+            )?
+        };
+        self.push_task(t)?;
         Ok(())
     }
 }
 
 #[cfg(feature = "async")]
-impl<'thread> AsyncSurface for YawwGdiSurface<'thread> {
+impl<'thread, S: SendsDirective + Sync> AsyncSurface for YawwGdiSurface<'thread, S> {
     #[inline]
     fn features(&self) -> SurfaceFeatures {
         FEATURES
     }
 
     #[inline]
-    fn set_stroke(&mut self, color: Color) -> crate::Result {
-        self.residual().pen = Some(color);
-        Ok(())
+    fn set_stroke_async<'future>(&'future mut self, color: Color) -> GenericResult<'future> {
+        Box::pin(future::ready(self.set_stroke(color)))
     }
 
     #[inline]
-    fn set_fill(&mut self, fill: FillRule) -> crate::Result {
-        match fill {
-            FillRule::SolidColor(color) => {
-                self.residual().brush = Some(color);
-                Ok(())
+    fn set_fill_async<'future>(&'future mut self, fill: FillRule) -> GenericResult<'future> {
+        Box::pin(future::ready(self.set_fill(fill)))
+    }
+
+    #[inline]
+    fn set_line_width_async<'future>(&'future mut self, width: usize) -> GenericResult<'future> {
+        Box::pin(future::ready(self.set_line_width(width)))
+    }
+
+    #[inline]
+    fn flush_async<'future>(&'future mut self) -> GenericResult<'future> {
+        Box::pin(async move {
+            let mut errors: Vec<crate::Error> = vec![];
+            let tasks: Vec<_> = self.residual().task_queue.drain(..).collect();
+            for t in tasks {
+                if let Err(e) = t.await {
+                    errors.push(e.into());
+                }
             }
-            _ => Err(crate::Error::NotSupported(crate::NSOpType::Gradients)),
-        }
+
+            let evicted = mem::take(&mut self.residual().evicted);
+            if !evicted.is_empty() {
+                self.residual().forget_selection();
+            }
+            for o in evicted {
+                let res = match o.delete(self.thread) {
+                    Ok(t) => t.await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = res {
+                    errors.push(e.into());
+                }
+            }
+
+            crate::Error::from_errors(errors)
+        })
     }
 
     #[inline]
-    fn set_line_width(&mut self, width: usize) -> crate::Result {
-        self.residual().width = width;
-        Ok(())
+    fn create_image_async<'future, 'a, 'b>(
+        &'a mut self,
+        image_bytes: &'b [u8],
+        width: u32,
+        height: u32,
+        format: ImageFormat,
+    ) -> GenericResult<'future, Image>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        Box::pin(async move {
+            let (image_bytes, format) = crate::image::bgra_to_rgba(image_bytes, format);
+            let image_bytes = &*image_bytes;
+
+            // create a compatible DC
+            let compat_dc = self.dc.create_compatible_dc(self.thread)?.await?;
+            let translucent = is_translucent(image_bytes, width, height, format);
+
+            let old_image = if translucent {
+                // AlphaBlend needs a 32-bit DIB section containing premultiplied pixels
+                let bitmap = compat_dc
+                    .create_dib_section(
+                        self.thread,
+                        width as _,
+                        height as _,
+                        premultiplied_pixels(image_bytes, width, height, format),
+                    )?
+                    .await?;
+                compat_dc.select_object(self.thread, bitmap)?.await?
+            } else {
+                // create a bitmap using that dc
+                let bitmap = compat_dc
+                    .create_compatible_bitmap(self.thread, width as _, height as _)?
+                    .await?;
+                let old_image = compat_dc.select_object(self.thread, bitmap)?.await?;
+
+                // draw pixels onto it
+                compat_dc
+                    .draw_pixels(
+                        self.thread,
+                        0,
+                        0,
+                        width as _,
+                        crate::image::iterate_pixels(image_bytes, width, height, format)
+                            .map(colorref),
+                    )?
+                    .await?;
+                old_image
+            };
+
+            // insert into images map
+            let residual = self.residual();
+            residual.image_dcs.insert(compat_dc, old_image);
+            residual.image_info.insert(
+                compat_dc,
+                ImageInfo {
+                    width,
+                    height,
+                    translucent,
+                },
+            );
+
+            // return
+            Ok(Image::from_raw(compat_dc.into_raw()))
+        })
     }
 
     #[inline]
-    fn flush(&mut self) -> crate::Result {
-        self.residual()
-            .task_queue
-            .drain(..)
-            .try_for_each::<_, crate::Result>(|t| {
-                t.wait()?;
-                Ok(())
-            })
+    fn destroy_image_async<'future>(&'future mut self, image: Image) -> GenericResult<'future> {
+        Box::pin(async move {
+            if let Some(brush) = self.residual().patterns.remove(&image) {
+                self.residual().evicted.push(brush.into_gdi_object());
+            }
+
+            let dc = Dc::from_raw(image.into_raw());
+            self.residual().image_info.remove(&dc);
+            if let Some(old_image) = self.residual().image_dcs.remove(&dc) {
+                let bitmap = dc.select_object(self.thread, old_image)?.await?;
+                let _ = bitmap.delete(self.thread)?;
+                let _ = dc.delete(self.thread)?;
+            }
+
+            Ok(())
+        })
     }
 
     #[inline]
-    fn draw_line(&mut self, x1: i32, y1: i32, x2: i32, y2: i32) -> crate::Result {
-        self.submit(Stroke)?;
-        self.line(x1, y1, x2, y2)
+    fn draw_line_async<'future>(
+        &'future mut self,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+    ) -> GenericResult<'future> {
+        Box::pin(async move {
+            self.submit_async(Stroke).await?;
+            self.line(x1, y1, x2, y2)
+        })
     }
 
     #[inline]
-    fn draw_lines(&mut self, lines: &[Line]) -> crate::Result {
-        self.submit(Stroke)?;
-        self.lines(lines)
+    fn draw_lines_async<'future, 'a, 'b>(
+        &'a mut self,
+        lines: &'b [LineSegment<f32>],
+    ) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        Box::pin(async move {
+            self.submit_async(Stroke).await?;
+            self.lines(lines)
+        })
     }
 
     #[inline]
-    fn draw_rectangle(&mut self, x1: i32, y1: i32, x2: i32, y2: i32) -> crate::Result {
-        self.submit(Stroke)?;
-        self.rectangle(x1, y1, x2, y2)
+    fn draw_rectangle_async<'future>(
+        &'future mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    ) -> GenericResult<'future> {
+        Box::pin(async move {
+            self.submit_async(Stroke).await?;
+            self.rectangle(x, y, width, height)
+        })
     }
 
     #[inline]
-    fn draw_rectangles(&mut self, rects: &[Rectangle]) -> crate::Result {
-        self.submit(Stroke)?;
-        self.rectangles(rects)
+    fn draw_rectangles_async<'future, 'a, 'b>(
+        &'a mut self,
+        rects: &'b [Rect<f32>],
+    ) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        Box::pin(async move {
+            self.submit_async(Stroke).await?;
+            self.rectangles(rects)
+        })
     }
 
     #[inline]
-    fn draw_arc(
-        &mut self,
-        x1: i32,
-        y1: i32,
-        x2: i32,
-        y2: i32,
-        start: Angle,
-        end: Angle,
-    ) -> crate::Result {
-        self.submit(Stroke)?;
-        self.arc(x1, y1, x2, y2, start, end)
+    fn draw_arc_async<'future>(
+        &'future mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+        start_angle: Angle<f32>,
+        sweep_angle: Angle<f32>,
+    ) -> GenericResult<'future> {
+        Box::pin(async move {
+            self.submit_async(Stroke).await?;
+            self.arc(
+                xcenter,
+                ycenter,
+                xradius,
+                yradius,
+                start_angle,
+                sweep_angle,
+                Stroke,
+            )
+        })
     }
 
     #[inline]
-    fn draw_arcs(&mut self, arcs: &[GeometricArc]) -> crate::Result {
-        self.submit(Stroke)?;
-        self.arcs(arcs)
+    fn draw_arcs_async<'future, 'a, 'b>(
+        &'a mut self,
+        arcs: &'b [Arc<f32>],
+    ) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        Box::pin(async move {
+            self.submit_async(Stroke).await?;
+            self.arcs(arcs, Stroke)
+        })
     }
 
     #[inline]
-    fn draw_ellipse(&mut self, x1: i32, y1: i32, x2: i32, y2: i32) -> crate::Result {
-        self.submit(Stroke)?;
-        self.ellipse(x1, y1, x2, y2)
+    fn draw_ellipse_async<'future>(
+        &'future mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+    ) -> GenericResult<'future> {
+        Box::pin(async move {
+            self.submit_async(Stroke).await?;
+            self.ellipse(xcenter, ycenter, xradius, yradius)
+        })
     }
 
     #[inline]
-    fn draw_ellipses(&mut self, rects: &[Rectangle]) -> crate::Result {
-        self.submit(Stroke)?;
-        self.ellipses(rects)
+    fn draw_ellipses_async<'future, 'a, 'b>(
+        &'a mut self,
+        rects: &'b [Ellipse],
+    ) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        Box::pin(async move {
+            self.submit_async(Stroke).await?;
+            self.ellipses(rects)
+        })
     }
 
     #[inline]
-    fn fill_polygon(&mut self, points: &[Point]) -> crate::Result {
-        self.submit(Fill)?;
-        self.polygon(points)
+    fn fill_polygon_async<'future, 'a, 'b>(
+        &'a mut self,
+        points: &'b [Point<f32>],
+    ) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        Box::pin(async move {
+            if self.gradient_shapes(&[], &[points])? {
+                return Ok(());
+            }
+
+            self.submit_async(Fill).await?;
+            self.polygon(points)
+        })
     }
 
     #[inline]
-    fn fill_rectangle(&mut self, x1: i32, y1: i32, x2: i32, y2: i32) -> crate::Result {
-        self.submit(Fill)?;
-        self.rectangle(x1, y1, x2, y2)
+    fn fill_rectangle_async<'future>(
+        &'future mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    ) -> GenericResult<'future> {
+        Box::pin(async move {
+            let rect = Rect::new(Point::new(x, y), Size::new(width, height));
+            if self.gradient_shapes(&[rect], &[])? {
+                return Ok(());
+            }
+
+            self.submit_async(Fill).await?;
+            self.rectangle(x, y, width, height)
+        })
     }
 
     #[inline]
-    fn fill_rectangles(&mut self, rects: &[Rectangle]) -> crate::Result {
-        self.submit(Fill)?;
-        self.rectangles(rects)
+    fn fill_rectangles_async<'future, 'a, 'b>(
+        &'a mut self,
+        rects: &'b [Rect<f32>],
+    ) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        Box::pin(async move {
+            if self.gradient_shapes(rects, &[])? {
+                return Ok(());
+            }
+
+            self.submit_async(Fill).await?;
+            self.rectangles(rects)
+        })
     }
 
     #[inline]
-    fn fill_arc(
-        &mut self,
-        x1: i32,
-        y1: i32,
-        x2: i32,
-        y2: i32,
-        start: Angle,
-        end: Angle,
-    ) -> crate::Result {
-        self.submit(Fill)?;
-        self.arc(x1, y1, x2, y2, start, end)
+    fn fill_arc_async<'future>(
+        &'future mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+        start_angle: Angle<f32>,
+        sweep_angle: Angle<f32>,
+    ) -> GenericResult<'future> {
+        Box::pin(async move {
+            self.submit_async(Fill).await?;
+            self.arc(
+                xcenter,
+                ycenter,
+                xradius,
+                yradius,
+                start_angle,
+                sweep_angle,
+                Fill,
+            )
+        })
     }
 
     #[inline]
-    fn fill_arcs(&mut self, arcs: &[GeometricArc]) -> crate::Result {
-        self.submit(Fill)?;
-        self.arcs(arcs)
+    fn fill_arcs_async<'future, 'a, 'b>(
+        &'a mut self,
+        arcs: &'b [Arc<f32>],
+    ) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        Box::pin(async move {
+            self.submit_async(Fill).await?;
+            self.arcs(arcs, Fill)
+        })
     }
 
     #[inline]
-    fn fill_ellipse(&mut self, x1: i32, y1: i32, x2: i32, y2: i32) -> crate::Result {
-        self.submit(Fill)?;
-        self.ellipse(x1, y1, x2, y2)
+    fn fill_ellipse_async<'future>(
+        &'future mut self,
+        xcenter: f32,
+        ycenter: f32,
+        xradius: f32,
+        yradius: f32,
+    ) -> GenericResult<'future> {
+        Box::pin(async move {
+            self.submit_async(Fill).await?;
+            self.ellipse(xcenter, ycenter, xradius, yradius)
+        })
     }
 
     #[inline]
-    fn fill_ellipses(&mut self, rects: &[Rectangle]) -> crate::Result {
-        self.submit(Fill)?;
-        self.ellipses(rects)
+    fn fill_ellipses_async<'future, 'a, 'b>(
+        &'a mut self,
+        rects: &'b [Ellipse],
+    ) -> GenericResult<'future>
+    where
+        'a: 'future,
+        'b: 'future,
+    {
+        Box::pin(async move {
+            self.submit_async(Fill).await?;
+            self.ellipses(rects)
+        })
+    }
+
+    #[inline]
+    fn copy_image_async<'future>(
+        &'future mut self,
+        src: Image,
+        src_x: i32,
+        src_y: i32,
+        dst_x: i32,
+        dst_y: i32,
+        width: u32,
+        height: u32,
+    ) -> GenericResult<'future> {
+        Box::pin(future::ready(
+            self.copy_image(src, src_x, src_y, dst_x, dst_y, width, height),
+        ))
     }
 }
 
 #[inline]
-fn calc_posns(arc: Arc<f32>) -> [i32; 8] {
+fn gdi_end_cap(cap: LineCap) -> PenEndCap {
+    match cap {
+        LineCap::Butt => PenEndCap::Flat,
+        LineCap::Round => PenEndCap::Round,
+        LineCap::Square => PenEndCap::Square,
+    }
+}
+
+#[inline]
+fn gdi_join(join: LineJoin) -> PenJoin {
+    match join {
+        LineJoin::Miter => PenJoin::Miter,
+        LineJoin::Round => PenJoin::Round,
+        LineJoin::Bevel => PenJoin::Bevel,
+    }
+}
+
+#[inline]
+fn gdi_fill_mode(mode: FillMode) -> PolyFillMode {
+    match mode {
+        FillMode::EvenOdd => PolyFillMode::Alternate,
+        FillMode::NonZero => PolyFillMode::Winding,
+    }
+}
+
+#[inline]
+fn yaww_point(rounding: RoundingMode, Point { x, y, .. }: Point<f32>) -> YawwPoint {
+    YawwPoint {
+        x: rounding.apply(x),
+        y: rounding.apply(y),
+    }
+}
+
+/// Convert a series of points to the integer points that GDI uses.
+#[inline]
+fn yaww_points(rounding: RoundingMode, pts: &[Point<f32>]) -> Vec<YawwPoint> {
+    pts.iter()
+        .copied()
+        .map(|pt| yaww_point(rounding, pt))
+        .collect()
+}
+
+/// Get the one pixel long lines that draw each of the points, as pairs of points for `PolyPolyline`.
+#[inline]
+fn point_strokes(rounding: RoundingMode, points: &[Point<f32>]) -> Vec<YawwPoint> {
+    points
+        .iter()
+        .copied()
+        .flat_map(|point| ArrayIter::new([point, point + Vector::new(1.0, 0.0)]))
+        .map(|pt| yaww_point(rounding, pt))
+        .collect()
+}
+
+/// Split line segments into runs of connected segments, so that each run can be submitted as a single polyline.
+#[inline]
+fn polyline_runs(rounding: RoundingMode, lines: &[LineSegment<f32>]) -> Vec<Vec<YawwPoint>> {
+    let mut runs: Vec<Vec<YawwPoint>> = vec![];
+    let mut last = None;
+    lines.iter().for_each(|line| {
+        if last != Some(line.from) {
+            runs.push(vec![yaww_point(rounding, line.from)]);
+        }
+        runs.last_mut().unwrap().push(yaww_point(rounding, line.to));
+        last = Some(line.to);
+    });
+    runs
+}
+
+/// Split positioned glyphs into runs of consecutive glyphs on the same baseline, keeping them in order.
+#[inline]
+fn glyph_runs(rounding: RoundingMode, glyphs: &[(GlyphId, Point<f32>)]) -> Vec<GlyphRun> {
+    let mut runs: Vec<GlyphRun> = vec![];
+    glyphs.iter().for_each(|&(GlyphId(glyph), pos)| {
+        let (x, y) = (rounding.apply(pos.x), rounding.apply(pos.y));
+        match runs.last_mut() {
+            Some(run) if run.y == y => {
+                let last_x = run.x + run.dx.iter().sum::<i32>();
+                *run.dx.last_mut().unwrap() = x - last_x;
+                run.glyphs.push(glyph);
+                run.dx.push(0);
+            }
+            _ => runs.push(GlyphRun {
+                x,
+                y,
+                glyphs: vec![glyph],
+                dx: vec![0],
+            }),
+        }
+    });
+    runs
+}
+
+/// Whether a task queue of the given length has grown past the auto-flush threshold.
+#[inline]
+fn needs_auto_flush(queue_len: usize, threshold: Option<usize>) -> bool {
+    threshold.map_or(false, |threshold| queue_len > threshold)
+}
+
+/// Flatten an arc into GDI points, for arcs that GDI can't draw because they're rotated.
+#[inline]
+fn rotated_arc_points(rounding: RoundingMode, arc: &Arc<f32>, tolerance: f32) -> Vec<YawwPoint> {
+    yaww_points(rounding, &flatten_arc(arc, tolerance))
+}
+
+/// Get the points of several polygons laid end to end, along with the number of points in each of them, as
+/// `PolyPolygon` takes them.
+#[inline]
+fn poly_polygon_points(
+    rounding: RoundingMode,
+    polys: &[&[Point<f32>]],
+) -> (Vec<YawwPoint>, Vec<usize>) {
+    let pts = polys
+        .iter()
+        .flat_map(|points| points.iter().copied())
+        .map(|pt| yaww_point(rounding, pt))
+        .collect();
+    let counts = polys.iter().map(|points| points.len()).collect();
+    (pts, counts)
+}
+
+/// Get the bounding rectangle and the starting and ending radial points of an arc, in the order that GDI's
+/// `Arc` and `Pie` take them.
+#[inline]
+fn calc_posns(rounding: RoundingMode, arc: Arc<f32>) -> [i32; 8] {
     let Arc {
         center: Point { x: cx, y: cy, .. },
         radii: Vector { x: rx, y: ry, .. },
@@ -831,19 +2615,708 @@ fn calc_posns(arc: Arc<f32>) -> [i32; 8] {
         ..
     } = arc;
 
-    let x1 = (cx - rx) as i32;
-    let y1 = (cy - ry) as i32;
-    let x2 = (cx + rx) as i32;
-    let y2 = (cy + ry) as i32;
+    let x1 = rounding.apply(cx - rx);
+    let y1 = rounding.apply(cy - ry);
+    let x2 = rounding.apply(cx + rx);
+    let y2 = rounding.apply(cy + ry);
 
     let mut calc_posn = move |degree: f32| {
         (
-            (cx + degree.cos() * rx).ceil() as i32,
-            (cy + degree.sin() * ry).ceil() as i32,
+            rounding.apply(cx + degree.cos() * rx),
+            rounding.apply(cy + degree.sin() * ry),
         )
     };
 
+    // lyon measures angles with the Y axis pointing down, so a positive sweep travels clockwise on screen and a
+    // negative sweep travels counter-clockwise. GDI always draws from the starting radial to the ending radial
+    // counter-clockwise, so for clockwise arcs we hand it the end of the arc as its start and vice versa in
+    // order to trace the same curve.
     let (asx, asy) = calc_posn(start_angle.radians);
-    let (aex, aey) = calc_posn(sweep_angle.radians);
-    [x1, y1, x2, y2, asx, asy, aex, aey]
+    let (aex, aey) = calc_posn(start_angle.radians + sweep_angle.radians);
+    if sweep_angle.radians < 0.0 {
+        [x1, y1, x2, y2, asx, asy, aex, aey]
+    } else {
+        [x1, y1, x2, y2, aex, aey, asx, asy]
+    }
+}
+
+/// Tell whether any pixels in an image are not fully opaque.
+#[inline]
+fn is_translucent(image_bytes: &[u8], width: u32, height: u32, format: ImageFormat) -> bool {
+    format.has_alpha_component()
+        && crate::image::iterate_pixels(image_bytes, width, height, format)
+            .any(|pixel| format.alpha_component(pixel) < 255)
+}
+
+/// Pack the channels of a pixel into a COLORREF, with the first channel in the lowest byte.
+#[inline]
+fn colorref(pixel: &[u8]) -> u32 {
+    pixel.iter().enumerate().fold(0, |color, (i, &channel)| {
+        color | ((channel as u32) << (i * 8))
+    })
+}
+
+/// Convert an image into the premultiplied BGRA pixels that AlphaBlend expects.
+#[inline]
+fn premultiplied_pixels(
+    image_bytes: &[u8],
+    width: u32,
+    height: u32,
+    format: ImageFormat,
+) -> Vec<u32> {
+    let mut bytes: Vec<u8> = crate::image::iterate_pixels(image_bytes, width, height, format)
+        .flat_map(|pixel| {
+            let a = format.alpha_component(pixel);
+            let channel = |c: u8| (c as u32 * a as u32 / 255) as u8;
+            [channel(pixel[0]), channel(pixel[1]), channel(pixel[2]), a]
+        })
+        .collect();
+
+    // DIB sections store their pixels as BGRA
+    crate::util::swizzle_rgba_to_bgra(&mut bytes);
+    bytes
+        .chunks_exact(4)
+        .map(|pixel| u32::from_le_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]))
+        .collect()
+}
+
+/// Get the image to make a pattern brush out of. Pattern brushes always tile from the origin of the DC, so other
+/// tiling modes and transforms aren't supported.
+#[inline]
+fn pattern_brush_image(pattern: &ImagePattern) -> crate::Result<Image> {
+    match pattern {
+        ImagePattern {
+            image,
+            tiling: TileMode::Tile,
+            transform: None,
+        } => Ok(*image),
+        _ => Err(crate::Error::NotSupported(crate::NSOpType::Pattern)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::num::NonZeroUsize;
+    use yaww::GuiThread;
+
+    fn arc(center: (f32, f32), radii: (f32, f32), start: f32, sweep: f32) -> Arc<f32> {
+        Arc {
+            center: Point::new(center.0, center.1),
+            radii: Vector::new(radii.0, radii.1),
+            start_angle: Angle::degrees(start),
+            sweep_angle: Angle::degrees(sweep),
+            x_rotation: Angle::zero(),
+        }
+    }
+
+    #[test]
+    fn calc_posns_quarter_from_zero() {
+        // clockwise on screen, so GDI is handed the end first
+        let posns = calc_posns(
+            RoundingMode::Round,
+            arc((0.0, 0.0), (10.0, 10.0), 0.0, 90.0),
+        );
+        assert_eq!(posns, [-10, -10, 10, 10, 0, 10, 10, 0]);
+    }
+
+    #[test]
+    fn calc_posns_quarter_from_ninety() {
+        let posns = calc_posns(
+            RoundingMode::Round,
+            arc((0.0, 0.0), (10.0, 10.0), 90.0, 90.0),
+        );
+        assert_eq!(posns, [-10, -10, 10, 10, -10, 0, 0, 10]);
+    }
+
+    #[test]
+    fn calc_posns_negative_sweep() {
+        // counter-clockwise on screen, which is the direction GDI already draws in
+        let posns = calc_posns(
+            RoundingMode::Round,
+            arc((0.0, 0.0), (10.0, 10.0), 0.0, -90.0),
+        );
+        assert_eq!(posns, [-10, -10, 10, 10, 10, 0, 0, -10]);
+    }
+
+    #[test]
+    fn fill_arc_uses_both_radii() {
+        // a wide, short ellipse whose vertical radius differs from its vertical center
+        let posns = calc_posns(
+            RoundingMode::Round,
+            arc((50.0, 40.0), (30.0, 10.0), 0.0, 90.0),
+        );
+        assert_eq!(posns[..4], [20, 30, 80, 50]);
+    }
+
+    #[test]
+    fn negative_halves_depend_on_rounding_mode() {
+        assert_eq!(RoundingMode::Round.apply(-0.5), -1);
+        assert_eq!(RoundingMode::Truncate.apply(-0.5), 0);
+        assert_eq!(RoundingMode::Floor.apply(-0.5), -1);
+        assert_eq!(RoundingMode::default(), RoundingMode::Round);
+    }
+
+    fn xy(points: &[YawwPoint]) -> Vec<(i32, i32)> {
+        points.iter().map(|p| (p.x, p.y)).collect()
+    }
+
+    #[test]
+    fn draw_points_is_one_batch() {
+        let points: Vec<Point<f32>> = (0..1000)
+            .map(|i| Point::new((i % 100) as f32, (i / 100) as f32))
+            .collect();
+        // every point goes into the single PolyPolyline call, as a line one pixel long
+        let strokes = xy(&point_strokes(RoundingMode::Round, &points));
+        assert_eq!(strokes.len(), 2000);
+        assert_eq!(strokes[..4], [(0, 0), (1, 0), (1, 0), (2, 0)]);
+        assert_eq!(strokes[1998..], [(99, 9), (100, 9)]);
+
+        let gt = GuiThread::new();
+        let mut surface = idle_surface(&gt);
+        surface.set_auto_flush(None);
+        let cp = surface.clear_pen().unwrap();
+        preselect(&mut surface, cp);
+        Surface::draw_points(&mut surface, &points).unwrap();
+        assert_eq!(surface.residual().task_queue.len(), 1);
+    }
+
+    #[test]
+    fn fill_polygons_is_one_batch() {
+        let triangles: Vec<[Point<f32>; 3]> = (0..50)
+            .map(|i| {
+                let x = i as f32 * 10.0;
+                [
+                    Point::new(x, 0.0),
+                    Point::new(x + 5.0, 10.0),
+                    Point::new(x, 10.0),
+                ]
+            })
+            .collect();
+        let polys: Vec<&[Point<f32>]> = triangles.iter().map(|t| &t[..]).collect();
+
+        // all 50 polygons go into the single PolyPolygon call
+        let (pts, counts) = poly_polygon_points(RoundingMode::Round, &polys);
+        assert_eq!(counts, vec![3; 50]);
+        let pts = xy(&pts);
+        assert_eq!(pts.len(), 150);
+        assert_eq!(pts[147..], [(490, 0), (495, 10), (490, 10)]);
+
+        let gt = GuiThread::new();
+        let mut surface = idle_surface(&gt);
+        surface.set_auto_flush(None);
+        Surface::set_fill_color(&mut surface, Color::BLACK).unwrap();
+        preselect_fill(&mut surface, Color::BLACK);
+        Surface::fill_polygons(&mut surface, &polys).unwrap();
+        assert_eq!(surface.residual().task_queue.len(), 1);
+    }
+
+    #[test]
+    fn polyline_is_one_batch() {
+        let pts = [
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(10.0, 10.0),
+            Point::new(20.4, 10.6),
+        ];
+        // the whole polyline goes into one Polyline call, instead of a MoveTo and LineTo for every segment
+        assert_eq!(
+            xy(&yaww_points(RoundingMode::Round, &pts)),
+            [(0, 0), (10, 0), (10, 10), (20, 11)]
+        );
+
+        let gt = GuiThread::new();
+        let mut surface = idle_surface(&gt);
+        surface.set_auto_flush(None);
+        let cp = surface.clear_pen().unwrap();
+        preselect(&mut surface, cp);
+        Surface::draw_polyline(&mut surface, &pts).unwrap();
+        assert_eq!(surface.residual().task_queue.len(), 1);
+    }
+
+    #[test]
+    fn half_transparent_fill_blends_with_background() {
+        let red = [255, 0, 0, 128];
+        assert!(is_translucent(&red, 1, 1, ImageFormat::Rgba));
+        assert!(!is_translucent(&[255, 0, 0, 255], 1, 1, ImageFormat::Rgba));
+
+        let pixels = premultiplied_pixels(&red, 1, 1, ImageFormat::Rgba);
+        assert_eq!(pixels, [0x8080_0000]);
+
+        // AlphaBlend with premultiplied pixels computes dst = src + (1 - src_alpha) * dst
+        let [b, g, r, a] = pixels[0].to_le_bytes();
+        let background = [0u32, 0, 255];
+        let blend = |src: u8, dst: u32| src as u32 + dst * (255 - a as u32) / 255;
+        assert_eq!(
+            [
+                blend(r, background[0]),
+                blend(g, background[1]),
+                blend(b, background[2])
+            ],
+            [128, 0, 127]
+        );
+
+        // filling with a translucent color goes through AlphaBlend instead of the solid brush, which needs a
+        // memory DC that the idle DC can't create
+        let gt = GuiThread::new();
+        let mut surface = idle_surface(&gt);
+        surface.set_auto_flush(None);
+        let half_red = Color::new(1.0, 0.0, 0.0, 0.5).unwrap();
+        Surface::set_fill(&mut surface, FillRule::SolidColor(half_red)).unwrap();
+        preselect_fill(&mut surface, half_red);
+        assert_eq!(surface.translucent_brush(), Some(half_red));
+        assert!(Surface::fill_rectangle(&mut surface, 0.0, 0.0, 4.0, 4.0).is_err());
+        assert!(surface.residual().task_queue.is_empty());
+
+        // once it is opaque, the solid brush draws the rectangle directly
+        let red = half_red.with_alpha(1.0);
+        Surface::set_fill(&mut surface, FillRule::SolidColor(red)).unwrap();
+        preselect_fill(&mut surface, red);
+        Surface::fill_rectangle(&mut surface, 0.0, 0.0, 4.0, 4.0).unwrap();
+        assert_eq!(surface.residual().task_queue.len(), 1);
+    }
+
+    #[test]
+    fn pattern_brush_from_small_bitmap() {
+        let bitmap = Image::from_raw(NonZeroUsize::new(0x40).unwrap());
+        let pattern = ImagePattern::new(bitmap, TileMode::Tile);
+        assert_eq!(pattern_brush_image(&pattern).unwrap(), bitmap);
+
+        let mirrored = ImagePattern::new(bitmap, TileMode::Mirror);
+        assert!(matches!(
+            pattern_brush_image(&mirrored),
+            Err(crate::Error::NotSupported(crate::NSOpType::Pattern))
+        ));
+        let transformed = ImagePattern {
+            transform: Some(Transform::identity()),
+            ..pattern
+        };
+        assert!(pattern_brush_image(&transformed).is_err());
+    }
+
+    #[test]
+    fn fill_modes_map_to_gdi() {
+        assert!(matches!(
+            gdi_fill_mode(FillMode::EvenOdd),
+            PolyFillMode::Alternate
+        ));
+        assert!(matches!(
+            gdi_fill_mode(FillMode::NonZero),
+            PolyFillMode::Winding
+        ));
+        // GDI starts out in alternate mode
+        assert_eq!(FillMode::default(), FillMode::EvenOdd);
+    }
+
+    #[test]
+    fn rotated_arc_is_not_axis_aligned() {
+        let rotated = Arc {
+            x_rotation: Angle::degrees(45.0),
+            ..arc((0.0, 0.0), (100.0, 50.0), 0.0, 90.0)
+        };
+        let points = xy(&rotated_arc_points(RoundingMode::Round, &rotated, 0.1));
+        // the start of the arc is on the rotated major axis, and the end is on the rotated minor axis
+        assert_eq!(points[0], (71, 71));
+        assert_eq!(points[points.len() - 1], (-35, 35));
+
+        // every point lies on the ellipse once it is rotated back
+        let (sin, cos) = Angle::degrees(45.0f32).sin_cos();
+        points.iter().for_each(|&(x, y)| {
+            let (x, y) = (x as f32, y as f32);
+            let (u, v) = (x * cos + y * sin, y * cos - x * sin);
+            let r = (u / 100.0).powi(2) + (v / 50.0).powi(2);
+            assert!((r - 1.0).abs() < 0.05, "({}, {}) is off the ellipse", x, y);
+        });
+    }
+
+    #[test]
+    fn connected_zigzag_is_one_polyline() {
+        let zigzag: Vec<LineSegment<f32>> = [(0.0, 0.0), (1.0, 1.0), (2.0, 0.0), (3.0, 1.0)]
+            .windows(2)
+            .map(|w| LineSegment {
+                from: Point::new(w[0].0, w[0].1),
+                to: Point::new(w[1].0, w[1].1),
+            })
+            .collect();
+        let runs = polyline_runs(RoundingMode::Round, &zigzag);
+        assert_eq!(runs.len(), 1);
+        assert_eq!(xy(&runs[0]), [(0, 0), (1, 1), (2, 0), (3, 1)]);
+
+        // a gap starts a new polyline
+        let mut broken = zigzag.clone();
+        broken.push(LineSegment {
+            from: Point::new(5.0, 5.0),
+            to: Point::new(6.0, 5.0),
+        });
+        let runs = polyline_runs(RoundingMode::Round, &broken);
+        assert_eq!(runs.len(), 2);
+        assert_eq!(xy(&runs[1]), [(5, 5), (6, 5)]);
+        assert!(polyline_runs(RoundingMode::Round, &[]).is_empty());
+    }
+
+    /// A surface on a real GUI thread, with a DC that is never drawn to.
+    fn idle_surface(gt: &GuiThread) -> YawwGdiSurface<'_, GuiThread> {
+        YawwGdiSurface::new(gt, Dc::from_raw(NonZeroUsize::new(1).unwrap()))
+    }
+
+    /// Mark `pen` and the stock null brush as already selected into an idle surface's DC. The idle DC rejects
+    /// `SelectObject`, so drawing afterwards only succeeds as long as nothing has to be selected again.
+    fn preselect(surface: &mut YawwGdiSurface<'_, GuiThread>, pen: Pen) {
+        let brush = surface.clear_brush().unwrap();
+        let residual = surface.residual();
+        residual.selected_pen = Some(pen);
+        residual.selected_brush = Some(brush);
+    }
+
+    /// Like `preselect`, but with the pen and brush used to fill shapes with `color`.
+    fn preselect_fill(surface: &mut YawwGdiSurface<'_, GuiThread>, color: Color) {
+        let pen = surface.get_pen_from_color(color).unwrap();
+        let brush = surface.get_brush_from_color(color).unwrap();
+        preselect(surface, pen);
+        surface.residual().selected_brush = Some(brush);
+    }
+
+    #[test]
+    fn poll_flush_on_empty_queue() {
+        let gt = GuiThread::new();
+        let mut surface = idle_surface(&gt);
+        assert_eq!(surface.poll_flush().unwrap(), 0);
+    }
+
+    #[test]
+    fn supports_follows_features() {
+        let gt = GuiThread::new();
+        let surface = idle_surface(&gt);
+        // gradients are rasterized in software, and clipping uses regions
+        assert!(Surface::supports(&surface, crate::NSOpType::Gradients));
+        assert!(Surface::supports(&surface, crate::NSOpType::Clipping));
+        assert!(!Surface::supports(&surface, crate::NSOpType::Text));
+        assert!(!Surface::supports(&surface, crate::NSOpType::Antialiasing));
+    }
+
+    #[test]
+    fn same_color_lines_select_the_pen_once() {
+        use crate::color::consts::{BLUE, RED};
+
+        let gt = GuiThread::new();
+        let mut surface = idle_surface(&gt);
+        surface.set_auto_flush(None);
+        Surface::set_stroke(&mut surface, RED).unwrap();
+
+        // stand in for the selection made by the first line
+        let pen = surface.get_pen_from_color(RED).unwrap();
+        preselect(&mut surface, pen);
+        (0..10).for_each(|i| {
+            let y = i as f32 * 2.0;
+            Surface::draw_line(&mut surface, 0.0, y, 10.0, y).unwrap();
+        });
+
+        // every line is a MoveTo and a LineTo, with nothing selected in between
+        assert_eq!(surface.residual().task_queue.len(), 20);
+        assert_eq!(surface.residual().selected_pen, Some(pen));
+
+        // a different color needs a different pen, which has to be selected
+        Surface::set_stroke(&mut surface, BLUE).unwrap();
+        assert!(Surface::draw_line(&mut surface, 0.0, 0.0, 10.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn two_thousand_lines_auto_flush() {
+        let longest_queue = |threshold| {
+            let gt = GuiThread::new();
+            let mut surface = idle_surface(&gt);
+            surface.set_auto_flush(threshold);
+            let cp = surface.clear_pen().unwrap();
+            preselect(&mut surface, cp);
+
+            (0..2000).fold(0, |longest, i| {
+                // the queue is drained whether or not the idle DC accepts the lines
+                let y = (i % 100) as f32;
+                let _ = Surface::draw_line(&mut surface, 0.0, y, 10.0, y);
+                cmp::max(longest, surface.residual().task_queue.len())
+            })
+        };
+
+        // each disconnected line is a MoveTo and a LineTo
+        assert_eq!(
+            longest_queue(Some(DEFAULT_AUTO_FLUSH_THRESHOLD)),
+            DEFAULT_AUTO_FLUSH_THRESHOLD
+        );
+        assert_eq!(longest_queue(Some(10)), 10);
+        assert_eq!(longest_queue(None), 4000);
+    }
+
+    #[test]
+    fn transforms_and_saves_auto_flush() {
+        let gt = GuiThread::new();
+        let mut surface = idle_surface(&gt);
+        surface.set_auto_flush(Some(10));
+
+        let longest = (0..100).fold(0, |longest, i| {
+            // the queue is drained whether or not the idle DC accepts the tasks
+            let _ = Surface::set_transform(&mut surface, Transform::translation(i as f32, 0.0));
+            let _ = Surface::save(&mut surface);
+            cmp::max(longest, surface.residual().task_queue.len())
+        });
+        assert!(longest <= 10, "{} tasks were queued", longest);
+        assert_eq!(surface.residual().saved.len(), 100);
+    }
+
+    #[test]
+    fn clear_caches_empties_pens_and_brushes() {
+        use crate::color::consts::RED;
+
+        let gt = GuiThread::new();
+        let mut surface = idle_surface(&gt);
+        Surface::set_stroke(&mut surface, RED).unwrap();
+        surface.get_pen_from_color(RED).unwrap();
+        surface.get_brush_from_color(RED).unwrap();
+        let cp = surface.clear_pen().unwrap();
+        preselect(&mut surface, cp);
+
+        surface.clear_caches().unwrap();
+        let key = (RED, 0, LineCap::Butt, LineJoin::Miter, vec![]);
+        let residual = surface.residual();
+        assert!(residual.pens.get(&key).is_none());
+        assert!(residual.brushes.get(&RED).is_none());
+        assert!(residual.evicted.is_empty());
+        // deleting them also forgets what was selected into the DC
+        assert_eq!(residual.selected_pen, None);
+
+        // the next line creates its pen from scratch, even though the idle DC then refuses to select it
+        preselect(&mut surface, cp);
+        assert!(Surface::draw_line(&mut surface, 0.0, 0.0, 10.0, 0.0).is_err());
+        assert!(surface.residual().pens.get(&key).is_some());
+    }
+
+    #[test]
+    fn finish_drains_the_queue() {
+        use crate::color::consts::RED;
+
+        let gt = GuiThread::new();
+        let mut surface = idle_surface(&gt);
+        surface.set_auto_flush(None);
+        Surface::set_stroke(&mut surface, RED).unwrap();
+        Surface::set_fill_color(&mut surface, RED).unwrap();
+        let cp = surface.clear_pen().unwrap();
+        preselect(&mut surface, cp);
+
+        // deleting a brush is one of the few tasks that doesn't go through the idle DC
+        let brush = gt
+            .create_solid_brush(YawwColor::from_rgb(255, 0, 0))
+            .unwrap()
+            .wait()
+            .unwrap();
+        let t = brush.delete(&gt).unwrap();
+        surface.push_task(t).unwrap();
+        assert_eq!(surface.residual().task_queue.len(), 1);
+
+        let residual = surface.finish().unwrap();
+        assert!(residual.task_queue.is_empty());
+        assert_eq!((residual.pen, residual.brush), (None, None));
+        assert_eq!(
+            (residual.selected_pen, residual.selected_brush),
+            (None, None)
+        );
+        residual.free(&gt).unwrap();
+    }
+
+    #[test]
+    fn flush_waits_on_every_task() {
+        let gt = GuiThread::new();
+        let mut surface = idle_surface(&gt);
+        surface.set_auto_flush(None);
+
+        // deleting brushes succeeds, but moving the idle DC's position fails
+        let delete_brush = || {
+            let brush = gt
+                .create_solid_brush(YawwColor::from_rgb(255, 0, 0))
+                .unwrap()
+                .wait()
+                .unwrap();
+            brush.delete(&gt).unwrap()
+        };
+        let tasks = vec![
+            delete_brush(),
+            surface.dc.move_to(&gt, 0, 0).unwrap(),
+            delete_brush(),
+            delete_brush(),
+        ];
+        surface.extend_tasks(tasks).unwrap();
+        assert_eq!(surface.residual().task_queue.len(), 4);
+
+        match Surface::flush(&mut surface) {
+            Err(crate::Error::Multiple(errors)) => assert_eq!(errors.len(), 1),
+            res => panic!("expected one aggregated error, got {:?}", res),
+        }
+        assert!(surface.residual().task_queue.is_empty());
+    }
+
+    #[test]
+    fn global_alpha_blends_opaque_fills() {
+        use crate::color::consts::RED;
+
+        let gt = GuiThread::new();
+        let mut surface = idle_surface(&gt);
+        surface.set_auto_flush(None);
+        Surface::set_fill_color(&mut surface, RED).unwrap();
+        preselect_fill(&mut surface, RED);
+
+        // at full opacity, the rectangle is drawn with the solid brush
+        assert_eq!(surface.translucent_brush(), None);
+        Surface::fill_rectangle(&mut surface, 0.0, 0.0, 4.0, 4.0).unwrap();
+        assert_eq!(surface.residual().task_queue.len(), 1);
+
+        // at half opacity, it is blended from a memory DC instead, which the idle DC can't create
+        Surface::set_global_alpha(&mut surface, 0.5).unwrap();
+        assert_eq!(surface.translucent_brush(), Some(RED.scale_alpha(0.5)));
+        assert!(Surface::fill_rectangle(&mut surface, 0.0, 0.0, 4.0, 4.0).is_err());
+        assert_eq!(surface.residual().task_queue.len(), 1);
+
+        // the global alpha is saved along with the rest of the state
+        Surface::save(&mut surface).unwrap();
+        Surface::set_global_alpha(&mut surface, 1.0).unwrap();
+        assert_eq!(surface.translucent_brush(), None);
+        Surface::restore(&mut surface).unwrap();
+        assert_eq!(surface.residual().alpha, 0.5);
+    }
+
+    #[test]
+    fn fill_color_matches_solid_fill() {
+        use crate::color::consts::RED;
+
+        let fill_state = |surface: &mut YawwGdiSurface<'_, GuiThread>| {
+            let residual = surface.residual();
+            (
+                residual.fill.clone(),
+                residual.brush,
+                residual.pattern,
+                residual.gradient.clone(),
+            )
+        };
+
+        // both surfaces start out filling with a pattern, which the solid color has to replace
+        let pattern = FillRule::Pattern(ImagePattern::new(
+            Image::from_raw(NonZeroUsize::new(0x40).unwrap()),
+            TileMode::Tile,
+        ));
+        let gt = GuiThread::new();
+        let mut by_color = idle_surface(&gt);
+        let mut by_rule = idle_surface(&gt);
+        Surface::set_fill(&mut by_color, pattern.clone()).unwrap();
+        Surface::set_fill(&mut by_rule, pattern).unwrap();
+
+        Surface::set_fill_color(&mut by_color, RED).unwrap();
+        Surface::set_fill(&mut by_rule, FillRule::SolidColor(RED)).unwrap();
+        assert_eq!(fill_state(&mut by_color), fill_state(&mut by_rule));
+        assert_eq!(
+            fill_state(&mut by_color),
+            (Some(FillRule::SolidColor(RED)), Some(RED), None, None)
+        );
+    }
+
+    #[test]
+    fn horizontal_gradient_edges() {
+        use crate::{
+            color::consts::{BLUE, RED},
+            gradient::{ColorStop, Gradient},
+            Intensity,
+        };
+
+        let stop = |color, position| ColorStop {
+            color,
+            position: Intensity::new(position).unwrap(),
+        };
+        let gradient = Gradient::try_new(vec![stop(RED, 0.0), stop(BLUE, 1.0)]).unwrap();
+        let rule = FillRule::LinearGradient(gradient, Angle::zero());
+
+        // this is what gets drawn over a 16x2 rectangle
+        let pixels = rule
+            .rasterize(Rect::new(Point::origin(), Size::new(16.0, 2.0)))
+            .unwrap();
+        assert!(!is_translucent(&pixels, 16, 2, ImageFormat::Rgba));
+        let colors: Vec<u32> = crate::image::iterate_pixels(&pixels, 16, 2, ImageFormat::Rgba)
+            .map(colorref)
+            .collect();
+
+        // COLORREFs keep red in the low byte and blue in the third byte
+        let red_blue = |color: u32| (color & 0xff, (color >> 16) & 0xff);
+        let (r, b) = red_blue(colors[0]);
+        assert!(r > 240 && b < 16, "{:#x}", colors[0]);
+        let (r, b) = red_blue(colors[15]);
+        assert!(r < 16 && b > 240, "{:#x}", colors[15]);
+        assert_eq!(colors[..16], colors[16..]);
+    }
+
+    #[test]
+    fn gradient_fill_follows_fallback_policy() {
+        use crate::{
+            color::consts::{BLUE, RED},
+            gradient::{ColorStop, Gradient},
+            Intensity,
+        };
+
+        let stop = |color, position| ColorStop {
+            color,
+            position: Intensity::new(position).unwrap(),
+        };
+        let gradient = Gradient::try_new(vec![stop(RED, 0.0), stop(BLUE, 1.0)]).unwrap();
+        let rule = FillRule::LinearGradient(gradient, Angle::zero());
+
+        let gt = GuiThread::new();
+        let mut surface = idle_surface(&gt);
+        Surface::set_fill_color(&mut surface, RED).unwrap();
+        Surface::set_fallback_policy(&mut surface, FallbackPolicy::Reject).unwrap();
+        assert!(matches!(
+            Surface::set_fill(&mut surface, rule.clone()),
+            Err(crate::Error::NotSupported(crate::NSOpType::Gradients))
+        ));
+
+        // a rejected fill leaves the previous one in place
+        assert_eq!(
+            Surface::fill_rule(&surface),
+            Some(&FillRule::SolidColor(RED))
+        );
+        assert_eq!(surface.residual().brush, Some(RED));
+        assert!(surface.residual().gradient.is_none());
+
+        Surface::set_fallback_policy(&mut surface, FallbackPolicy::Emulate).unwrap();
+        Surface::set_fill(&mut surface, rule.clone()).unwrap();
+        assert_eq!(Surface::fill_rule(&surface), Some(&rule));
+    }
+
+    #[test]
+    fn glyphs_are_drawn_in_order() {
+        let glyphs = [
+            (GlyphId(3), Point::new(0.0, 10.0)),
+            (GlyphId(1), Point::new(7.4, 10.0)),
+            (GlyphId(2), Point::new(15.0, 10.0)),
+            (GlyphId(4), Point::new(0.0, 30.0)),
+            (GlyphId(4), Point::new(8.0, 30.0)),
+        ];
+        let run = |x, y, glyphs: &[u16], dx: &[i32]| GlyphRun {
+            x,
+            y,
+            glyphs: glyphs.to_vec(),
+            dx: dx.to_vec(),
+        };
+        assert_eq!(
+            glyph_runs(RoundingMode::Round, &glyphs),
+            vec![
+                run(0, 10, &[3, 1, 2], &[7, 8, 0]),
+                run(0, 30, &[4, 4], &[8, 0]),
+            ]
+        );
+
+        let gt = GuiThread::new();
+        let mut surface = idle_surface(&gt);
+        surface.set_auto_flush(None);
+        let font = Font::new("Arial", 12.0);
+        assert!(Surface::features(&surface).text);
+        Surface::draw_glyphs(&mut surface, &[], &font).unwrap();
+
+        // the idle DC can't select the font, so nothing is drawn
+        assert!(Surface::draw_glyphs(&mut surface, &glyphs, &font).is_err());
+        assert!(surface.residual().task_queue.is_empty());
+    }
 }